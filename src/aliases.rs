@@ -0,0 +1,202 @@
+//! Logical model names that resolve to provider models.
+//!
+//! An alias such as `fast-chat` maps to an ordered list of targets written as
+//! `provider/model` (or just `provider`, letting the catalog pick the model).
+//! When a request's `model` names an alias, the router tries the targets in
+//! order instead of its usual provider selection, so applications never need
+//! vendor model ids. Aliases come from the `[aliases]` table in
+//! `config.toml` or from the catalog (`freegin-ai alias set`); the
+//! configuration wins when both define a name.
+
+use std::{collections::HashMap, fmt, hash::BuildHasher};
+
+use serde::{Serialize, Serializer};
+
+use crate::{error::AppError, providers::Provider};
+
+/// One provider model an alias resolves to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasTarget {
+    /// Provider serving the model.
+    pub provider: Provider,
+    /// Model identifier; `None` lets the catalog choose.
+    pub model: Option<String>,
+}
+
+impl AliasTarget {
+    /// Parses `provider/model` or `provider`.
+    ///
+    /// Only the first `/` separates the provider, so model ids such as
+    /// `@cf/meta/llama-3.1-8b-instruct` keep theirs.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the provider is unknown or the model is empty.
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        let value = value.trim();
+        let (name, model) = match value.split_once('/') {
+            Some((name, model)) => (name, Some(model.trim())),
+            None => (value, None),
+        };
+        let provider = Provider::from_alias(name.trim()).ok_or_else(|| {
+            AppError::InvalidRequest(format!(
+                "Unknown provider '{name}' in alias target '{value}'"
+            ))
+        })?;
+        if model.is_some_and(str::is_empty) {
+            return Err(AppError::InvalidRequest(format!(
+                "Alias target '{value}' has an empty model"
+            )));
+        }
+        Ok(Self {
+            provider,
+            model: model.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for AliasTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.model {
+            Some(model) => write!(f, "{}/{model}", self.provider),
+            None => write!(f, "{}", self.provider),
+        }
+    }
+}
+
+impl Serialize for AliasTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Where an alias is defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AliasSource {
+    /// The `[aliases]` table in `config.toml`.
+    Config,
+    /// The catalog database.
+    Catalog,
+}
+
+impl AliasSource {
+    /// Returns the lowercase name of the source.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::Catalog => "catalog",
+        }
+    }
+}
+
+/// A named, ordered list of targets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelAlias {
+    /// Alias name used as the request `model`.
+    pub name: String,
+    /// Targets in the order they are tried.
+    pub targets: Vec<AliasTarget>,
+    /// Where the alias is defined.
+    pub source: AliasSource,
+}
+
+/// Checks that `name` can be used as an alias.
+///
+/// # Errors
+///
+/// Fails when the name is empty or has characters other than ASCII letters,
+/// digits, `-`, `_` and `.`.
+pub fn validate_name(name: &str) -> Result<(), AppError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+    {
+        return Err(AppError::InvalidRequest(format!(
+            "Invalid alias name '{name}'; use letters, digits, '-', '_' or '.'"
+        )));
+    }
+    Ok(())
+}
+
+/// Parses the `[aliases]` table of the configuration.
+///
+/// # Errors
+///
+/// Returns a configuration error naming the first invalid alias.
+pub fn from_config<S: BuildHasher>(
+    aliases: &HashMap<String, Vec<String>, S>,
+) -> Result<HashMap<String, Vec<AliasTarget>>, AppError> {
+    aliases
+        .iter()
+        .map(|(name, targets)| {
+            validate_name(name).map_err(|err| config_error(name, err))?;
+            if targets.is_empty() {
+                return Err(AppError::ConfigError(format!(
+                    "Alias '{name}' has no targets"
+                )));
+            }
+            let targets = targets
+                .iter()
+                .map(|target| AliasTarget::parse(target))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| config_error(name, err))?;
+            Ok((name.clone(), targets))
+        })
+        .collect()
+}
+
+/// Reports an invalid configured alias as a configuration error.
+fn config_error(name: &str, err: AppError) -> AppError {
+    match err {
+        AppError::InvalidRequest(message) => {
+            AppError::ConfigError(format!("Alias '{name}': {message}"))
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_targets_with_slashes_in_model_ids() {
+        let target = AliasTarget::parse("cloudflare/@cf/meta/llama-3.1-8b-instruct").unwrap();
+        assert_eq!(target.provider, Provider::Cloudflare);
+        assert_eq!(
+            target.model.as_deref(),
+            Some("@cf/meta/llama-3.1-8b-instruct")
+        );
+        assert_eq!(
+            target.to_string(),
+            "cloudflare/@cf/meta/llama-3.1-8b-instruct"
+        );
+
+        let target = AliasTarget::parse("gemini").unwrap();
+        assert_eq!(target.provider, Provider::Google);
+        assert_eq!(target.model, None);
+
+        assert!(AliasTarget::parse("nope/model").is_err());
+        assert!(AliasTarget::parse("groq/").is_err());
+    }
+
+    #[test]
+    fn validates_configured_aliases() {
+        let mut aliases = HashMap::new();
+        drop(aliases.insert(
+            "fast-chat".to_string(),
+            vec![
+                "groq/llama-3.1-8b-instant".to_string(),
+                "cerebras".to_string(),
+            ],
+        ));
+        let parsed = from_config(&aliases).unwrap();
+        assert_eq!(parsed["fast-chat"].len(), 2);
+
+        drop(aliases.insert("bad name".to_string(), vec!["groq".to_string()]));
+        assert!(from_config(&aliases).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Opt-in audit log of full requests and responses.
+//!
+//! `provider_usage` only keeps metrics. When `[audit] enabled = true`, the
+//! router also stores each request it routed together with the final
+//! response or error, so a bad generation can be inspected with
+//! `freegin-ai history` and re-run with `freegin-ai replay`. Logged text is
+//! passed through the PII redaction rules unless `redact = false`, and old
+//! entries are pruned according to the retention limits every
+//! [`PRUNE_INTERVAL`] writes.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sqlx::{sqlite::SqliteRow, Row};
+
+use crate::{
+    config::{AuditConfig, RedactionConfig, RedactionPolicy},
+    database::{DbError, DbPool},
+    error::AppError,
+    models::{AIRequest, AIResponse},
+    providers::Provider,
+    redaction::{RedactionMap, Redactor},
+};
+
+/// Metadata key linking a replayed request to the entry it came from.
+pub const REPLAY_OF_KEY: &str = "replay_of";
+
+/// Number of writes between retention passes. The first write of a process
+/// always prunes.
+pub const PRUNE_INTERVAL: u32 = 100;
+
+/// A logged request and its outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// Opaque entry identifier.
+    pub id: String,
+    /// Provider that answered; `None` when the request failed.
+    pub provider: Option<String>,
+    /// Model that answered.
+    pub model: Option<String>,
+    /// Whether the request succeeded.
+    pub success: bool,
+    /// Wall-clock time spent routing the request, including fallbacks.
+    pub latency_ms: i64,
+    /// The request as received by the router.
+    pub request: AIRequest,
+    /// Response content on success.
+    pub response: Option<String>,
+    /// Error message on failure.
+    pub error: Option<String>,
+    /// Identifier of the entry this request replayed, if any.
+    pub replay_of: Option<String>,
+    /// RFC 3339 timestamp.
+    pub created_at: String,
+}
+
+/// Filters for [`AuditLog::search`].
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    /// Substring matched against the logged request and response.
+    pub text: Option<String>,
+    /// Only entries answered by this provider.
+    pub provider: Option<Provider>,
+    /// Only failed entries.
+    pub failures_only: bool,
+    /// Maximum number of entries, newest first.
+    pub limit: i64,
+}
+
+/// Persistence for audit entries.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    pool: Arc<DbPool>,
+    config: AuditConfig,
+    redactor: Option<Arc<Redactor>>,
+    writes: Arc<AtomicU32>,
+}
+
+const AUDIT_COLUMNS: &str =
+    "id, provider, model, success, latency_ms, request, response, error, replay_of, created_at";
+
+impl AuditLog {
+    /// Creates an audit log. `custom_patterns` extend the built-in PII rules
+    /// used when `config.redact` is set.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a custom redaction pattern is not a valid regex.
+    pub fn new(
+        pool: Arc<DbPool>,
+        config: AuditConfig,
+        custom_patterns: &[String],
+    ) -> Result<Self, AppError> {
+        let redactor = if config.redact {
+            Redactor::from_config(&RedactionConfig {
+                enabled: true,
+                default_policy: RedactionPolicy::Always,
+                custom_patterns: custom_patterns.to_vec(),
+                providers: HashMap::new(),
+            })?
+            .map(Arc::new)
+        } else {
+            None
+        };
+        Ok(Self {
+            pool,
+            config,
+            redactor,
+            writes: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    /// Stores a routed request and its outcome, applying retention every
+    /// [`PRUNE_INTERVAL`] writes.
+    ///
+    /// Returns the new entry identifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the entry cannot be encoded, stored or pruned.
+    pub async fn record(
+        &self,
+        request: &AIRequest,
+        outcome: &Result<AIResponse, AppError>,
+        latency_ms: i64,
+    ) -> Result<String, AppError> {
+        let mut logged = request.clone();
+        let mut map = RedactionMap::default();
+        let mut scrub = |text: &str| {
+            self.redactor.as_ref().map_or_else(
+                || text.to_string(),
+                |redactor| redactor.redact(text, &mut map),
+            )
+        };
+        logged.prompt = scrub(&logged.prompt);
+        for context in &mut logged.context {
+            *context = scrub(context);
+        }
+        for turn in &mut logged.history {
+            turn.content = scrub(&turn.content);
+        }
+        let encoded =
+            serde_json::to_string(&logged).map_err(|err| AppError::ApiError(err.to_string()))?;
+
+        let (provider, model, response, error) = match outcome {
+            Ok(response) => (
+                Some(response.provider.as_str()),
+                response.model.clone(),
+                Some(scrub(&response.content)),
+                None,
+            ),
+            Err(err) => (None, None, None, Some(scrub(&err.to_string()))),
+        };
+
+        let id = format!("{:032x}", rand::random::<u128>());
+        let result = sqlx::query(
+            r"INSERT INTO audit_log
+               (id, provider, model, success, latency_ms, request, response, error, replay_of, created_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(provider)
+        .bind(model)
+        .bind(i32::from(outcome.is_ok()))
+        .bind(latency_ms)
+        .bind(encoded)
+        .bind(response)
+        .bind(error)
+        .bind(request.metadata.get(REPLAY_OF_KEY))
+        .bind(Utc::now().to_rfc3339())
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+
+        if self
+            .writes
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(PRUNE_INTERVAL)
+        {
+            let _ = self.prune().await?;
+        }
+        Ok(id)
+    }
+
+    /// Deletes entries beyond the retention age or count.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a delete fails.
+    pub async fn prune(&self) -> Result<u64, AppError> {
+        let mut removed = 0;
+        if self.config.retention_days > 0 {
+            let cutoff = Utc::now() - Duration::days(i64::from(self.config.retention_days));
+            let result = sqlx::query("DELETE FROM audit_log WHERE created_at < ?")
+                .bind(cutoff.to_rfc3339())
+                .execute(&*self.pool)
+                .await
+                .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            removed += result.rows_affected();
+        }
+        if self.config.max_entries > 0 {
+            let result = sqlx::query(
+                r"DELETE FROM audit_log WHERE id NOT IN
+                   (SELECT id FROM audit_log ORDER BY created_at DESC, rowid DESC LIMIT ?)",
+            )
+            .bind(i64::from(self.config.max_entries))
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            removed += result.rows_affected();
+        }
+        Ok(removed)
+    }
+
+    /// Loads an entry by identifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails or the stored entry cannot be decoded.
+    pub async fn get(&self, id: &str) -> Result<Option<AuditEntry>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {AUDIT_COLUMNS} FROM audit_log WHERE id = ?"
+        ))
+        .bind(id)
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        row.as_ref().map(entry_from_row).transpose()
+    }
+
+    /// Returns matching entries, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails or a stored entry cannot be decoded.
+    pub async fn search(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AppError> {
+        let mut sql = format!("SELECT {AUDIT_COLUMNS} FROM audit_log WHERE 1 = 1");
+        if query.text.is_some() {
+            sql.push_str(" AND (instr(request, ?) > 0 OR instr(COALESCE(response, ''), ?) > 0)");
+        }
+        if query.provider.is_some() {
+            sql.push_str(" AND provider = ?");
+        }
+        if query.failures_only {
+            sql.push_str(" AND success = 0");
+        }
+        sql.push_str(" ORDER BY created_at DESC, rowid DESC LIMIT ?");
+
+        let mut statement = sqlx::query(&sql);
+        if let Some(text) = &query.text {
+            statement = statement.bind(text).bind(text);
+        }
+        if let Some(provider) = query.provider {
+            statement = statement.bind(provider.as_str());
+        }
+        let rows = statement
+            .bind(query.limit)
+            .fetch_all(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        rows.iter().map(entry_from_row).collect()
+    }
+}
+
+fn entry_from_row(row: &SqliteRow) -> Result<AuditEntry, AppError> {
+    let request: String = row.get("request");
+    let success: i64 = row.get("success");
+    Ok(AuditEntry {
+        id: row.get("id"),
+        provider: row.get("provider"),
+        model: row.get("model"),
+        success: success != 0,
+        latency_ms: row.get("latency_ms"),
+        request: serde_json::from_str(&request)
+            .map_err(|err| AppError::ApiError(format!("Stored audit request is invalid: {err}")))?,
+        response: row.get("response"),
+        error: row.get("error"),
+        replay_of: row.get("replay_of"),
+        created_at: row.get("created_at"),
+    })
+}
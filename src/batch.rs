@@ -0,0 +1,305 @@
+//! Batch execution of JSONL request files.
+//!
+//! Each non-empty input line is an `AIRequest`. Requests run through a single
+//! shared router with bounded concurrency, so provider health and quota
+//! tracking apply across the whole run. Results are appended to the output
+//! file as they complete, one JSON object per line, tagged with the input
+//! line number. A resumed run keeps the successful results already in the
+//! output file and only re-runs lines that failed or never finished. A
+//! request whose worker panics is recorded as a failure like any other.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::task::{self, JoinError, JoinSet};
+use tracing::{info, warn};
+
+use crate::{error::AppError, models::AIRequest, providers::ProviderRouter};
+
+/// Default number of requests in flight at once.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Settings for a batch run.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// JSONL file with one `AIRequest` per line.
+    pub input: PathBuf,
+    /// JSONL file receiving one `BatchResult` per line.
+    pub output: PathBuf,
+    /// Maximum number of requests in flight at once.
+    pub concurrency: usize,
+    /// Keep successful results from an existing output file and skip their lines.
+    pub resume: bool,
+}
+
+/// Outcome of a single input line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// 1-based line number in the input file.
+    pub line: usize,
+    /// Caller-supplied identifier taken from `metadata.id`, if present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Provider that produced the content.
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model that produced the content.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Wall-clock time spent on the request, including fallbacks.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Generated content on success.
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Error message on failure.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Counts reported at the end of a batch run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchSummary {
+    /// Requests executed in this run.
+    pub processed: usize,
+    /// Executed requests that succeeded.
+    pub succeeded: usize,
+    /// Executed requests that failed (including unparsable lines).
+    pub failed: usize,
+    /// Lines skipped because a previous run already completed them.
+    pub skipped: usize,
+}
+
+impl BatchResult {
+    /// Returns whether the line completed successfully.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    const fn failure(line: usize, id: Option<String>, latency_ms: u64, error: String) -> Self {
+        Self {
+            line,
+            id,
+            provider: None,
+            model: None,
+            latency_ms,
+            content: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Runs every pending line of `options.input` and writes results to `options.output`.
+///
+/// # Errors
+///
+/// Fails when the input cannot be read or the output cannot be written.
+pub async fn run_batch(
+    router: Arc<ProviderRouter>,
+    options: &BatchOptions,
+) -> Result<BatchSummary, AppError> {
+    let input = fs::read_to_string(&options.input).map_err(|err| {
+        AppError::ConfigError(format!(
+            "Failed to read batch input {}: {err}",
+            options.input.display()
+        ))
+    })?;
+
+    let completed = if options.resume {
+        retain_completed(&options.output)?
+    } else {
+        HashSet::new()
+    };
+
+    let mut writer = open_output(&options.output, options.resume)?;
+    let mut summary = BatchSummary::default();
+    let concurrency = options.concurrency.max(1);
+    let mut in_flight = JoinSet::new();
+    // Input line and caller id of each running task, for reporting panics.
+    let mut tasks = HashMap::new();
+
+    for (idx, raw) in input.lines().enumerate() {
+        let line = idx + 1;
+        if raw.trim().is_empty() {
+            continue;
+        }
+        if completed.contains(&line) {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let request = match serde_json::from_str::<AIRequest>(raw) {
+            Ok(request) => request,
+            Err(err) => {
+                let result = BatchResult::failure(line, None, 0, format!("Invalid request: {err}"));
+                record(&mut writer, &result, &mut summary)?;
+                continue;
+            }
+        };
+
+        while in_flight.len() >= concurrency {
+            if let Some(joined) = in_flight.join_next_with_id().await {
+                let result = joined_result(joined, &mut tasks);
+                record(&mut writer, &result, &mut summary)?;
+            }
+        }
+
+        let router = Arc::clone(&router);
+        let id = request.metadata.get("id").cloned();
+        let task = in_flight.spawn(async move { execute(&router, line, request).await });
+        drop(tasks.insert(task.id(), (line, id)));
+    }
+
+    while let Some(joined) = in_flight.join_next_with_id().await {
+        record(
+            &mut writer,
+            &joined_result(joined, &mut tasks),
+            &mut summary,
+        )?;
+    }
+
+    info!(
+        processed = summary.processed,
+        succeeded = summary.succeeded,
+        failed = summary.failed,
+        skipped = summary.skipped,
+        "Batch run finished"
+    );
+    Ok(summary)
+}
+
+async fn execute(router: &ProviderRouter, line: usize, request: AIRequest) -> BatchResult {
+    let id = request.metadata.get("id").cloned();
+    let start = Instant::now();
+    let outcome = router.generate(&request).await;
+    let latency_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    match outcome {
+        Ok(response) => BatchResult {
+            line,
+            id,
+            provider: Some(response.provider.as_str().to_string()),
+            model: response.model,
+            latency_ms,
+            content: Some(response.content),
+            error: None,
+        },
+        Err(err) => BatchResult::failure(line, id, latency_ms, err.to_string()),
+    }
+}
+
+/// Turns a finished task into its result, or a failure for its line if the
+/// worker panicked.
+fn joined_result(
+    joined: Result<(task::Id, BatchResult), JoinError>,
+    tasks: &mut HashMap<task::Id, (usize, Option<String>)>,
+) -> BatchResult {
+    match joined {
+        Ok((task, result)) => {
+            drop(tasks.remove(&task));
+            result
+        }
+        Err(err) => {
+            let (line, id) = tasks.remove(&err.id()).unwrap_or_default();
+            BatchResult::failure(line, id, 0, format!("Batch worker failed: {err}"))
+        }
+    }
+}
+
+fn record(
+    writer: &mut BufWriter<File>,
+    result: &BatchResult,
+    summary: &mut BatchSummary,
+) -> Result<(), AppError> {
+    summary.processed += 1;
+    if result.is_success() {
+        summary.succeeded += 1;
+    } else {
+        summary.failed += 1;
+        warn!(line = result.line, error = ?result.error, "Batch request failed");
+    }
+
+    let encoded =
+        serde_json::to_string(result).map_err(|err| AppError::ApiError(err.to_string()))?;
+    // Flush per line so an interrupted run can be resumed from what is on disk.
+    writeln!(writer, "{encoded}")
+        .and_then(|()| writer.flush())
+        .map_err(|err| AppError::ConfigError(format!("Failed to write batch output: {err}")))
+}
+
+/// Rewrites the output file keeping only successful results and returns
+/// their line numbers. Failed and unparsable entries are dropped so the
+/// retried lines do not appear twice. The kept results are written to a
+/// temporary file that replaces the output, so an interrupted rewrite never
+/// loses them.
+fn retain_completed(path: &Path) -> Result<HashSet<usize>, AppError> {
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => {
+            return Err(AppError::ConfigError(format!(
+                "Failed to read batch output {}: {err}",
+                path.display()
+            )))
+        }
+    };
+
+    let mut completed = HashSet::new();
+    let mut kept = String::new();
+    for raw in existing.lines() {
+        let Ok(result) = serde_json::from_str::<BatchResult>(raw) else {
+            continue;
+        };
+        if result.is_success() && completed.insert(result.line) {
+            kept.push_str(raw);
+            kept.push('\n');
+        }
+    }
+
+    let mut staged = path.as_os_str().to_owned();
+    staged.push(".tmp");
+    let staged = PathBuf::from(staged);
+    fs::write(&staged, kept)
+        .and_then(|()| fs::rename(&staged, path))
+        .map_err(|err| {
+            AppError::ConfigError(format!(
+                "Failed to rewrite batch output {}: {err}",
+                path.display()
+            ))
+        })?;
+    Ok(completed)
+}
+
+fn open_output(path: &Path, append: bool) -> Result<BufWriter<File>, AppError> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(|err| {
+            AppError::ConfigError(format!(
+                "Failed to create output directory {}: {err}",
+                parent.display()
+            ))
+        })?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|err| {
+            AppError::ConfigError(format!(
+                "Failed to open batch output {}: {err}",
+                path.display()
+            ))
+        })?;
+    Ok(BufWriter::new(file))
+}
@@ -0,0 +1,408 @@
+//! Request cost estimates and spend caps for paid models.
+//!
+//! Prices come from the `input_price_per_mtok` and `output_price_per_mtok`
+//! capabilities of catalog models. Every successful call records its
+//! estimated tokens and cost in `provider_usage`; the `[budget]` section of
+//! `config.toml` caps that spend per UTC day and calendar month, globally and
+//! per provider. Once a cap is reached the router skips paid models and keeps
+//! using free ones. Calls to models without recorded prices cost nothing
+//! here, so caps only cover models whose prices are in the catalog.
+
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::{
+    capabilities::ModelCapabilities,
+    config::{BudgetConfig, SpendLimit},
+    context,
+    database::{DbError, DbPool},
+    error::AppError,
+    models::AIRequest,
+    providers::Provider,
+};
+
+/// Estimated tokens and cost of one call, in millionths of a US dollar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CallCost {
+    /// Tokens sent to the model.
+    pub prompt_tokens: i64,
+    /// Tokens in the reply.
+    pub completion_tokens: i64,
+    /// Cost of the prompt tokens.
+    pub input_cost_micros: i64,
+    /// Cost of the reply tokens.
+    pub output_cost_micros: i64,
+}
+
+impl CallCost {
+    /// Prices `prompt_tokens` and `completion_tokens` with the model's
+    /// recorded prices; missing prices count as free.
+    #[must_use]
+    pub fn estimate(
+        capabilities: &ModelCapabilities,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+    ) -> Self {
+        let prompt_tokens = i64::try_from(prompt_tokens).unwrap_or(i64::MAX);
+        let completion_tokens = i64::try_from(completion_tokens).unwrap_or(i64::MAX);
+        let free = capabilities.free_tier == Some(true);
+        let price = |price: Option<f64>| price.filter(|_| !free).unwrap_or(0.0);
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            input_cost_micros: micros(prompt_tokens, price(capabilities.input_price_per_mtok)),
+            output_cost_micros: micros(
+                completion_tokens,
+                price(capabilities.output_price_per_mtok),
+            ),
+        }
+    }
+
+    /// Total tokens of the call.
+    #[must_use]
+    pub const fn total_tokens(&self) -> i64 {
+        self.prompt_tokens.saturating_add(self.completion_tokens)
+    }
+
+    /// Total cost of the call.
+    #[must_use]
+    pub const fn total_micros(&self) -> i64 {
+        self.input_cost_micros
+            .saturating_add(self.output_cost_micros)
+    }
+}
+
+/// A price per million tokens in dollars is a price per token in micro-dollars.
+// Token counts and costs stay far below 2^53; `as` saturates on overflow.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn micros(tokens: i64, price_per_mtok: f64) -> i64 {
+    (tokens as f64 * price_per_mtok).round() as i64
+}
+
+/// Converts micro-dollars back to dollars.
+// Spend stays far below 2^53 micro-dollars, past which `f64` loses precision.
+#[allow(clippy::cast_precision_loss)]
+fn dollars(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+/// Returns whether calls to `model` of `provider` cost money: it has a
+/// non-zero price, or it has no prices and the provider bills every request.
+#[must_use]
+pub fn is_paid(provider: Provider, capabilities: &ModelCapabilities) -> bool {
+    if capabilities.free_tier == Some(true) {
+        return false;
+    }
+    match (
+        capabilities.input_price_per_mtok,
+        capabilities.output_price_per_mtok,
+    ) {
+        (None, None) => provider.is_pay_as_you_go(),
+        (input, output) => input.unwrap_or(0.0) > 0.0 || output.unwrap_or(0.0) > 0.0,
+    }
+}
+
+/// Estimates what `request` costs on a model, assuming a reply of
+/// [`context::RESPONSE_RESERVE`] tokens. Returns `None` for a paid model
+/// without recorded prices.
+#[must_use]
+pub fn estimate_request(
+    provider: Provider,
+    capabilities: &ModelCapabilities,
+    request: &AIRequest,
+) -> Option<i64> {
+    if !is_paid(provider, capabilities) {
+        return Some(0);
+    }
+    if capabilities.input_price_per_mtok.is_none() && capabilities.output_price_per_mtok.is_none() {
+        return None;
+    }
+    let cost = CallCost::estimate(
+        capabilities,
+        context::request_tokens(request),
+        context::RESPONSE_RESERVE,
+    );
+    Some(cost.total_micros())
+}
+
+/// Length of the window a cap applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    /// The current UTC day.
+    Daily,
+    /// The current calendar month (UTC).
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// Returns the lowercase name of the period.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// Returns when the period containing `now` started.
+    #[must_use]
+    pub fn start(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.date_naive();
+        let first = match self {
+            Self::Daily => today,
+            Self::Monthly => {
+                NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today)
+            }
+        };
+        first.and_time(chrono::NaiveTime::MIN).and_utc()
+    }
+}
+
+/// Spend against one configured cap.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BudgetUsage {
+    /// Provider the cap applies to; `None` for the global cap.
+    pub provider: Option<Provider>,
+    /// Period of the cap.
+    pub period: BudgetPeriod,
+    /// Spend so far in the period, in US dollars.
+    pub spent_usd: f64,
+    /// The cap, in US dollars.
+    pub limit_usd: f64,
+}
+
+impl BudgetUsage {
+    /// Share of the cap spent so far.
+    #[must_use]
+    pub fn share(&self) -> f64 {
+        self.spent_usd / self.limit_usd
+    }
+
+    /// Returns whether the cap has been reached.
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.spent_usd >= self.limit_usd
+    }
+
+    /// Describes the cap and its spend, e.g. `deepseek daily spend $0.45 of $0.50`.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let scope = self.provider.map_or("total", |provider| provider.as_str());
+        format!(
+            "{scope} {} spend ${:.2} of ${:.2}",
+            self.period.as_str(),
+            self.spent_usd,
+            self.limit_usd
+        )
+    }
+}
+
+/// Configured spend caps, checked against the costs in `provider_usage`.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pool: Arc<DbPool>,
+    global: SpendLimit,
+    providers: HashMap<Provider, SpendLimit>,
+    warn_at: f64,
+}
+
+impl Budget {
+    /// Builds the caps from the `[budget]` configuration section.
+    ///
+    /// Returns `None` when no cap is configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error for an unknown provider, a cap that is not
+    /// a positive number or a `warn_at` outside 0-1.
+    pub fn from_config(pool: Arc<DbPool>, config: &BudgetConfig) -> Result<Option<Self>, AppError> {
+        if !(0.0..=1.0).contains(&config.warn_at) {
+            return Err(AppError::ConfigError(
+                "budget.warn_at must be between 0 and 1".into(),
+            ));
+        }
+        let global = SpendLimit {
+            daily_usd: config.daily_usd,
+            monthly_usd: config.monthly_usd,
+        };
+        validate_limit("budget", global)?;
+        let mut providers = HashMap::new();
+        for (name, limit) in &config.providers {
+            let provider = Provider::from_alias(name).ok_or_else(|| {
+                AppError::ConfigError(format!("Unknown provider '{name}' in budget"))
+            })?;
+            validate_limit(&format!("budget.providers.{name}"), *limit)?;
+            let _ = providers.insert(provider, *limit);
+        }
+        if global == SpendLimit::default()
+            && providers
+                .values()
+                .all(|limit| *limit == SpendLimit::default())
+        {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            pool,
+            global,
+            providers,
+            warn_at: config.warn_at,
+        }))
+    }
+
+    /// Share of a cap at which warnings start.
+    #[must_use]
+    pub const fn warn_at(&self) -> f64 {
+        self.warn_at
+    }
+
+    /// Returns the spend against every configured cap.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the usage log cannot be read.
+    pub async fn usage(&self, now: DateTime<Utc>) -> Result<Vec<BudgetUsage>, AppError> {
+        let mut scopes: Vec<(Option<Provider>, SpendLimit)> = vec![(None, self.global)];
+        let mut providers: Vec<_> = self.providers.iter().collect();
+        providers.sort_by_key(|(provider, _)| provider.as_str());
+        scopes.extend(
+            providers
+                .into_iter()
+                .map(|(provider, limit)| (Some(*provider), *limit)),
+        );
+
+        let mut usage = Vec::new();
+        for (provider, limit) in scopes {
+            for (period, cap) in [
+                (BudgetPeriod::Daily, limit.daily_usd),
+                (BudgetPeriod::Monthly, limit.monthly_usd),
+            ] {
+                if let Some(limit_usd) = cap {
+                    usage.push(self.usage_of(provider, period, limit_usd, now).await?);
+                }
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Returns the first global or `provider` cap that has been reached.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the usage log cannot be read.
+    pub async fn exhausted(
+        &self,
+        provider: Provider,
+        now: DateTime<Utc>,
+    ) -> Result<Option<BudgetUsage>, AppError> {
+        let own = self.providers.get(&provider).copied().unwrap_or_default();
+        for (scope, limit) in [(None, self.global), (Some(provider), own)] {
+            for (period, cap) in [
+                (BudgetPeriod::Daily, limit.daily_usd),
+                (BudgetPeriod::Monthly, limit.monthly_usd),
+            ] {
+                if let Some(limit_usd) = cap {
+                    let usage = self.usage_of(scope, period, limit_usd, now).await?;
+                    if usage.is_exhausted() {
+                        return Ok(Some(usage));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn usage_of(
+        &self,
+        provider: Option<Provider>,
+        period: BudgetPeriod,
+        limit_usd: f64,
+        now: DateTime<Utc>,
+    ) -> Result<BudgetUsage, AppError> {
+        let micros = self.spent_micros(provider, period.start(now)).await?;
+        Ok(BudgetUsage {
+            provider,
+            period,
+            spent_usd: dollars(micros),
+            limit_usd,
+        })
+    }
+
+    /// Sums recorded costs since `since`, for one provider or all of them.
+    async fn spent_micros(
+        &self,
+        provider: Option<Provider>,
+        since: DateTime<Utc>,
+    ) -> Result<i64, AppError> {
+        let spent: i64 = sqlx::query_scalar(
+            r"SELECT COALESCE(SUM(total_cost_micros), 0) FROM provider_usage
+               WHERE created_at >= ? AND (? IS NULL OR provider = ?)",
+        )
+        .bind(since.to_rfc3339())
+        .bind(provider.map(|provider| provider.as_str()))
+        .bind(provider.map(|provider| provider.as_str()))
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        Ok(spent)
+    }
+}
+
+fn validate_limit(section: &str, limit: SpendLimit) -> Result<(), AppError> {
+    for (key, cap) in [
+        ("daily_usd", limit.daily_usd),
+        ("monthly_usd", limit.monthly_usd),
+    ] {
+        if cap.is_some_and(|cap| !cap.is_finite() || cap <= 0.0) {
+            return Err(AppError::ConfigError(format!(
+                "{section}.{key} must be a positive number"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_calls_and_classifies_paid_models() {
+        let priced = ModelCapabilities {
+            input_price_per_mtok: Some(0.27),
+            output_price_per_mtok: Some(1.10),
+            ..ModelCapabilities::default()
+        };
+        let cost = CallCost::estimate(&priced, 1000, 500);
+        assert_eq!(cost.input_cost_micros, 270);
+        assert_eq!(cost.output_cost_micros, 550);
+        assert_eq!(cost.total_micros(), 820);
+        assert!(is_paid(Provider::Groq, &priced));
+
+        let unpriced = ModelCapabilities::default();
+        assert!(is_paid(Provider::DeepSeek, &unpriced));
+        assert!(!is_paid(Provider::Groq, &unpriced));
+
+        let free = ModelCapabilities {
+            free_tier: Some(true),
+            ..priced
+        };
+        assert!(!is_paid(Provider::Together, &free));
+        assert_eq!(CallCost::estimate(&free, 1000, 500).total_micros(), 0);
+    }
+
+    #[test]
+    fn periods_start_at_midnight_utc() {
+        let now = "2026-03-17T15:04:05Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            BudgetPeriod::Daily.start(now).to_rfc3339(),
+            "2026-03-17T00:00:00+00:00"
+        );
+        assert_eq!(
+            BudgetPeriod::Monthly.start(now).to_rfc3339(),
+            "2026-03-01T00:00:00+00:00"
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//! Typed model capabilities stored in catalog metadata.
+//!
+//! Each catalog entry carries a JSON metadata object. The keys below have a
+//! fixed meaning and type; other keys (such as `safety_tuned` or the
+//! discovery marker `unavailable_since`) are kept as they are. Metadata is
+//! validated when a model is adopted or imported, shown by `list-models`,
+//! and the router skips models that explicitly lack something a request
+//! needs. A capability that is not recorded is treated as unknown, not as
+//! missing.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    context,
+    error::AppError,
+    models::{AIRequest, ChatRole, RequestComplexity},
+};
+
+/// Capabilities of one model, read from its catalog metadata.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    /// Context window in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u64>,
+    /// Longest reply the model produces, in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    /// Whether the model supports tool (function) calling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<bool>,
+    /// Whether the model supports a native JSON output mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<bool>,
+    /// Whether the model accepts image input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+    /// Whether the model can stream its reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming: Option<bool>,
+    /// Whether the model honours a system prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<bool>,
+    /// Price per million input tokens, in US dollars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_price_per_mtok: Option<f64>,
+    /// Price per million output tokens, in US dollars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_price_per_mtok: Option<f64>,
+    /// Whether the model is usable on the provider's free tier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_tier: Option<bool>,
+    /// Date the provider retires the model (`YYYY-MM-DD`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_date: Option<NaiveDate>,
+    /// Size class of the model, matched against request complexity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<ModelTier>,
+    /// Other metadata keys, kept verbatim.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ModelCapabilities {
+    /// Parses and validates catalog metadata.
+    ///
+    /// Capability keys must have the documented type and sensible values;
+    /// unknown keys are accepted as-is.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the metadata is not a JSON object or a capability key has the
+    /// wrong type or an invalid value.
+    pub fn parse(metadata: &str) -> Result<Self, AppError> {
+        let value: serde_json::Value = serde_json::from_str(metadata)
+            .map_err(|err| AppError::InvalidRequest(format!("Invalid metadata JSON: {err}")))?;
+        if !value.is_object() {
+            return Err(AppError::InvalidRequest(
+                "Model metadata must be a JSON object".into(),
+            ));
+        }
+        let capabilities: Self = serde_json::from_value(value)
+            .map_err(|err| AppError::InvalidRequest(format!("Invalid model metadata: {err}")))?;
+        capabilities.validate()?;
+        Ok(capabilities)
+    }
+
+    /// Reads capabilities from stored metadata, ignoring metadata that does
+    /// not parse (entries written before validation existed).
+    pub fn from_metadata(metadata: Option<&str>) -> Self {
+        metadata
+            .map(str::trim)
+            .filter(|raw| !raw.is_empty())
+            .and_then(|raw| serde_json::from_str(raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        if self.context_window == Some(0) {
+            return Err(AppError::InvalidRequest(
+                "context_window must be greater than zero".into(),
+            ));
+        }
+        if self.max_output_tokens == Some(0) {
+            return Err(AppError::InvalidRequest(
+                "max_output_tokens must be greater than zero".into(),
+            ));
+        }
+        if let (Some(output), Some(window)) = (self.max_output_tokens, self.context_window) {
+            if output > window {
+                return Err(AppError::InvalidRequest(format!(
+                    "max_output_tokens ({output}) exceeds context_window ({window})"
+                )));
+            }
+        }
+        for (key, price) in [
+            ("input_price_per_mtok", self.input_price_per_mtok),
+            ("output_price_per_mtok", self.output_price_per_mtok),
+        ] {
+            if price.is_some_and(|price| !price.is_finite() || price < 0.0) {
+                return Err(AppError::InvalidRequest(format!(
+                    "{key} must be a non-negative number"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the deprecation date has been reached on `today`.
+    #[must_use]
+    pub fn is_deprecated(&self, today: NaiveDate) -> bool {
+        self.deprecation_date.is_some_and(|date| date <= today)
+    }
+
+    /// Explains why the model cannot serve a request with `needs`, or
+    /// returns `None` when nothing rules it out.
+    #[must_use]
+    pub fn unmet(&self, needs: &Requirements, today: NaiveDate) -> Option<Unmet> {
+        if let Some(date) = self.deprecation_date.filter(|_| self.is_deprecated(today)) {
+            return Some(Unmet::Deprecated(date));
+        }
+        if let Some(window) = self.context_window {
+            if usize::try_from(window).is_ok_and(|window| window < needs.context_tokens) {
+                return Some(Unmet::ContextWindow {
+                    needed: needs.context_tokens,
+                    window,
+                });
+            }
+        }
+        [
+            (needs.json, self.json_mode, "JSON mode"),
+            (needs.vision, self.vision, "image input"),
+            (needs.system_prompt, self.system_prompt, "system prompts"),
+        ]
+        .into_iter()
+        .find(|(needed, supported, _)| *needed && *supported == Some(false))
+        .map(|(_, _, name)| Unmet::Missing(name))
+    }
+
+    /// Short human-readable summary, e.g. `128K ctx, 8K out, json, vision`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(window) = self.context_window {
+            parts.push(format!("{} ctx", tokens_label(window)));
+        }
+        if let Some(output) = self.max_output_tokens {
+            parts.push(format!("{} out", tokens_label(output)));
+        }
+        for (flag, name) in [
+            (self.tools, "tools"),
+            (self.json_mode, "json"),
+            (self.vision, "vision"),
+            (self.streaming, "streaming"),
+            (self.system_prompt, "system"),
+        ] {
+            match flag {
+                Some(true) => parts.push(name.to_string()),
+                Some(false) => parts.push(format!("no {name}")),
+                None => {}
+            }
+        }
+        match (self.input_price_per_mtok, self.output_price_per_mtok) {
+            (None, None) => {}
+            (input, output) => parts.push(format!(
+                "${}/${} per Mtok",
+                price_label(input),
+                price_label(output)
+            )),
+        }
+        if let Some(tier) = self.tier {
+            parts.push(format!("{} tier", tier.as_str()));
+        }
+        if self.free_tier == Some(true) {
+            parts.push("free tier".into());
+        }
+        if let Some(date) = self.deprecation_date {
+            parts.push(format!("deprecated {date}"));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Size class of a model within a provider's catalog, e.g. an 8B model is
+/// `small` and a 70B+ model is `large`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelTier {
+    /// Small, fast models with generous quotas.
+    Small,
+    /// Mid-sized models.
+    Medium,
+    /// The largest, most capable models.
+    Large,
+}
+
+impl ModelTier {
+    /// Returns the tier suited to a request of `complexity`.
+    #[must_use]
+    pub const fn for_complexity(complexity: RequestComplexity) -> Self {
+        match complexity {
+            RequestComplexity::Low => Self::Small,
+            RequestComplexity::Medium => Self::Medium,
+            RequestComplexity::High => Self::Large,
+        }
+    }
+
+    /// Returns the tier name as stored in metadata.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+
+    /// Number of steps between two tiers.
+    #[must_use]
+    pub const fn distance(self, other: Self) -> u8 {
+        (self as u8).abs_diff(other as u8)
+    }
+}
+
+/// Why a model cannot serve a request, from [`ModelCapabilities::unmet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unmet {
+    /// The model's deprecation date has passed.
+    Deprecated(NaiveDate),
+    /// The request needs more tokens than the context window holds.
+    ContextWindow {
+        /// Estimated tokens the request needs.
+        needed: usize,
+        /// The model's context window.
+        window: u64,
+    },
+    /// The model lacks a capability the request needs.
+    Missing(&'static str),
+}
+
+impl fmt::Display for Unmet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deprecated(date) => write!(f, "deprecated since {date}"),
+            Self::ContextWindow { needed, window } => {
+                write!(f, "needs about {needed} tokens, context window is {window}")
+            }
+            Self::Missing(name) => write!(f, "does not support {name}"),
+        }
+    }
+}
+
+/// What a request needs from a model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Requirements {
+    /// Minimum context window, from [`context::required_window`].
+    pub context_tokens: usize,
+    /// The caller requires JSON output.
+    pub json: bool,
+    /// The request carries images.
+    pub vision: bool,
+    /// The conversation has a system turn.
+    pub system_prompt: bool,
+}
+
+impl Requirements {
+    /// Derives the requirements of `request`.
+    #[must_use]
+    pub fn of(request: &AIRequest) -> Self {
+        Self {
+            context_tokens: context::required_window(request),
+            json: request.wants_json(),
+            vision: !request.images.is_empty(),
+            system_prompt: request
+                .history
+                .iter()
+                .any(|turn| turn.role == ChatRole::System),
+        }
+    }
+}
+
+fn tokens_label(tokens: u64) -> String {
+    if tokens >= 1024 && tokens.is_multiple_of(1024) {
+        format!("{}K", tokens / 1024)
+    } else if tokens >= 1000 && tokens.is_multiple_of(1000) {
+        format!("{}K", tokens / 1000)
+    } else {
+        tokens.to_string()
+    }
+}
+
+fn price_label(price: Option<f64>) -> String {
+    price.map_or_else(|| "?".to_string(), |price| format!("{price:.2}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_validates_metadata() {
+        let capabilities = ModelCapabilities::parse(
+            r#"{"context_window":131072,"max_output_tokens":8192,"json_mode":true,
+                "vision":false,"input_price_per_mtok":0.59,"deprecation_date":"2026-01-15",
+                "tier":"large","safety_tuned":true}"#,
+        )
+        .unwrap();
+        assert_eq!(capabilities.context_window, Some(131_072));
+        assert_eq!(capabilities.extra["safety_tuned"], serde_json::json!(true));
+        assert_eq!(
+            capabilities.summary(),
+            "128K ctx, 8K out, json, no vision, $0.59/$? per Mtok, large tier, deprecated 2026-01-15"
+        );
+
+        for invalid in [
+            "[1]",
+            r#"{"vision":"yes"}"#,
+            r#"{"context_window":-5}"#,
+            r#"{"context_window":4096,"max_output_tokens":8192}"#,
+            r#"{"input_price_per_mtok":-1}"#,
+            r#"{"deprecation_date":"next year"}"#,
+            r#"{"tier":"huge"}"#,
+        ] {
+            assert!(ModelCapabilities::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn unmet_requirements_are_reported() {
+        let today = NaiveDate::from_ymd_opt(2026, 6, 1).unwrap();
+        let capabilities = ModelCapabilities {
+            context_window: Some(8192),
+            vision: Some(false),
+            ..ModelCapabilities::default()
+        };
+        let needs = Requirements {
+            context_tokens: 2000,
+            json: true,
+            ..Requirements::default()
+        };
+        // JSON mode is unknown, so the model stays eligible.
+        assert_eq!(capabilities.unmet(&needs, today), None);
+
+        let images = Requirements {
+            vision: true,
+            ..needs
+        };
+        let unmet = capabilities.unmet(&images, today);
+        assert_eq!(unmet, Some(Unmet::Missing("image input")));
+        assert_eq!(
+            unmet.map(|unmet| unmet.to_string()).as_deref(),
+            Some("does not support image input")
+        );
+
+        let long = Requirements {
+            context_tokens: 9000,
+            ..needs
+        };
+        assert_eq!(
+            capabilities.unmet(&long, today),
+            Some(Unmet::ContextWindow {
+                needed: 9000,
+                window: 8192
+            })
+        );
+
+        let retired = ModelCapabilities {
+            deprecation_date: NaiveDate::from_ymd_opt(2026, 5, 1),
+            ..capabilities
+        };
+        assert!(matches!(
+            retired.unmet(&needs, today),
+            Some(Unmet::Deprecated(_))
+        ));
+    }
+}
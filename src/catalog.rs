@@ -1,18 +1,26 @@
 //! Model catalog management for providers and workloads.
 
-use std::sync::Arc;
+use std::{collections::HashSet, fmt::Write as _, sync::Arc};
 
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::Row;
+use sqlx::{sqlite::SqliteRow, Row};
 
 use crate::{
+    aliases::{self, AliasSource, AliasTarget, ModelAlias},
+    capabilities::ModelCapabilities,
     database::{DbError, DbPool},
+    deprecation,
     error::AppError,
     models::Workload,
-    providers::Provider,
+    providers::{AvailableModel, Provider},
+    roster::{Roster, RosterEntry},
 };
 
+/// Priority points between a model that scored 0 and one that scored 1 in
+/// the offline evaluation (`freegin-ai eval`).
+pub const EVAL_PRIORITY_WEIGHT: i64 = 20;
+
 /// Store for managing model catalog entries and suggestions.
 #[derive(Clone, Debug)]
 pub struct CatalogStore {
@@ -86,8 +94,8 @@ impl CatalogStore {
         workload: Option<Workload>,
     ) -> Result<Vec<SuggestionEntry>, AppError> {
         let mut query = String::from(
-            "SELECT id, provider, workload, model, status, rationale, metadata, created_at, updated_at \
-             FROM provider_model_suggestions",
+            "SELECT id, provider, workload, model, status, rationale, metadata, trial_share, \
+             created_at, updated_at FROM provider_model_suggestions",
         );
         let mut filters = Vec::new();
         let mut args: Vec<String> = Vec::new();
@@ -115,21 +123,95 @@ impl CatalogStore {
             .await
             .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
 
-        Ok(rows
-            .into_iter()
-            .map(|row| SuggestionEntry {
-                id: row.get("id"),
-                provider: Provider::from_alias(row.get::<String, _>("provider").as_str())
-                    .unwrap_or(Provider::HuggingFace),
-                workload: workload_from_key(row.get::<String, _>("workload").as_str()),
-                model: row.get("model"),
-                status: row.get("status"),
-                rationale: row.get("rationale"),
-                metadata: row.get("metadata"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            })
-            .collect())
+        Ok(rows.iter().map(suggestion_from_row).collect())
+    }
+
+    /// Returns the suggestions on trial for a workload.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn trials(&self, workload: Workload) -> Result<Vec<SuggestionEntry>, AppError> {
+        let rows = sqlx::query(
+            "SELECT id, provider, workload, model, status, rationale, metadata, trial_share, \
+             created_at, updated_at FROM provider_model_suggestions \
+             WHERE status = 'trial' AND workload = ?",
+        )
+        .bind(workload_key(workload))
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(rows.iter().map(suggestion_from_row).collect())
+    }
+
+    /// Puts a model on trial: it receives `share` (0.0-1.0) of matching
+    /// requests as shadow traffic. Creates the suggestion if needed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `share` is outside 0.0-1.0 or the upsert fails.
+    pub async fn start_trial(
+        &self,
+        provider: Provider,
+        workload: Workload,
+        model: &str,
+        share: f64,
+    ) -> Result<(), AppError> {
+        if !(0.0..=1.0).contains(&share) {
+            return Err(AppError::InvalidRequest(format!(
+                "Trial share must be between 0 and 1, got {share}"
+            )));
+        }
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r"INSERT INTO provider_model_suggestions
+               (provider, workload, model, status, trial_share, created_at, updated_at)
+               VALUES (?, ?, ?, 'trial', ?, ?, ?)
+               ON CONFLICT(provider, workload, model) DO UPDATE SET
+                   status = 'trial',
+                   trial_share = excluded.trial_share,
+                   updated_at = excluded.updated_at",
+        )
+        .bind(provider.as_str())
+        .bind(workload_key(workload))
+        .bind(model)
+        .bind(share)
+        .bind(&now)
+        .bind(&now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Ends a trial, returning the suggestion to `pending`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn end_trial(
+        &self,
+        provider: Provider,
+        workload: Workload,
+        model: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r"UPDATE provider_model_suggestions
+               SET status = 'pending', trial_share = NULL, updated_at = ?
+             WHERE provider = ? AND workload = ? AND model = ? AND status = 'trial'",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(provider.as_str())
+        .bind(workload_key(workload))
+        .bind(model)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(result.rows_affected() > 0)
     }
 
     /// Inserts or updates a suggestion for a given provider, workload, and model.
@@ -170,6 +252,9 @@ impl CatalogStore {
     }
 
     /// Adopts a model (suggestion or new) into the active roster for a provider/workload.
+    ///
+    /// `metadata` must be a JSON object whose capability keys follow the
+    /// schema in [`ModelCapabilities`].
     pub async fn adopt_model(
         &self,
         provider: Provider,
@@ -179,6 +264,9 @@ impl CatalogStore {
         metadata: Option<String>,
         priority: i64,
     ) -> Result<(), AppError> {
+        if let Some(raw) = &metadata {
+            drop(ModelCapabilities::parse(raw)?);
+        }
         let now = Utc::now().to_rfc3339();
         let result = sqlx::query(
             r#"INSERT INTO provider_models
@@ -207,13 +295,13 @@ impl CatalogStore {
 
         let result = sqlx::query(
             r#"UPDATE provider_model_suggestions
-               SET status = 'adopted', updated_at = ?
+               SET status = 'adopted', trial_share = NULL, updated_at = ?
              WHERE provider = ? AND workload = ? AND model = ?"#,
         )
         .bind(&now)
         .bind(provider.as_str())
         .bind(workload_key(workload))
-        .bind(model)
+        .bind(&model)
         .execute(&*self.pool)
         .await
         .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
@@ -246,20 +334,377 @@ impl CatalogStore {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Retires `model` on every workload once its last `threshold` calls in
+    /// `provider_usage` all failed with a "model not found" error. The reason
+    /// is kept in the entry's metadata. Returns the entries that were retired.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the usage log cannot be read or the catalog cannot be
+    /// updated.
+    pub async fn retire_if_gone(
+        &self,
+        provider: Provider,
+        model: &str,
+        threshold: u32,
+    ) -> Result<Vec<ModelEntry>, AppError> {
+        if threshold == 0 {
+            return Ok(Vec::new());
+        }
+        let rows = sqlx::query(
+            r"SELECT success, error_message FROM provider_usage
+             WHERE provider = ? AND model = ?
+             ORDER BY id DESC LIMIT ?",
+        )
+        .bind(provider.as_str())
+        .bind(model)
+        .bind(i64::from(threshold))
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let errors: Vec<String> = rows
+            .iter()
+            .filter(|row| row.get::<i64, _>("success") == 0)
+            .filter_map(|row| row.get::<Option<String>, _>("error_message"))
+            .filter(|error| deprecation::is_model_gone(error))
+            .collect();
+        let enough = u32::try_from(rows.len()).is_ok_and(|count| count == threshold);
+        if !enough || errors.len() < rows.len() {
+            return Ok(Vec::new());
+        }
+
+        let reason = format!(
+            "{threshold} consecutive model-not-found errors (last: {})",
+            errors[0]
+        );
+        let now = Utc::now().to_rfc3339();
+        let mut retired = Vec::new();
+        for entry in self.list_models(Some(provider), None).await? {
+            if entry.model != model || entry.status != "active" {
+                continue;
+            }
+            let mut metadata = entry.metadata_object().unwrap_or_default();
+            drop(metadata.insert(
+                deprecation::RETIRED_REASON_KEY.into(),
+                reason.clone().into(),
+            ));
+            let result = sqlx::query(
+                r"UPDATE provider_models SET status = 'retired', metadata = ?, updated_at = ?
+                 WHERE provider = ? AND workload = ? AND model = ?",
+            )
+            .bind(serde_json::Value::Object(metadata).to_string())
+            .bind(&now)
+            .bind(provider.as_str())
+            .bind(workload_key(entry.workload))
+            .bind(model)
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            let _ = result.rows_affected();
+            retired.push(entry);
+        }
+        Ok(retired)
+    }
+
+    /// Replaces the provider's list of available models with a fresh listing
+    /// from its API and reconciles the catalog against it.
+    ///
+    /// Catalog entries that are still listed get the advertised context
+    /// window and prices merged into their metadata. Entries that are no
+    /// longer listed are flagged with `unavailable_since` in their metadata;
+    /// the flag is cleared once the model is listed again.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the listing or the catalog cannot be updated.
+    pub async fn record_available_models(
+        &self,
+        provider: Provider,
+        models: &[AvailableModel],
+    ) -> Result<AvailabilityChanges, AppError> {
+        let now = Utc::now().to_rfc3339();
+        let previous: HashSet<String> = self
+            .available_models(provider)
+            .await?
+            .into_iter()
+            .map(|model| model.id)
+            .collect();
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let result = sqlx::query("DELETE FROM provider_available_models WHERE provider = ?")
+            .bind(provider.as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        for model in models {
+            let result = sqlx::query(
+                r"INSERT OR REPLACE INTO provider_available_models
+                   (provider, model, context_window, input_price_per_mtok, output_price_per_mtok,
+                    discovered_at)
+                   VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(provider.as_str())
+            .bind(&model.id)
+            .bind(
+                model
+                    .context_window
+                    .and_then(|tokens| i64::try_from(tokens).ok()),
+            )
+            .bind(model.input_price_per_mtok)
+            .bind(model.output_price_per_mtok)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            let _ = result.rows_affected();
+        }
+        tx.commit()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        // On the first discovery every model would be "new"; only report
+        // additions relative to an earlier listing.
+        let mut changes = AvailabilityChanges {
+            new_models: models
+                .iter()
+                .filter(|model| !previous.is_empty() && !previous.contains(&model.id))
+                .map(|model| model.id.clone())
+                .collect(),
+            ..AvailabilityChanges::default()
+        };
+        for entry in self.list_models(Some(provider), None).await? {
+            let listed = models.iter().find(|model| model.id == entry.model);
+            if listed.is_none() && entry.status == "active" {
+                changes.missing.push(entry.clone());
+            }
+            let Some(mut metadata) = entry.metadata_object() else {
+                continue;
+            };
+            if let Some(model) = listed {
+                if let Some(tokens) = model.context_window {
+                    drop(metadata.insert("context_window".into(), tokens.into()));
+                }
+                if let Some(price) = model.input_price_per_mtok {
+                    drop(metadata.insert("input_price_per_mtok".into(), price.into()));
+                }
+                if let Some(price) = model.output_price_per_mtok {
+                    drop(metadata.insert("output_price_per_mtok".into(), price.into()));
+                }
+                drop(metadata.remove("unavailable_since"));
+            } else if !metadata.contains_key("unavailable_since") {
+                drop(metadata.insert("unavailable_since".into(), now.clone().into()));
+            }
+
+            let updated = serde_json::Value::Object(metadata).to_string();
+            if entry.metadata.as_deref() == Some(updated.as_str()) {
+                continue;
+            }
+            let result = sqlx::query(
+                r"UPDATE provider_models SET metadata = ?, updated_at = ?
+                 WHERE provider = ? AND workload = ? AND model = ?",
+            )
+            .bind(&updated)
+            .bind(&now)
+            .bind(provider.as_str())
+            .bind(workload_key(entry.workload))
+            .bind(&entry.model)
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            let _ = result.rows_affected();
+            if listed.is_some() {
+                changes.updated += 1;
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Returns the models last listed by the provider's API.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn available_models(
+        &self,
+        provider: Provider,
+    ) -> Result<Vec<AvailableModel>, AppError> {
+        let rows = sqlx::query(
+            "SELECT model, context_window, input_price_per_mtok, output_price_per_mtok \
+             FROM provider_available_models WHERE provider = ? ORDER BY model",
+        )
+        .bind(provider.as_str())
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AvailableModel {
+                id: row.get("model"),
+                context_window: row
+                    .get::<Option<i64>, _>("context_window")
+                    .and_then(|tokens| u64::try_from(tokens).ok()),
+                input_price_per_mtok: row.get("input_price_per_mtok"),
+                output_price_per_mtok: row.get("output_price_per_mtok"),
+            })
+            .collect())
+    }
+
+    /// Defines or replaces a catalog alias with `targets`, tried in order.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the name is invalid, `targets` is empty or the transaction
+    /// fails.
+    pub async fn set_alias(&self, name: &str, targets: &[AliasTarget]) -> Result<(), AppError> {
+        aliases::validate_name(name)?;
+        if targets.is_empty() {
+            return Err(AppError::InvalidRequest(format!(
+                "Alias '{name}' needs at least one target"
+            )));
+        }
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let result = sqlx::query("DELETE FROM model_aliases WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        for (position, target) in (0_i64..).zip(targets) {
+            let result = sqlx::query(
+                r"INSERT INTO model_aliases (name, position, provider, model, updated_at)
+                   VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(name)
+            .bind(position)
+            .bind(target.provider.as_str())
+            .bind(&target.model)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            let _ = result.rows_affected();
+        }
+        tx.commit()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))
+    }
+
+    /// Removes a catalog alias. Returns whether it existed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the delete fails.
+    pub async fn remove_alias(&self, name: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM model_aliases WHERE name = ?")
+            .bind(name)
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns the catalog aliases ordered by name.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn aliases(&self) -> Result<Vec<ModelAlias>, AppError> {
+        let rows =
+            sqlx::query("SELECT name, provider, model FROM model_aliases ORDER BY name, position")
+                .fetch_all(&*self.pool)
+                .await
+                .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let mut aliases: Vec<ModelAlias> = Vec::new();
+        for row in &rows {
+            let name: String = row.get("name");
+            let provider: String = row.get("provider");
+            let Some(provider) = Provider::from_alias(&provider) else {
+                continue;
+            };
+            let target = AliasTarget {
+                provider,
+                model: row.get("model"),
+            };
+            match aliases.last_mut() {
+                Some(alias) if alias.name == name => alias.targets.push(target),
+                _ => aliases.push(ModelAlias {
+                    name,
+                    targets: vec![target],
+                    source: AliasSource::Catalog,
+                }),
+            }
+        }
+        Ok(aliases)
+    }
+
+    /// Returns the targets of a catalog alias, if it is defined.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn alias(&self, name: &str) -> Result<Option<Vec<AliasTarget>>, AppError> {
+        let rows = sqlx::query(
+            "SELECT provider, model FROM model_aliases WHERE name = ? ORDER BY position",
+        )
+        .bind(name)
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let targets: Vec<AliasTarget> = rows
+            .iter()
+            .filter_map(|row| {
+                let provider: String = row.get("provider");
+                Some(AliasTarget {
+                    provider: Provider::from_alias(&provider)?,
+                    model: row.get("model"),
+                })
+            })
+            .collect();
+        Ok((!targets.is_empty()).then_some(targets))
+    }
+
     /// Returns all active models for a provider, optionally filtered by workload.
+    ///
+    /// Models are ordered by priority, adjusted by their evaluation score
+    /// (see [`EVAL_PRIORITY_WEIGHT`]).
     pub async fn active_models(
         &self,
         provider: Provider,
         workload: Option<Workload>,
     ) -> Result<Vec<ModelEntry>, AppError> {
         let mut query = String::from(
-            "SELECT provider, workload, model, status, priority, rationale, metadata, created_at, updated_at \
-             FROM provider_models WHERE status = 'active' AND provider = ?",
+            "SELECT m.provider, m.workload, m.model, m.status, m.priority, m.rationale, m.metadata, \
+             m.created_at, m.updated_at \
+             FROM provider_models m \
+             LEFT JOIN eval_scores e \
+             ON e.provider = m.provider AND e.workload = m.workload AND e.model = m.model \
+             WHERE m.status = 'active' AND m.provider = ?",
         );
         if workload.is_some() {
-            query.push_str(" AND workload = ?");
+            query.push_str(" AND m.workload = ?");
         }
-        query.push_str(" ORDER BY priority ASC, updated_at DESC");
+        // Unscored models take the neutral midpoint so measured results can
+        // move a model up or down without overriding configured priorities.
+        let _ = write!(
+            query,
+            " ORDER BY m.priority + COALESCE(ROUND((1.0 - e.score) * {EVAL_PRIORITY_WEIGHT}), {}) ASC, \
+             m.updated_at DESC",
+            EVAL_PRIORITY_WEIGHT / 2
+        );
 
         let mut sql = sqlx::query(&query).bind(provider.as_str());
         if let Some(w) = workload {
@@ -288,246 +733,54 @@ impl CatalogStore {
     }
 
     /// Seeds default models if none exist for a provider/workload combination.
+    ///
+    /// The defaults are the compiled-in roster ([`crate::roster::DEFAULT_ROSTER`]).
     pub async fn seed_defaults(&self) -> Result<(), AppError> {
-        // Default models per provider/workload
-        let defaults = vec![
-            // Groq defaults (ultra-fast inference)
-            (
-                Provider::Groq,
-                Workload::Chat,
-                "llama-3.3-70b-versatile",
-                10,
-                "Fast, versatile Llama model",
-            ),
-            (
-                Provider::Groq,
-                Workload::Code,
-                "llama-3.3-70b-versatile",
-                10,
-                "Versatile model suitable for code",
-            ),
-            (
-                Provider::Groq,
-                Workload::Summarization,
-                "llama-3.3-70b-versatile",
-                20,
-                "Fast summarization",
-            ),
-            (
-                Provider::Groq,
-                Workload::Creative,
-                "llama-3.3-70b-versatile",
-                15,
-                "Creative and versatile",
-            ),
-            // DeepSeek defaults (pay-as-you-go, very low cost)
-            (
-                Provider::DeepSeek,
-                Workload::Chat,
-                "deepseek-chat",
-                20,
-                "Powerful reasoning and chat",
-            ),
-            (
-                Provider::DeepSeek,
-                Workload::Code,
-                "deepseek-chat",
-                15,
-                "Strong coding capabilities",
-            ),
-            (
-                Provider::DeepSeek,
-                Workload::Summarization,
-                "deepseek-chat",
-                25,
-                "Effective summarization",
-            ),
-            (
-                Provider::DeepSeek,
-                Workload::Extraction,
-                "deepseek-chat",
-                20,
-                "Information extraction",
-            ),
-            (
-                Provider::DeepSeek,
-                Workload::Creative,
-                "deepseek-chat",
-                25,
-                "Creative writing",
-            ),
-            (
-                Provider::DeepSeek,
-                Workload::Classification,
-                "deepseek-chat",
-                25,
-                "Text classification",
-            ),
-            // Together AI defaults
-            (
-                Provider::Together,
-                Workload::Chat,
-                "meta-llama/Llama-3.3-70B-Instruct-Turbo-Free",
-                30,
-                "Free Llama model",
-            ),
-            (
-                Provider::Together,
-                Workload::Code,
-                "meta-llama/Llama-3.3-70B-Instruct-Turbo-Free",
-                25,
-                "Code-capable free model",
-            ),
-            // Google Gemini defaults
-            (
-                Provider::Google,
-                Workload::Chat,
-                "gemini-2.0-flash",
-                40,
-                "Fast multimodal Gemini",
-            ),
-            (
-                Provider::Google,
-                Workload::Code,
-                "gemini-2.0-flash",
-                35,
-                "Gemini with code capabilities",
-            ),
-            (
-                Provider::Google,
-                Workload::Summarization,
-                "gemini-2.0-flash",
-                40,
-                "Fast summarization",
-            ),
-            // Cloudflare Workers AI defaults (serverless GPU inference)
-            (
-                Provider::Cloudflare,
-                Workload::Chat,
-                "@cf/meta/llama-3.3-70b-instruct",
-                18,
-                "Serverless Llama 3.3 70B",
-            ),
-            (
-                Provider::Cloudflare,
-                Workload::Code,
-                "@cf/meta/llama-3.3-70b-instruct",
-                18,
-                "Serverless code-capable model",
-            ),
-            (
-                Provider::Cloudflare,
-                Workload::Creative,
-                "@cf/openai/gpt-oss-120b",
-                20,
-                "OpenAI open-source 120B model",
-            ),
-            // Cerebras AI defaults (ultra-fast, 1M tokens/day free)
-            (
-                Provider::Cerebras,
-                Workload::Chat,
-                "llama-3.1-70b",
-                12,
-                "Ultra-fast Llama 3.1 70B",
-            ),
-            (
-                Provider::Cerebras,
-                Workload::Code,
-                "llama-3.1-70b",
-                12,
-                "Fast code-capable model",
-            ),
-            (
-                Provider::Cerebras,
-                Workload::Summarization,
-                "llama-3.1-8b",
-                15,
-                "Fast summarization with 8B model",
-            ),
-            // Mistral AI defaults (free tier with rate limits)
-            (
-                Provider::Mistral,
-                Workload::Chat,
-                "mistral-small-latest",
-                22,
-                "Mistral Small for chat",
-            ),
-            (
-                Provider::Mistral,
-                Workload::Code,
-                "mistral-small-latest",
-                22,
-                "Mistral Small for code",
-            ),
-            (
-                Provider::Mistral,
-                Workload::Summarization,
-                "mistral-small-latest",
-                25,
-                "Mistral Small for summarization",
-            ),
-            // Clarifai AI defaults (1K requests/month free)
-            (
-                Provider::Clarifai,
-                Workload::Chat,
-                "gpt-4",
-                45,
-                "GPT-4 via Clarifai",
-            ),
-            (
-                Provider::Clarifai,
-                Workload::Code,
-                "gpt-4",
-                45,
-                "GPT-4 code via Clarifai",
-            ),
-            // GitHub Models defaults (50-150 RPD depending on plan)
-            (
-                Provider::GitHubModels,
-                Workload::Chat,
-                "gpt-4o",
-                35,
-                "GPT-4o via GitHub",
-            ),
-            (
-                Provider::GitHubModels,
-                Workload::Code,
-                "gpt-4o",
-                35,
-                "GPT-4o code via GitHub",
-            ),
-            // OpenRouter defaults (50 req/day for :free models)
-            (
-                Provider::OpenRouter,
-                Workload::Chat,
-                "deepseek/deepseek-r1:free",
-                50,
-                "DeepSeek R1 free via OpenRouter",
-            ),
-            (
-                Provider::OpenRouter,
-                Workload::Code,
-                "deepseek/deepseek-r1:free",
-                50,
-                "DeepSeek R1 code via OpenRouter",
-            ),
-        ];
-
-        for (provider, workload, model, priority, rationale) in defaults {
-            // Check if any active models exist for this provider/workload
-            let existing = self.active_models(provider, Some(workload)).await?;
-            if existing.is_empty() {
+        self.seed_roster(&Roster::defaults()?).await
+    }
+
+    /// Seeds the active models of `defaults` for provider/workload
+    /// combinations that have no active model yet.
+    ///
+    /// Metadata of the seeded models (context windows, vision support) is
+    /// also filled in on existing rows that have none. Rows with curated or
+    /// imported metadata are never touched.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the catalog cannot be read or updated.
+    pub async fn seed_roster(&self, defaults: &Roster) -> Result<(), AppError> {
+        // Decide which provider/workload pairs are empty before inserting, so
+        // every default of such a pair is seeded, not just the first one.
+        let mut empty = HashSet::new();
+        for entry in &defaults.models {
+            if self
+                .active_models(entry.provider, Some(entry.workload))
+                .await?
+                .is_empty()
+            {
+                let _ = empty.insert((entry.provider, entry.workload));
+            }
+        }
+
+        for entry in defaults
+            .models
+            .iter()
+            .filter(|entry| entry.status == "active")
+        {
+            if empty.contains(&(entry.provider, entry.workload)) {
                 let now = Utc::now().to_rfc3339();
                 let result = sqlx::query(
                     r#"INSERT OR IGNORE INTO provider_models
                        (provider, workload, model, status, priority, rationale, metadata, created_at, updated_at)
-                       VALUES (?, ?, ?, 'active', ?, ?, NULL, ?, ?)"#,
+                       VALUES (?, ?, ?, 'active', ?, ?, ?, ?, ?)"#,
                 )
-                .bind(provider.as_str())
-                .bind(workload_key(workload))
-                .bind(model)
-                .bind(priority)
-                .bind(rationale)
+                .bind(entry.provider.as_str())
+                .bind(workload_key(entry.workload))
+                .bind(&entry.model)
+                .bind(entry.priority)
+                .bind(&entry.rationale)
+                .bind(entry.metadata_json())
                 .bind(&now)
                 .bind(&now)
                 .execute(&*self.pool)
@@ -537,6 +790,60 @@ impl CatalogStore {
             }
         }
 
+        for entry in &defaults.models {
+            let Some(metadata) = entry.metadata_json() else {
+                continue;
+            };
+            let result = sqlx::query(
+                r"UPDATE provider_models SET metadata = ?
+                   WHERE provider = ? AND model = ? AND metadata IS NULL",
+            )
+            .bind(metadata)
+            .bind(entry.provider.as_str())
+            .bind(&entry.model)
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            let _ = result.rows_affected();
+        }
+
+        Ok(())
+    }
+
+    /// Inserts or replaces a catalog entry, including its status and metadata.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the upsert fails.
+    pub async fn upsert_model(&self, entry: &RosterEntry) -> Result<(), AppError> {
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r"INSERT INTO provider_models
+               (provider, workload, model, status, priority, rationale, metadata, created_at, updated_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+               ON CONFLICT(provider, workload, model) DO UPDATE SET
+                   status = excluded.status,
+                   priority = excluded.priority,
+                   rationale = excluded.rationale,
+                   metadata = excluded.metadata,
+                   updated_at = excluded.updated_at",
+        )
+        .bind(entry.provider.as_str())
+        .bind(workload_key(entry.workload))
+        .bind(&entry.model)
+        .bind(&entry.status)
+        .bind(entry.priority)
+        .bind(&entry.rationale)
+        // An empty object rather than NULL marks the metadata as curated,
+        // so seeding never fills it back in.
+        .bind(entry.metadata_json().unwrap_or_else(|| "{}".to_string()))
+        .bind(&now)
+        .bind(&now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
         Ok(())
     }
 
@@ -629,6 +936,72 @@ pub struct ModelEntry {
     pub updated_at: String,
 }
 
+impl ModelEntry {
+    /// Returns whether the JSON metadata sets `key` to `true`.
+    #[must_use]
+    pub fn metadata_flag(&self, key: &str) -> bool {
+        self.metadata
+            .as_deref()
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok())
+            .and_then(|value| value.get(key).and_then(serde_json::Value::as_bool))
+            .unwrap_or(false)
+    }
+
+    /// Returns the JSON metadata value of `key` when it is a non-negative integer.
+    #[must_use]
+    pub fn metadata_number(&self, key: &str) -> Option<u64> {
+        self.metadata
+            .as_deref()
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok())
+            .and_then(|value| value.get(key).and_then(serde_json::Value::as_u64))
+    }
+
+    /// Returns when model discovery last found the model missing from the
+    /// provider's listing, if it is still missing.
+    #[must_use]
+    pub fn unavailable_since(&self) -> Option<String> {
+        self.metadata_object()
+            .and_then(|metadata| metadata.get("unavailable_since").cloned())
+            .and_then(|value| value.as_str().map(str::to_string))
+    }
+
+    /// Returns the metadata as a JSON object; empty when unset, `None` when
+    /// it holds something other than an object.
+    pub fn metadata_object(&self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        match self.metadata.as_deref().map(str::trim) {
+            None | Some("") => Some(serde_json::Map::new()),
+            Some(raw) => match serde_json::from_str(raw) {
+                Ok(serde_json::Value::Object(map)) => Some(map),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the typed capabilities recorded in the metadata.
+    #[must_use]
+    pub fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::from_metadata(self.metadata.as_deref())
+    }
+
+    /// Returns the model's context window in tokens, when recorded in metadata.
+    #[must_use]
+    pub fn context_window(&self) -> Option<usize> {
+        self.metadata_number("context_window")
+            .and_then(|tokens| usize::try_from(tokens).ok())
+    }
+}
+
+/// Result of reconciling the catalog with a provider's model listing.
+#[derive(Debug, Clone, Default)]
+pub struct AvailabilityChanges {
+    /// Models that were not in the previous listing.
+    pub new_models: Vec<String>,
+    /// Catalog entries whose metadata was refreshed from the listing.
+    pub updated: usize,
+    /// Active catalog entries that the provider no longer lists.
+    pub missing: Vec<ModelEntry>,
+}
+
 /// A suggestion entry representing a candidate model for adoption.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuggestionEntry {
@@ -646,12 +1019,30 @@ pub struct SuggestionEntry {
     pub rationale: Option<String>,
     /// JSON metadata with cost estimates, etc.
     pub metadata: Option<String>,
+    /// Share of shadow traffic while on trial.
+    pub trial_share: Option<f64>,
     /// RFC3339 timestamp when created.
     pub created_at: String,
     /// RFC3339 timestamp when last updated.
     pub updated_at: String,
 }
 
+fn suggestion_from_row(row: &SqliteRow) -> SuggestionEntry {
+    SuggestionEntry {
+        id: row.get("id"),
+        provider: Provider::from_alias(row.get::<String, _>("provider").as_str())
+            .unwrap_or(Provider::HuggingFace),
+        workload: workload_from_key(row.get::<String, _>("workload").as_str()),
+        model: row.get("model"),
+        status: row.get("status"),
+        rationale: row.get("rationale"),
+        metadata: row.get("metadata"),
+        trial_share: row.get("trial_share"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
 fn workload_key(workload: Workload) -> String {
     match workload {
         Workload::Chat => "chat",
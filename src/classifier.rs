@@ -0,0 +1,320 @@
+//! Workload inference for requests that do not name one.
+//!
+//! Without a workload hint the catalog has nothing to match models against,
+//! so the router asks this classifier first. Keyword cues in the prompt give
+//! a guess and a confidence; when the confidence is below
+//! `[classifier] min_confidence` and a small, fast model is configured, the
+//! router asks that model instead. The inferred workload is then used for
+//! model selection and recorded with the request's usage.
+
+use crate::{
+    config::ClassifierConfig,
+    error::AppError,
+    models::{AIRequest, Workload},
+    providers::Provider,
+};
+
+/// Workloads in the order ties between equally strong cues are broken.
+const WORKLOADS: [Workload; 6] = [
+    Workload::Code,
+    Workload::Summarization,
+    Workload::Extraction,
+    Workload::Classification,
+    Workload::Creative,
+    Workload::Chat,
+];
+
+/// Characters of the prompt sent to the classification model.
+const MODEL_PROMPT_CHARS: usize = 2000;
+
+/// Phrases that suggest a workload. A cue matches at the start of a word,
+/// so `summari` matches "summarize" and "summarise".
+const fn cues(workload: Workload) -> &'static [&'static str] {
+    match workload {
+        Workload::Code => &[
+            "```",
+            "function",
+            "def ",
+            "fn ",
+            "class ",
+            "compile",
+            "stack trace",
+            "traceback",
+            "exception",
+            "refactor",
+            "debug",
+            "bug",
+            "unit test",
+            "regex",
+            "sql",
+            "python",
+            "rust",
+            "javascript",
+            "typescript",
+            "golang",
+            "c++",
+            "snippet",
+            "implement",
+        ],
+        Workload::Summarization => &[
+            "summari",
+            "summary",
+            "tl;dr",
+            "tldr",
+            "key points",
+            "main points",
+            "condense",
+            "shorten",
+            "recap",
+            "in a nutshell",
+        ],
+        Workload::Extraction => &[
+            "extract",
+            "pull out",
+            "find all",
+            "list all",
+            "parse",
+            "fields",
+            "entities",
+            "email addresses",
+            "phone numbers",
+            "as json",
+            "into json",
+        ],
+        Workload::Classification => &[
+            "classify",
+            "categori",
+            "category",
+            "sentiment",
+            "label",
+            "spam",
+            "positive or negative",
+            "which of the following",
+        ],
+        Workload::Creative => &[
+            "poem",
+            "story",
+            "haiku",
+            "lyrics",
+            "song",
+            "slogan",
+            "tagline",
+            "limerick",
+            "fiction",
+            "screenplay",
+            "imagine",
+            "brainstorm",
+        ],
+        Workload::Chat => &[
+            "hello", "thanks", "what is", "who is", "how do", "can you", "tell me",
+        ],
+    }
+}
+
+/// A workload guessed from the prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Classification {
+    /// The most likely workload.
+    pub workload: Workload,
+    /// How sure the guess is, from 0.0 (no cues) to just under 1.0.
+    pub confidence: f64,
+}
+
+/// Guesses the workload of `request` from keyword cues in its prompt.
+///
+/// The confidence grows with the number of cues for the winning workload and
+/// shrinks with cues for other workloads. Prompts without any cue are
+/// classified as chat with a confidence of zero.
+#[must_use]
+pub fn classify(request: &AIRequest) -> Classification {
+    let prompt = request.prompt.to_lowercase();
+    let mut scores = WORKLOADS.map(|workload| {
+        let matched = cues(workload)
+            .iter()
+            .filter(|cue| has_cue(&prompt, cue))
+            .count();
+        (workload, u32::try_from(matched).unwrap_or(u32::MAX))
+    });
+    if request.wants_json() {
+        for (workload, score) in &mut scores {
+            if *workload == Workload::Extraction {
+                *score += 1;
+            }
+        }
+    }
+
+    let total: u32 = scores.iter().map(|(_, score)| score).sum();
+    let mut best = (Workload::Chat, 0);
+    for (workload, score) in scores {
+        if score > best.1 {
+            best = (workload, score);
+        }
+    }
+    if total == 0 {
+        return Classification {
+            workload: Workload::Chat,
+            confidence: 0.0,
+        };
+    }
+    let best_score = f64::from(best.1);
+    let share = best_score / f64::from(total);
+    let strength = best_score / (best_score + 1.0);
+    Classification {
+        workload: best.0,
+        confidence: share * strength,
+    }
+}
+
+/// Returns whether `cue` occurs in `text` at the start of a word.
+fn has_cue(text: &str, cue: &str) -> bool {
+    text.match_indices(cue).any(|(start, _)| {
+        text[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|before| !before.is_alphanumeric())
+    })
+}
+
+/// Builds the prompt that asks a model for the workload of `prompt`.
+#[must_use]
+pub fn classification_prompt(prompt: &str) -> String {
+    let excerpt: String = prompt.chars().take(MODEL_PROMPT_CHARS).collect();
+    format!(
+        "Classify the request below into exactly one workload: chat, code, summarization, \
+         extraction, creative or classification. Answer with the workload name only.\n\n\
+         Request: {excerpt}"
+    )
+}
+
+/// Reads the workload named first in a classification model's answer.
+#[must_use]
+pub fn parse_answer(answer: &str) -> Option<Workload> {
+    let answer = answer.to_lowercase();
+    WORKLOADS
+        .iter()
+        .filter_map(|workload| {
+            let name = match workload {
+                Workload::Summarization => "summar",
+                other => other.as_str(),
+            };
+            answer.find(name).map(|position| (position, *workload))
+        })
+        .min_by_key(|(position, _)| *position)
+        .map(|(_, workload)| workload)
+}
+
+/// Settings of the classifier stage.
+#[derive(Debug, Clone)]
+pub struct WorkloadClassifier {
+    min_confidence: f64,
+    model: Option<(Provider, String)>,
+}
+
+impl WorkloadClassifier {
+    /// Builds the classifier from the `[classifier]` configuration section.
+    ///
+    /// Returns `None` when classification is disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error when `min_confidence` is outside 0-1, the
+    /// provider is unknown, or only one of provider and model is set.
+    pub fn from_config(config: &ClassifierConfig) -> Result<Option<Self>, AppError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if !(0.0..=1.0).contains(&config.min_confidence) {
+            return Err(AppError::ConfigError(
+                "classifier.min_confidence must be between 0 and 1".into(),
+            ));
+        }
+        let model = match (&config.provider, &config.model) {
+            (Some(provider), Some(model)) => {
+                let provider = Provider::from_alias(provider).ok_or_else(|| {
+                    AppError::ConfigError(format!("Unknown classifier provider '{provider}'"))
+                })?;
+                Some((provider, model.clone()))
+            }
+            (None, None) => None,
+            _ => {
+                return Err(AppError::ConfigError(
+                    "classifier.provider and classifier.model must be set together".into(),
+                ))
+            }
+        };
+        Ok(Some(Self {
+            min_confidence: config.min_confidence,
+            model,
+        }))
+    }
+
+    /// Confidence below which the classification model is asked.
+    #[must_use]
+    pub const fn min_confidence(&self) -> f64 {
+        self.min_confidence
+    }
+
+    /// Returns the configured classification model, if any.
+    #[must_use]
+    pub fn model(&self) -> Option<(Provider, &str)> {
+        self.model
+            .as_ref()
+            .map(|(provider, model)| (*provider, model.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(prompt: &str) -> AIRequest {
+        AIRequest {
+            prompt: prompt.into(),
+            ..AIRequest::default()
+        }
+    }
+
+    #[test]
+    fn classifies_prompts_by_cues() {
+        for (prompt, expected) in [
+            (
+                "Refactor this Python function to fix the bug",
+                Workload::Code,
+            ),
+            (
+                "Summarize the key points of the meeting notes",
+                Workload::Summarization,
+            ),
+            (
+                "Extract all email addresses from the text",
+                Workload::Extraction,
+            ),
+            (
+                "Classify the sentiment of this review",
+                Workload::Classification,
+            ),
+            ("Write a haiku about rain", Workload::Creative),
+        ] {
+            let guess = classify(&request(prompt));
+            assert_eq!(guess.workload, expected, "{prompt}");
+            assert!(guess.confidence >= 0.5, "{prompt}: {}", guess.confidence);
+        }
+
+        let unclear = classify(&request("Good morning"));
+        assert_eq!(unclear.workload, Workload::Chat);
+        assert!(unclear.confidence < f64::EPSILON);
+
+        // "trust" is not a cue for Rust.
+        assert!(classify(&request("Do you trust me")).confidence < f64::EPSILON);
+    }
+
+    #[test]
+    fn parses_model_answers() {
+        assert_eq!(parse_answer("Code"), Some(Workload::Code));
+        assert_eq!(
+            parse_answer("Workload: summarisation."),
+            Some(Workload::Summarization)
+        );
+        assert_eq!(parse_answer("I am not sure"), None);
+    }
+}
@@ -0,0 +1,280 @@
+//! Side-by-side comparison of one request across providers and models.
+//!
+//! Every target receives the same request at the same time. Targets are
+//! pinned: a failing provider is reported as failed rather than answered by
+//! the fallback chain, so each row really shows the target it names.
+//! Provider responses carry no token counts, so usage is estimated with
+//! [`estimate_tokens`].
+
+use std::{fmt::Write as _, fs, path::Path, sync::Arc, time::Instant};
+
+use serde::Serialize;
+use tokio::task::JoinSet;
+
+use crate::{
+    catalog::CatalogStore,
+    context::{estimate_tokens, request_tokens},
+    error::AppError,
+    models::AIRequest,
+    providers::{Provider, ProviderRouter},
+};
+
+/// A provider, optionally with a specific model, to run a comparison on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompareTarget {
+    /// Provider receiving the request.
+    pub provider: Provider,
+    /// Model to request; the router's catalog choice when unset.
+    pub model: Option<String>,
+}
+
+impl CompareTarget {
+    /// Parses `provider` or `provider:model`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the provider is unknown.
+    pub fn parse(spec: &str) -> Result<Self, AppError> {
+        let (name, model) = match spec.split_once(':') {
+            Some((name, model)) => (name, Some(model.trim())),
+            None => (spec, None),
+        };
+        let provider = Provider::from_alias(name.trim()).ok_or_else(|| {
+            AppError::InvalidRequest(format!("Unknown provider '{}' in '{spec}'", name.trim()))
+        })?;
+        Ok(Self {
+            provider,
+            model: model.filter(|model| !model.is_empty()).map(str::to_string),
+        })
+    }
+
+    /// Resolves `provider:model`, or a bare model name through the catalog.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the provider is unknown, or a bare model is not in the
+    /// catalog.
+    pub async fn resolve_model(catalog: &CatalogStore, spec: &str) -> Result<Self, AppError> {
+        if let Some((name, _)) = spec.split_once(':') {
+            if Provider::from_alias(name.trim()).is_some() {
+                return Self::parse(spec);
+            }
+        }
+        let entry = catalog
+            .list_models(None, None)
+            .await?
+            .into_iter()
+            .find(|entry| entry.model == spec && entry.status == "active")
+            .ok_or_else(|| {
+                AppError::InvalidRequest(format!(
+                    "Model '{spec}' is not in the catalog; use provider:model"
+                ))
+            })?;
+        Ok(Self {
+            provider: entry.provider,
+            model: Some(entry.model),
+        })
+    }
+
+    /// Returns the target as `provider` or `provider:model`.
+    #[must_use]
+    pub fn label(&self) -> String {
+        self.model.as_ref().map_or_else(
+            || self.provider.to_string(),
+            |model| format!("{}:{model}", self.provider),
+        )
+    }
+}
+
+/// Outcome of one comparison target.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompareResult {
+    /// The target as given (`provider` or `provider:model`).
+    pub target: String,
+    /// Provider that answered.
+    pub provider: String,
+    /// Model that answered, when known.
+    pub model: Option<String>,
+    /// Wall-clock time spent on the request.
+    pub latency_ms: u64,
+    /// Estimated prompt tokens sent.
+    pub prompt_tokens: usize,
+    /// Estimated completion tokens received.
+    pub completion_tokens: usize,
+    /// Generated content on success.
+    pub content: Option<String>,
+    /// Error message on failure.
+    pub error: Option<String>,
+}
+
+impl CompareResult {
+    /// Returns whether the target answered.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Runs `request` on every target concurrently and returns the results in
+/// target order.
+///
+/// # Errors
+///
+/// Fails when a comparison task panics or is cancelled. Provider errors are
+/// reported in the results instead.
+pub async fn run_comparison(
+    router: Arc<ProviderRouter>,
+    request: &AIRequest,
+    targets: &[CompareTarget],
+) -> Result<Vec<CompareResult>, AppError> {
+    let mut in_flight = JoinSet::new();
+    for (idx, target) in targets.iter().enumerate() {
+        let router = Arc::clone(&router);
+        let target = target.clone();
+        let mut request = request.clone();
+        if let Some(model) = &target.model {
+            request.model.clone_from(model);
+        }
+        drop(in_flight.spawn(async move { (idx, execute(&router, &target, &request).await) }));
+    }
+
+    let mut results: Vec<Option<CompareResult>> = vec![None; targets.len()];
+    while let Some(joined) = in_flight.join_next().await {
+        let (idx, result) =
+            joined.map_err(|err| AppError::ApiError(format!("Comparison task failed: {err}")))?;
+        results[idx] = Some(result);
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+async fn execute(
+    router: &ProviderRouter,
+    target: &CompareTarget,
+    request: &AIRequest,
+) -> CompareResult {
+    let prompt_tokens = request_tokens(request);
+    let start = Instant::now();
+    let outcome = router.generate_on(target.provider, request).await;
+    let latency_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    match outcome {
+        Ok(response) => CompareResult {
+            target: target.label(),
+            provider: response.provider.as_str().to_string(),
+            model: response.model,
+            latency_ms,
+            prompt_tokens,
+            completion_tokens: estimate_tokens(&response.content),
+            content: Some(response.content),
+            error: None,
+        },
+        Err(err) => CompareResult {
+            target: target.label(),
+            provider: target.provider.as_str().to_string(),
+            model: target.model.clone(),
+            latency_ms,
+            prompt_tokens,
+            completion_tokens: 0,
+            content: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+/// Writes the results to `path`: JSON for a `.json` file, Markdown otherwise.
+///
+/// # Errors
+///
+/// Fails when the report cannot be encoded or written.
+pub fn write_report(path: &Path, prompt: &str, results: &[CompareResult]) -> Result<(), AppError> {
+    let is_json = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let report = if is_json {
+        serde_json::to_string_pretty(&serde_json::json!({
+            "prompt": prompt,
+            "results": results,
+        }))
+        .map_err(|err| AppError::ApiError(err.to_string()))?
+    } else {
+        markdown_report(prompt, results)
+    };
+    fs::write(path, report).map_err(|err| {
+        AppError::ConfigError(format!(
+            "Failed to write comparison to {}: {err}",
+            path.display()
+        ))
+    })
+}
+
+/// Renders the results as a Markdown summary table followed by each output.
+#[must_use]
+pub fn markdown_report(prompt: &str, results: &[CompareResult]) -> String {
+    let mut out = String::from("# Model comparison\n\n## Prompt\n\n");
+    out.push_str(prompt.trim_end());
+    out.push_str("\n\n| Target | Model | Latency (ms) | Tokens in/out (est.) | Status |\n");
+    out.push_str("|---|---|---:|---:|---|\n");
+    for result in results {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {}/{} | {} |",
+            result.target,
+            result.model.as_deref().unwrap_or("-"),
+            result.latency_ms,
+            result.prompt_tokens,
+            result.completion_tokens,
+            if result.is_success() { "ok" } else { "failed" },
+        );
+    }
+    for result in results {
+        let _ = write!(out, "\n## {}\n\n", result.target);
+        match (&result.content, &result.error) {
+            (Some(content), _) => out.push_str(content.trim_end()),
+            (None, Some(error)) => {
+                let _ = write!(out, "_Failed: {error}_");
+            }
+            (None, None) => {}
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_provider_and_model_targets() {
+        let target = CompareTarget::parse("groq").unwrap();
+        assert_eq!(target.provider, Provider::Groq);
+        assert_eq!(target.model, None);
+
+        let target = CompareTarget::parse("openrouter:meta-llama/llama-3.3-70b:free").unwrap();
+        assert_eq!(target.provider, Provider::OpenRouter);
+        assert_eq!(
+            target.model.as_deref(),
+            Some("meta-llama/llama-3.3-70b:free")
+        );
+        assert_eq!(target.label(), "openrouter:meta-llama/llama-3.3-70b:free");
+
+        assert!(CompareTarget::parse("nope").is_err());
+    }
+
+    #[test]
+    fn markdown_report_lists_failures() {
+        let results = vec![CompareResult {
+            target: "groq".into(),
+            provider: "groq".into(),
+            model: None,
+            latency_ms: 12,
+            prompt_tokens: 3,
+            completion_tokens: 0,
+            content: None,
+            error: Some("rate limited".into()),
+        }];
+        let report = markdown_report("hi", &results);
+        assert!(report.contains("| groq | - | 12 | 3/0 | failed |"));
+        assert!(report.contains("_Failed: rate limited_"));
+    }
+}
@@ -8,6 +8,7 @@ use config::{Config, ConfigError, Environment, File};
 use dirs::{config_dir, data_dir, home_dir};
 use serde::Deserialize;
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
@@ -21,6 +22,42 @@ pub struct AppConfig {
     pub database: DatabaseConfig,
     /// Configuration for AI providers.
     pub providers: ProvidersConfig,
+    /// Moderation rules applied to requests with the `strict` guardrail hint.
+    #[serde(default)]
+    pub guardrails: GuardrailConfig,
+    /// PII redaction applied before prompts reach third-party providers.
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Background job queue used by `/api/v1/jobs`.
+    #[serde(default)]
+    pub jobs: JobsConfig,
+    /// Handling of context files that exceed the token budget.
+    #[serde(default)]
+    pub context: ContextConfig,
+    /// Location of named prompt templates.
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    /// Opt-in logging of full requests and responses.
+    #[serde(default)]
+    pub audit: AuditConfig,
+    /// Shadow traffic for suggested models on trial.
+    #[serde(default)]
+    pub trials: TrialsConfig,
+    /// Model roster seeded into the catalog.
+    #[serde(default)]
+    pub catalog: CatalogConfig,
+    /// Workload inference for requests without a workload hint.
+    #[serde(default)]
+    pub classifier: ClassifierConfig,
+    /// Spend caps for pay-as-you-go providers.
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    /// Use of stored provider credentials.
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    /// Model aliases: logical names mapped to `provider/model` targets.
+    #[serde(default)]
+    pub aliases: HashMap<String, Vec<String>>,
 }
 
 /// Server-specific configuration.
@@ -95,6 +132,304 @@ pub struct ProviderDetails {
     pub api_base_url: String,
 }
 
+/// Moderation settings for requests that ask for strict guardrails.
+#[derive(Debug, Default, Deserialize)]
+pub struct GuardrailConfig {
+    /// Case-insensitive keywords that trigger the guardrail.
+    #[serde(default)]
+    pub blocked_terms: Vec<String>,
+    /// Regular expressions that trigger the guardrail.
+    #[serde(default)]
+    pub blocked_patterns: Vec<String>,
+    /// What to do when a rule matches.
+    #[serde(default)]
+    pub action: GuardrailAction,
+    /// Provider hosting a moderation model (e.g., "groq").
+    #[serde(default)]
+    pub moderation_provider: Option<String>,
+    /// Moderation model identifier (e.g., "meta-llama/llama-guard-4-12b").
+    #[serde(default)]
+    pub moderation_model: Option<String>,
+}
+
+/// Action taken when a guardrail rule matches.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardrailAction {
+    /// Reject the request or response with an error.
+    #[default]
+    Block,
+    /// Replace matching text and continue.
+    Redact,
+}
+
+/// PII redaction settings.
+#[derive(Debug, Default, Deserialize)]
+pub struct RedactionConfig {
+    /// Enables the redaction layer.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Policy for providers without an explicit entry.
+    #[serde(default)]
+    pub default_policy: RedactionPolicy,
+    /// Additional regular expressions to redact.
+    #[serde(default)]
+    pub custom_patterns: Vec<String>,
+    /// Per-provider overrides keyed by provider alias.
+    #[serde(default)]
+    pub providers: HashMap<String, RedactionPolicy>,
+}
+
+/// Whether requests to a provider are redacted.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionPolicy {
+    /// Always redact before sending.
+    #[default]
+    Always,
+    /// Never redact (e.g., trusted or local endpoints).
+    Never,
+}
+
+/// Settings for the asynchronous job queue.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// Number of worker tasks draining the queue (0 disables the workers).
+    pub workers: usize,
+    /// How often idle workers re-check the queue, in milliseconds.
+    pub poll_interval_ms: u64,
+    /// Timeout for webhook callbacks, in seconds.
+    pub webhook_timeout_secs: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            poll_interval_ms: 1000,
+            webhook_timeout_secs: 10,
+        }
+    }
+}
+
+/// Settings for the request/response audit log.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Records every routed request and its outcome (off by default).
+    pub enabled: bool,
+    /// Entries older than this many days are deleted (0 keeps them forever).
+    pub retention_days: u32,
+    /// Maximum number of entries kept; the oldest are deleted first (0 is unlimited).
+    pub max_entries: u32,
+    /// Masks PII in logged prompts and responses with the redaction rules.
+    pub redact: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: 30,
+            max_entries: 10_000,
+            redact: true,
+        }
+    }
+}
+
+/// Settings for trials of suggested models.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct TrialsConfig {
+    /// Sends shadow copies of requests to models on trial.
+    pub enabled: bool,
+    /// Share of matching requests shadowed when a trial sets none (0.0-1.0).
+    pub default_share: f64,
+}
+
+impl Default for TrialsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_share: 0.1,
+        }
+    }
+}
+
+/// Settings for the model catalog.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CatalogConfig {
+    /// Roster file (TOML or YAML) seeded instead of the built-in defaults,
+    /// so `freegin-ai catalog import` and startup seeding agree.
+    pub roster: Option<PathBuf>,
+    /// Consecutive "model not found" failures after which a catalog model
+    /// is retired automatically; 0 disables automatic retirement.
+    pub auto_retire_after: u32,
+}
+
+impl Default for CatalogConfig {
+    fn default() -> Self {
+        Self {
+            roster: None,
+            auto_retire_after: 3,
+        }
+    }
+}
+
+/// Settings for inferring the workload of requests that do not set one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    /// Infers a missing workload from the prompt.
+    pub enabled: bool,
+    /// Heuristic confidence (0.0-1.0) below which the model is asked.
+    pub min_confidence: f64,
+    /// Provider alias of the classification model.
+    pub provider: Option<String>,
+    /// Small, fast model asked when the heuristics are unsure.
+    pub model: Option<String>,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_confidence: 0.5,
+            provider: None,
+            model: None,
+        }
+    }
+}
+
+/// Spend caps, in US dollars, for paid models.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Cap on the spend of all providers per UTC day.
+    pub daily_usd: Option<f64>,
+    /// Cap on the spend of all providers per calendar month.
+    pub monthly_usd: Option<f64>,
+    /// Share of a cap (0.0-1.0) at which `status` starts warning.
+    pub warn_at: f64,
+    /// Per-provider caps keyed by provider alias.
+    pub providers: HashMap<String, SpendLimit>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            daily_usd: None,
+            monthly_usd: None,
+            warn_at: 0.8,
+            providers: HashMap::new(),
+        }
+    }
+}
+
+/// Daily and monthly caps for one provider.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SpendLimit {
+    /// Cap per UTC day.
+    pub daily_usd: Option<f64>,
+    /// Cap per calendar month.
+    pub monthly_usd: Option<f64>,
+}
+
+/// Settings for stored provider credentials.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct CredentialsConfig {
+    /// How a call picks among a provider's stored keys.
+    pub selection: KeySelection,
+    /// Where the key that encrypts stored credentials comes from.
+    pub master_key: MasterKeySource,
+}
+
+/// Where the credential master key is kept.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MasterKeySource {
+    /// A random key in `secret.key` next to the config.
+    #[default]
+    File,
+    /// A key derived with Argon2 from a passphrase that is prompted for or
+    /// read from `FREEGIN_PASSPHRASE`.
+    Passphrase,
+    /// A random key held by the desktop Secret Service (Linux builds with
+    /// the `keyring` feature).
+    Keyring,
+}
+
+impl MasterKeySource {
+    /// Config spelling of the source.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Passphrase => "passphrase",
+            Self::Keyring => "keyring",
+        }
+    }
+}
+
+/// How a call picks among several keys of one provider.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySelection {
+    /// The key used least recently.
+    #[default]
+    RoundRobin,
+    /// The key with the fewest requests today (UTC).
+    LeastUsed,
+}
+
+/// Settings for the prompt template library.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct TemplatesConfig {
+    /// Directory holding `<name>.toml` templates (defaults to
+    /// `~/.config/freegin-ai/templates`).
+    pub dir: Option<PathBuf>,
+}
+
+/// Settings for oversized `--context-file` input.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct ContextConfig {
+    /// What to do when context files exceed `max_tokens`.
+    pub strategy: ContextStrategy,
+    /// Estimated token budget for all context files combined.
+    pub max_tokens: usize,
+    /// Size of the pieces summarized independently by the `summarize` strategy.
+    pub chunk_tokens: usize,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ContextStrategy::None,
+            max_tokens: 6000,
+            chunk_tokens: 2000,
+        }
+    }
+}
+
+/// How oversized context is reduced before it is sent.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    /// Send context as-is; routing still skips models that are too small.
+    #[default]
+    None,
+    /// Cut each file down to its share of the budget.
+    Truncate,
+    /// Summarize chunks, then summarize the summaries until the budget is met.
+    Summarize,
+}
+
 impl AppConfig {
     /// Loads the application configuration.
     ///
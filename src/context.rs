@@ -0,0 +1,309 @@
+//! Token estimation and context-window fitting.
+//!
+//! Providers tokenize differently and none of them expose a tokenizer we can
+//! call offline, so sizes are estimated from character counts. The ratios are
+//! chosen to err on the high side for English prose, code and CJK text alike,
+//! so a request that fits here also fits the model.
+//!
+//! Oversized context files can be cut to a budget or condensed with a
+//! map-reduce pass of summarization requests through the router.
+
+use tracing::debug;
+
+use crate::{
+    config::{ContextConfig, ContextStrategy},
+    error::AppError,
+    models::{AIRequest, ChatRole, RequestHints, Workload},
+    providers::ProviderRouter,
+};
+
+/// Context window assumed for models whose catalog entry does not declare one.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
+/// Tokens kept free for the model's reply when fitting a request.
+pub const RESPONSE_RESERVE: usize = 1024;
+
+/// Fixed per-message overhead (role markers, separators).
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Estimates the number of tokens in `text`.
+///
+/// ASCII is counted at three characters per token, which is above what
+/// tokenizers produce for English (about four) and close to code. Any other
+/// character counts as a whole token: CJK ideographs and most accented or
+/// symbol characters take one token or more each.
+#[must_use]
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0_usize, 0_usize), |(ascii, other), ch| {
+        if ch.is_ascii() {
+            (ascii + 1, other)
+        } else {
+            (ascii, other + 1)
+        }
+    });
+    ascii.div_ceil(3) + other
+}
+
+/// Marker appended to context cut by the `truncate` strategy.
+const TRUNCATION_MARKER: &str = "\n[... truncated ...]";
+
+/// Maximum number of reduce rounds before falling back to truncation.
+const MAX_SUMMARY_ROUNDS: usize = 3;
+
+/// Estimates the tokens a request sends: the prompt and the history.
+///
+/// `context` is not counted; callers fold context into the prompt.
+#[must_use]
+pub fn request_tokens(request: &AIRequest) -> usize {
+    let history: usize = request
+        .history
+        .iter()
+        .map(|turn| estimate_tokens(&turn.content) + MESSAGE_OVERHEAD)
+        .sum();
+    estimate_tokens(&request.prompt) + MESSAGE_OVERHEAD + history
+}
+
+/// Estimates the smallest window a request can be sent to: everything that
+/// truncation cannot remove (prompt and system turns) plus the reply reserve.
+#[must_use]
+pub fn required_window(request: &AIRequest) -> usize {
+    let system: usize = request
+        .history
+        .iter()
+        .filter(|turn| turn.role == ChatRole::System)
+        .map(|turn| estimate_tokens(&turn.content) + MESSAGE_OVERHEAD)
+        .sum();
+    estimate_tokens(&request.prompt) + MESSAGE_OVERHEAD + system + RESPONSE_RESERVE
+}
+
+/// Drops the oldest non-system history turns until the request fits in
+/// `window` tokens with room for the reply. Returns the number of turns
+/// removed; the current prompt and system turns are always kept.
+pub fn truncate_history(request: &mut AIRequest, window: usize) -> usize {
+    let budget = window.saturating_sub(RESPONSE_RESERVE);
+    let mut removed = 0;
+    while request_tokens(request) > budget {
+        let Some(oldest) = request
+            .history
+            .iter()
+            .position(|turn| turn.role != ChatRole::System)
+        else {
+            break;
+        };
+        drop(request.history.remove(oldest));
+        removed += 1;
+    }
+    removed
+}
+
+/// Reduces context blocks to `config.max_tokens` using the configured strategy.
+///
+/// Blocks already within budget, and the `none` strategy, pass through
+/// unchanged. Summarization sends one request per chunk through `router`
+/// with the `summarization` workload hint.
+///
+/// # Errors
+///
+/// Fails when a summarization request fails.
+pub async fn fit_context(
+    router: &ProviderRouter,
+    blocks: Vec<String>,
+    config: &ContextConfig,
+) -> Result<Vec<String>, AppError> {
+    if config.strategy == ContextStrategy::None || total_tokens(&blocks) <= config.max_tokens {
+        return Ok(blocks);
+    }
+    match config.strategy {
+        ContextStrategy::None => Ok(blocks),
+        ContextStrategy::Truncate => Ok(truncate_blocks(blocks, config.max_tokens)),
+        ContextStrategy::Summarize => summarize_blocks(router, blocks, config).await,
+    }
+}
+
+/// Cuts every block down to a share of `budget` proportional to its size.
+#[must_use]
+pub fn truncate_blocks(blocks: Vec<String>, budget: usize) -> Vec<String> {
+    let total = total_tokens(&blocks);
+    if total <= budget {
+        return blocks;
+    }
+    let marker = estimate_tokens(TRUNCATION_MARKER);
+    blocks
+        .into_iter()
+        .map(|block| {
+            let share = (estimate_tokens(&block) * budget / total).saturating_sub(marker);
+            let cut = token_boundary(&block, share);
+            if cut < block.len() {
+                format!("{}{TRUNCATION_MARKER}", &block[..cut])
+            } else {
+                block
+            }
+        })
+        .collect()
+}
+
+async fn summarize_blocks(
+    router: &ProviderRouter,
+    mut blocks: Vec<String>,
+    config: &ContextConfig,
+) -> Result<Vec<String>, AppError> {
+    let chunk_tokens = config.chunk_tokens.max(1);
+    for round in 1..=MAX_SUMMARY_ROUNDS {
+        let chunk_count: usize = blocks
+            .iter()
+            .map(|block| split_chunks(block, chunk_tokens).len())
+            .sum();
+        // Ask each summary for its share of the budget so the reduced blocks fit.
+        let target_words = (config.max_tokens * 3 / 4 / chunk_count.max(1)).max(30);
+
+        let mut reduced = Vec::with_capacity(blocks.len());
+        for block in &blocks {
+            let mut summaries = Vec::new();
+            for chunk in split_chunks(block, chunk_tokens) {
+                summaries.push(summarize_chunk(router, chunk, target_words).await?);
+            }
+            reduced.push(summaries.join("\n\n"));
+        }
+        blocks = reduced;
+
+        let total = total_tokens(&blocks);
+        debug!(
+            round,
+            chunks = chunk_count,
+            tokens = total,
+            "Summarized context"
+        );
+        if total <= config.max_tokens {
+            return Ok(blocks);
+        }
+    }
+    Ok(truncate_blocks(blocks, config.max_tokens))
+}
+
+async fn summarize_chunk(
+    router: &ProviderRouter,
+    chunk: &str,
+    target_words: usize,
+) -> Result<String, AppError> {
+    let request = AIRequest {
+        prompt: format!(
+            "Summarize the following text in at most {target_words} words. Keep names, \
+             numbers, identifiers and decisions; drop filler. Reply with the summary only.\n\n{chunk}"
+        ),
+        hints: RequestHints {
+            workload: Some(Workload::Summarization),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+    Ok(router.generate(&request).await?.content.trim().to_string())
+}
+
+/// Splits `text` into pieces of about `chunk_tokens`, preferring line breaks.
+fn split_chunks(text: &str, chunk_tokens: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while estimate_tokens(rest) > chunk_tokens {
+        let limit = token_boundary(rest, chunk_tokens);
+        let cut = rest[..limit]
+            .rfind('\n')
+            .filter(|&idx| idx > limit / 2)
+            .map_or(limit, |idx| idx + 1);
+        chunks.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    if !rest.trim().is_empty() || chunks.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Returns the byte length of the longest prefix of `text` estimated at no
+/// more than `tokens`.
+fn token_boundary(text: &str, tokens: usize) -> usize {
+    let (mut ascii, mut other) = (0_usize, 0_usize);
+    for (idx, ch) in text.char_indices() {
+        if ch.is_ascii() {
+            ascii += 1;
+        } else {
+            other += 1;
+        }
+        if ascii.div_ceil(3) + other > tokens {
+            return idx;
+        }
+    }
+    text.len()
+}
+
+fn total_tokens(blocks: &[String]) -> usize {
+    blocks.iter().map(|block| estimate_tokens(block)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ChatMessage;
+
+    fn turn(role: ChatRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn estimates_err_on_the_high_side() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcd"), 2);
+        assert_eq!(estimate_tokens("日本語"), 3);
+        assert_eq!(estimate_tokens("héllo"), 3);
+    }
+
+    #[test]
+    fn truncation_drops_oldest_turns_but_keeps_system() {
+        let long = "x".repeat(4000);
+        let mut request = AIRequest {
+            prompt: "latest question".into(),
+            history: vec![
+                turn(ChatRole::System, "be brief"),
+                turn(ChatRole::User, &long),
+                turn(ChatRole::Assistant, &long),
+                turn(ChatRole::User, "short"),
+                turn(ChatRole::Assistant, "reply"),
+            ],
+            ..AIRequest::default()
+        };
+
+        let removed = truncate_history(&mut request, RESPONSE_RESERVE + 600);
+        assert_eq!(removed, 2);
+        assert_eq!(request.history.len(), 3);
+        assert_eq!(request.history[0].role, ChatRole::System);
+        assert_eq!(request.history[1].content, "short");
+        assert_eq!(truncate_history(&mut request, RESPONSE_RESERVE + 600), 0);
+    }
+
+    #[test]
+    fn truncation_shares_budget_proportionally() {
+        let blocks = vec!["a".repeat(8000), "b".repeat(2000)];
+        let fitted = truncate_blocks(blocks, 1000);
+        assert!(fitted[0].ends_with(TRUNCATION_MARKER));
+        assert!(fitted[1].ends_with(TRUNCATION_MARKER));
+        assert!(total_tokens(&fitted) <= 1000);
+        assert!(fitted[0].len() > fitted[1].len());
+
+        let cjk = vec!["字".repeat(3000)];
+        assert!(total_tokens(&truncate_blocks(cjk, 1000)) <= 1000);
+
+        let small = vec!["short".to_string()];
+        assert_eq!(truncate_blocks(small.clone(), 1000), small);
+    }
+
+    #[test]
+    fn chunks_prefer_line_breaks() {
+        let text = format!("{}\n{}", "x".repeat(30), "y".repeat(30));
+        let chunks = split_chunks(&text, 12);
+        assert_eq!(chunks[0], format!("{}\n", "x".repeat(30)));
+        assert_eq!(chunks.concat(), text);
+    }
+}
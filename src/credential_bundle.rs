@@ -0,0 +1,157 @@
+//! Passphrase-encrypted bundles of stored credentials for moving them to
+//! another machine.
+//!
+//! `freegin-ai credentials export` writes every stored key with its status,
+//! daily limit and the provider's configured base URL, sealed with the same
+//! XChaCha20-Poly1305 cipher as the credential store under a key derived
+//! from a passphrase with Argon2id. `credentials import` opens the bundle
+//! and stores the keys again under the local master key.
+
+use std::fmt;
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{credentials::KeyStatus, error::AppError, master_key, providers::Provider};
+
+/// Environment variable holding the bundle passphrase.
+pub const PASSPHRASE_ENV: &str = "FREEGIN_BUNDLE_PASSPHRASE";
+
+/// Marks a bundle file and its format version.
+const MAGIC: &[u8] = b"freegin-credentials-v1\n";
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+
+/// The contents of an export bundle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialBundle {
+    /// When the bundle was written.
+    pub exported_at: DateTime<Utc>,
+    /// Every exported key.
+    pub keys: Vec<BundledKey>,
+}
+
+/// One stored key in a bundle.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledKey {
+    /// Provider the key belongs to.
+    pub provider: Provider,
+    /// Key name, unique per provider.
+    pub name: String,
+    /// The secret itself.
+    pub token: String,
+    /// Whether the key takes part in selection.
+    pub status: KeyStatus,
+    /// Requests allowed per UTC day, if limited.
+    #[serde(default)]
+    pub daily_limit: Option<i64>,
+    /// The provider's configured base URL on the exporting machine.
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl fmt::Debug for BundledKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BundledKey")
+            .field("provider", &self.provider)
+            .field("name", &self.name)
+            .field("status", &self.status)
+            .field("daily_limit", &self.daily_limit)
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CredentialBundle {
+    /// Wraps keys exported now.
+    #[must_use]
+    pub fn new(keys: Vec<BundledKey>) -> Self {
+        Self {
+            exported_at: Utc::now(),
+            keys,
+        }
+    }
+
+    /// Encrypts the bundle under `passphrase`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the bundle cannot be encoded or encrypted.
+    pub fn seal(&self, passphrase: &str) -> Result<Vec<u8>, AppError> {
+        let plaintext = serde_json::to_vec(self)
+            .map_err(|err| AppError::ConfigError(format!("Failed to encode bundle: {err}")))?;
+
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let key = master_key::derive_key(passphrase, &salt)?;
+        let ciphertext = XChaCha20Poly1305::new(&key.into())
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|err| AppError::ConfigError(format!("Failed to encrypt bundle: {err}")))?;
+
+        let mut sealed =
+            Vec::with_capacity(MAGIC.len() + SALT_SIZE + NONCE_SIZE + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&salt);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypts a bundle written by [`CredentialBundle::seal`].
+    ///
+    /// # Errors
+    ///
+    /// Fails when the data is not a bundle, the passphrase is wrong or the
+    /// bundle is damaged.
+    pub fn open(sealed: &[u8], passphrase: &str) -> Result<Self, AppError> {
+        let body = sealed
+            .strip_prefix(MAGIC)
+            .filter(|body| body.len() > SALT_SIZE + NONCE_SIZE)
+            .ok_or_else(|| AppError::InvalidRequest("Not a freegin-ai credential bundle".into()))?;
+        let (salt, rest) = body.split_at(SALT_SIZE);
+        let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+        let key = master_key::derive_key(passphrase, salt)?;
+        let plaintext = XChaCha20Poly1305::new(&key.into())
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                AppError::InvalidRequest("Wrong bundle passphrase or damaged bundle".into())
+            })?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|err| AppError::InvalidRequest(format!("Unreadable bundle contents: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_opens_only_with_its_passphrase() {
+        let bundle = CredentialBundle::new(vec![BundledKey {
+            provider: Provider::Groq,
+            name: "work".into(),
+            token: "gsk-secret".into(),
+            status: KeyStatus::Disabled,
+            daily_limit: Some(14_400),
+            base_url: Some("https://api.groq.com/openai/v1".into()),
+        }]);
+
+        let sealed = bundle.seal("moving day").expect("seal");
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!String::from_utf8_lossy(&sealed).contains("gsk-secret"));
+        assert_eq!(
+            CredentialBundle::open(&sealed, "moving day").expect("open"),
+            bundle
+        );
+        assert!(CredentialBundle::open(&sealed, "wrong").is_err());
+        assert!(CredentialBundle::open(b"plain text", "moving day").is_err());
+        assert!(!format!("{:?}", bundle.keys[0]).contains("gsk-secret"));
+    }
+}
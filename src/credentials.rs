@@ -1,27 +1,138 @@
 //! Secure storage for provider credentials.
+//!
+//! A provider can have several named API keys, since free-tier limits apply
+//! per key. Each key keeps its own usage counters and health so a key that
+//! hit its quota is skipped while the others keep serving (see
+//! [`KeyPool`](crate::providers::key_pool::KeyPool)).
+//!
+//! Tokens are encrypted with a master key from one of the sources in
+//! [`master_key`](crate::master_key).
 
-use std::{fmt, path::PathBuf, sync::Arc};
+use std::{fmt, sync::Arc};
 
 use chacha20poly1305::{
     aead::{Aead, KeyInit, OsRng},
     XChaCha20Poly1305, XNonce,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use sqlx::Row;
-use tokio::fs;
 
 use crate::{
+    config::{KeySelection, MasterKeySource},
+    credential_bundle::BundledKey,
     database::{DbError, DbPool},
     error::AppError,
+    master_key::{self, NewMasterKey, KEY_SIZE},
     providers::Provider,
 };
 
-const KEY_FILENAME: &str = "secret.key";
-const KEY_SIZE: usize = 32;
 const NONCE_SIZE: usize = 24;
 const DEFAULT_HF_BASE_URL: &str = "https://api-inference.huggingface.co";
 
+/// Name of the key written by `add-service` and the setup wizard.
+pub const DEFAULT_KEY_NAME: &str = "default";
+
+/// Whether a stored key may be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    /// The key takes part in selection.
+    Active,
+    /// The key is kept but never selected.
+    Disabled,
+}
+
+impl KeyStatus {
+    /// Returns the status as stored in the database.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Disabled => "disabled",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "disabled" => Self::Disabled,
+            _ => Self::Active,
+        }
+    }
+}
+
+/// A stored key's usage and health, without the secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialKey {
+    /// Provider the key belongs to.
+    pub provider: Provider,
+    /// Name of the key, unique per provider.
+    pub name: String,
+    /// Whether the key takes part in selection.
+    pub status: KeyStatus,
+    /// Requests per UTC day after which the key is skipped.
+    pub daily_limit: Option<i64>,
+    /// Requests sent with the key today (UTC).
+    pub requests_today: i64,
+    /// Requests sent with the key since it was added.
+    pub total_requests: i64,
+    /// When the key was last selected.
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Last rate-limit, quota or authentication error of the key.
+    pub last_error: Option<String>,
+    /// The key is skipped until then after such an error.
+    pub retry_after: Option<DateTime<Utc>>,
+}
+
+impl CredentialKey {
+    /// Returns why the key cannot serve a call at `now`, or `None` when it can.
+    #[must_use]
+    pub fn unavailable(&self, now: DateTime<Utc>) -> Option<String> {
+        if self.status == KeyStatus::Disabled {
+            return Some("disabled".into());
+        }
+        if let Some(until) = self.retry_after.filter(|until| *until > now) {
+            return Some(format!("cooling down until {}", until.format("%H:%M UTC")));
+        }
+        if let Some(limit) = self
+            .daily_limit
+            .filter(|limit| self.requests_today >= *limit)
+        {
+            return Some(format!("daily limit of {limit} reached"));
+        }
+        None
+    }
+}
+
+/// Orders the usable keys for a call, best first.
+///
+/// Round-robin prefers the key used least recently; least-used prefers the
+/// key with the fewest requests today. Names break ties.
+#[must_use]
+pub fn order_keys(
+    mut keys: Vec<CredentialKey>,
+    selection: KeySelection,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    keys.retain(|key| key.unavailable(now).is_none());
+    match selection {
+        KeySelection::RoundRobin => {
+            keys.sort_by(|a, b| (a.last_used_at, &a.name).cmp(&(b.last_used_at, &b.name)));
+        }
+        KeySelection::LeastUsed => {
+            keys.sort_by(|a, b| {
+                (a.requests_today, a.last_used_at, &a.name).cmp(&(
+                    b.requests_today,
+                    b.last_used_at,
+                    &b.name,
+                ))
+            });
+        }
+    }
+    keys.into_iter().map(|key| key.name).collect()
+}
+
 /// Manages provider credentials stored in the database with encryption.
 #[derive(Clone)]
 pub struct CredentialStore {
@@ -36,66 +147,95 @@ impl fmt::Debug for CredentialStore {
 }
 
 impl CredentialStore {
-    /// Initialises the store (generates and loads the master key).
-    pub async fn new(pool: Arc<DbPool>) -> Result<Self, AppError> {
-        let key_path = key_file_path()?;
-        let key_bytes = load_or_create_key(&key_path).await?;
-        let cipher = XChaCha20Poly1305::new(&key_bytes.into());
-        Ok(Self {
+    /// Initialises the store with the master key from `source`, creating
+    /// the key on first use.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the master key cannot be read, unlocked or created.
+    pub async fn new(pool: Arc<DbPool>, source: MasterKeySource) -> Result<Self, AppError> {
+        let key_bytes = master_key::load(source, &master_key::key_dir()?).await?;
+        Ok(Self::with_key(pool, key_bytes))
+    }
+
+    /// Builds a store that encrypts with the given master key.
+    #[must_use]
+    pub fn with_key(pool: Arc<DbPool>, key: [u8; KEY_SIZE]) -> Self {
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        Self {
             pool,
             cipher: Arc::new(cipher),
-        })
+        }
     }
 
     /// Retrieves a decrypted credential for the given provider.
+    ///
+    /// With several keys, the active key named `default` is preferred, then
+    /// the first active key by name.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails or the stored key cannot be decrypted.
     pub async fn get_token(&self, provider: Provider) -> Result<Option<String>, AppError> {
         let record = sqlx::query_as::<_, (Vec<u8>, Vec<u8>)>(
-            r#"SELECT nonce, ciphertext FROM provider_credentials WHERE provider = ?"#,
+            r"SELECT nonce, ciphertext FROM provider_credentials
+               WHERE provider = ? AND status = 'active'
+               ORDER BY name = ? DESC, name
+               LIMIT 1",
         )
         .bind(provider.as_str())
+        .bind(DEFAULT_KEY_NAME)
         .fetch_optional(&*self.pool)
         .await
         .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
 
-        let Some(record) = record else {
-            return Ok(None);
-        };
-
-        let nonce = XNonce::from_slice(&record.0);
-        let plaintext = self
-            .cipher
-            .decrypt(nonce, record.1.as_slice())
-            .map_err(|err| AppError::ApiError(format!("Failed to decrypt credential: {err}")))?;
+        record
+            .map(|(nonce, ciphertext)| self.decrypt(&nonce, &ciphertext))
+            .transpose()
+    }
 
-        let token = String::from_utf8(plaintext)
-            .map_err(|err| AppError::ApiError(format!("Invalid UTF-8 credential: {err}")))?;
+    /// Returns the names and decrypted secrets of the provider's active keys.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails or a key cannot be decrypted.
+    pub async fn active_keys(&self, provider: Provider) -> Result<Vec<(String, String)>, AppError> {
+        let records = sqlx::query_as::<_, (String, Vec<u8>, Vec<u8>)>(
+            r"SELECT name, nonce, ciphertext FROM provider_credentials
+               WHERE provider = ? AND status = 'active'
+               ORDER BY name",
+        )
+        .bind(provider.as_str())
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
 
-        Ok(Some(token))
+        records
+            .into_iter()
+            .map(|(name, nonce, ciphertext)| Ok((name, self.decrypt(&nonce, &ciphertext)?)))
+            .collect()
     }
 
-    /// Inserts or updates a provider credential.
+    /// Inserts or updates a provider's `default` credential.
+    ///
+    /// # Errors
+    ///
+    /// Fails when encryption or the upsert fails.
     pub async fn set_token(&self, provider: Provider, token: &str) -> Result<(), AppError> {
-        let mut nonce_bytes = [0u8; NONCE_SIZE];
-        OsRng.fill_bytes(&mut nonce_bytes);
-        let nonce = XNonce::from_slice(&nonce_bytes);
-
-        let ciphertext = self
-            .cipher
-            .encrypt(nonce, token.as_bytes())
-            .map_err(|err| AppError::ApiError(format!("Failed to encrypt credential: {err}")))?;
-
+        let (nonce, ciphertext) = self.encrypt(token)?;
         let now = Utc::now().to_rfc3339();
 
         let result = sqlx::query(
-            r#"INSERT INTO provider_credentials (provider, nonce, ciphertext, created_at, updated_at)
-               VALUES (?, ?, ?, ?, ?)
-               ON CONFLICT(provider) DO UPDATE SET
+            r"INSERT INTO provider_credentials (provider, name, nonce, ciphertext, created_at, updated_at)
+               VALUES (?, ?, ?, ?, ?, ?)
+               ON CONFLICT(provider, name) DO UPDATE SET
                    nonce = excluded.nonce,
                    ciphertext = excluded.ciphertext,
-                   updated_at = excluded.updated_at"#,
+                   updated_at = excluded.updated_at",
         )
         .bind(provider.as_str())
-        .bind(nonce_bytes.to_vec())
+        .bind(DEFAULT_KEY_NAME)
+        .bind(nonce)
         .bind(ciphertext)
         .bind(now.clone())
         .bind(now)
@@ -108,6 +248,197 @@ impl CredentialStore {
         Ok(())
     }
 
+    /// Stores an additional named key for a provider.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the provider already has a key of that name; use
+    /// [`rotate_key`](Self::rotate_key) to replace its secret. Also fails
+    /// when encryption or the insert fails.
+    pub async fn add_key(
+        &self,
+        provider: Provider,
+        name: &str,
+        token: &str,
+        daily_limit: Option<i64>,
+    ) -> Result<(), AppError> {
+        if self.key(provider, name).await?.is_some() {
+            return Err(AppError::InvalidRequest(format!(
+                "{provider} already has a key named '{name}'"
+            )));
+        }
+        let (nonce, ciphertext) = self.encrypt(token)?;
+        let now = Utc::now().to_rfc3339();
+
+        let result = sqlx::query(
+            r"INSERT INTO provider_credentials
+               (provider, name, nonce, ciphertext, daily_limit, created_at, updated_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(provider.as_str())
+        .bind(name)
+        .bind(nonce)
+        .bind(ciphertext)
+        .bind(daily_limit)
+        .bind(now.clone())
+        .bind(now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Replaces the secret of a named key and clears its errors and today's
+    /// request count. Returns `false` when the key does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Fails when encryption or the update fails.
+    pub async fn rotate_key(
+        &self,
+        provider: Provider,
+        name: &str,
+        token: &str,
+    ) -> Result<bool, AppError> {
+        let (nonce, ciphertext) = self.encrypt(token)?;
+        let result = sqlx::query(
+            r"UPDATE provider_credentials
+               SET nonce = ?, ciphertext = ?, last_error = NULL, retry_after = NULL,
+                   usage_day = NULL, requests_today = 0, updated_at = ?
+               WHERE provider = ? AND name = ?",
+        )
+        .bind(nonce)
+        .bind(ciphertext)
+        .bind(Utc::now().to_rfc3339())
+        .bind(provider.as_str())
+        .bind(name)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Decrypts every stored key for an export bundle. Base URLs are left
+    /// for the caller to fill in from the config.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails or a key cannot be decrypted.
+    pub async fn export_keys(&self) -> Result<Vec<BundledKey>, AppError> {
+        let rows = sqlx::query(
+            r"SELECT provider, name, nonce, ciphertext, status, daily_limit
+               FROM provider_credentials
+               ORDER BY provider, name",
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let mut keys = Vec::with_capacity(rows.len());
+        for row in rows {
+            let Some(provider) = Provider::from_alias(&row.get::<String, _>("provider")) else {
+                continue;
+            };
+            let nonce: Vec<u8> = row.get("nonce");
+            let ciphertext: Vec<u8> = row.get("ciphertext");
+            keys.push(BundledKey {
+                provider,
+                name: row.get("name"),
+                token: self.decrypt(&nonce, &ciphertext)?,
+                status: KeyStatus::from_str(&row.get::<String, _>("status")),
+                daily_limit: row.get("daily_limit"),
+                base_url: None,
+            });
+        }
+        Ok(keys)
+    }
+
+    /// Stores a key from an import bundle, replacing the secret, status and
+    /// limit of an existing key of the same name and clearing its errors.
+    ///
+    /// # Errors
+    ///
+    /// Fails when encryption or the upsert fails.
+    pub async fn import_key(&self, key: &BundledKey) -> Result<(), AppError> {
+        let (nonce, ciphertext) = self.encrypt(&key.token)?;
+        let now = Utc::now().to_rfc3339();
+
+        let result = sqlx::query(
+            r"INSERT INTO provider_credentials
+                   (provider, name, nonce, ciphertext, status, daily_limit, created_at, updated_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+               ON CONFLICT(provider, name) DO UPDATE SET
+                   nonce = excluded.nonce,
+                   ciphertext = excluded.ciphertext,
+                   status = excluded.status,
+                   daily_limit = excluded.daily_limit,
+                   last_error = NULL,
+                   retry_after = NULL,
+                   updated_at = excluded.updated_at",
+        )
+        .bind(key.provider.as_str())
+        .bind(&key.name)
+        .bind(nonce)
+        .bind(ciphertext)
+        .bind(key.status.as_str())
+        .bind(key.daily_limit)
+        .bind(now.clone())
+        .bind(now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Enables or disables a named key. Returns `false` when the key does
+    /// not exist.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn set_key_status(
+        &self,
+        provider: Provider,
+        name: &str,
+        status: KeyStatus,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r"UPDATE provider_credentials SET status = ?, updated_at = ?
+               WHERE provider = ? AND name = ?",
+        )
+        .bind(status.as_str())
+        .bind(Utc::now().to_rfc3339())
+        .bind(provider.as_str())
+        .bind(name)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes one named key.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the delete fails.
+    pub async fn remove_key(&self, provider: Provider, name: &str) -> Result<bool, AppError> {
+        let result =
+            sqlx::query("DELETE FROM provider_credentials WHERE provider = ? AND name = ?")
+                .bind(provider.as_str())
+                .bind(name)
+                .execute(&*self.pool)
+                .await
+                .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Convenience helper for fetching base URLs (with defaults).
     pub fn resolve_base_url<'a>(&self, provider: Provider, configured: Option<&'a str>) -> &'a str {
         match provider {
@@ -116,7 +447,7 @@ impl CredentialStore {
         }
     }
 
-    /// Removes a stored credential.
+    /// Removes every stored key of a provider.
     pub async fn remove_token(&self, provider: Provider) -> Result<bool, AppError> {
         let result = sqlx::query("DELETE FROM provider_credentials WHERE provider = ?")
             .bind(provider.as_str())
@@ -140,7 +471,7 @@ impl CredentialStore {
 
     /// Lists providers that currently have stored credentials.
     pub async fn stored_providers(&self) -> Result<Vec<Provider>, AppError> {
-        let rows = sqlx::query("SELECT provider FROM provider_credentials")
+        let rows = sqlx::query("SELECT DISTINCT provider FROM provider_credentials")
             .fetch_all(&*self.pool)
             .await
             .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
@@ -155,41 +486,304 @@ impl CredentialStore {
 
         Ok(providers)
     }
-}
 
-fn key_file_path() -> Result<PathBuf, AppError> {
-    let config_root = dirs::config_dir()
-        .ok_or_else(|| AppError::ConfigError("Unable to determine config directory".into()))?
-        .join("freegin-ai");
-    Ok(config_root.join(KEY_FILENAME))
-}
+    /// Lists stored keys with their usage and health, optionally for one
+    /// provider.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn list_keys(
+        &self,
+        provider: Option<Provider>,
+    ) -> Result<Vec<CredentialKey>, AppError> {
+        let rows = sqlx::query(
+            r"SELECT provider, name, status, daily_limit, usage_day, requests_today,
+                      total_requests, last_used_at, last_error, retry_after
+               FROM provider_credentials
+               WHERE ? IS NULL OR provider = ?
+               ORDER BY provider, name",
+        )
+        .bind(provider.map(|provider| provider.as_str()))
+        .bind(provider.map(|provider| provider.as_str()))
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
 
-async fn load_or_create_key(path: &PathBuf) -> Result<[u8; KEY_SIZE], AppError> {
-    if let Ok(bytes) = fs::read(path).await {
-        if bytes.len() == KEY_SIZE {
-            let mut key = [0u8; KEY_SIZE];
-            key.copy_from_slice(&bytes);
-            return Ok(key);
+        let today = usage_day(Utc::now());
+        let mut keys = Vec::with_capacity(rows.len());
+        for row in rows {
+            let Some(provider) = Provider::from_alias(&row.get::<String, _>("provider")) else {
+                continue;
+            };
+            let counted_today =
+                row.get::<Option<String>, _>("usage_day").as_deref() == Some(&today);
+            keys.push(CredentialKey {
+                provider,
+                name: row.get("name"),
+                status: KeyStatus::from_str(&row.get::<String, _>("status")),
+                daily_limit: row.get("daily_limit"),
+                requests_today: if counted_today {
+                    row.get("requests_today")
+                } else {
+                    0
+                },
+                total_requests: row.get("total_requests"),
+                last_used_at: row
+                    .get::<Option<String>, _>("last_used_at")
+                    .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|dt| dt.with_timezone(&Utc)),
+                last_error: row.get("last_error"),
+                retry_after: row
+                    .get::<Option<String>, _>("retry_after")
+                    .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|dt| dt.with_timezone(&Utc)),
+            });
         }
+        Ok(keys)
     }
 
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .await
-            .map_err(|err| AppError::ConfigError(format!("Failed to create config dir: {err}")))?;
+    /// Returns one stored key's usage and health.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn key(
+        &self,
+        provider: Provider,
+        name: &str,
+    ) -> Result<Option<CredentialKey>, AppError> {
+        Ok(self
+            .list_keys(Some(provider))
+            .await?
+            .into_iter()
+            .find(|key| key.name == name))
+    }
+
+    /// Names of the provider's keys that can serve a call now, best first.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn key_order(
+        &self,
+        provider: Provider,
+        selection: KeySelection,
+    ) -> Result<Vec<String>, AppError> {
+        let keys = self.list_keys(Some(provider)).await?;
+        Ok(order_keys(keys, selection, Utc::now()))
     }
 
-    let mut key = [0u8; KEY_SIZE];
-    OsRng.fill_bytes(&mut key);
-    fs::write(path, &key)
+    /// Counts a call made with a key.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn record_key_use(&self, provider: Provider, name: &str) -> Result<(), AppError> {
+        let now = Utc::now();
+        let today = usage_day(now);
+        let result = sqlx::query(
+            r"UPDATE provider_credentials
+               SET requests_today = CASE WHEN usage_day = ? THEN requests_today + 1 ELSE 1 END,
+                   usage_day = ?,
+                   total_requests = total_requests + 1,
+                   last_used_at = ?
+               WHERE provider = ? AND name = ?",
+        )
+        .bind(&today)
+        .bind(&today)
+        .bind(now.to_rfc3339())
+        .bind(provider.as_str())
+        .bind(name)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Clears a key's error after a successful call.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn record_key_success(&self, provider: Provider, name: &str) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"UPDATE provider_credentials SET last_error = NULL, retry_after = NULL
+               WHERE provider = ? AND name = ? AND last_error IS NOT NULL",
+        )
+        .bind(provider.as_str())
+        .bind(name)
+        .execute(&*self.pool)
         .await
-        .map_err(|err| AppError::ConfigError(format!("Failed to write key file: {err}")))?;
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(0o600);
-        std::fs::set_permissions(path, perms)
-            .map_err(|err| AppError::ConfigError(format!("Failed to set key perms: {err}")))?;
-    }
-    Ok(key)
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Records a key-specific error; the key is skipped until `retry_after`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn record_key_failure(
+        &self,
+        provider: Provider,
+        name: &str,
+        error_message: &str,
+        retry_after: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"UPDATE provider_credentials SET last_error = ?, retry_after = ?
+               WHERE provider = ? AND name = ?",
+        )
+        .bind(error_message)
+        .bind(retry_after.to_rfc3339())
+        .bind(provider.as_str())
+        .bind(name)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Re-encrypts every stored key under `new_key` and returns a store
+    /// using it, along with the number of keys rewritten.
+    ///
+    /// The rewrite runs in one transaction. The new key is staged next to
+    /// the current one before the commit and only moved into place after
+    /// it, so a wrong old key, an unreadable row or a failed commit leaves
+    /// the key in use and the database untouched. If moving the committed
+    /// key into place fails, the error names where the staged key is kept.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a stored key cannot be decrypted with the current key, the
+    /// new key cannot be staged or installed, or the transaction fails.
+    pub async fn rekey(&self, new_key: &NewMasterKey) -> Result<(Self, u64), AppError> {
+        let rekeyed = Self::with_key(Arc::clone(&self.pool), *new_key.key());
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let records = sqlx::query_as::<_, (String, String, Vec<u8>, Vec<u8>)>(
+            "SELECT provider, name, nonce, ciphertext FROM provider_credentials",
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let mut rewritten = 0;
+        for (provider, name, nonce, ciphertext) in &records {
+            let token = self.decrypt(nonce, ciphertext)?;
+            let (nonce, ciphertext) = rekeyed.encrypt(&token)?;
+            let result = sqlx::query(
+                r"UPDATE provider_credentials SET nonce = ?, ciphertext = ?
+                   WHERE provider = ? AND name = ?",
+            )
+            .bind(nonce)
+            .bind(ciphertext)
+            .bind(provider)
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            rewritten += result.rows_affected();
+        }
+
+        new_key.stage().await?;
+        if let Err(err) = tx.commit().await {
+            new_key.discard().await;
+            return Err(AppError::DatabaseError(DbError::QueryFailed(err)));
+        }
+        new_key.install().await.map_err(|err| {
+            AppError::ConfigError(format!(
+                "Credentials now use the new key, but it could not be moved into place ({err}); it is kept in {}",
+                new_key.staged_location()
+            ))
+        })?;
+        Ok((rekeyed, rewritten))
+    }
+
+    fn encrypt(&self, token: &str) -> Result<(Vec<u8>, Vec<u8>), AppError> {
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = XNonce::from_slice(&nonce_bytes);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(nonce, token.as_bytes())
+            .map_err(|err| AppError::ApiError(format!("Failed to encrypt credential: {err}")))?;
+        Ok((nonce_bytes.to_vec(), ciphertext))
+    }
+
+    fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<String, AppError> {
+        let nonce = XNonce::from_slice(nonce);
+        let plaintext = self
+            .cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|err| AppError::ApiError(format!("Failed to decrypt credential: {err}")))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|err| AppError::ApiError(format!("Invalid UTF-8 credential: {err}")))
+    }
+}
+
+fn usage_day(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn key(name: &str, requests_today: i64, last_used_minutes_ago: Option<i64>) -> CredentialKey {
+        let now = Utc::now();
+        CredentialKey {
+            provider: Provider::Groq,
+            name: name.into(),
+            status: KeyStatus::Active,
+            daily_limit: None,
+            requests_today,
+            total_requests: requests_today,
+            last_used_at: last_used_minutes_ago.map(|minutes| now - Duration::minutes(minutes)),
+            last_error: None,
+            retry_after: None,
+        }
+    }
+
+    #[test]
+    fn keys_are_ordered_by_selection_and_skipped_when_unusable() {
+        let now = Utc::now();
+        let keys = vec![
+            key("a", 5, Some(1)),
+            key("b", 9, Some(30)),
+            key("c", 1, Some(10)),
+        ];
+        assert_eq!(
+            order_keys(keys.clone(), KeySelection::RoundRobin, now),
+            ["b", "c", "a"]
+        );
+        assert_eq!(
+            order_keys(keys.clone(), KeySelection::LeastUsed, now),
+            ["c", "a", "b"]
+        );
+
+        let mut unusable = keys;
+        unusable[0].status = KeyStatus::Disabled;
+        unusable[1].retry_after = Some(now + Duration::minutes(1));
+        unusable[2].daily_limit = Some(1);
+        assert!(order_keys(unusable.clone(), KeySelection::RoundRobin, now).is_empty());
+        assert_eq!(
+            unusable[2].unavailable(now).as_deref(),
+            Some("daily limit of 1 reached")
+        );
+    }
 }
@@ -28,6 +28,27 @@ pub enum DbError {
 /// A handle to the database connection pool.
 pub type DbPool = Pool<Sqlite>;
 
+/// Named, encrypted API keys with their per-key usage and health.
+const CREATE_PROVIDER_CREDENTIALS: &str = r"
+        CREATE TABLE IF NOT EXISTS provider_credentials (
+            provider TEXT NOT NULL,
+            name TEXT NOT NULL,
+            nonce BLOB NOT NULL,
+            ciphertext BLOB NOT NULL,
+            status TEXT NOT NULL DEFAULT 'active',
+            daily_limit INTEGER,
+            usage_day TEXT,
+            requests_today INTEGER NOT NULL DEFAULT 0,
+            total_requests INTEGER NOT NULL DEFAULT 0,
+            last_used_at TEXT,
+            last_error TEXT,
+            retry_after TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (provider, name)
+        )
+        ";
+
 /// Initializes the database connection pool.
 ///
 /// # Arguments
@@ -49,153 +70,300 @@ pub async fn init_db(database_url: &str) -> Result<DbPool, DbError> {
 
 /// Ensures the database schema exists.
 pub async fn ensure_schema(pool: &DbPool) -> Result<(), DbError> {
-    let result = sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS provider_credentials (
-            provider TEXT PRIMARY KEY,
-            nonce BLOB NOT NULL,
-            ciphertext BLOB NOT NULL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .map_err(DbError::QueryFailed)?;
+    create_provider_tables(pool).await?;
 
-    let _ = result.rows_affected();
-
-    let result = sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS provider_usage (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            provider TEXT NOT NULL,
-            model TEXT,
-            success INTEGER NOT NULL,
-            latency_ms INTEGER NOT NULL,
-            error_message TEXT,
-            prompt_tokens INTEGER,
-            completion_tokens INTEGER,
-            total_tokens INTEGER,
-            input_cost_micros INTEGER,
-            output_cost_micros INTEGER,
-            total_cost_micros INTEGER,
-            created_at TEXT NOT NULL
-        )
-        "#,
-    )
-    .execute(pool)
-    .await
-    .map_err(DbError::QueryFailed)?;
+    // Migrate existing databases - add columns if they don't exist
+    migrate_provider_usage_columns(pool).await?;
+    migrate_suggestion_columns(pool).await?;
+    migrate_credential_keys(pool).await?;
+
+    create_provider_indexes(pool).await?;
+    create_job_tables(pool).await?;
+    create_session_tables(pool).await?;
+    create_audit_tables(pool).await?;
+    create_evaluation_tables(pool).await
+}
 
-    let _ = result.rows_affected();
+/// Runs schema statements in order.
+async fn execute_all(pool: &DbPool, statements: &[&str]) -> Result<(), DbError> {
+    for statement in statements {
+        let result = sqlx::query(statement)
+            .execute(pool)
+            .await
+            .map_err(DbError::QueryFailed)?;
+        let _ = result.rows_affected();
+    }
+    Ok(())
+}
 
-    let result = sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS provider_models (
-            provider TEXT NOT NULL,
-            workload TEXT NOT NULL,
-            model TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'active',
-            priority INTEGER NOT NULL DEFAULT 100,
-            rationale TEXT,
-            metadata TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            UNIQUE(provider, workload, model)
-        )
-        "#,
+/// Creates the credential, usage, catalog, health, discovery and alias tables.
+async fn create_provider_tables(pool: &DbPool) -> Result<(), DbError> {
+    execute_all(
+        pool,
+        &[
+            CREATE_PROVIDER_CREDENTIALS,
+            r"
+            CREATE TABLE IF NOT EXISTS provider_usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                model TEXT,
+                workload TEXT,
+                success INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                error_message TEXT,
+                prompt_tokens INTEGER,
+                completion_tokens INTEGER,
+                total_tokens INTEGER,
+                input_cost_micros INTEGER,
+                output_cost_micros INTEGER,
+                total_cost_micros INTEGER,
+                created_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS provider_models (
+                provider TEXT NOT NULL,
+                workload TEXT NOT NULL,
+                model TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'active',
+                priority INTEGER NOT NULL DEFAULT 100,
+                rationale TEXT,
+                metadata TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE(provider, workload, model)
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS provider_model_suggestions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                workload TEXT NOT NULL,
+                model TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                rationale TEXT,
+                metadata TEXT,
+                trial_share REAL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE(provider, workload, model)
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS provider_health (
+                provider TEXT PRIMARY KEY,
+                status TEXT NOT NULL DEFAULT 'available',
+                last_error TEXT,
+                last_error_at TEXT,
+                retry_after TEXT,
+                consecutive_failures INTEGER NOT NULL DEFAULT 0,
+                last_success_at TEXT,
+                updated_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS provider_available_models (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                context_window INTEGER,
+                input_price_per_mtok REAL,
+                output_price_per_mtok REAL,
+                discovered_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model)
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS model_aliases (
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (name, position)
+            )
+            ",
+        ],
     )
-    .execute(pool)
     .await
-    .map_err(DbError::QueryFailed)?;
-
-    let _ = result.rows_affected();
+}
 
-    let result = sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS provider_model_suggestions (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            provider TEXT NOT NULL,
-            workload TEXT NOT NULL,
-            model TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'pending',
-            rationale TEXT,
-            metadata TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            UNIQUE(provider, workload, model)
-        )
-        "#,
+/// Creates the catalog and usage indexes; runs after the column migrations.
+async fn create_provider_indexes(pool: &DbPool) -> Result<(), DbError> {
+    execute_all(
+        pool,
+        &[
+            r"
+            CREATE INDEX IF NOT EXISTS idx_provider_models_active
+            ON provider_models(provider, workload, status, priority)
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_provider_model_suggestions
+            ON provider_model_suggestions(provider, workload, status)
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_provider_usage_provider_model_time
+            ON provider_usage(provider, model, created_at)
+            ",
+        ],
     )
-    .execute(pool)
     .await
-    .map_err(DbError::QueryFailed)?;
-
-    let _ = result.rows_affected();
+}
 
-    let result = sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS provider_health (
-            provider TEXT PRIMARY KEY,
-            status TEXT NOT NULL DEFAULT 'available',
-            last_error TEXT,
-            last_error_at TEXT,
-            retry_after TEXT,
-            consecutive_failures INTEGER NOT NULL DEFAULT 0,
-            last_success_at TEXT,
-            updated_at TEXT NOT NULL
-        )
-        "#,
+/// Creates the background job queue.
+async fn create_job_tables(pool: &DbPool) -> Result<(), DbError> {
+    execute_all(
+        pool,
+        &[
+            r"
+            CREATE TABLE IF NOT EXISTS generation_jobs (
+                id TEXT PRIMARY KEY,
+                status TEXT NOT NULL DEFAULT 'queued',
+                request TEXT NOT NULL,
+                response TEXT,
+                error TEXT,
+                webhook_url TEXT,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                completed_at TEXT
+            )
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_generation_jobs_status
+            ON generation_jobs(status)
+            ",
+        ],
     )
-    .execute(pool)
     .await
-    .map_err(DbError::QueryFailed)?;
-
-    let _ = result.rows_affected();
-
-    // Migrate existing databases - add columns if they don't exist
-    migrate_provider_usage_columns(pool).await?;
+}
 
-    // Create indexes for performance
-    let result = sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_provider_models_active
-        ON provider_models(provider, workload, status, priority)
-        "#,
+/// Creates the chat session tables.
+async fn create_session_tables(pool: &DbPool) -> Result<(), DbError> {
+    execute_all(
+        pool,
+        &[
+            r"
+            CREATE TABLE IF NOT EXISTS chat_sessions (
+                id TEXT PRIMARY KEY,
+                name TEXT UNIQUE,
+                provider TEXT,
+                model TEXT,
+                workload TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS chat_messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                provider TEXT,
+                model TEXT,
+                created_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_chat_messages_session
+            ON chat_messages(session_id, id)
+            ",
+        ],
     )
-    .execute(pool)
     .await
-    .map_err(DbError::QueryFailed)?;
-
-    let _ = result.rows_affected();
+}
 
-    let result = sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_provider_model_suggestions
-        ON provider_model_suggestions(provider, workload, status)
-        "#,
+/// Creates the audit log.
+async fn create_audit_tables(pool: &DbPool) -> Result<(), DbError> {
+    execute_all(
+        pool,
+        &[
+            r"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                provider TEXT,
+                model TEXT,
+                success INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                request TEXT NOT NULL,
+                response TEXT,
+                error TEXT,
+                replay_of TEXT,
+                created_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_audit_log_created
+            ON audit_log(created_at)
+            ",
+        ],
     )
-    .execute(pool)
     .await
-    .map_err(DbError::QueryFailed)?;
-
-    let _ = result.rows_affected();
+}
 
-    let result = sqlx::query(
-        r#"
-        CREATE INDEX IF NOT EXISTS idx_provider_usage_provider_model_time
-        ON provider_usage(provider, model, created_at)
-        "#,
+/// Creates the evaluation and shadow-trial tables.
+async fn create_evaluation_tables(pool: &DbPool) -> Result<(), DbError> {
+    execute_all(
+        pool,
+        &[
+            r"
+            CREATE TABLE IF NOT EXISTS eval_results (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id TEXT NOT NULL,
+                suite TEXT NOT NULL,
+                case_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                workload TEXT NOT NULL,
+                score REAL NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                output TEXT,
+                detail TEXT,
+                error TEXT,
+                created_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS eval_scores (
+                provider TEXT NOT NULL,
+                workload TEXT NOT NULL,
+                model TEXT NOT NULL,
+                score REAL NOT NULL,
+                cases INTEGER NOT NULL,
+                run_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, workload, model)
+            )
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_eval_results_run
+            ON eval_results(run_id)
+            ",
+            r"
+            CREATE TABLE IF NOT EXISTS shadow_results (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                workload TEXT NOT NULL,
+                model TEXT NOT NULL,
+                primary_provider TEXT NOT NULL,
+                primary_model TEXT,
+                success INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                primary_latency_ms INTEGER NOT NULL,
+                output_chars INTEGER NOT NULL,
+                primary_chars INTEGER NOT NULL,
+                similarity REAL,
+                error TEXT,
+                created_at TEXT NOT NULL
+            )
+            ",
+            r"
+            CREATE INDEX IF NOT EXISTS idx_shadow_results_model
+            ON shadow_results(provider, workload, model)
+            ",
+        ],
     )
-    .execute(pool)
     .await
-    .map_err(DbError::QueryFailed)?;
-
-    let _ = result.rows_affected();
-
-    Ok(())
 }
 
 async fn migrate_provider_usage_columns(pool: &DbPool) -> Result<(), DbError> {
@@ -213,6 +381,19 @@ async fn migrate_provider_usage_columns(pool: &DbPool) -> Result<(), DbError> {
         let _ = result.rows_affected();
     }
 
+    // Check if 'workload' column exists in provider_usage
+    let check_result = sqlx::query("SELECT workload FROM provider_usage LIMIT 1")
+        .fetch_optional(pool)
+        .await;
+
+    if check_result.is_err() {
+        let result = sqlx::query("ALTER TABLE provider_usage ADD COLUMN workload TEXT")
+            .execute(pool)
+            .await
+            .map_err(DbError::QueryFailed)?;
+        let _ = result.rows_affected();
+    }
+
     // Check and add cost tracking columns
     let columns = vec![
         "prompt_tokens",
@@ -262,3 +443,50 @@ fn extract_sqlite_path(database_url: &str) -> Option<std::path::PathBuf> {
         Some(Path::new(path).to_path_buf())
     }
 }
+
+async fn migrate_suggestion_columns(pool: &DbPool) -> Result<(), DbError> {
+    // Share of shadow traffic for suggestions on trial
+    let check_result = sqlx::query("SELECT trial_share FROM provider_model_suggestions LIMIT 1")
+        .fetch_optional(pool)
+        .await;
+
+    if check_result.is_err() {
+        let result =
+            sqlx::query("ALTER TABLE provider_model_suggestions ADD COLUMN trial_share REAL")
+                .execute(pool)
+                .await
+                .map_err(DbError::QueryFailed)?;
+        let _ = result.rows_affected();
+    }
+
+    Ok(())
+}
+
+/// Moves credentials stored one per provider into the named-key layout,
+/// keeping each existing key under the name `default`.
+async fn migrate_credential_keys(pool: &DbPool) -> Result<(), DbError> {
+    let check_result = sqlx::query("SELECT name FROM provider_credentials LIMIT 1")
+        .fetch_optional(pool)
+        .await;
+    if check_result.is_ok() {
+        return Ok(());
+    }
+
+    // SQLite cannot change a primary key in place, so the table is rebuilt.
+    let mut tx = pool.begin().await.map_err(DbError::QueryFailed)?;
+    for statement in [
+        "ALTER TABLE provider_credentials RENAME TO provider_credentials_old",
+        CREATE_PROVIDER_CREDENTIALS,
+        r"INSERT INTO provider_credentials (provider, name, nonce, ciphertext, created_at, updated_at)
+           SELECT provider, 'default', nonce, ciphertext, created_at, updated_at
+           FROM provider_credentials_old",
+        "DROP TABLE provider_credentials_old",
+    ] {
+        let result = sqlx::query(statement)
+            .execute(&mut *tx)
+            .await
+            .map_err(DbError::QueryFailed)?;
+        let _ = result.rows_affected();
+    }
+    tx.commit().await.map_err(DbError::QueryFailed)
+}
@@ -0,0 +1,162 @@
+//! Tracking of deprecated and vanished catalog models.
+//!
+//! Providers rename and retire models without notice. Three signals are
+//! collected here: the `deprecation_date` capability, the
+//! `unavailable_since` marker left by model discovery, and repeated "model
+//! not found" errors from real requests, which make the router retire the
+//! model automatically (recorded as `retired_reason` in its metadata).
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{catalog::CatalogStore, error::AppError, models::Workload, providers::Provider};
+
+/// Metadata key holding why a model was retired automatically.
+pub const RETIRED_REASON_KEY: &str = "retired_reason";
+
+/// Returns whether a provider error says the requested model does not exist
+/// (any more), as opposed to a rate limit or outage.
+#[must_use]
+pub fn is_model_gone(error: &str) -> bool {
+    const PATTERNS: [&str; 10] = [
+        "model_not_found",
+        "model not found",
+        "decommissioned",
+        "has been deprecated",
+        "no longer supported",
+        "no longer available",
+        "does not exist",
+        "unknown model",
+        "invalid model",
+        "no such model",
+    ];
+    let lower = error.to_lowercase();
+    PATTERNS.iter().any(|pattern| lower.contains(pattern))
+        || (lower.contains("404") && lower.contains("model"))
+}
+
+/// Why a catalog entry appears in the deprecation report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum DeprecationReason {
+    /// The deprecation date has passed.
+    Deprecated {
+        /// Date the provider retires the model.
+        date: NaiveDate,
+    },
+    /// The deprecation date is within the report window.
+    DeprecatesSoon {
+        /// Date the provider retires the model.
+        date: NaiveDate,
+        /// Days until that date.
+        days_left: i64,
+    },
+    /// Model discovery no longer finds the model.
+    Unlisted {
+        /// When discovery first missed it.
+        since: String,
+    },
+    /// The router retired the model after repeated "not found" errors.
+    AutoRetired {
+        /// Recorded reason, including the last error.
+        reason: String,
+    },
+}
+
+impl DeprecationReason {
+    /// Describes the reason in one line.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Deprecated { date } => format!("deprecated since {date}"),
+            Self::DeprecatesSoon { date, days_left } => {
+                format!("deprecated on {date} (in {days_left} days)")
+            }
+            Self::Unlisted { since } => format!("not listed by provider since {since}"),
+            Self::AutoRetired { reason } => format!("retired automatically: {reason}"),
+        }
+    }
+}
+
+/// One catalog entry that is deprecated, about to be, or gone.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeprecationEntry {
+    /// Provider serving the model.
+    pub provider: Provider,
+    /// Workload of the entry.
+    pub workload: Workload,
+    /// Model identifier.
+    pub model: String,
+    /// Catalog status (`active` or `retired`).
+    pub status: String,
+    /// Why the entry is listed.
+    pub reason: DeprecationReason,
+}
+
+/// Lists catalog entries past their deprecation date or within
+/// `within_days` of it, active entries discovery no longer finds, and
+/// entries the router retired automatically.
+///
+/// # Errors
+///
+/// Fails when the catalog cannot be read.
+pub async fn report(
+    catalog: &CatalogStore,
+    provider: Option<Provider>,
+    today: NaiveDate,
+    within_days: i64,
+) -> Result<Vec<DeprecationEntry>, AppError> {
+    let mut entries = Vec::new();
+    for model in catalog.list_models(provider, None).await? {
+        let active = model.status == "active";
+        let capabilities = model.capabilities();
+        let reason = if let Some(reason) = capabilities
+            .extra
+            .get(RETIRED_REASON_KEY)
+            .and_then(serde_json::Value::as_str)
+            .filter(|_| !active)
+        {
+            DeprecationReason::AutoRetired {
+                reason: reason.to_string(),
+            }
+        } else if let Some(date) = capabilities.deprecation_date.filter(|_| active) {
+            let days_left = (date - today).num_days();
+            if days_left <= 0 {
+                DeprecationReason::Deprecated { date }
+            } else if days_left <= within_days {
+                DeprecationReason::DeprecatesSoon { date, days_left }
+            } else {
+                continue;
+            }
+        } else if let Some(since) = model.unavailable_since().filter(|_| active) {
+            DeprecationReason::Unlisted { since }
+        } else {
+            continue;
+        };
+        entries.push(DeprecationEntry {
+            provider: model.provider,
+            workload: model.workload,
+            model: model.model,
+            status: model.status,
+            reason,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_model_not_found_errors() {
+        assert!(is_model_gone(
+            "API provider error: Groq API error (400 Bad Request): The model `mixtral-8x7b-32768` has been decommissioned"
+        ));
+        assert!(is_model_gone(
+            r#"API provider error: 404 Not Found: {"error":{"code":"model_not_found"}}"#
+        ));
+        assert!(!is_model_gone("API provider error: 429 Too Many Requests"));
+        assert!(!is_model_gone("API provider error: 404 page not found"));
+    }
+}
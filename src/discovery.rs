@@ -0,0 +1,90 @@
+//! Live model discovery from the providers' own model-listing APIs.
+//!
+//! `refresh-models` asks an LLM for candidates, which may name models that
+//! do not exist. Discovery instead records what each provider actually
+//! serves (OpenAI-style `/models`, Gemini `models.list`, Cloudflare's model
+//! search, `OpenRouter`'s free models), copies advertised context lengths and
+//! prices into catalog metadata, and flags active catalog entries the
+//! provider no longer lists.
+
+use serde::Serialize;
+
+use crate::{
+    catalog::CatalogStore,
+    error::AppError,
+    providers::{Provider, ProviderRouter},
+};
+
+/// Outcome of discovering one provider's models.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryReport {
+    /// Provider that was queried.
+    pub provider: Provider,
+    /// Models the provider lists.
+    pub available: usize,
+    /// Models that were not in the previous listing.
+    pub new_models: Vec<String>,
+    /// Catalog entries whose metadata was refreshed.
+    pub updated: usize,
+    /// Active catalog entries the provider no longer lists, as `workload/model`.
+    pub missing: Vec<String>,
+    /// Error when the listing could not be fetched.
+    pub error: Option<String>,
+}
+
+/// Discovers the models of each provider and reconciles the catalog.
+///
+/// A provider whose listing fails is reported with its error; the catalog
+/// is left untouched for it so a transient outage does not flag every model
+/// as missing.
+///
+/// # Errors
+///
+/// Fails when the catalog cannot be updated. Listing failures are reported per
+/// provider instead.
+pub async fn discover(
+    router: &ProviderRouter,
+    catalog: &CatalogStore,
+    providers: &[Provider],
+) -> Result<Vec<DiscoveryReport>, AppError> {
+    let mut reports = Vec::with_capacity(providers.len());
+    for &provider in providers {
+        let models = match router.list_models(provider).await {
+            Ok(models) if !models.is_empty() => models,
+            Ok(_) => {
+                reports.push(failed(provider, "Provider listed no models".into()));
+                continue;
+            }
+            Err(err) => {
+                reports.push(failed(provider, err.to_string()));
+                continue;
+            }
+        };
+
+        let changes = catalog.record_available_models(provider, &models).await?;
+        reports.push(DiscoveryReport {
+            provider,
+            available: models.len(),
+            new_models: changes.new_models,
+            updated: changes.updated,
+            missing: changes
+                .missing
+                .iter()
+                .map(|entry| format!("{}/{}", entry.workload.as_str(), entry.model))
+                .collect(),
+            error: None,
+        });
+    }
+    Ok(reports)
+}
+
+const fn failed(provider: Provider, error: String) -> DiscoveryReport {
+    DiscoveryReport {
+        provider,
+        available: 0,
+        new_models: Vec::new(),
+        updated: 0,
+        missing: Vec::new(),
+        error: Some(error),
+    }
+}
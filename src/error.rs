@@ -27,6 +27,30 @@ pub enum AppError {
     #[error("Network error: {0}")]
     NetworkError(String),
 
+    /// The request itself is malformed or inconsistent.
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    /// Providers answered, but no response satisfied the requested output format.
+    #[error("Invalid provider response: {0}")]
+    InvalidResponse(String),
+
+    /// The requested resource does not exist.
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// The request clashes with existing state, such as a name already in use.
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// Content was rejected by the moderation pipeline.
+    #[error("Guardrail violation: {0}")]
+    GuardrailViolation(String),
+
+    /// Every remaining candidate is a paid model and a spend cap has been reached.
+    #[error("Spend cap reached: {0}")]
+    BudgetExceeded(String),
+
     /// Represents a scenario where no provider was available to handle a request.
     #[error("No available AI provider to handle the request. Run 'freegin-ai status' to check provider health and 'freegin-ai list-services' to verify configuration.")]
     NoProviderAvailable,
@@ -35,14 +59,20 @@ pub enum AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
-            AppError::ConfigError(msg) | AppError::ApiError(msg) | AppError::NetworkError(msg) => {
+            Self::ConfigError(msg) | Self::ApiError(msg) | Self::NetworkError(msg) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, msg)
             }
-            AppError::DatabaseError(db_err) => (
+            Self::DatabaseError(db_err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Internal database issue: {db_err}"),
             ),
-            AppError::NoProviderAvailable => (
+            Self::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            Self::InvalidResponse(msg) => (StatusCode::BAD_GATEWAY, msg),
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            Self::GuardrailViolation(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+            Self::BudgetExceeded(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
+            Self::NoProviderAvailable => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 "All AI providers are currently unavailable or have exceeded their quotas."
                     .to_string(),
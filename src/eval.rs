@@ -0,0 +1,620 @@
+//! Offline evaluation of catalog models against a suite of scored prompts.
+//!
+//! A suite is a YAML file listing prompts per workload, each with a check:
+//!
+//! ```yaml
+//! name: smoke
+//! judge:
+//!   provider: groq
+//!   model: llama-3.3-70b-versatile
+//! cases:
+//!   - id: capital
+//!     workload: chat
+//!     prompt: "What is the capital of France? Answer with one word."
+//!     check: { type: exact, expected: "Paris", ignore_case: true }
+//!   - id: fizzbuzz
+//!     workload: code
+//!     prompt: "Write fizzbuzz in Python."
+//!     check: { type: regex, pattern: "def \\w+\\(" }
+//!   - id: extract
+//!     workload: extraction
+//!     prompt: "Return the names in 'Ann met Bob' as a JSON array."
+//!     check: { type: json }
+//!   - id: tone
+//!     workload: creative
+//!     prompt: "Write a haiku about rain."
+//!     check: { type: judge, criteria: "A 5-7-5 haiku about rain." }
+//! ```
+//!
+//! Every case runs on each active catalog model of its workload whose
+//! provider is configured. Scores range from 0 to 1 and are stored per
+//! result; each model's mean score for the run is kept in `eval_scores`,
+//! which [`CatalogStore::active_models`](crate::catalog::CatalogStore::active_models)
+//! uses to adjust priorities. Generation errors are recorded but not
+//! averaged in, so a rate-limited run does not demote a model.
+
+use std::{collections::HashSet, fs, path::Path, sync::Arc, time::Instant};
+
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::Row;
+use tracing::warn;
+
+use crate::{
+    catalog::CatalogStore,
+    database::{DbError, DbPool},
+    error::AppError,
+    models::{AIRequest, RequestHints, ResponseFormat, Workload},
+    providers::{Provider, ProviderRouter},
+    structured,
+};
+
+/// A set of scored prompts.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalSuite {
+    /// Suite name; defaults to the file stem.
+    #[serde(default)]
+    pub name: String,
+    /// Model grading `judge` checks; normal routing when unset.
+    #[serde(default)]
+    pub judge: Option<JudgeTarget>,
+    /// Prompts to run.
+    pub cases: Vec<EvalCase>,
+}
+
+/// Provider and optional model used as the LLM judge.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JudgeTarget {
+    /// Judge provider.
+    pub provider: String,
+    /// Judge model; the catalog choice when unset.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// A single prompt and how to score the answer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalCase {
+    /// Identifier unique within the suite.
+    pub id: String,
+    /// Workload whose catalog models are evaluated.
+    #[serde(default = "default_workload")]
+    pub workload: Workload,
+    /// Prompt sent to each model.
+    pub prompt: String,
+    /// Scoring rule.
+    pub check: Check,
+}
+
+const fn default_workload() -> Workload {
+    Workload::Chat
+}
+
+/// Scoring rule for a case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Check {
+    /// The trimmed answer equals `expected`.
+    Exact {
+        /// Expected answer.
+        expected: String,
+        /// Compare case-insensitively.
+        #[serde(default)]
+        ignore_case: bool,
+    },
+    /// The answer matches a regular expression.
+    Regex {
+        /// Pattern searched for in the answer.
+        pattern: String,
+    },
+    /// The answer contains valid JSON, optionally matching a schema.
+    Json {
+        /// JSON Schema the document must satisfy.
+        #[serde(default)]
+        schema: Option<Value>,
+    },
+    /// Another model grades the answer from 0 to 10.
+    Judge {
+        /// What a good answer looks like.
+        criteria: String,
+        /// Optional reference answer shown to the judge.
+        #[serde(default)]
+        reference: Option<String>,
+    },
+}
+
+impl EvalSuite {
+    /// Reads and validates a suite file.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or is not a valid suite.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let raw = fs::read_to_string(path).map_err(|err| {
+            AppError::ConfigError(format!("Failed to read suite {}: {err}", path.display()))
+        })?;
+        let mut suite: Self = serde_norway::from_str(&raw).map_err(|err| {
+            AppError::ConfigError(format!("Invalid suite {}: {err}", path.display()))
+        })?;
+        if suite.name.is_empty() {
+            suite.name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("suite")
+                .to_string();
+        }
+        suite.validate()?;
+        Ok(suite)
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        if self.cases.is_empty() {
+            return Err(AppError::ConfigError(format!(
+                "Suite '{}' has no cases",
+                self.name
+            )));
+        }
+        let mut seen = HashSet::new();
+        for case in &self.cases {
+            if !seen.insert(case.id.as_str()) {
+                return Err(AppError::ConfigError(format!(
+                    "Duplicate case id '{}' in suite '{}'",
+                    case.id, self.name
+                )));
+            }
+            if let Check::Regex { pattern } = &case.check {
+                if let Err(err) = Regex::new(pattern) {
+                    return Err(AppError::ConfigError(format!(
+                        "Invalid pattern in case '{}': {err}",
+                        case.id
+                    )));
+                }
+            }
+            if let Check::Json {
+                schema: Some(schema),
+            } = &case.check
+            {
+                structured::check_schema(schema).map_err(|err| {
+                    AppError::ConfigError(format!("Invalid schema in case '{}': {err}", case.id))
+                })?;
+            }
+        }
+        if let Some(judge) = &self.judge {
+            if Provider::from_alias(&judge.provider).is_none() {
+                return Err(AppError::ConfigError(format!(
+                    "Unknown judge provider '{}'",
+                    judge.provider
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Score of one case on one model.
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalResult {
+    /// Case identifier.
+    pub case_id: String,
+    /// Provider evaluated.
+    pub provider: Provider,
+    /// Model evaluated.
+    pub model: String,
+    /// Workload of the case.
+    pub workload: Workload,
+    /// Score between 0 and 1.
+    pub score: f64,
+    /// Generation latency in milliseconds.
+    pub latency_ms: i64,
+    /// Model output.
+    pub output: Option<String>,
+    /// Why the check passed or failed.
+    pub detail: Option<String>,
+    /// Generation error, if the model did not answer.
+    pub error: Option<String>,
+}
+
+/// Mean score of a model over one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelScore {
+    /// Provider of the model.
+    pub provider: String,
+    /// Workload scored.
+    pub workload: String,
+    /// Model identifier.
+    pub model: String,
+    /// Mean score between 0 and 1.
+    pub score: f64,
+    /// Number of answered cases averaged.
+    pub cases: i64,
+    /// Run that produced the score.
+    pub run_id: String,
+    /// RFC 3339 timestamp.
+    pub updated_at: String,
+}
+
+/// Outcome of [`run_suite`].
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    /// Identifier of the run.
+    pub run_id: String,
+    /// Every case/model result.
+    pub results: Vec<EvalResult>,
+    /// Per-model mean scores stored for this run.
+    pub scores: Vec<ModelScore>,
+}
+
+/// Persistence for evaluation results and per-model scores.
+#[derive(Clone, Debug)]
+pub struct EvalStore {
+    pool: Arc<DbPool>,
+}
+
+impl EvalStore {
+    /// Creates a new evaluation store backed by the `SQLite` pool.
+    #[must_use]
+    pub const fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Stores one result.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the insert fails.
+    pub async fn record(
+        &self,
+        run_id: &str,
+        suite: &str,
+        result: &EvalResult,
+    ) -> Result<(), AppError> {
+        let outcome = sqlx::query(
+            r"INSERT INTO eval_results
+               (run_id, suite, case_id, provider, model, workload, score, latency_ms, output, detail, error, created_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(run_id)
+        .bind(suite)
+        .bind(&result.case_id)
+        .bind(result.provider.as_str())
+        .bind(&result.model)
+        .bind(result.workload.as_str())
+        .bind(result.score)
+        .bind(result.latency_ms)
+        .bind(&result.output)
+        .bind(&result.detail)
+        .bind(&result.error)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = outcome.rows_affected();
+        Ok(())
+    }
+
+    /// Replaces the stored score of every model answered in `run_id` with
+    /// its mean over the run, ignoring generation errors.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the scores cannot be updated or read back.
+    pub async fn update_scores(&self, run_id: &str) -> Result<Vec<ModelScore>, AppError> {
+        let outcome = sqlx::query(
+            r"INSERT INTO eval_scores (provider, workload, model, score, cases, run_id, updated_at)
+               SELECT provider, workload, model, AVG(score), COUNT(*), run_id, ?
+               FROM eval_results
+               WHERE run_id = ? AND error IS NULL
+               GROUP BY provider, workload, model
+               ON CONFLICT(provider, workload, model) DO UPDATE SET
+                   score = excluded.score,
+                   cases = excluded.cases,
+                   run_id = excluded.run_id,
+                   updated_at = excluded.updated_at",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(run_id)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = outcome.rows_affected();
+
+        let rows = sqlx::query(
+            r"SELECT provider, workload, model, score, cases, run_id, updated_at
+               FROM eval_scores WHERE run_id = ?
+               ORDER BY workload, score DESC",
+        )
+        .bind(run_id)
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        Ok(rows.iter().map(score_from_row).collect())
+    }
+
+    /// Lists stored model scores, best first within each workload.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn scores(&self, provider: Option<Provider>) -> Result<Vec<ModelScore>, AppError> {
+        let mut sql = String::from(
+            "SELECT provider, workload, model, score, cases, run_id, updated_at FROM eval_scores",
+        );
+        if provider.is_some() {
+            sql.push_str(" WHERE provider = ?");
+        }
+        sql.push_str(" ORDER BY workload, score DESC, provider, model");
+
+        let mut query = sqlx::query(&sql);
+        if let Some(provider) = provider {
+            query = query.bind(provider.as_str());
+        }
+        let rows = query
+            .fetch_all(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        Ok(rows.iter().map(score_from_row).collect())
+    }
+}
+
+fn score_from_row(row: &sqlx::sqlite::SqliteRow) -> ModelScore {
+    ModelScore {
+        provider: row.get("provider"),
+        workload: row.get("workload"),
+        model: row.get("model"),
+        score: row.get("score"),
+        cases: row.get("cases"),
+        run_id: row.get("run_id"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// Runs every case of `suite` against the matching catalog models, stores
+/// the results and returns them with the updated per-model scores.
+///
+/// `provider` limits the run to one provider's models.
+///
+/// # Errors
+///
+/// Fails when the catalog cannot be read or a result cannot be stored.
+pub async fn run_suite(
+    router: &ProviderRouter,
+    catalog: &CatalogStore,
+    store: &EvalStore,
+    suite: &EvalSuite,
+    provider: Option<Provider>,
+) -> Result<EvalReport, AppError> {
+    let run_id = format!("{:032x}", rand::random::<u128>());
+    let mut results = Vec::new();
+
+    for case in &suite.cases {
+        let models: Vec<_> = catalog
+            .list_models(provider, Some(case.workload))
+            .await?
+            .into_iter()
+            .filter(|entry| entry.status == "active" && router.has_provider(entry.provider))
+            .collect();
+        if models.is_empty() {
+            warn!(case = %case.id, workload = case.workload.as_str(), "No configured catalog models for case");
+        }
+
+        for entry in models {
+            let request = AIRequest {
+                model: entry.model.clone(),
+                prompt: case.prompt.clone(),
+                hints: RequestHints {
+                    workload: Some(case.workload),
+                    ..RequestHints::default()
+                },
+                tags: vec![format!("eval:{}", suite.name)],
+                ..AIRequest::default()
+            };
+            let start = Instant::now();
+            let outcome = router.generate_on(entry.provider, &request).await;
+            let latency_ms = i64::try_from(start.elapsed().as_millis()).unwrap_or(i64::MAX);
+
+            let result = match outcome {
+                Ok(response) => {
+                    let (points, detail, error) =
+                        match score_answer(router, suite, case, &response.content).await {
+                            Ok((points, detail)) => (points, Some(detail), None),
+                            Err(err) => (0.0, None, Some(err)),
+                        };
+                    EvalResult {
+                        case_id: case.id.clone(),
+                        provider: entry.provider,
+                        model: entry.model,
+                        workload: case.workload,
+                        score: points,
+                        latency_ms,
+                        output: Some(response.content),
+                        detail,
+                        error,
+                    }
+                }
+                Err(err) => EvalResult {
+                    case_id: case.id.clone(),
+                    provider: entry.provider,
+                    model: entry.model,
+                    workload: case.workload,
+                    score: 0.0,
+                    latency_ms,
+                    output: None,
+                    detail: None,
+                    error: Some(err.to_string()),
+                },
+            };
+            store.record(&run_id, &suite.name, &result).await?;
+            results.push(result);
+        }
+    }
+
+    let scores = store.update_scores(&run_id).await?;
+    Ok(EvalReport {
+        run_id,
+        results,
+        scores,
+    })
+}
+
+/// Scores an answer.
+///
+/// A judge that fails to answer says nothing about the model under test, so
+/// its error is returned and the case is recorded as an error, which keeps it
+/// out of the mean score.
+async fn score_answer(
+    router: &ProviderRouter,
+    suite: &EvalSuite,
+    case: &EvalCase,
+    answer: &str,
+) -> Result<(f64, String), String> {
+    match &case.check {
+        Check::Judge {
+            criteria,
+            reference,
+        } => judge(router, suite, case, criteria, reference.as_deref(), answer)
+            .await
+            .map_err(|err| format!("judge failed: {err}")),
+        check => Ok(score_locally(check, answer)),
+    }
+}
+
+/// Applies the checks that need no model: exact, regex and JSON.
+#[must_use]
+pub fn score_locally(check: &Check, answer: &str) -> (f64, String) {
+    let pass = |ok: bool, detail: String| (if ok { 1.0 } else { 0.0 }, detail);
+    match check {
+        Check::Exact {
+            expected,
+            ignore_case,
+        } => {
+            let answer = answer.trim();
+            let ok = if *ignore_case {
+                answer.eq_ignore_ascii_case(expected.trim())
+            } else {
+                answer == expected.trim()
+            };
+            pass(ok, format!("expected '{}'", expected.trim()))
+        }
+        Check::Regex { pattern } => match Regex::new(pattern) {
+            Ok(regex) => pass(regex.is_match(answer), format!("pattern /{pattern}/")),
+            Err(err) => (0.0, format!("invalid pattern: {err}")),
+        },
+        Check::Json { schema } => match structured::parse_and_validate(answer, schema.as_ref()) {
+            Ok(_) => (1.0, "valid JSON".into()),
+            Err(err) => (0.0, err),
+        },
+        Check::Judge { .. } => (0.0, "judge checks need a model".into()),
+    }
+}
+
+async fn judge(
+    router: &ProviderRouter,
+    suite: &EvalSuite,
+    case: &EvalCase,
+    criteria: &str,
+    reference: Option<&str>,
+    answer: &str,
+) -> Result<(f64, String), AppError> {
+    let reference = reference
+        .map(|text| format!("\nReference answer:\n{text}\n"))
+        .unwrap_or_default();
+    let request = AIRequest {
+        model: suite
+            .judge
+            .as_ref()
+            .and_then(|judge| judge.model.clone())
+            .unwrap_or_default(),
+        prompt: format!(
+            "You are grading an AI model's answer.\n\nQuestion:\n{}\n\nCriteria:\n{criteria}\n{reference}\n\
+             Answer to grade:\n{answer}\n\n\
+             Reply with JSON only: {{\"score\": <integer 0-10>, \"reason\": \"<one sentence>\"}}",
+            case.prompt
+        ),
+        hints: RequestHints {
+            response_format: Some(ResponseFormat::Json),
+            ..RequestHints::default()
+        },
+        tags: vec![format!("eval-judge:{}", suite.name)],
+        ..AIRequest::default()
+    };
+    let response = match suite
+        .judge
+        .as_ref()
+        .and_then(|judge| Provider::from_alias(&judge.provider))
+    {
+        Some(provider) => router.generate_on(provider, &request).await?,
+        None => router.generate(&request).await?,
+    };
+    parse_verdict(&response.content).map_err(AppError::InvalidResponse)
+}
+
+/// Reads `{"score": 0-10, "reason": "..."}` into a 0-1 score and the reason.
+fn parse_verdict(content: &str) -> Result<(f64, String), String> {
+    let value = structured::extract_json(content)?;
+    let score = value
+        .get("score")
+        .and_then(Value::as_f64)
+        .ok_or_else(|| "judge verdict has no numeric score".to_string())?;
+    let reason = value
+        .get("reason")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    Ok(((score / 10.0).clamp(0.0, 1.0), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_suite_checks() {
+        let suite: EvalSuite = serde_norway::from_str(
+            r#"
+cases:
+  - id: capital
+    prompt: "Capital of France?"
+    check: { type: exact, expected: "Paris", ignore_case: true }
+  - id: json
+    workload: extraction
+    prompt: "Names as JSON"
+    check: { type: json }
+"#,
+        )
+        .unwrap();
+        assert_eq!(suite.cases[0].workload, Workload::Chat);
+        assert_eq!(suite.cases[1].workload, Workload::Extraction);
+        assert!(suite.validate().is_ok());
+    }
+
+    #[test]
+    fn scores_local_checks() {
+        let score = |check: &Check, answer: &str| score_locally(check, answer).0;
+        let exact = Check::Exact {
+            expected: "Paris".into(),
+            ignore_case: true,
+        };
+        assert!((score(&exact, " paris\n") - 1.0).abs() < f64::EPSILON);
+        assert!(score(&exact, "Lyon").abs() < f64::EPSILON);
+
+        let regex = Check::Regex {
+            pattern: r"def \w+\(".into(),
+        };
+        assert!((score(&regex, "def fizz(n):") - 1.0).abs() < f64::EPSILON);
+
+        let json = Check::Json { schema: None };
+        assert!((score(&json, "```json\n[\"Ann\"]\n```") - 1.0).abs() < f64::EPSILON);
+        assert!(score(&json, "Ann and Bob").abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn normalises_judge_verdicts() {
+        let (score, reason) = parse_verdict(r#"{"score": 7, "reason": "mostly right"}"#).unwrap();
+        assert!((score - 0.7).abs() < f64::EPSILON);
+        assert_eq!(reason, "mostly right");
+        assert!((parse_verdict(r#"{"score": 14}"#).unwrap().0 - 1.0).abs() < f64::EPSILON);
+        assert!(parse_verdict("great answer").is_err());
+    }
+}
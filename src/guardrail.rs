@@ -0,0 +1,243 @@
+//! Moderation pipeline for requests that carry the `strict` guardrail hint.
+//!
+//! The pipeline has two stages. Local rules (keywords and regular
+//! expressions from `[guardrails]` in the configuration) run on every strict
+//! request and either block or redact matching text. An optional provider
+//! moderation model (for example Llama Guard on Groq or Cloudflare) is then
+//! consulted by the router, which owns the provider clients.
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::{GuardrailAction, GuardrailConfig},
+    error::AppError,
+    providers::Provider,
+};
+
+/// Replacement text used when the pipeline redacts content.
+pub const REDACTION_MARKER: &str = "[REDACTED]";
+
+/// Which side of the provider call is being moderated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationStage {
+    /// The prompt and context sent to the provider.
+    Input,
+    /// The content returned by the provider.
+    Output,
+}
+
+impl ModerationStage {
+    /// Returns a lowercase label for messages and logs.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Output => "output",
+        }
+    }
+}
+
+/// Compiled moderation rules and the optional moderation model.
+#[derive(Debug, Clone)]
+pub struct ModerationPipeline {
+    rules: Vec<Rule>,
+    action: GuardrailAction,
+    moderator: Option<(Provider, String)>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    label: String,
+    pattern: Regex,
+}
+
+impl Default for ModerationPipeline {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            action: GuardrailAction::Block,
+            moderator: None,
+        }
+    }
+}
+
+impl ModerationPipeline {
+    /// Compiles the pipeline from the `[guardrails]` configuration section.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error when a blocked term or pattern does not
+    /// compile.
+    pub fn from_config(config: &GuardrailConfig) -> Result<Self, AppError> {
+        let mut rules = Vec::new();
+
+        for term in config.blocked_terms.iter().map(|term| term.trim()) {
+            if term.is_empty() {
+                continue;
+            }
+            let pattern = RegexBuilder::new(&format!(r"\b{}\b", regex::escape(term)))
+                .case_insensitive(true)
+                .build()
+                .map_err(|err| {
+                    AppError::ConfigError(format!("Invalid guardrail term '{term}': {err}"))
+                })?;
+            rules.push(Rule {
+                label: format!("term '{term}'"),
+                pattern,
+            });
+        }
+
+        for raw in &config.blocked_patterns {
+            let pattern = Regex::new(raw).map_err(|err| {
+                AppError::ConfigError(format!("Invalid guardrail pattern '{raw}': {err}"))
+            })?;
+            rules.push(Rule {
+                label: format!("pattern '{raw}'"),
+                pattern,
+            });
+        }
+
+        let moderator = match (&config.moderation_provider, &config.moderation_model) {
+            (Some(provider), Some(model)) => {
+                let provider = Provider::from_alias(provider).ok_or_else(|| {
+                    AppError::ConfigError(format!(
+                        "Unknown guardrail moderation provider '{provider}'"
+                    ))
+                })?;
+                Some((provider, model.clone()))
+            }
+            (None, None) => None,
+            _ => {
+                return Err(AppError::ConfigError(
+                    "guardrails.moderation_provider and guardrails.moderation_model must be set together"
+                        .into(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            rules,
+            action: config.action,
+            moderator,
+        })
+    }
+
+    /// Returns the configured moderation model, if any.
+    #[must_use]
+    pub fn moderator(&self) -> Option<(Provider, &str)> {
+        self.moderator
+            .as_ref()
+            .map(|(provider, model)| (*provider, model.as_str()))
+    }
+
+    /// Returns the configured action for rule matches.
+    #[must_use]
+    pub const fn action(&self) -> GuardrailAction {
+        self.action
+    }
+
+    /// Applies the local keyword/regex rules to `text`.
+    ///
+    /// Returns the (possibly redacted) text.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `GuardrailViolation` when a rule matches and the pipeline is
+    /// configured to block.
+    pub fn apply_rules(&self, stage: ModerationStage, text: &str) -> Result<String, AppError> {
+        let mut current = text.to_string();
+        for rule in &self.rules {
+            if !rule.pattern.is_match(&current) {
+                continue;
+            }
+            match self.action {
+                GuardrailAction::Block => {
+                    return Err(AppError::GuardrailViolation(format!(
+                        "{} matched blocked {}",
+                        stage.as_str(),
+                        rule.label
+                    )))
+                }
+                GuardrailAction::Redact => {
+                    current = rule
+                        .pattern
+                        .replace_all(&current, REDACTION_MARKER)
+                        .into_owned();
+                }
+            }
+        }
+        Ok(current)
+    }
+}
+
+/// Builds the prompt sent to a moderation model for the given content.
+#[must_use]
+pub fn moderation_prompt(stage: ModerationStage, text: &str) -> String {
+    let role = match stage {
+        ModerationStage::Input => "User",
+        ModerationStage::Output => "Agent",
+    };
+    format!(
+        "Task: Check if there is unsafe content in the '{role}' message below according to \
+         your safety policy. Answer 'safe' or 'unsafe' on the first line, followed by the \
+         violated categories on the second line if unsafe.\n\n{role}: {text}"
+    )
+}
+
+/// Interprets a moderation model verdict.
+///
+/// Returns `Some(categories)` when the content was flagged as unsafe.
+pub fn unsafe_verdict(verdict: &str) -> Option<String> {
+    let mut lines = verdict.trim().lines();
+    let first = lines.next()?.trim().to_lowercase();
+    if !first.starts_with("unsafe") {
+        return None;
+    }
+    let categories = lines.next().map(str::trim).unwrap_or_default();
+    Some(if categories.is_empty() {
+        "unspecified".to_string()
+    } else {
+        categories.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(action: GuardrailAction) -> GuardrailConfig {
+        GuardrailConfig {
+            blocked_terms: vec!["Project Falcon".into()],
+            blocked_patterns: vec![r"\d{3}-\d{2}-\d{4}".into()],
+            action,
+            moderation_provider: None,
+            moderation_model: None,
+        }
+    }
+
+    #[test]
+    fn blocks_matching_terms_case_insensitively() {
+        let pipeline = ModerationPipeline::from_config(&config(GuardrailAction::Block)).unwrap();
+        let result = pipeline.apply_rules(ModerationStage::Input, "status of project falcon?");
+        assert!(matches!(result, Err(AppError::GuardrailViolation(_))));
+        assert!(pipeline
+            .apply_rules(ModerationStage::Input, "status of falconry?")
+            .is_ok());
+    }
+
+    #[test]
+    fn redacts_matching_patterns() {
+        let pipeline = ModerationPipeline::from_config(&config(GuardrailAction::Redact)).unwrap();
+        let text = pipeline
+            .apply_rules(ModerationStage::Output, "SSN is 123-45-6789.")
+            .unwrap();
+        assert_eq!(text, "SSN is [REDACTED].");
+    }
+
+    #[test]
+    fn parses_moderation_verdicts() {
+        assert_eq!(unsafe_verdict("safe"), None);
+        assert_eq!(unsafe_verdict("unsafe\nS1,S10"), Some("S1,S10".into()));
+        assert_eq!(unsafe_verdict(" UNSAFE "), Some("unspecified".into()));
+    }
+}
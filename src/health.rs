@@ -298,6 +298,17 @@ fn classify_error(error_msg: &str) -> ErrorType {
     ErrorType::Transient
 }
 
+/// Returns until when a single API key that failed with `error_message`
+/// should be skipped, or `None` when the error is not specific to the key
+/// (outages and network errors affect every key of the provider alike).
+pub(crate) fn key_retry_after(error_message: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match classify_error(error_message) {
+        ErrorType::RateLimit => Some(now + Duration::minutes(1)),
+        ErrorType::OutOfCredits | ErrorType::AuthFailure => Some(now + Duration::hours(24)),
+        ErrorType::ServiceUnavailable | ErrorType::Transient => None,
+    }
+}
+
 /// Calculates exponential backoff in minutes.
 fn calculate_backoff(consecutive_failures: i64) -> i64 {
     // Start at 1 minute, double each time, cap at 60 minutes
@@ -378,6 +389,20 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_key_retry_after_only_for_key_errors() {
+        let now = Utc::now();
+        assert_eq!(
+            key_retry_after("HTTP 429 Too Many Requests", now),
+            Some(now + Duration::minutes(1))
+        );
+        assert_eq!(
+            key_retry_after("Invalid API key", now),
+            Some(now + Duration::hours(24))
+        );
+        assert_eq!(key_retry_after("Service unavailable", now), None);
+    }
+
     #[test]
     fn test_exponential_backoff() {
         assert_eq!(calculate_backoff(1), 2);
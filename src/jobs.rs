@@ -0,0 +1,440 @@
+//! SQLite-backed queue for asynchronous generation jobs.
+//!
+//! `POST /api/v1/jobs` stores the request and returns immediately; worker
+//! tasks inside the server claim queued jobs, run them through the
+//! `ProviderRouter` and persist the outcome. Because the queue lives in the
+//! database, queued jobs survive a restart, and jobs that were running when
+//! the process stopped are put back in the queue on start-up.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqliteRow, Row};
+use tokio::{sync::Notify, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::JobsConfig,
+    database::{DbError, DbPool},
+    error::AppError,
+    models::{AIRequest, AIResponse},
+    outbound::PublicTarget,
+    providers::ProviderRouter,
+};
+
+/// Lifecycle state of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for a worker.
+    Queued,
+    /// Claimed by a worker and in progress.
+    Running,
+    /// Finished with a response.
+    Succeeded,
+    /// Finished with an error.
+    Failed,
+}
+
+impl JobStatus {
+    /// Returns the identifier stored in the database.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn from_key(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(Self::Queued),
+            "running" => Some(Self::Running),
+            "succeeded" => Some(Self::Succeeded),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A queued or completed generation job.
+#[derive(Debug, Serialize)]
+pub struct Job {
+    /// Opaque job identifier.
+    pub id: String,
+    /// Current lifecycle state.
+    pub status: JobStatus,
+    /// The request to run (not echoed back to clients).
+    #[serde(skip)]
+    pub request: AIRequest,
+    /// Provider response once the job succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<AIResponse>,
+    /// Error message once the job failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// URL notified with the job when it finishes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Number of times a worker has claimed the job.
+    pub attempts: i64,
+    /// RFC 3339 creation timestamp.
+    pub created_at: String,
+    /// RFC 3339 timestamp of the last state change.
+    pub updated_at: String,
+    /// RFC 3339 completion timestamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+}
+
+/// Persistence for generation jobs.
+#[derive(Clone, Debug)]
+pub struct JobStore {
+    pool: Arc<DbPool>,
+}
+
+const JOB_COLUMNS: &str =
+    "id, status, request, response, error, webhook_url, attempts, created_at, updated_at, completed_at";
+
+impl JobStore {
+    /// Creates a new job store backed by the `SQLite` pool.
+    #[must_use]
+    pub const fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a new queued job.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the request cannot be encoded or the insert fails.
+    pub async fn enqueue(
+        &self,
+        request: &AIRequest,
+        webhook_url: Option<&str>,
+    ) -> Result<Job, AppError> {
+        let id = format!("{:032x}", rand::random::<u128>());
+        let now = Utc::now().to_rfc3339();
+        let encoded =
+            serde_json::to_string(request).map_err(|err| AppError::ApiError(err.to_string()))?;
+
+        let result = sqlx::query(
+            r"INSERT INTO generation_jobs
+               (id, status, request, webhook_url, attempts, created_at, updated_at)
+               VALUES (?, 'queued', ?, ?, 0, ?, ?)",
+        )
+        .bind(&id)
+        .bind(encoded)
+        .bind(webhook_url)
+        .bind(&now)
+        .bind(&now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+
+        Ok(Job {
+            id,
+            status: JobStatus::Queued,
+            request: request.clone(),
+            response: None,
+            error: None,
+            webhook_url: webhook_url.map(str::to_string),
+            attempts: 0,
+            created_at: now.clone(),
+            updated_at: now,
+            completed_at: None,
+        })
+    }
+
+    /// Loads a job by identifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails or the stored job cannot be decoded.
+    pub async fn get(&self, id: &str) -> Result<Option<Job>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {JOB_COLUMNS} FROM generation_jobs WHERE id = ?"
+        ))
+        .bind(id)
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        row.as_ref().map(job_from_row).transpose()
+    }
+
+    /// Atomically claims the oldest queued job, marking it as running.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails or the claimed job cannot be decoded.
+    pub async fn claim_next(&self) -> Result<Option<Job>, AppError> {
+        let now = Utc::now().to_rfc3339();
+        let row = sqlx::query(&format!(
+            r"UPDATE generation_jobs
+               SET status = 'running', attempts = attempts + 1, updated_at = ?
+               WHERE status = 'queued'
+                 AND id = (SELECT id FROM generation_jobs WHERE status = 'queued' ORDER BY rowid LIMIT 1)
+               RETURNING {JOB_COLUMNS}"
+        ))
+        .bind(&now)
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        row.as_ref().map(job_from_row).transpose()
+    }
+
+    /// Records a successful outcome.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the response cannot be encoded or the update fails.
+    pub async fn complete(&self, id: &str, response: &AIResponse) -> Result<(), AppError> {
+        let encoded =
+            serde_json::to_string(response).map_err(|err| AppError::ApiError(err.to_string()))?;
+        self.finish(id, JobStatus::Succeeded, Some(encoded), None)
+            .await
+    }
+
+    /// Records a failed outcome.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn fail(&self, id: &str, error: &str) -> Result<(), AppError> {
+        self.finish(id, JobStatus::Failed, None, Some(error.to_string()))
+            .await
+    }
+
+    async fn finish(
+        &self,
+        id: &str,
+        status: JobStatus,
+        response: Option<String>,
+        error: Option<String>,
+    ) -> Result<(), AppError> {
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r"UPDATE generation_jobs
+               SET status = ?, response = ?, error = ?, updated_at = ?, completed_at = ?
+               WHERE id = ?",
+        )
+        .bind(status.as_str())
+        .bind(response)
+        .bind(error)
+        .bind(&now)
+        .bind(&now)
+        .bind(id)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Returns jobs left running by a previous process to the queue.
+    ///
+    /// Call once at start-up, before any worker is spawned.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn requeue_interrupted(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            "UPDATE generation_jobs SET status = 'queued', updated_at = ? WHERE status = 'running'",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn job_from_row(row: &SqliteRow) -> Result<Job, AppError> {
+    let status: String = row.get("status");
+    let request: String = row.get("request");
+    let response: Option<String> = row.get("response");
+
+    Ok(Job {
+        id: row.get("id"),
+        status: JobStatus::from_key(&status)
+            .ok_or_else(|| AppError::ApiError(format!("Unknown job status '{status}'")))?,
+        request: serde_json::from_str(&request)
+            .map_err(|err| AppError::ApiError(format!("Stored job request is invalid: {err}")))?,
+        response: response
+            .map(|raw| serde_json::from_str(&raw))
+            .transpose()
+            .map_err(|err| AppError::ApiError(format!("Stored job response is invalid: {err}")))?,
+        error: row.get("error"),
+        webhook_url: row.get("webhook_url"),
+        attempts: row.get("attempts"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        completed_at: row.get("completed_at"),
+    })
+}
+
+/// Job store plus the signal used to wake idle workers.
+#[derive(Clone, Debug)]
+pub struct JobQueue {
+    store: JobStore,
+    notify: Arc<Notify>,
+}
+
+impl JobQueue {
+    /// Creates a queue over the given store.
+    #[must_use]
+    pub fn new(store: JobStore) -> Self {
+        Self {
+            store,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Returns the underlying store.
+    #[must_use]
+    pub const fn store(&self) -> &JobStore {
+        &self.store
+    }
+
+    /// Validates and enqueues a request, waking one idle worker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an invalid-request error when the webhook URL is not http(s) or
+    /// its host resolves to a non-public address, or the store's error when
+    /// the insert fails.
+    pub async fn submit(
+        &self,
+        request: &AIRequest,
+        webhook_url: Option<&str>,
+    ) -> Result<Job, AppError> {
+        if let Some(url) = webhook_url {
+            drop(PublicTarget::resolve("Webhook", url).await?);
+        }
+        let job = self.store.enqueue(request, webhook_url).await?;
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    /// Spawns the configured number of worker tasks.
+    #[must_use]
+    pub fn spawn_workers(
+        &self,
+        router: &Arc<ProviderRouter>,
+        config: &JobsConfig,
+    ) -> Vec<JoinHandle<()>> {
+        let webhook_timeout = Duration::from_secs(config.webhook_timeout_secs);
+        let poll_interval = Duration::from_millis(config.poll_interval_ms.max(10));
+
+        (0..config.workers)
+            .map(|worker| {
+                let queue = self.clone();
+                let router = Arc::clone(router);
+                tokio::spawn(async move {
+                    queue
+                        .run_worker(worker, &router, webhook_timeout, poll_interval)
+                        .await;
+                })
+            })
+            .collect()
+    }
+
+    async fn run_worker(
+        &self,
+        worker: usize,
+        router: &Arc<ProviderRouter>,
+        webhook_timeout: Duration,
+        poll_interval: Duration,
+    ) {
+        info!(worker, "Job worker started");
+        loop {
+            match self.store.claim_next().await {
+                Ok(Some(job)) => self.process(worker, router, webhook_timeout, job).await,
+                Ok(None) => {
+                    tokio::select! {
+                        () = self.notify.notified() => {}
+                        () = tokio::time::sleep(poll_interval) => {}
+                    }
+                }
+                Err(err) => {
+                    warn!(worker, error = %err, "Failed to claim job");
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Runs one claimed job and records its outcome.
+    ///
+    /// Generation runs in its own task so a panic fails the job instead of
+    /// leaving it `running` until the next restart.
+    async fn process(
+        &self,
+        worker: usize,
+        router: &Arc<ProviderRouter>,
+        webhook_timeout: Duration,
+        job: Job,
+    ) {
+        debug!(worker, job = %job.id, "Running job");
+        let generation = tokio::spawn({
+            let router = Arc::clone(router);
+            let request = job.request.clone();
+            async move { router.generate(&request).await }
+        });
+        let outcome = match generation.await {
+            Ok(Ok(response)) => self.store.complete(&job.id, &response).await,
+            Ok(Err(err)) => self.store.fail(&job.id, &err.to_string()).await,
+            Err(err) => {
+                warn!(worker, job = %job.id, error = %err, "Job generation panicked");
+                self.store
+                    .fail(&job.id, &format!("Job aborted: {err}"))
+                    .await
+            }
+        };
+        if let Err(err) = outcome {
+            warn!(worker, job = %job.id, error = %err, "Failed to record job outcome");
+            return;
+        }
+
+        let Some(url) = job.webhook_url.as_deref() else {
+            return;
+        };
+        match self.store.get(&job.id).await {
+            Ok(Some(finished)) => match deliver_webhook(url, &finished, webhook_timeout).await {
+                Ok(()) => debug!(job = %job.id, "Delivered job webhook"),
+                Err(err) => warn!(job = %job.id, error = %err, "Job webhook delivery failed"),
+            },
+            Ok(None) => {}
+            Err(err) => warn!(job = %job.id, error = %err, "Failed to reload job for webhook"),
+        }
+    }
+}
+
+/// POSTs a finished job to its webhook.
+///
+/// The URL is checked again at delivery time because its DNS may have changed
+/// since submission; the connection is pinned to the checked address and
+/// redirects are not followed.
+async fn deliver_webhook(url: &str, job: &Job, timeout: Duration) -> Result<(), AppError> {
+    let target = PublicTarget::resolve("Webhook", url).await?;
+    let client = target
+        .client_builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|err| AppError::ConfigError(format!("Failed to build HTTP client: {err}")))?;
+    client
+        .post(target.url().clone())
+        .json(job)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map(drop)
+        .map_err(|err| AppError::NetworkError(format!("Webhook {url}: {err}")))
+}
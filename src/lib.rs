@@ -4,15 +4,37 @@
 //! Library entry point exposing the project's modules for reuse in the binary
 //! and integration tests.
 
+pub mod aliases;
+pub mod audit;
+pub mod batch;
+pub mod budget;
+pub mod capabilities;
 pub mod catalog;
+pub mod classifier;
+pub mod compare;
 pub mod config;
+pub mod context;
+pub mod credential_bundle;
 pub mod credentials;
 pub mod database;
+pub mod deprecation;
+pub mod discovery;
 pub mod error;
+pub mod eval;
+pub mod guardrail;
 pub mod health;
+pub mod jobs;
+pub mod master_key;
 pub mod models;
+pub mod outbound;
 pub mod providers;
+pub mod redaction;
+pub mod roster;
 pub mod routes;
+pub mod sessions;
+pub mod shadow;
+pub mod structured;
+pub mod templates;
 pub mod usage;
 
 pub use routes::AppState;
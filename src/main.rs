@@ -8,31 +8,49 @@
 //! - Start the Axum web server and expose HTTP routes.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     env, fs,
-    io::{self, Read},
+    io::{self, Read, Write},
     net::SocketAddr,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
     sync::Arc,
+    time::Instant,
 };
 
 use tracing::{error, info};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use freegin_ai::{
-    catalog::CatalogStore,
-    config,
-    credentials::CredentialStore,
+    aliases::{self, AliasSource, AliasTarget, ModelAlias},
+    audit::{AuditEntry, AuditLog, AuditQuery, REPLAY_OF_KEY},
+    batch::{self, BatchOptions},
+    budget::{Budget, BudgetUsage},
+    catalog::{CatalogStore, ModelEntry},
+    compare::{self, CompareTarget},
+    config::{self, ContextStrategy, MasterKeySource, RedactionConfig, RedactionPolicy},
+    context,
+    credential_bundle::{self, CredentialBundle},
+    credentials::{CredentialStore, KeyStatus, DEFAULT_KEY_NAME},
     database::{self, DbPool},
+    deprecation::{self, DeprecationReason},
+    discovery,
     error::AppError,
-    health::HealthTracker,
+    eval::{self, EvalStore, EvalSuite},
+    health::{HealthTracker, ProviderHealth},
+    jobs::{JobQueue, JobStore},
+    master_key::{self, NewMasterKey},
     models::{
-        AIRequest, RequestComplexity, RequestGuardrail, RequestHints, RequestQuality, RequestSpeed,
-        ResponseFormat, Workload,
+        AIRequest, AIResponse, ChatMessage, ChatRole, ImageInput, RequestComplexity,
+        RequestGuardrail, RequestHints, RequestQuality, RequestSpeed, ResponseFormat, Workload,
     },
     providers::{Provider, ProviderRouter},
+    redaction::{RedactionMap, Redactor},
+    roster::{self, Roster, RosterFormat},
     routes::{self, AppState},
+    sessions::{Session, SessionStore},
+    shadow::ShadowLog,
+    templates::TemplateLibrary,
     usage::UsageLogger,
 };
 use serde::{Deserialize, Serialize};
@@ -44,12 +62,34 @@ enum CliCommand {
     Version,
     Init,
     Generate(GenerateOptions),
+    Batch(BatchOptions),
+    Chat(ChatOptions),
+    Compare(CompareOptions),
+    Eval(EvalOptions),
     RefreshModels(RefreshOptions),
+    DiscoverModels(DiscoverOptions),
+    CatalogExport(CatalogExportOptions),
+    CatalogImport(CatalogImportOptions),
+    AliasList { json: bool },
+    AliasSet(AliasSetOptions),
+    AliasRemove(String),
     ListModels(ListModelsOptions),
     AdoptModel(AdoptModelOptions),
+    RetireModel(RetireModelOptions),
+    DeprecationReport(DeprecationReportOptions),
+    TrialModel(TrialModelOptions),
+    TrialReport(Option<Provider>),
     AddService(Provider),
     RemoveService(Provider),
     ListServices,
+    CredentialsList(Option<Provider>),
+    CredentialsKey(CredentialKeyOptions),
+    CredentialsRekey(Option<MasterKeySource>),
+    CredentialsExport(CredentialExportOptions),
+    CredentialsImport(CredentialImportOptions),
+    ListTemplates,
+    History(HistoryOptions),
+    Replay(ReplayOptions),
     Status(StatusOptions),
 }
 
@@ -59,16 +99,63 @@ struct GenerateOptions {
     prompt_file: Option<PathBuf>,
     output_file: Option<PathBuf>,
     context_files: Vec<PathBuf>,
+    context_strategy: Option<ContextStrategy>,
+    context_budget: Option<usize>,
     metadata: HashMap<String, String>,
     tags: Vec<String>,
     hints: RequestHints,
     response_format: Option<ResponseFormat>,
+    schema_file: Option<PathBuf>,
+    images: Vec<String>,
+    template: Option<String>,
+    template_vars: Vec<(String, String)>,
     provider_override: Option<String>,
     model: Option<String>,
     emit_metadata: bool,
     verbose: bool,
 }
 
+#[derive(Default, Clone, Debug)]
+struct ChatOptions {
+    session: Option<String>,
+    provider: Option<Provider>,
+    model: Option<String>,
+    workload: Option<Workload>,
+}
+
+#[derive(Default, Clone, Debug)]
+struct CompareOptions {
+    prompt: Option<String>,
+    prompt_file: Option<PathBuf>,
+    context_files: Vec<PathBuf>,
+    providers: Vec<String>,
+    models: Vec<String>,
+    workload: Option<Workload>,
+    output_file: Option<PathBuf>,
+    json: bool,
+}
+
+#[derive(Default, Clone, Debug)]
+struct EvalOptions {
+    suite: Option<PathBuf>,
+    provider: Option<Provider>,
+    scores: bool,
+    json: bool,
+}
+
+/// Slash commands understood by the chat REPL.
+#[derive(Debug, PartialEq, Eq)]
+enum ChatCommand {
+    Provider(Option<Provider>),
+    Model(Option<String>),
+    Workload(Option<Workload>),
+    Save(String),
+    Load(Option<String>),
+    Reset,
+    Help,
+    Quit,
+}
+
 #[derive(Default, Clone, Debug)]
 struct RefreshOptions {
     provider: Option<Provider>,
@@ -89,6 +176,124 @@ struct AdoptModelOptions {
     workload: Workload,
     model: String,
     priority: i64,
+    rationale: Option<String>,
+    metadata: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct CatalogExportOptions {
+    out: Option<PathBuf>,
+    format: Option<RosterFormat>,
+    provider: Option<Provider>,
+    active_only: bool,
+}
+
+#[derive(Clone, Debug)]
+struct CatalogImportOptions {
+    file: PathBuf,
+    dry_run: bool,
+    prune: bool,
+}
+
+#[derive(Clone, Debug)]
+struct AliasSetOptions {
+    name: String,
+    targets: Vec<AliasTarget>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct DiscoverOptions {
+    provider: Option<Provider>,
+    json: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyAction {
+    Add,
+    Enable,
+    Disable,
+    Rotate,
+    Remove,
+}
+
+#[derive(Clone, Debug)]
+struct CredentialKeyOptions {
+    action: KeyAction,
+    provider: Provider,
+    name: String,
+    daily_limit: Option<i64>,
+}
+
+/// What `credentials import` does with a key that already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ImportConflict {
+    Skip,
+    Overwrite,
+    Prompt,
+}
+
+#[derive(Clone, Debug)]
+struct CredentialExportOptions {
+    out: PathBuf,
+    force: bool,
+}
+
+#[derive(Clone, Debug)]
+struct CredentialImportOptions {
+    path: PathBuf,
+    on_conflict: ImportConflict,
+}
+
+#[derive(Clone, Debug)]
+struct RetireModelOptions {
+    provider: Provider,
+    model: String,
+    workload: Option<Workload>,
+}
+
+#[derive(Clone, Debug)]
+struct DeprecationReportOptions {
+    provider: Option<Provider>,
+    within_days: i64,
+    retire: bool,
+    json: bool,
+}
+
+#[derive(Clone, Debug)]
+struct TrialModelOptions {
+    provider: Provider,
+    workload: Workload,
+    model: String,
+    share: Option<f64>,
+    stop: bool,
+}
+
+#[derive(Clone, Debug)]
+struct HistoryOptions {
+    id: Option<String>,
+    search: Option<String>,
+    provider: Option<Provider>,
+    failed: bool,
+    limit: i64,
+}
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        Self {
+            id: None,
+            search: None,
+            provider: None,
+            failed: false,
+            limit: 20,
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+struct ReplayOptions {
+    id: String,
+    provider: Option<Provider>,
+    model: Option<String>,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -96,6 +301,21 @@ struct StatusOptions {
     provider: Option<Provider>,
 }
 
+/// Shared infrastructure opened once for every command except `help`,
+/// `version` and `init`.
+struct Runtime {
+    config: config::AppConfig,
+    db_pool: Arc<DbPool>,
+    credential_store: CredentialStore,
+    catalog: CatalogStore,
+}
+
+impl Runtime {
+    fn usage_logger(&self) -> UsageLogger {
+        UsageLogger::new(Arc::clone(&self.db_pool))
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let command = match parse_cli_command() {
@@ -126,8 +346,20 @@ async fn main() {
         _ => {}
     }
 
-    // Load configuration (falls back to defaults if the secrets file is missing).
+    let runtime = open_runtime().await;
+    if matches!(command, CliCommand::Run) {
+        serve(runtime).await;
+        return;
+    }
+    if let Err(err) = run_command(command, &runtime).await {
+        eprintln!("freegin-ai: {err}");
+        process::exit(1);
+    }
+}
 
+/// Loads the configuration and opens the database, credential store and
+/// catalog, exiting the process when any of them is unusable.
+async fn open_runtime() -> Runtime {
     // Load configuration (falls back to defaults if the secrets file is missing).
     let config = match config::AppConfig::load() {
         Ok(cfg) => cfg,
@@ -152,7 +384,8 @@ async fn main() {
         process::exit(1);
     }
 
-    let credential_store = match CredentialStore::new(Arc::clone(&db_pool)).await {
+    let key_source = config.credentials.master_key;
+    let credential_store = match CredentialStore::new(Arc::clone(&db_pool), key_source).await {
         Ok(store) => store,
         Err(err) => {
             error!(error = %err, "Failed to initialise credential store");
@@ -164,84 +397,182 @@ async fn main() {
     let catalog = CatalogStore::new(Arc::clone(&db_pool));
 
     // Seed default models if needed
-    if let Err(err) = catalog.seed_defaults().await {
+    let defaults = config
+        .catalog
+        .roster
+        .as_deref()
+        .map_or_else(Roster::defaults, Roster::load);
+    let seeded = match defaults {
+        Ok(defaults) => catalog.seed_roster(&defaults).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = seeded {
         error!(error = %err, "Failed to seed default models");
         // Non-fatal; continue anyway
     }
 
+    Runtime {
+        config,
+        db_pool,
+        credential_store,
+        catalog,
+    }
+}
+
+/// Runs a one-shot CLI command against the opened runtime.
+async fn run_command(command: CliCommand, runtime: &Runtime) -> Result<(), AppError> {
+    let Runtime {
+        config,
+        db_pool,
+        credential_store,
+        catalog,
+    } = runtime;
+    match command {
+        CliCommand::Generate(_)
+        | CliCommand::Batch(_)
+        | CliCommand::Chat(_)
+        | CliCommand::Compare(_)
+        | CliCommand::Eval(_)
+        | CliCommand::RefreshModels(_)
+        | CliCommand::Replay(_) => run_provider_command(command, runtime).await,
+        CliCommand::CatalogExport(options) => handle_catalog_export(catalog, options).await,
+        CliCommand::CatalogImport(options) => handle_catalog_import(catalog, options).await,
+        CliCommand::AliasList { json } => handle_alias_list(config, catalog, json).await,
+        CliCommand::AliasSet(options) => handle_alias_set(config, catalog, options).await,
+        CliCommand::AliasRemove(name) => handle_alias_remove(config, catalog, &name).await,
+        CliCommand::DiscoverModels(options) => {
+            handle_discover_models(options, config, credential_store, catalog).await
+        }
+        CliCommand::ListModels(options) => handle_list_models(catalog, options).await,
+        CliCommand::AdoptModel(options) => handle_adopt_model(catalog, options).await,
+        CliCommand::RetireModel(options) => handle_retire_model(catalog, options).await,
+        CliCommand::DeprecationReport(options) => handle_deprecation_report(catalog, options).await,
+        CliCommand::TrialModel(options) => handle_trial_model(catalog, config, options).await,
+        CliCommand::TrialReport(provider) => {
+            let shadow_log = ShadowLog::new(Arc::clone(db_pool));
+            handle_trial_report(catalog, &shadow_log, provider).await
+        }
+        CliCommand::AddService(provider) => handle_add_service(provider, credential_store).await,
+        CliCommand::RemoveService(provider) => {
+            handle_remove_service(provider, credential_store).await
+        }
+        CliCommand::ListServices => handle_list_services(credential_store, config).await,
+        CliCommand::CredentialsList(provider) => {
+            handle_credentials_list(credential_store, provider).await
+        }
+        CliCommand::CredentialsKey(options) => {
+            handle_credentials_key(options, credential_store).await
+        }
+        CliCommand::CredentialsRekey(target) => {
+            handle_credentials_rekey(target, config, credential_store).await
+        }
+        CliCommand::CredentialsExport(options) => {
+            handle_credentials_export(&options, config, credential_store).await
+        }
+        CliCommand::CredentialsImport(options) => {
+            handle_credentials_import(options, config, credential_store).await
+        }
+        CliCommand::ListTemplates => handle_list_templates(config),
+        CliCommand::History(options) => handle_history(options, config, Arc::clone(db_pool)).await,
+        CliCommand::Status(options) => {
+            handle_status(catalog, Arc::clone(db_pool), &config.budget, options).await
+        }
+        CliCommand::Run | CliCommand::Help | CliCommand::Version | CliCommand::Init => {
+            unreachable!()
+        }
+    }
+}
+
+/// Runs a CLI command that sends requests to providers and logs their usage.
+async fn run_provider_command(command: CliCommand, runtime: &Runtime) -> Result<(), AppError> {
+    let Runtime {
+        config,
+        db_pool,
+        credential_store,
+        catalog,
+    } = runtime;
+    let usage_logger = runtime.usage_logger();
     match command {
         CliCommand::Generate(options) => {
-            let usage_logger = UsageLogger::new(Arc::clone(&db_pool));
-            if let Err(err) = handle_generate(
+            handle_generate(
                 options,
-                &config,
-                &credential_store,
-                &catalog,
+                config,
+                credential_store,
+                catalog,
                 Some(usage_logger),
             )
             .await
-            {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
-        }
-        CliCommand::RefreshModels(options) => {
-            let usage_logger = UsageLogger::new(Arc::clone(&db_pool));
-            if let Err(err) =
-                handle_refresh_models(options, &config, &credential_store, &catalog, usage_logger)
-                    .await
-            {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
         }
-        CliCommand::ListModels(options) => {
-            if let Err(err) = handle_list_models(&catalog, options).await {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
+        CliCommand::Batch(options) => {
+            handle_batch(
+                options,
+                config,
+                credential_store,
+                catalog,
+                Some(usage_logger),
+            )
+            .await
         }
-        CliCommand::AdoptModel(options) => {
-            if let Err(err) = handle_adopt_model(&catalog, options).await {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
+        CliCommand::Chat(options) => {
+            let sessions = SessionStore::new(Arc::clone(db_pool));
+            handle_chat(
+                options,
+                config,
+                credential_store,
+                catalog,
+                Some(usage_logger),
+                &sessions,
+            )
+            .await
         }
-        CliCommand::AddService(provider) => {
-            if let Err(err) = handle_add_service(provider, &credential_store).await {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
+        CliCommand::Compare(options) => {
+            handle_compare(
+                options,
+                config,
+                credential_store,
+                catalog,
+                Some(usage_logger),
+            )
+            .await
         }
-        CliCommand::RemoveService(provider) => {
-            if let Err(err) = handle_remove_service(provider, &credential_store).await {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
+        CliCommand::Eval(options) => {
+            let store = EvalStore::new(Arc::clone(db_pool));
+            handle_eval(
+                options,
+                config,
+                credential_store,
+                catalog,
+                usage_logger,
+                &store,
+            )
+            .await
         }
-        CliCommand::ListServices => {
-            if let Err(err) = handle_list_services(&credential_store, &config).await {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
+        CliCommand::RefreshModels(options) => {
+            handle_refresh_models(options, config, credential_store, catalog, usage_logger).await
         }
-        CliCommand::Status(options) => {
-            if let Err(err) = handle_status(&catalog, Arc::clone(&db_pool), options).await {
-                eprintln!("freegin-ai: {err}");
-                process::exit(1);
-            }
-            return;
+        CliCommand::Replay(options) => {
+            handle_replay(
+                options,
+                config,
+                credential_store,
+                catalog,
+                usage_logger,
+                Arc::clone(db_pool),
+            )
+            .await
         }
-        CliCommand::Run => {}
-        CliCommand::Help | CliCommand::Version | CliCommand::Init => unreachable!(),
+        _ => unreachable!(),
     }
+}
+
+/// Starts the job workers and serves the HTTP API until the process exits.
+async fn serve(runtime: Runtime) {
+    let Runtime {
+        config,
+        db_pool,
+        credential_store,
+        catalog,
+    } = runtime;
 
     // Initialize tracing based on RUST_LOG or the fallback filter.
     tracing_subscriber::registry()
@@ -275,8 +606,28 @@ async fn main() {
     // Initialize the database connection pool once migrations exist.
     // info!("Database connection pool established");
 
+    // Start the background job workers; jobs interrupted by a previous shutdown
+    // go back to the queue first.
+    let job_queue = JobQueue::new(JobStore::new(Arc::clone(&db_pool)));
+    match job_queue.store().requeue_interrupted().await {
+        Ok(0) => {}
+        Ok(count) => info!(count, "Requeued jobs interrupted by the previous shutdown"),
+        Err(err) => error!(error = %err, "Failed to requeue interrupted jobs"),
+    }
+    let workers = job_queue.spawn_workers(&provider_router, &config.jobs);
+    info!(workers = workers.len(), "Job workers started");
+
     // Build the HTTP router.
-    let state = AppState::new(provider_router);
+    let state = AppState::new(provider_router)
+        .with_jobs(job_queue)
+        .with_sessions(SessionStore::new(Arc::clone(&db_pool)));
+    let state = match TemplateLibrary::from_config(&config.templates) {
+        Ok(templates) => state.with_templates(templates),
+        Err(err) => {
+            error!(error = %err, "Prompt templates disabled");
+            state
+        }
+    };
     let app = routes::api_router(state);
 
     let addr_str = format!("{}:{}", config.server.host, config.server.port);
@@ -297,67 +648,129 @@ async fn main() {
 }
 
 fn parse_cli_command() -> Result<CliCommand, String> {
-    let mut args: Vec<String> = env::args().collect();
-    if args.is_empty() {
-        return Ok(CliCommand::Run);
-    }
-
-    let _program = args.remove(0);
-    if args.is_empty() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((first, rest)) = args.split_first() else {
         return Ok(CliCommand::Run);
-    }
+    };
 
-    let mut iter = args.into_iter();
-    let first = iter.next().unwrap();
     match first.as_str() {
         "-h" | "--help" | "help" => Ok(CliCommand::Help),
         "-V" | "--version" | "version" => Ok(CliCommand::Version),
         "--init" | "init" => Ok(CliCommand::Init),
-        "generate" => {
-            let remaining: Vec<String> = iter.collect();
-            let options = parse_generate_options(&remaining)?;
-            Ok(CliCommand::Generate(options))
-        }
-        "refresh-models" => {
-            let remaining: Vec<String> = iter.collect();
-            let options = parse_refresh_options(&remaining)?;
-            Ok(CliCommand::RefreshModels(options))
-        }
-        "list-models" => {
-            let remaining: Vec<String> = iter.collect();
-            let options = parse_list_models_options(&remaining)?;
-            Ok(CliCommand::ListModels(options))
-        }
-        "adopt-model" => {
-            let remaining: Vec<String> = iter.collect();
-            let options = parse_adopt_model_options(&remaining)?;
-            Ok(CliCommand::AdoptModel(options))
+        "generate" => parse_generate_options(rest).map(CliCommand::Generate),
+        "batch" => parse_batch_options(rest).map(CliCommand::Batch),
+        "chat" => parse_chat_options(rest).map(CliCommand::Chat),
+        "compare" => parse_compare_options(rest).map(CliCommand::Compare),
+        "eval" => parse_eval_options(rest).map(CliCommand::Eval),
+        "refresh-models" => parse_refresh_options(rest).map(CliCommand::RefreshModels),
+        "catalog" => parse_catalog_command(rest),
+        "alias" => parse_alias_command(rest),
+        "discover-models" => parse_discover_options(rest).map(CliCommand::DiscoverModels),
+        "list-models" => parse_list_models_options(rest).map(CliCommand::ListModels),
+        "adopt-model" => parse_adopt_model_options(rest).map(CliCommand::AdoptModel),
+        "retire-model" => parse_retire_model_options(rest).map(CliCommand::RetireModel),
+        "deprecation-report" => {
+            parse_deprecation_report_options(rest).map(CliCommand::DeprecationReport)
         }
+        "trial-model" => parse_trial_model_options(rest).map(CliCommand::TrialModel),
+        "trial-report" => match rest {
+            [] => Ok(CliCommand::TrialReport(None)),
+            [flag, name] if flag == "--provider" => {
+                Ok(CliCommand::TrialReport(Some(parse_provider(name)?)))
+            }
+            _ => Err("Usage: trial-report [--provider <name>]".into()),
+        },
         "add-service" => {
-            let name = iter
-                .next()
+            let name = rest
+                .first()
                 .ok_or_else(|| "add-service requires a provider name".to_string())?;
-            let provider = parse_provider(&name)?;
-            Ok(CliCommand::AddService(provider))
+            parse_provider(name).map(CliCommand::AddService)
         }
         "remove-service" => {
-            let name = iter
-                .next()
+            let name = rest
+                .first()
                 .ok_or_else(|| "remove-service requires a provider name".to_string())?;
-            let provider = parse_provider(&name)?;
-            Ok(CliCommand::RemoveService(provider))
+            parse_provider(name).map(CliCommand::RemoveService)
         }
         "list-services" => Ok(CliCommand::ListServices),
-        "status" => {
-            let remaining: Vec<String> = iter.collect();
-            let options = parse_status_options(&remaining)?;
-            Ok(CliCommand::Status(options))
-        }
+        "credentials" => parse_credentials_command(rest),
+        "list-templates" => Ok(CliCommand::ListTemplates),
+        "history" => parse_history_options(rest).map(CliCommand::History),
+        "replay" => parse_replay_options(rest).map(CliCommand::Replay),
+        "status" => parse_status_options(rest).map(CliCommand::Status),
         other if other.starts_with('-') => Err(format!("Unknown option '{other}'")),
         _ => Ok(CliCommand::Run),
     }
 }
 
+/// Parses `catalog export|import ...`.
+fn parse_catalog_command(args: &[String]) -> Result<CliCommand, String> {
+    let (action, remaining) = args
+        .split_first()
+        .ok_or_else(|| "catalog requires 'export' or 'import'".to_string())?;
+    match action.as_str() {
+        "export" => parse_catalog_export_options(remaining).map(CliCommand::CatalogExport),
+        "import" => parse_catalog_import_options(remaining).map(CliCommand::CatalogImport),
+        other => Err(format!(
+            "Unknown catalog action '{other}'; expected export or import"
+        )),
+    }
+}
+
+/// Parses `alias list|set|remove ...`.
+fn parse_alias_command(args: &[String]) -> Result<CliCommand, String> {
+    let (action, remaining) = args
+        .split_first()
+        .ok_or_else(|| "alias requires 'list', 'set' or 'remove'".to_string())?;
+    match action.as_str() {
+        "list" => match remaining {
+            [] => Ok(CliCommand::AliasList { json: false }),
+            [flag] if flag == "--json" => Ok(CliCommand::AliasList { json: true }),
+            _ => Err("alias list only accepts --json".into()),
+        },
+        "set" => parse_alias_set_options(remaining).map(CliCommand::AliasSet),
+        "remove" => match remaining {
+            [name] => Ok(CliCommand::AliasRemove(name.clone())),
+            _ => Err("alias remove requires exactly one alias name".into()),
+        },
+        other => Err(format!(
+            "Unknown alias action '{other}'; expected list, set or remove"
+        )),
+    }
+}
+
+/// Parses `credentials <action> ...`.
+fn parse_credentials_command(args: &[String]) -> Result<CliCommand, String> {
+    let (action, remaining) = args.split_first().ok_or_else(|| {
+        "credentials requires 'list', 'add', 'enable', 'disable', 'rotate', 'remove', 'rekey', 'export' or 'import'"
+            .to_string()
+    })?;
+    match action.as_str() {
+        "list" => match remaining {
+            [] => Ok(CliCommand::CredentialsList(None)),
+            [flag, name] if flag == "--provider" => {
+                Ok(CliCommand::CredentialsList(Some(parse_provider(name)?)))
+            }
+            _ => Err("Usage: credentials list [--provider <name>]".into()),
+        },
+        "rekey" => match remaining {
+            [] => Ok(CliCommand::CredentialsRekey(None)),
+            [flag, source] if flag == "--to" => Ok(CliCommand::CredentialsRekey(Some(
+                parse_master_key_source(source)?,
+            ))),
+            _ => Err("Usage: credentials rekey [--to file|passphrase|keyring]".into()),
+        },
+        "export" => parse_credential_export_options(remaining).map(CliCommand::CredentialsExport),
+        "import" => parse_credential_import_options(remaining).map(CliCommand::CredentialsImport),
+        "add" | "enable" | "disable" | "rotate" | "remove" => {
+            parse_credential_key_options(action, remaining).map(CliCommand::CredentialsKey)
+        }
+        other => Err(format!(
+            "Unknown credentials action '{other}'; expected list, add, enable, disable, rotate, remove, rekey, export or import"
+        )),
+    }
+}
+
 fn parse_generate_options(args: &[String]) -> Result<GenerateOptions, String> {
     let mut options = GenerateOptions::default();
     let mut iter = args.iter();
@@ -406,53 +819,28 @@ fn parse_generate_options(args: &[String]) -> Result<GenerateOptions, String> {
                     .ok_or_else(|| "--tag requires a value".to_string())?;
                 options.tags.push(value.trim().to_string());
             }
-            "--complexity" => {
-                let value = iter
-                    .next()
-                    .ok_or_else(|| "--complexity requires low|medium|high".to_string())?;
-                options.hints.complexity = Some(parse_complexity(value)?);
-            }
-            "--quality" => {
-                let value = iter
-                    .next()
-                    .ok_or_else(|| "--quality requires standard|balanced|premium".to_string())?;
-                options.hints.quality = Some(parse_quality(value)?);
-            }
-            "--speed" => {
-                let value = iter
-                    .next()
-                    .ok_or_else(|| "--speed requires fast|normal".to_string())?;
-                options.hints.speed = Some(parse_speed(value)?);
-            }
-            "--guardrail" => {
-                let value = iter
-                    .next()
-                    .ok_or_else(|| "--guardrail requires strict|lenient".to_string())?;
-                options.hints.guardrail = Some(parse_guardrail(value)?);
-            }
-            "--format" => {
+            "--template" => {
                 let value = iter
                     .next()
-                    .ok_or_else(|| "--format requires text|markdown|json".to_string())?;
-                let format = parse_response_format(value)?;
-                options.response_format = Some(format);
-                options.hints.response_format = Some(format);
+                    .ok_or_else(|| "--template requires a template name".to_string())?;
+                options.template = Some(value.clone());
             }
-            "--provider" => {
+            "--var" => {
                 let value = iter
                     .next()
-                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
-                let provider = parse_provider(value)?;
-                let alias = provider.as_str().to_string();
-                options.provider_override = Some(alias.clone());
-                options.hints.provider = Some(alias.clone());
-                options.tags.push(format!("provider:{alias}"));
+                    .ok_or_else(|| "--var requires key=value".to_string())?;
+                let (key, val) = value.split_once('=').ok_or_else(|| {
+                    format!("Invalid --var '{value}'. Expected key=value or key=@file")
+                })?;
+                options
+                    .template_vars
+                    .push((key.trim().to_string(), val.to_string()));
             }
-            "--model" => {
+            "--image" => {
                 let value = iter
                     .next()
-                    .ok_or_else(|| "--model requires a value".to_string())?;
-                options.model = Some(value.clone());
+                    .ok_or_else(|| "--image requires a path or URL".to_string())?;
+                options.images.push(value.clone());
             }
             "--emit-metadata" => {
                 options.emit_metadata = true;
@@ -461,7 +849,9 @@ fn parse_generate_options(args: &[String]) -> Result<GenerateOptions, String> {
                 options.verbose = true;
             }
             other => {
-                return Err(format!("Unknown generate option '{other}'"));
+                if !parse_generate_hint(other, &mut iter, &mut options)? {
+                    return Err(format!("Unknown generate option '{other}'"));
+                }
             }
         }
     }
@@ -473,30 +863,425 @@ fn parse_generate_options(args: &[String]) -> Result<GenerateOptions, String> {
     Ok(options)
 }
 
-fn parse_refresh_options(args: &[String]) -> Result<RefreshOptions, String> {
-    let mut options = RefreshOptions::default();
-    let mut iter = args.iter();
-    while let Some(arg) = iter.next() {
-        match arg.as_str() {
-            "--provider" => {
-                let value = iter
-                    .next()
-                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
-                options.provider = Some(parse_provider(value)?);
-            }
-            "--workload" => {
-                let value = iter
-                    .next()
-                    .ok_or_else(|| "--workload requires a value".to_string())?;
-                options.workload = Some(parse_workload(value)?);
-            }
-            "--dry-run" => options.dry_run = true,
-            other => return Err(format!("Unknown refresh-models option '{other}'")),
+/// Parses a `generate` flag that steers routing, context fitting or the
+/// output format. Returns `Ok(false)` when `flag` is not one of them.
+fn parse_generate_hint(
+    flag: &str,
+    iter: &mut std::slice::Iter<'_, String>,
+    options: &mut GenerateOptions,
+) -> Result<bool, String> {
+    match flag {
+        "--context-strategy" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--context-strategy requires a value".to_string())?;
+            options.context_strategy = Some(parse_context_strategy(value)?);
         }
-    }
-    Ok(options)
-}
-
+        "--context-budget" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--context-budget requires a token count".to_string())?;
+            options.context_budget = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("Invalid --context-budget '{value}'"))?,
+            );
+        }
+        "--complexity" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--complexity requires low|medium|high".to_string())?;
+            options.hints.complexity = Some(parse_complexity(value)?);
+        }
+        "--workload" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--workload requires a value".to_string())?;
+            options.hints.workload = Some(parse_workload(value)?);
+        }
+        "--quality" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--quality requires standard|balanced|premium".to_string())?;
+            options.hints.quality = Some(parse_quality(value)?);
+        }
+        "--speed" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--speed requires fast|normal".to_string())?;
+            options.hints.speed = Some(parse_speed(value)?);
+        }
+        "--guardrail" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--guardrail requires strict|lenient".to_string())?;
+            options.hints.guardrail = Some(parse_guardrail(value)?);
+        }
+        "--format" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--format requires text|markdown|json".to_string())?;
+            let format = parse_response_format(value)?;
+            options.response_format = Some(format);
+            options.hints.response_format = Some(format);
+        }
+        "--schema" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--schema requires a path".to_string())?;
+            options.schema_file = Some(PathBuf::from(value));
+            options.response_format = Some(ResponseFormat::Json);
+            options.hints.response_format = Some(ResponseFormat::Json);
+        }
+        "--provider" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--provider requires a provider name".to_string())?;
+            let provider = parse_provider(value)?;
+            let alias = provider.as_str().to_string();
+            options.provider_override = Some(alias.clone());
+            options.hints.provider = Some(alias.clone());
+            options.tags.push(format!("provider:{alias}"));
+        }
+        "--model" => {
+            let value = iter
+                .next()
+                .ok_or_else(|| "--model requires a value".to_string())?;
+            options.model = Some(value.clone());
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn parse_refresh_options(args: &[String]) -> Result<RefreshOptions, String> {
+    let mut options = RefreshOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--workload" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--workload requires a value".to_string())?;
+                options.workload = Some(parse_workload(value)?);
+            }
+            "--dry-run" => options.dry_run = true,
+            other => return Err(format!("Unknown refresh-models option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_batch_options(args: &[String]) -> Result<BatchOptions, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut concurrency = batch::DEFAULT_CONCURRENCY;
+    let mut resume = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--input" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--input requires a path".to_string())?;
+                input = Some(PathBuf::from(value));
+            }
+            "--output" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--output requires a path".to_string())?;
+                output = Some(PathBuf::from(value));
+            }
+            "--concurrency" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--concurrency requires a positive integer".to_string())?;
+                concurrency = value
+                    .parse()
+                    .ok()
+                    .filter(|value: &usize| *value > 0)
+                    .ok_or_else(|| "--concurrency must be a positive integer".to_string())?;
+            }
+            "--resume" => resume = true,
+            other => return Err(format!("Unknown batch option '{other}'")),
+        }
+    }
+
+    Ok(BatchOptions {
+        input: input.ok_or_else(|| "batch requires --input <file>".to_string())?,
+        output: output.ok_or_else(|| "batch requires --output <file>".to_string())?,
+        concurrency,
+        resume,
+    })
+}
+
+fn parse_compare_options(args: &[String]) -> Result<CompareOptions, String> {
+    let mut options = CompareOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--prompt" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--prompt requires a value".to_string())?;
+                options.prompt = Some(value.clone());
+            }
+            "--prompt-file" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--prompt-file requires a path".to_string())?;
+                options.prompt_file = Some(PathBuf::from(value));
+            }
+            "--context-file" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--context-file requires a path".to_string())?;
+                options.context_files.push(PathBuf::from(value));
+            }
+            "--providers" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--providers requires a comma-separated list".to_string())?;
+                options.providers.extend(split_list(value));
+            }
+            "--models" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--models requires a comma-separated list".to_string())?;
+                options.models.extend(split_list(value));
+            }
+            "--workload" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--workload requires a value".to_string())?;
+                options.workload = Some(parse_workload(value)?);
+            }
+            "--output-file" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--output-file requires a path".to_string())?;
+                options.output_file = Some(PathBuf::from(value));
+            }
+            "--json" => options.json = true,
+            other => return Err(format!("Unknown compare option '{other}'")),
+        }
+    }
+
+    if options.prompt.is_some() && options.prompt_file.is_some() {
+        return Err("Use either --prompt or --prompt-file, not both".into());
+    }
+    if options.providers.len() + options.models.len() < 2 {
+        return Err("compare requires at least two targets in --providers or --models".into());
+    }
+    Ok(options)
+}
+
+fn parse_eval_options(args: &[String]) -> Result<EvalOptions, String> {
+    let mut options = EvalOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--suite" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--suite requires a path".to_string())?;
+                options.suite = Some(PathBuf::from(value));
+            }
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--scores" => options.scores = true,
+            "--json" => options.json = true,
+            other => return Err(format!("Unknown eval option '{other}'")),
+        }
+    }
+
+    if options.suite.is_none() && !options.scores {
+        return Err("eval requires --suite <file> (or --scores to list stored scores)".into());
+    }
+    Ok(options)
+}
+
+fn parse_catalog_export_options(args: &[String]) -> Result<CatalogExportOptions, String> {
+    let mut options = CatalogExportOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--out" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--out requires a path".to_string())?;
+                options.out = Some(PathBuf::from(value));
+            }
+            "--format" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--format requires toml or yaml".to_string())?;
+                options.format = Some(
+                    RosterFormat::from_key(value)
+                        .ok_or_else(|| format!("Unknown catalog format '{value}'"))?,
+                );
+            }
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--active" => options.active_only = true,
+            other => return Err(format!("Unknown catalog export option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_catalog_import_options(args: &[String]) -> Result<CatalogImportOptions, String> {
+    let mut file = None;
+    let mut dry_run = false;
+    let mut prune = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--file" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--file requires a path".to_string())?;
+                file = Some(PathBuf::from(value));
+            }
+            "--dry-run" => dry_run = true,
+            "--prune" => prune = true,
+            other => return Err(format!("Unknown catalog import option '{other}'")),
+        }
+    }
+    Ok(CatalogImportOptions {
+        file: file.ok_or_else(|| "catalog import requires --file <catalog.toml>".to_string())?,
+        dry_run,
+        prune,
+    })
+}
+
+fn parse_alias_set_options(args: &[String]) -> Result<AliasSetOptions, String> {
+    let (name, targets) = args
+        .split_first()
+        .ok_or_else(|| "alias set requires a name and at least one target".to_string())?;
+    if targets.is_empty() {
+        return Err(format!(
+            "alias set {name} requires at least one provider/model target"
+        ));
+    }
+    aliases::validate_name(name).map_err(|err| err.to_string())?;
+    let targets = targets
+        .iter()
+        .map(|target| AliasTarget::parse(target).map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(AliasSetOptions {
+        name: name.clone(),
+        targets,
+    })
+}
+
+fn parse_discover_options(args: &[String]) -> Result<DiscoverOptions, String> {
+    let mut options = DiscoverOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--json" => options.json = true,
+            other => return Err(format!("Unknown discover-models option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+}
+
+fn parse_chat_options(args: &[String]) -> Result<ChatOptions, String> {
+    let mut options = ChatOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--session" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--session requires a name or id".to_string())?;
+                options.session = Some(value.clone());
+            }
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--model" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--model requires a model name".to_string())?;
+                options.model = Some(value.clone());
+            }
+            "--workload" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--workload requires a value".to_string())?;
+                options.workload = Some(parse_workload(value)?);
+            }
+            other => return Err(format!("Unknown chat option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+/// Parses a REPL line starting with `/`. `auto` (or no argument) clears a
+/// provider, model or workload override.
+fn parse_chat_command(line: &str) -> Result<ChatCommand, String> {
+    let mut parts = line.trim().splitn(2, char::is_whitespace);
+    let command = parts.next().unwrap_or_default();
+    let argument = parts
+        .next()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let choice = argument.filter(|value| !value.eq_ignore_ascii_case("auto"));
+
+    match command {
+        "/provider" => Ok(ChatCommand::Provider(
+            choice.map(parse_provider).transpose()?,
+        )),
+        "/model" => Ok(ChatCommand::Model(choice.map(str::to_string))),
+        "/workload" => Ok(ChatCommand::Workload(
+            choice.map(parse_workload).transpose()?,
+        )),
+        "/save" => argument
+            .map(|name| ChatCommand::Save(name.to_string()))
+            .ok_or_else(|| "/save requires a session name".to_string()),
+        "/load" => Ok(ChatCommand::Load(argument.map(str::to_string))),
+        "/reset" => Ok(ChatCommand::Reset),
+        "/help" => Ok(ChatCommand::Help),
+        "/quit" | "/exit" => Ok(ChatCommand::Quit),
+        other => Err(format!(
+            "Unknown command '{other}'. Type /help for commands."
+        )),
+    }
+}
+
 fn parse_list_models_options(args: &[String]) -> Result<ListModelsOptions, String> {
     let mut options = ListModelsOptions::default();
     let mut iter = args.iter();
@@ -533,6 +1318,8 @@ fn parse_adopt_model_options(args: &[String]) -> Result<AdoptModelOptions, Strin
     let provider = parse_provider(provider)?;
     let mut workload = Workload::Chat;
     let mut priority = 100;
+    let mut rationale = None;
+    let mut metadata = None;
 
     while let Some(arg) = iter.next() {
         match arg.as_str() {
@@ -550,6 +1337,22 @@ fn parse_adopt_model_options(args: &[String]) -> Result<AdoptModelOptions, Strin
                     .parse()
                     .map_err(|_| "--priority must be an integer".to_string())?;
             }
+            "--rationale" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--rationale requires text".to_string())?;
+                rationale = Some(value.clone());
+            }
+            "--metadata" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--metadata requires a JSON object or @file".to_string())?;
+                metadata = Some(match value.strip_prefix('@') {
+                    Some(path) => fs::read_to_string(path)
+                        .map_err(|err| format!("Failed to read {path}: {err}"))?,
+                    None => value.clone(),
+                });
+            }
             other => return Err(format!("Unknown adopt-model option '{other}'")),
         }
     }
@@ -559,32 +1362,253 @@ fn parse_adopt_model_options(args: &[String]) -> Result<AdoptModelOptions, Strin
         workload,
         model: model.clone(),
         priority,
+        rationale,
+        metadata,
     })
 }
 
-fn parse_status_options(args: &[String]) -> Result<StatusOptions, String> {
-    let mut options = StatusOptions::default();
+fn parse_credential_key_options(
+    action_name: &str,
+    args: &[String],
+) -> Result<CredentialKeyOptions, String> {
+    let action = match action_name {
+        "add" => KeyAction::Add,
+        "enable" => KeyAction::Enable,
+        "disable" => KeyAction::Disable,
+        "rotate" => KeyAction::Rotate,
+        _ => KeyAction::Remove,
+    };
     let mut iter = args.iter();
+    let provider = iter
+        .next()
+        .ok_or_else(|| format!("credentials {action_name} requires a provider"))?;
+
+    let mut options = CredentialKeyOptions {
+        action,
+        provider: parse_provider(provider)?,
+        name: DEFAULT_KEY_NAME.to_string(),
+        daily_limit: None,
+    };
     while let Some(arg) = iter.next() {
         match arg.as_str() {
-            "--provider" => {
+            "--name" => {
                 let value = iter
                     .next()
-                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
-                options.provider = Some(parse_provider(value)?);
+                    .filter(|value| !value.trim().is_empty())
+                    .ok_or_else(|| "--name requires a key name".to_string())?;
+                options.name = value.trim().to_string();
+            }
+            "--daily-limit" if action == KeyAction::Add => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--daily-limit requires a number of requests".to_string())?;
+                let limit: u32 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid --daily-limit '{value}'"))?;
+                options.daily_limit = Some(i64::from(limit));
+            }
+            other => {
+                return Err(format!(
+                    "Unknown credentials {action_name} option '{other}'"
+                ))
             }
-            other => return Err(format!("Unknown status option '{other}'")),
         }
     }
     Ok(options)
 }
 
-fn parse_provider(name: &str) -> Result<Provider, String> {
-    Provider::from_alias(name).ok_or_else(|| format!("Unknown provider '{name}'"))
-}
-
-fn parse_workload(value: &str) -> Result<Workload, String> {
-    match value.to_lowercase().as_str() {
+fn parse_retire_model_options(args: &[String]) -> Result<RetireModelOptions, String> {
+    let mut iter = args.iter();
+    let provider = iter
+        .next()
+        .ok_or_else(|| "retire-model requires a provider".to_string())?;
+    let model = iter
+        .next()
+        .ok_or_else(|| "retire-model requires a model identifier".to_string())?;
+
+    let mut options = RetireModelOptions {
+        provider: parse_provider(provider)?,
+        model: model.clone(),
+        workload: None,
+    };
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--workload" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--workload requires a value".to_string())?;
+                options.workload = Some(parse_workload(value)?);
+            }
+            other => return Err(format!("Unknown retire-model option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_deprecation_report_options(args: &[String]) -> Result<DeprecationReportOptions, String> {
+    let mut options = DeprecationReportOptions {
+        provider: None,
+        within_days: 30,
+        retire: false,
+        json: false,
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--within" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--within requires a number of days".to_string())?;
+                options.within_days = value
+                    .parse()
+                    .ok()
+                    .filter(|days| *days >= 0)
+                    .ok_or_else(|| "--within must be a non-negative number of days".to_string())?;
+            }
+            "--retire" => options.retire = true,
+            "--json" => options.json = true,
+            other => return Err(format!("Unknown deprecation-report option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_trial_model_options(args: &[String]) -> Result<TrialModelOptions, String> {
+    let mut iter = args.iter();
+    let provider = iter
+        .next()
+        .ok_or_else(|| "trial-model requires a provider".to_string())?;
+    let model = iter
+        .next()
+        .ok_or_else(|| "trial-model requires a model identifier".to_string())?;
+
+    let mut options = TrialModelOptions {
+        provider: parse_provider(provider)?,
+        workload: Workload::Chat,
+        model: model.clone(),
+        share: None,
+        stop: false,
+    };
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--workload" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--workload requires a value".to_string())?;
+                options.workload = parse_workload(value)?;
+            }
+            "--share" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--share requires a number between 0 and 1".to_string())?;
+                options.share = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|share: &f64| (0.0..=1.0).contains(share))
+                        .ok_or_else(|| "--share must be a number between 0 and 1".to_string())?,
+                );
+            }
+            "--stop" => options.stop = true,
+            other => return Err(format!("Unknown trial-model option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_status_options(args: &[String]) -> Result<StatusOptions, String> {
+    let mut options = StatusOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            other => return Err(format!("Unknown status option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_history_options(args: &[String]) -> Result<HistoryOptions, String> {
+    let mut options = HistoryOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--search" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--search requires text".to_string())?;
+                options.search = Some(value.clone());
+            }
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--failed" => options.failed = true,
+            "--limit" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--limit requires a number".to_string())?;
+                options.limit = value
+                    .parse()
+                    .map_err(|_| format!("Invalid --limit '{value}'"))?;
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("Unknown history option '{other}'"));
+            }
+            id => options.id = Some(id.to_string()),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_replay_options(args: &[String]) -> Result<ReplayOptions, String> {
+    let mut options = ReplayOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--provider requires a provider name".to_string())?;
+                options.provider = Some(parse_provider(value)?);
+            }
+            "--model" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--model requires a model name".to_string())?;
+                options.model = Some(value.clone());
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("Unknown replay option '{other}'"));
+            }
+            id => options.id = id.to_string(),
+        }
+    }
+    if options.id.is_empty() {
+        return Err("replay requires an entry id (see 'freegin-ai history')".into());
+    }
+    Ok(options)
+}
+
+fn parse_provider(name: &str) -> Result<Provider, String> {
+    Provider::from_alias(name).ok_or_else(|| format!("Unknown provider '{name}'"))
+}
+
+fn parse_workload(value: &str) -> Result<Workload, String> {
+    match value.to_lowercase().as_str() {
         "chat" => Ok(Workload::Chat),
         "summarization" | "summary" => Ok(Workload::Summarization),
         "code" => Ok(Workload::Code),
@@ -598,6 +1622,80 @@ fn parse_workload(value: &str) -> Result<Workload, String> {
     }
 }
 
+fn parse_context_strategy(value: &str) -> Result<ContextStrategy, String> {
+    match value.to_lowercase().as_str() {
+        "none" => Ok(ContextStrategy::None),
+        "truncate" => Ok(ContextStrategy::Truncate),
+        "summarize" | "summarise" => Ok(ContextStrategy::Summarize),
+        other => Err(format!(
+            "Unknown context strategy '{other}'. Expected none, truncate or summarize"
+        )),
+    }
+}
+
+fn parse_credential_export_options(args: &[String]) -> Result<CredentialExportOptions, String> {
+    let mut out = None;
+    let mut force = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--out" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| "--out requires a path".to_string())?;
+                out = Some(PathBuf::from(value));
+            }
+            "--force" => force = true,
+            other => return Err(format!("Unknown credentials export option '{other}'")),
+        }
+    }
+    let out = out.ok_or_else(|| "Usage: credentials export --out <file> [--force]".to_string())?;
+    Ok(CredentialExportOptions { out, force })
+}
+
+fn parse_credential_import_options(args: &[String]) -> Result<CredentialImportOptions, String> {
+    let mut iter = args.iter();
+    let path = iter
+        .next()
+        .ok_or_else(|| "credentials import requires a bundle file".to_string())?;
+    let mut options = CredentialImportOptions {
+        path: PathBuf::from(path),
+        on_conflict: ImportConflict::Prompt,
+    };
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--on-conflict" => {
+                let value = iter.next().ok_or_else(|| {
+                    "--on-conflict requires skip, overwrite or prompt".to_string()
+                })?;
+                options.on_conflict = match value.to_lowercase().as_str() {
+                    "skip" => ImportConflict::Skip,
+                    "overwrite" => ImportConflict::Overwrite,
+                    "prompt" => ImportConflict::Prompt,
+                    other => {
+                        return Err(format!(
+                            "Unknown --on-conflict '{other}'. Expected skip, overwrite or prompt"
+                        ))
+                    }
+                };
+            }
+            other => return Err(format!("Unknown credentials import option '{other}'")),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_master_key_source(value: &str) -> Result<MasterKeySource, String> {
+    match value.to_lowercase().as_str() {
+        "file" => Ok(MasterKeySource::File),
+        "passphrase" => Ok(MasterKeySource::Passphrase),
+        "keyring" => Ok(MasterKeySource::Keyring),
+        other => Err(format!(
+            "Unknown master key source '{other}'. Expected file, passphrase or keyring"
+        )),
+    }
+}
+
 fn parse_complexity(value: &str) -> Result<RequestComplexity, String> {
     match value.to_lowercase().as_str() {
         "low" => Ok(RequestComplexity::Low),
@@ -649,23 +1747,62 @@ Usage:
   {name} [OPTIONS]
   {name} --init
   {name} generate [GENERATE_OPTIONS]
+  {name} batch --input <file> --output <file> [BATCH_OPTIONS]
+  {name} chat [--session <name|id>] [--provider <name>] [--model <name>] [--workload <workload>]
+  {name} compare --prompt <text> --providers <a,b,...> [COMPARE_OPTIONS]
+  {name} eval --suite <file.yaml> [--provider <name>] [--json]
+  {name} eval --scores [--provider <name>]
   {name} add-service <provider>
   {name} remove-service <provider>
   {name} list-services
+  {name} credentials list [--provider <provider>]
+  {name} credentials add <provider> [--name <key>] [--daily-limit N]
+  {name} credentials enable|disable|rotate|remove <provider> [--name <key>]
+  {name} credentials rekey [--to file|passphrase|keyring]
+  {name} credentials export --out <file> [--force]
+  {name} credentials import <file> [--on-conflict skip|overwrite|prompt]
+  {name} list-templates
+  {name} history [<id>] [--search <text>] [--provider <name>] [--failed] [--limit N]
+  {name} replay <id> [--provider <name>] [--model <name>]
   {name} list-models [OPTIONS]
   {name} adopt-model --provider <provider> --workload <workload> --model <model> [OPTIONS]
+  {name} retire-model <provider> <model> [--workload <workload>]
+  {name} deprecation-report [--provider <provider>] [--within <days>] [--retire] [--json]
+  {name} trial-model <provider> <model> [--workload <workload>] [--share 0.1] [--stop]
+  {name} trial-report [--provider <provider>]
   {name} refresh-models --provider <provider> --workload <workload>
+  {name} discover-models [--provider <provider>] [--json]
+  {name} catalog export [--out <file>] [--format toml|yaml] [--provider <provider>] [--active]
+  {name} catalog import --file <catalog.toml> [--dry-run] [--prune]
+  {name} alias list [--json]
+  {name} alias set <name> <provider/model> [<provider/model>...]
+  {name} alias remove <name>
   {name} status [--provider <provider>]
 
 Commands:
   --init             Interactive setup wizard for provider credentials
   generate           Run a single inference request
+  batch              Run JSONL requests with bounded concurrency
+  chat               Interactive conversation with saved sessions
+  compare            Run one prompt on several providers or models side by side
+  eval               Score catalog models against a test suite
   add-service        Add encrypted provider credentials
   remove-service     Remove provider credentials
   list-services      Show configured providers
+  credentials        Manage, rekey, export and import stored API keys
+  list-templates     Show prompt templates and their variables
+  history            Search the audit log of past requests
+  replay             Re-run a logged request, optionally on another provider
   list-models        List active models and suggestions
   adopt-model        Add a model to the active roster
+  retire-model       Remove a model from the active roster
+  deprecation-report List deprecated, unlisted and auto-retired models
+  trial-model        Send a share of shadow traffic to a suggested model
+  trial-report       Compare models on trial with the active models
   refresh-models     Discover new models using LLM
+  discover-models    List the models providers serve and flag missing ones
+  catalog            Export or import the model roster as TOML/YAML
+  alias              Manage model aliases usable as --model
   status             Show provider health and model status
 
 Generate Options:
@@ -673,15 +1810,37 @@ Generate Options:
   --prompt-file <file>      Read prompt from file
   --output-file <file>      Write response to file
   --context-file <file>     Add context (repeatable)
+  --context-strategy <s>    none|truncate|summarize oversized context
+  --context-budget <tokens> Token budget for all context files
+  --workload <workload>     Task type for model selection (inferred if omitted)
   --complexity <level>      low|medium|high
   --quality <level>         standard|balanced|premium
   --speed <level>           fast|normal
   --provider <name>         Force specific provider
-  --model <name>            Override model selection
+  --model <name>            Override model selection (model id or alias)
   --format <format>         text|markdown|json
+  --schema <file>           Require JSON output matching a JSON Schema
+  --image <path|url>        Attach an image for vision models (repeatable)
+  --template <name>         Use a named prompt template instead of --prompt
+  --var <key=value|key=@f>  Template variable, @ reads a file (repeatable)
   -v, --verbose             Show provider and model metadata
   --emit-metadata           Output metadata as JSON
 
+Batch Options:
+  --input <file>            JSONL file with one request per line
+  --output <file>           JSONL file for per-line results
+  --concurrency <n>         Requests in flight at once (default 4)
+  --resume                  Keep successful results and retry the rest
+
+Compare Options:
+  --prompt <text>           Inline prompt text (or --prompt-file, or stdin)
+  --context-file <file>     Add context (repeatable)
+  --providers <a,b,...>     Providers to compare, optionally provider:model
+  --models <a,b,...>        Catalog models or provider:model pairs to compare
+  --workload <workload>     Workload hint for catalog model selection
+  --output-file <file>      Save results (.json for JSON, otherwise Markdown)
+  --json                    Print results as JSON
+
 Options:
   -h, --help       Show this help message and exit
   -V, --version    Print version information
@@ -714,6 +1873,95 @@ fn print_version() {
     );
 }
 
+/// Providers offered by `init`, with their name, free-tier summary and the
+/// page where an API key can be created.
+const INIT_PROVIDERS: &[(Provider, &str, &str, &str)] = &[
+    (
+        Provider::Groq,
+        "Groq",
+        "Ultra-fast inference (14,400 requests/day free)",
+        "https://console.groq.com/keys",
+    ),
+    (
+        Provider::DeepSeek,
+        "DeepSeek",
+        "Unlimited free tier with powerful reasoning",
+        "https://platform.deepseek.com/api_keys",
+    ),
+    (
+        Provider::Together,
+        "Together AI",
+        "Requires $5 deposit, then free models available (Llama 3.3 70B)",
+        "https://api.together.xyz/settings/api-keys",
+    ),
+    (
+        Provider::Google,
+        "Google Gemini",
+        "100 requests/day free (Gemini 2.5 Pro)",
+        "https://makersuite.google.com/app/apikey",
+    ),
+    (
+        Provider::Cloudflare,
+        "Cloudflare Workers AI",
+        "10,000 Neurons/day free (~100-10K requests), 100K requests/day platform limit",
+        "https://dash.cloudflare.com/profile/api-tokens",
+    ),
+    (
+        Provider::Cerebras,
+        "Cerebras AI",
+        "1 million tokens/day free (ultra-fast inference)",
+        "https://cloud.cerebras.ai/",
+    ),
+    (
+        Provider::Mistral,
+        "Mistral AI",
+        "Free tier with rate limits",
+        "https://console.mistral.ai/",
+    ),
+    (
+        Provider::HuggingFace,
+        "Hugging Face",
+        "Rate-limited serverless API",
+        "https://huggingface.co/settings/tokens",
+    ),
+    (
+        Provider::Clarifai,
+        "Clarifai AI",
+        "1,000 requests/month free (login first, then Settings → Security)",
+        "https://clarifai.com/login",
+    ),
+    (
+        Provider::GitHubModels,
+        "GitHub Models",
+        "50-150 requests/day (requires GitHub PAT with models:read scope)",
+        "https://github.com/settings/tokens",
+    ),
+    (
+        Provider::OpenAI,
+        "OpenAI",
+        "Pay-as-you-go (no free tier)",
+        "https://platform.openai.com/api-keys",
+    ),
+    (
+        Provider::Anthropic,
+        "Anthropic Claude",
+        "Pay-as-you-go with limited free credits",
+        "https://console.anthropic.com/",
+    ),
+    (
+        Provider::Cohere,
+        "Cohere",
+        "Free tier for experimentation",
+        "https://dashboard.cohere.com/api-keys",
+    ),
+    (
+        Provider::OpenRouter,
+        "OpenRouter",
+        "50 requests/day for :free models (aggregator)",
+        "https://openrouter.ai/keys",
+    ),
+];
+
 async fn handle_init() -> Result<(), AppError> {
     println!("=== freegin-ai Provider Setup ===\n");
     println!("This wizard will help you configure AI providers with encrypted credential storage.");
@@ -730,103 +1978,15 @@ async fn handle_init() -> Result<(), AppError> {
     database::ensure_schema(db_pool.as_ref())
         .await
         .map_err(|e| AppError::DatabaseError(e))?;
-    let store = CredentialStore::new(Arc::clone(&db_pool)).await?;
+    let store = CredentialStore::new(Arc::clone(&db_pool), config.credentials.master_key).await?;
 
     // Get already configured providers
     let stored = store.stored_providers().await?;
     let stored_set: HashSet<_> = stored.into_iter().collect();
 
-    // Define providers with their details
-    let providers = vec![
-        (
-            Provider::Groq,
-            "Groq",
-            "Ultra-fast inference (14,400 requests/day free)",
-            "https://console.groq.com/keys",
-        ),
-        (
-            Provider::DeepSeek,
-            "DeepSeek",
-            "Unlimited free tier with powerful reasoning",
-            "https://platform.deepseek.com/api_keys",
-        ),
-        (
-            Provider::Together,
-            "Together AI",
-            "Requires $5 deposit, then free models available (Llama 3.3 70B)",
-            "https://api.together.xyz/settings/api-keys",
-        ),
-        (
-            Provider::Google,
-            "Google Gemini",
-            "100 requests/day free (Gemini 2.5 Pro)",
-            "https://makersuite.google.com/app/apikey",
-        ),
-        (
-            Provider::Cloudflare,
-            "Cloudflare Workers AI",
-            "10,000 Neurons/day free (~100-10K requests), 100K requests/day platform limit",
-            "https://dash.cloudflare.com/profile/api-tokens",
-        ),
-        (
-            Provider::Cerebras,
-            "Cerebras AI",
-            "1 million tokens/day free (ultra-fast inference)",
-            "https://cloud.cerebras.ai/",
-        ),
-        (
-            Provider::Mistral,
-            "Mistral AI",
-            "Free tier with rate limits",
-            "https://console.mistral.ai/",
-        ),
-        (
-            Provider::HuggingFace,
-            "Hugging Face",
-            "Rate-limited serverless API",
-            "https://huggingface.co/settings/tokens",
-        ),
-        (
-            Provider::Clarifai,
-            "Clarifai AI",
-            "1,000 requests/month free (login first, then Settings → Security)",
-            "https://clarifai.com/login",
-        ),
-        (
-            Provider::GitHubModels,
-            "GitHub Models",
-            "50-150 requests/day (requires GitHub PAT with models:read scope)",
-            "https://github.com/settings/tokens",
-        ),
-        (
-            Provider::OpenAI,
-            "OpenAI",
-            "Pay-as-you-go (no free tier)",
-            "https://platform.openai.com/api-keys",
-        ),
-        (
-            Provider::Anthropic,
-            "Anthropic Claude",
-            "Pay-as-you-go with limited free credits",
-            "https://console.anthropic.com/",
-        ),
-        (
-            Provider::Cohere,
-            "Cohere",
-            "Free tier for experimentation",
-            "https://dashboard.cohere.com/api-keys",
-        ),
-        (
-            Provider::OpenRouter,
-            "OpenRouter",
-            "50 requests/day for :free models (aggregator)",
-            "https://openrouter.ai/keys",
-        ),
-    ];
-
     let mut configured_count = 0;
 
-    for (provider, name, description, url) in providers {
+    for &(provider, name, description, url) in INIT_PROVIDERS {
         if stored_set.contains(&provider) {
             println!("✓ {} - Already configured (stored)", name);
             configured_count += 1;
@@ -847,7 +2007,10 @@ async fn handle_init() -> Result<(), AppError> {
             continue;
         }
 
-        println!("  Saving encrypted API key ({} characters)...", token.chars().count());
+        println!(
+            "  Saving encrypted API key ({} characters)...",
+            token.chars().count()
+        );
         store.set_token(provider, &token).await?;
         println!("  ✓ {} configured successfully!", name);
         configured_count += 1;
@@ -865,6 +2028,17 @@ async fn handle_init() -> Result<(), AppError> {
 }
 
 async fn handle_add_service(provider: Provider, store: &CredentialStore) -> Result<(), AppError> {
+    let token = read_api_key(provider)?;
+    store.set_token(provider, &token).await?;
+    println!(
+        "{} API key saved locally. It is stored encrypted on disk.",
+        provider.as_str()
+    );
+    Ok(())
+}
+
+/// Prompts for a provider's API key without echoing it.
+fn read_api_key(provider: Provider) -> Result<String, AppError> {
     let (url, prompt) = match provider {
         Provider::Groq => (
             "https://console.groq.com/keys",
@@ -932,32 +2106,473 @@ async fn handle_add_service(provider: Provider, store: &CredentialStore) -> Resu
         return Err(AppError::ConfigError("API key cannot be empty".into()));
     }
     println!("Captured API key ({} characters).", token.chars().count());
-    store.set_token(provider, &token).await?;
-    println!(
-        "{} API key saved locally. It is stored encrypted on disk.",
-        provider.as_str()
-    );
-    Ok(())
+    Ok(token)
 }
 
-async fn handle_remove_service(
-    provider: Provider,
+async fn handle_credentials_list(
     store: &CredentialStore,
+    provider: Option<Provider>,
 ) -> Result<(), AppError> {
-    let removed = store.remove_token(provider).await?;
-    if removed {
-        println!("Removed {} API key from local store.", provider.as_str());
-    } else {
-        println!("No stored API key found for {}.", provider.as_str());
+    let keys = store.list_keys(provider).await?;
+    if keys.is_empty() {
+        println!("No stored API keys. Add one with 'freegin-ai credentials add <provider>'.");
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    println!(
+        "{:<13} {:<16} {:<9} {:>13} {:>8}  {:<17} State",
+        "Provider", "Key", "Status", "Today", "Total", "Last used"
+    );
+    println!("{}", "-".repeat(100));
+    for key in keys {
+        let today = key.daily_limit.map_or_else(
+            || key.requests_today.to_string(),
+            |limit| format!("{}/{limit}", key.requests_today),
+        );
+        let last_used = key.last_used_at.map_or_else(
+            || "never".to_string(),
+            |at| at.format("%Y-%m-%d %H:%M").to_string(),
+        );
+        let state = key.unavailable(now).unwrap_or_else(|| "ready".into());
+        println!(
+            "{:<13} {:<16} {:<9} {:>13} {:>8}  {:<17} {}",
+            key.provider.as_str(),
+            key.name,
+            key.status.as_str(),
+            today,
+            key.total_requests,
+            last_used,
+            state
+        );
+        if let Some(error) = &key.last_error {
+            let error: String = error.chars().take(80).collect();
+            println!("{:>14} last error: {error}", "");
+        }
     }
     Ok(())
 }
 
-async fn handle_list_services(
+async fn handle_credentials_key(
+    options: CredentialKeyOptions,
     store: &CredentialStore,
-    config: &config::AppConfig,
 ) -> Result<(), AppError> {
-    let stored: HashSet<_> = store.stored_providers().await?.into_iter().collect();
+    let CredentialKeyOptions {
+        action,
+        provider,
+        name,
+        daily_limit,
+    } = options;
+    if action != KeyAction::Add && store.key(provider, &name).await?.is_none() {
+        return Err(AppError::NotFound(format!("{provider} key '{name}'")));
+    }
+
+    match action {
+        KeyAction::Add => {
+            let token = read_api_key(provider)?;
+            store.add_key(provider, &name, &token, daily_limit).await?;
+            println!("Added {provider} key '{name}'. It is stored encrypted on disk.");
+        }
+        KeyAction::Rotate => {
+            let token = read_api_key(provider)?;
+            let _ = store.rotate_key(provider, &name, &token).await?;
+            println!("Replaced {provider} key '{name}' and cleared its errors.");
+        }
+        KeyAction::Enable => {
+            let _ = store
+                .set_key_status(provider, &name, KeyStatus::Active)
+                .await?;
+            println!("Enabled {provider} key '{name}'.");
+        }
+        KeyAction::Disable => {
+            let _ = store
+                .set_key_status(provider, &name, KeyStatus::Disabled)
+                .await?;
+            println!("Disabled {provider} key '{name}'.");
+        }
+        KeyAction::Remove => {
+            let _ = store.remove_key(provider, &name).await?;
+            println!("Removed {provider} key '{name}'.");
+        }
+    }
+    Ok(())
+}
+
+async fn handle_credentials_rekey(
+    target: Option<MasterKeySource>,
+    config: &config::AppConfig,
+    store: &CredentialStore,
+) -> Result<(), AppError> {
+    let configured = config.credentials.master_key;
+    let target = target.unwrap_or(configured);
+    let new_key = NewMasterKey::generate(target, &master_key::key_dir()?)?;
+    let (_, count) = store.rekey(&new_key).await?;
+    println!(
+        "Re-encrypted {count} stored key(s) under a new {} master key.",
+        target.as_str()
+    );
+    if target != configured {
+        println!(
+            "Set master_key = \"{}\" under [credentials] in config.toml before the next run.",
+            target.as_str()
+        );
+    }
+    Ok(())
+}
+
+/// The provider's `[providers.*]` entry in the config, if any.
+const fn provider_details(
+    config: &config::AppConfig,
+    provider: Provider,
+) -> Option<&config::ProviderDetails> {
+    let providers = &config.providers;
+    match provider {
+        Provider::OpenAI => providers.openai.as_ref(),
+        Provider::Google => providers.google.as_ref(),
+        Provider::HuggingFace => providers.hugging_face.as_ref(),
+        Provider::Anthropic => providers.anthropic.as_ref(),
+        Provider::Cohere => providers.cohere.as_ref(),
+        Provider::Groq => providers.groq.as_ref(),
+        Provider::DeepSeek => providers.deepseek.as_ref(),
+        Provider::Together => providers.together.as_ref(),
+        Provider::Cloudflare => providers.cloudflare.as_ref(),
+        Provider::Cerebras => providers.cerebras.as_ref(),
+        Provider::Mistral => providers.mistral.as_ref(),
+        Provider::Clarifai => providers.clarifai.as_ref(),
+        Provider::GitHubModels => providers.github_models.as_ref(),
+        Provider::OpenRouter => providers.openrouter.as_ref(),
+    }
+}
+
+fn configured_base_url(config: &config::AppConfig, provider: Provider) -> Option<&str> {
+    provider_details(config, provider)
+        .map(|details| details.api_base_url.trim())
+        .filter(|url| !url.is_empty())
+}
+
+async fn handle_credentials_export(
+    options: &CredentialExportOptions,
+    config: &config::AppConfig,
+    store: &CredentialStore,
+) -> Result<(), AppError> {
+    let path = options.out.as_path();
+    if path.exists() && !options.force {
+        return Err(refuse_overwrite(path));
+    }
+    let mut keys = store.export_keys().await?;
+    if keys.is_empty() {
+        return Err(AppError::NotFound("stored API keys to export".into()));
+    }
+    for key in &mut keys {
+        key.base_url = configured_base_url(config, key.provider).map(str::to_string);
+    }
+
+    let passphrase =
+        master_key::read_passphrase(credential_bundle::PASSPHRASE_ENV, "Bundle passphrase", true)?;
+    let count = keys.len();
+    let sealed = CredentialBundle::new(keys).seal(&passphrase)?;
+    write_bundle(path, &sealed, options.force)?;
+    println!(
+        "Exported {count} key(s) to {}. Import them with 'freegin-ai credentials import {}'.",
+        path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Writes an export bundle readable by the owner only. Without `force` an
+/// existing file is never replaced.
+fn write_bundle(path: &Path, sealed: &[u8], force: bool) -> Result<(), AppError> {
+    let write_error = |err: io::Error| {
+        AppError::ConfigError(format!("Failed to write {}: {err}", path.display()))
+    };
+    let mut open = fs::OpenOptions::new();
+    let _ = open
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!force);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        let _ = open.mode(0o600);
+    }
+    let mut file = match open.open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(refuse_overwrite(path))
+        }
+        Err(err) => return Err(write_error(err)),
+    };
+    // The mode above only applies to new files; a replaced one keeps its own.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(write_error)?;
+    }
+    file.write_all(sealed).map_err(write_error)
+}
+
+fn refuse_overwrite(path: &Path) -> AppError {
+    AppError::InvalidRequest(format!(
+        "{} already exists; pass --force to overwrite it",
+        path.display()
+    ))
+}
+
+async fn handle_credentials_import(
+    options: CredentialImportOptions,
+    config: &config::AppConfig,
+    store: &CredentialStore,
+) -> Result<(), AppError> {
+    let sealed = fs::read(&options.path).map_err(|err| {
+        AppError::ConfigError(format!("Failed to read {}: {err}", options.path.display()))
+    })?;
+    let passphrase = master_key::read_passphrase(
+        credential_bundle::PASSPHRASE_ENV,
+        "Bundle passphrase",
+        false,
+    )?;
+    let bundle = CredentialBundle::open(&sealed, &passphrase)?;
+
+    let (mut added, mut replaced, mut skipped) = (0, 0, 0);
+    let mut base_urls = BTreeMap::new();
+    for key in &bundle.keys {
+        let exists = store.key(key.provider, &key.name).await?.is_some();
+        let write = !exists
+            || match options.on_conflict {
+                ImportConflict::Skip => false,
+                ImportConflict::Overwrite => true,
+                ImportConflict::Prompt => confirm(&format!(
+                    "Replace existing {} key '{}'? [y/N] ",
+                    key.provider, key.name
+                ))?,
+            };
+        if !write {
+            skipped += 1;
+            continue;
+        }
+        store.import_key(key).await?;
+        if exists {
+            replaced += 1;
+        } else {
+            added += 1;
+        }
+        if let Some(url) = &key.base_url {
+            if configured_base_url(config, key.provider) != Some(url.as_str()) {
+                drop(base_urls.insert(key.provider.as_str(), url.clone()));
+            }
+        }
+    }
+
+    println!(
+        "Bundle from {} with {} key(s): {added} added, {replaced} replaced, {skipped} skipped.",
+        bundle.exported_at.format("%Y-%m-%d %H:%M UTC"),
+        bundle.keys.len()
+    );
+    for (provider, url) in base_urls {
+        println!(
+            "Note: the exporting machine used api_base_url = \"{url}\" for {provider}; set it under [providers.{provider}] in config.toml if needed."
+        );
+    }
+    Ok(())
+}
+
+/// Asks a yes/no question on the terminal; anything but `y` means no.
+fn confirm(prompt: &str) -> Result<bool, AppError> {
+    print!("{prompt}");
+    io::stdout()
+        .flush()
+        .map_err(|err| AppError::ConfigError(format!("Failed to write prompt: {err}")))?;
+    let mut answer = String::new();
+    let _ = io::stdin()
+        .read_line(&mut answer)
+        .map_err(|err| AppError::ConfigError(format!("Failed to read answer: {err}")))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+async fn handle_remove_service(
+    provider: Provider,
+    store: &CredentialStore,
+) -> Result<(), AppError> {
+    let removed = store.remove_token(provider).await?;
+    if removed {
+        println!("Removed {} API key from local store.", provider.as_str());
+    } else {
+        println!("No stored API key found for {}.", provider.as_str());
+    }
+    Ok(())
+}
+
+async fn handle_history(
+    options: HistoryOptions,
+    config: &config::AppConfig,
+    pool: Arc<DbPool>,
+) -> Result<(), AppError> {
+    let audit = AuditLog::new(pool, config.audit, &config.redaction.custom_patterns)?;
+
+    if let Some(id) = &options.id {
+        let entry = audit
+            .get(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("audit entry '{id}'")))?;
+        let encoded = serde_json::to_string_pretty(&entry)
+            .map_err(|err| AppError::ApiError(err.to_string()))?;
+        println!("{encoded}");
+        return Ok(());
+    }
+
+    let entries = audit
+        .search(&AuditQuery {
+            text: options.search,
+            provider: options.provider,
+            failures_only: options.failed,
+            limit: options.limit,
+        })
+        .await?;
+    if entries.is_empty() {
+        if config.audit.enabled {
+            println!("No matching entries.");
+        } else {
+            println!("No matching entries. Enable logging with [audit] enabled = true.");
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{:<32}  {:<20}  {:<28}  {:>8}  {:<4}  Prompt",
+        "Id", "Time", "Provider/Model", "Latency", "OK"
+    );
+    println!("{}", "-".repeat(130));
+    for entry in entries {
+        let prompt: String = entry
+            .request
+            .prompt
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(40)
+            .collect();
+        println!(
+            "{:<32}  {:<20}  {:<28}  {:>6}ms  {:<4}  {}",
+            entry.id,
+            entry.created_at.get(..19).unwrap_or(&entry.created_at),
+            answered_by(&entry),
+            entry.latency_ms,
+            if entry.success { "yes" } else { "no" },
+            prompt
+        );
+    }
+    Ok(())
+}
+
+async fn handle_replay(
+    options: ReplayOptions,
+    config: &config::AppConfig,
+    credential_store: &CredentialStore,
+    catalog: &CatalogStore,
+    usage_logger: UsageLogger,
+    pool: Arc<DbPool>,
+) -> Result<(), AppError> {
+    let audit = AuditLog::new(pool, config.audit, &config.redaction.custom_patterns)?;
+    let entry = audit
+        .get(&options.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("audit entry '{}'", options.id)))?;
+
+    let mut request = entry.request.clone();
+    drop(
+        request
+            .metadata
+            .insert(REPLAY_OF_KEY.into(), entry.id.clone()),
+    );
+    if let Some(provider) = options.provider {
+        request.hints.provider = Some(provider.as_str().to_string());
+        request.tags.retain(|tag| !tag.starts_with("provider:"));
+        // The logged model belongs to the original provider.
+        if entry.provider.as_deref() != Some(provider.as_str()) {
+            request.model.clear();
+        }
+    }
+    if let Some(model) = options.model {
+        request.model = model;
+    }
+
+    let router = ProviderRouter::from_config(
+        config,
+        credential_store,
+        Some(usage_logger),
+        Some(catalog.clone()),
+    )
+    .await?;
+    let start = Instant::now();
+    let outcome = router.generate(&request).await;
+    let elapsed = start.elapsed().as_millis();
+
+    println!(
+        "=== Original [{} · {} ms] ===",
+        answered_by(&entry),
+        entry.latency_ms
+    );
+    match (&entry.response, &entry.error) {
+        (Some(content), _) => println!("{content}"),
+        (None, Some(error)) => println!("error: {error}"),
+        (None, None) => println!("(no output)"),
+    }
+    println!();
+    match outcome {
+        Ok(response) => {
+            println!(
+                "=== Replay [{}/{} · {elapsed} ms] ===",
+                response.provider,
+                response.model.as_deref().unwrap_or("default")
+            );
+            println!("{}", response.content);
+            Ok(())
+        }
+        Err(err) => {
+            println!("=== Replay [failed · {elapsed} ms] ===");
+            Err(err)
+        }
+    }
+}
+
+fn answered_by(entry: &AuditEntry) -> String {
+    match (&entry.provider, &entry.model) {
+        (Some(provider), Some(model)) => format!("{provider}/{model}"),
+        (Some(provider), None) => provider.clone(),
+        _ => "-".to_string(),
+    }
+}
+
+fn handle_list_templates(config: &config::AppConfig) -> Result<(), AppError> {
+    let library = TemplateLibrary::from_config(&config.templates)?;
+    let templates = library.list()?;
+    if templates.is_empty() {
+        println!("No templates in {}", library.dir().display());
+        return Ok(());
+    }
+
+    println!("{:<24}  {:<30}  Description", "Template", "Variables");
+    println!("{}", "-".repeat(80));
+    for template in templates {
+        println!(
+            "{:<24}  {:<30}  {}",
+            template.name,
+            template.variables().join(", "),
+            template.description.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+async fn handle_list_services(
+    store: &CredentialStore,
+    config: &config::AppConfig,
+) -> Result<(), AppError> {
+    let stored: HashSet<_> = store.stored_providers().await?.into_iter().collect();
 
     println!("Provider       Configured");
     println!("---------------------------");
@@ -1078,6 +2693,20 @@ async fn handle_list_services(
     Ok(())
 }
 
+/// Resolves a `--var` value: `@path` reads the file, anything else is literal.
+fn read_template_var(value: &str) -> Result<String, AppError> {
+    value.strip_prefix('@').map_or_else(
+        || Ok(value.to_string()),
+        |path| {
+            fs::read_to_string(path).map_err(|err| {
+                AppError::ConfigError(format!(
+                    "Failed to read template variable file {path}: {err}"
+                ))
+            })
+        },
+    )
+}
+
 async fn handle_generate(
     options: GenerateOptions,
     config: &config::AppConfig,
@@ -1085,9 +2714,195 @@ async fn handle_generate(
     catalog: &CatalogStore,
     usage_logger: Option<UsageLogger>,
 ) -> Result<(), AppError> {
-    let mut prompt = if let Some(p) = options.prompt.clone() {
-        p
-    } else if let Some(path) = &options.prompt_file {
+    let template_request = load_template_request(&options, config)?;
+
+    let (prompt, context_blocks) = read_prompt_input(
+        template_request
+            .as_ref()
+            .map(|template| template.prompt.clone())
+            .or_else(|| options.prompt.clone()),
+        options.prompt_file.as_deref(),
+        &options.context_files,
+    )?;
+
+    let router = ProviderRouter::from_config(
+        config,
+        credential_store,
+        usage_logger,
+        Some(catalog.clone()),
+    )
+    .await?;
+
+    let mut context_config = config.context;
+    if let Some(strategy) = options.context_strategy {
+        context_config.strategy = strategy;
+    }
+    if let Some(budget) = options.context_budget {
+        context_config.max_tokens = budget;
+    }
+    let context_blocks = context::fit_context(&router, context_blocks, &context_config).await?;
+    let prompt = prepend_context(&prompt, &context_blocks);
+
+    let mut hints = template_request
+        .as_ref()
+        .map_or_else(|| options.hints.clone(), |template| template.hints.clone());
+    if let Some(provider_override) = options.provider_override.clone() {
+        hints.provider = Some(provider_override.clone());
+    }
+
+    let mut tags = options.tags.clone();
+    if let Some(template) = &template_request {
+        tags.extend(template.tags.iter().cloned());
+    }
+    if let Some(provider_hint) = hints.provider.as_ref() {
+        tags.push(format!("provider:{}", provider_hint));
+    }
+
+    let mut metadata = options.metadata.clone();
+    drop(metadata.insert("cli".into(), "true".into()));
+
+    let response_schema = options
+        .schema_file
+        .as_deref()
+        .map(read_schema_file)
+        .transpose()?;
+
+    let images = options
+        .images
+        .iter()
+        .map(|value| ImageInput::from_cli_arg(value))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let request = AIRequest {
+        model: options
+            .model
+            .clone()
+            .or_else(|| {
+                template_request
+                    .as_ref()
+                    .map(|template| template.model.clone())
+                    .filter(|model| !model.is_empty())
+            })
+            .unwrap_or_default(),
+        prompt,
+        tags,
+        context: context_blocks,
+        metadata,
+        hints,
+        response_schema,
+        images,
+        history: Vec::new(),
+    };
+
+    let response = router.generate(&request).await?;
+
+    write_generate_output(&options, &response)
+}
+
+/// Renders a `--template` request from the library, or `None` without one.
+fn load_template_request(
+    options: &GenerateOptions,
+    config: &config::AppConfig,
+) -> Result<Option<AIRequest>, AppError> {
+    let Some(name) = &options.template else {
+        if !options.template_vars.is_empty() {
+            return Err(AppError::ConfigError("--var requires --template".into()));
+        }
+        return Ok(None);
+    };
+    if options.prompt.is_some() || options.prompt_file.is_some() {
+        return Err(AppError::ConfigError(
+            "--template cannot be combined with --prompt or --prompt-file".into(),
+        ));
+    }
+    let library = TemplateLibrary::from_config(&config.templates)?;
+    let mut vars = HashMap::new();
+    for (key, value) in &options.template_vars {
+        drop(vars.insert(key.clone(), read_template_var(value)?));
+    }
+    Ok(Some(library.load(name)?.to_request(&vars, &options.hints)?))
+}
+
+/// Reads a `--schema-file` JSON Schema.
+fn read_schema_file(path: &Path) -> Result<serde_json::Value, AppError> {
+    let raw = fs::read_to_string(path).map_err(|err| {
+        AppError::ConfigError(format!(
+            "Failed to read schema file {}: {err}",
+            path.display()
+        ))
+    })?;
+    serde_json::from_str(&raw).map_err(|err| {
+        AppError::ConfigError(format!(
+            "Schema file {} is not valid JSON: {err}",
+            path.display()
+        ))
+    })
+}
+
+/// Prints or writes a `generate` response in the requested format, with the
+/// optional metadata header and trailer.
+fn write_generate_output(options: &GenerateOptions, response: &AIResponse) -> Result<(), AppError> {
+    let output_string = match options.response_format.unwrap_or(ResponseFormat::Text) {
+        ResponseFormat::Json => serde_json::to_string_pretty(&json!({
+            "provider": response.provider.as_str(),
+            // The router has already validated JSON content; embed it as a document.
+            "content": serde_json::from_str::<serde_json::Value>(&response.content)
+                .unwrap_or_else(|_| serde_json::Value::String(response.content.clone())),
+        }))
+        .map_err(|err| AppError::ApiError(err.to_string()))?,
+        ResponseFormat::Markdown | ResponseFormat::Text => response.content.clone(),
+    };
+
+    // Output metadata header for verbose mode (before content)
+    if options.verbose && !matches!(options.response_format, Some(ResponseFormat::Json)) {
+        eprintln!("=== Metadata ===");
+        eprintln!("Provider: {}", response.provider.as_str());
+        eprintln!("\n=== Response ===");
+    }
+
+    // Output main content
+    if let Some(path) = &options.output_file {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| {
+                AppError::ConfigError(format!(
+                    "Failed to create output directory {}: {err}",
+                    parent.display()
+                ))
+            })?;
+        }
+        fs::write(path, output_string).map_err(|err| {
+            AppError::ConfigError(format!(
+                "Failed to write output file {}: {err}",
+                path.display()
+            ))
+        })?;
+    } else {
+        println!("{}", output_string);
+    }
+
+    // Output JSON metadata for --emit-metadata mode (after content)
+    if options.emit_metadata && !matches!(options.response_format, Some(ResponseFormat::Json)) {
+        let metadata_json = serde_json::to_string(&json!({
+            "provider": response.provider.as_str(),
+        }))
+        .map_err(|err| AppError::ApiError(err.to_string()))?;
+        println!("{}", metadata_json);
+    }
+    // By default: no metadata output (clean response only)
+
+    Ok(())
+}
+
+/// Reads the prompt (`prompt`, else `prompt_file`, else stdin) and the
+/// `--context-file` contents shared by `generate` and `compare`.
+fn read_prompt_input(
+    prompt: Option<String>,
+    prompt_file: Option<&Path>,
+    context_files: &[PathBuf],
+) -> Result<(String, Vec<String>), AppError> {
+    let prompt = if let Some(prompt) = prompt {
+        prompt
+    } else if let Some(path) = prompt_file {
         fs::read_to_string(path).map_err(|err| {
             AppError::ConfigError(format!(
                 "Failed to read prompt file {}: {err}",
@@ -1101,13 +2916,12 @@ async fn handle_generate(
             .map_err(|err| AppError::ConfigError(format!("Failed to read stdin: {err}")))?;
         buffer
     };
-
     if prompt.trim().is_empty() {
         return Err(AppError::ConfigError("Prompt cannot be empty".into()));
     }
 
     let mut context_blocks = Vec::new();
-    for path in &options.context_files {
+    for path in context_files {
         let content = fs::read_to_string(path).map_err(|err| {
             AppError::ConfigError(format!(
                 "Failed to read context file {}: {err}",
@@ -1116,95 +2930,493 @@ async fn handle_generate(
         })?;
         context_blocks.push(content);
     }
+    Ok((prompt, context_blocks))
+}
 
-    if !context_blocks.is_empty() {
-        let combined_context = context_blocks
-            .iter()
-            .enumerate()
-            .map(|(idx, ctx)| format!("Context {}:\n{}", idx + 1, ctx))
-            .collect::<Vec<_>>()
-            .join("\n\n");
-        prompt = format!("{}\n\n{}", combined_context, prompt);
+/// Puts numbered context blocks ahead of the prompt.
+fn prepend_context(prompt: &str, context_blocks: &[String]) -> String {
+    if context_blocks.is_empty() {
+        return prompt.to_string();
     }
+    let combined_context = context_blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, ctx)| format!("Context {}:\n{ctx}", idx + 1))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!("{combined_context}\n\n{prompt}")
+}
 
-    let mut hints = options.hints.clone();
-    if let Some(provider_override) = options.provider_override.clone() {
-        hints.provider = Some(provider_override.clone());
+async fn handle_batch(
+    options: BatchOptions,
+    config: &config::AppConfig,
+    credential_store: &CredentialStore,
+    catalog: &CatalogStore,
+    usage_logger: Option<UsageLogger>,
+) -> Result<(), AppError> {
+    let router = ProviderRouter::from_config(
+        config,
+        credential_store,
+        usage_logger,
+        Some(catalog.clone()),
+    )
+    .await?;
+    let summary = batch::run_batch(Arc::new(router), &options).await?;
+
+    eprintln!(
+        "Batch complete: {} processed ({} succeeded, {} failed), {} skipped. Results in {}",
+        summary.processed,
+        summary.succeeded,
+        summary.failed,
+        summary.skipped,
+        options.output.display()
+    );
+    Ok(())
+}
+
+async fn handle_compare(
+    options: CompareOptions,
+    config: &config::AppConfig,
+    credential_store: &CredentialStore,
+    catalog: &CatalogStore,
+    usage_logger: Option<UsageLogger>,
+) -> Result<(), AppError> {
+    let mut targets = Vec::new();
+    for spec in &options.providers {
+        targets.push(CompareTarget::parse(spec)?);
+    }
+    for spec in &options.models {
+        targets.push(CompareTarget::resolve_model(catalog, spec).await?);
+    }
+
+    let (prompt, context_blocks) = read_prompt_input(
+        options.prompt.clone(),
+        options.prompt_file.as_deref(),
+        &options.context_files,
+    )?;
+    let prompt = prepend_context(&prompt, &context_blocks);
+
+    let router = ProviderRouter::from_config(
+        config,
+        credential_store,
+        usage_logger,
+        Some(catalog.clone()),
+    )
+    .await?;
+
+    let mut metadata = HashMap::new();
+    drop(metadata.insert("cli".into(), "true".into()));
+    let request = AIRequest {
+        prompt: prompt.clone(),
+        hints: RequestHints {
+            workload: options.workload,
+            ..RequestHints::default()
+        },
+        tags: vec!["compare".into()],
+        metadata,
+        ..AIRequest::default()
+    };
+
+    let results = compare::run_comparison(Arc::new(router), &request, &targets).await?;
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&results)
+            .map_err(|err| AppError::ApiError(err.to_string()))?;
+        println!("{json}");
+    } else {
+        println!(
+            "{:<40} {:<36} {:>10} {:>14}  STATUS",
+            "TARGET", "MODEL", "LATENCY", "TOKENS IN/OUT"
+        );
+        for result in &results {
+            println!(
+                "{:<40} {:<36} {:>8}ms {:>14}  {}",
+                result.target,
+                result.model.as_deref().unwrap_or("-"),
+                result.latency_ms,
+                format!("~{}/~{}", result.prompt_tokens, result.completion_tokens),
+                if result.is_success() { "ok" } else { "failed" },
+            );
+        }
+        for result in &results {
+            println!("\n=== {} ===", result.target);
+            match (&result.content, &result.error) {
+                (Some(content), _) => println!("{}", content.trim_end()),
+                (None, Some(error)) => println!("[failed] {error}"),
+                (None, None) => {}
+            }
+        }
+    }
+
+    if let Some(path) = &options.output_file {
+        compare::write_report(path, &prompt, &results)?;
+        eprintln!("Comparison written to {}", path.display());
+    }
+    Ok(())
+}
+
+async fn handle_eval(
+    options: EvalOptions,
+    config: &config::AppConfig,
+    credential_store: &CredentialStore,
+    catalog: &CatalogStore,
+    usage_logger: UsageLogger,
+    store: &EvalStore,
+) -> Result<(), AppError> {
+    let Some(path) = &options.suite else {
+        let scores = store.scores(options.provider).await?;
+        if options.json {
+            let json = serde_json::to_string_pretty(&scores)
+                .map_err(|err| AppError::ApiError(err.to_string()))?;
+            println!("{json}");
+        } else if scores.is_empty() {
+            println!("No evaluation scores stored. Run `freegin-ai eval --suite <file>`.");
+        } else {
+            print_eval_scores(&scores);
+        }
+        return Ok(());
+    };
+
+    let suite = EvalSuite::load(path)?;
+    let router = ProviderRouter::from_config(
+        config,
+        credential_store,
+        Some(usage_logger),
+        Some(catalog.clone()),
+    )
+    .await?;
+    let report = eval::run_suite(&router, catalog, store, &suite, options.provider).await?;
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|err| AppError::ApiError(err.to_string()))?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<12} {:<40} {:>6} {:>9}  DETAIL",
+        "CASE", "PROVIDER", "MODEL", "SCORE", "LATENCY"
+    );
+    for result in &report.results {
+        let detail = result
+            .error
+            .as_deref()
+            .map(|err| format!("error: {err}"))
+            .or_else(|| result.detail.clone())
+            .unwrap_or_default();
+        println!(
+            "{:<16} {:<12} {:<40} {:>6.2} {:>7}ms  {}",
+            result.case_id,
+            result.provider.as_str(),
+            result.model,
+            result.score,
+            result.latency_ms,
+            detail.lines().next().unwrap_or_default()
+        );
+    }
+    println!();
+    if report.scores.is_empty() {
+        println!("No model answered; catalog scores unchanged.");
+    } else {
+        print_eval_scores(&report.scores);
+    }
+    println!("\nRun {} of suite '{}'", report.run_id, suite.name);
+    Ok(())
+}
+
+fn print_eval_scores(scores: &[eval::ModelScore]) {
+    println!(
+        "{:<15} {:<12} {:<40} {:>6} {:>6}",
+        "WORKLOAD", "PROVIDER", "MODEL", "SCORE", "CASES"
+    );
+    for score in scores {
+        println!(
+            "{:<15} {:<12} {:<40} {:>6.2} {:>6}",
+            score.workload, score.provider, score.model, score.score, score.cases
+        );
+    }
+}
+
+const CHAT_HELP: &str = "Commands:
+  /provider <name|auto>   Pin a provider for the next turns
+  /model <name|auto>      Pin a model for the next turns
+  /workload <name|auto>   Set the workload hint
+  /save <name>            Name the current session so it can be loaded later
+  /load [name|id]         Resume a saved session (lists sessions without argument)
+  /reset                  Start a new, empty session
+  /quit                   Exit (Ctrl-D also works)";
+
+async fn handle_chat(
+    options: ChatOptions,
+    config: &config::AppConfig,
+    credential_store: &CredentialStore,
+    catalog: &CatalogStore,
+    usage_logger: Option<UsageLogger>,
+    sessions: &SessionStore,
+) -> Result<(), AppError> {
+    use rustyline::{error::ReadlineError, DefaultEditor};
+
+    let router = ProviderRouter::from_config(
+        config,
+        credential_store,
+        usage_logger,
+        Some(catalog.clone()),
+    )
+    .await?;
+
+    let session = match &options.session {
+        Some(key) => sessions
+            .find(key)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("session '{key}'")))?,
+        None => sessions.create(None, None, None, None).await?,
+    };
+    let mut chat = ChatState::open(sessions, session).await?;
+    chat.provider = options.provider.or(chat.provider);
+    chat.model = options.model.or(chat.model);
+    chat.workload = options.workload.or(chat.workload);
+    chat.save_routing(sessions).await?;
+
+    println!(
+        "Session {} ({} earlier messages). Type /help for commands, /quit to exit.",
+        chat.label(),
+        chat.history.len()
+    );
+
+    let mut editor = DefaultEditor::new()
+        .map_err(|err| AppError::ConfigError(format!("Failed to start line editor: {err}")))?;
+    let history_file = dirs::data_dir().map(|dir| dir.join("freegin-ai").join("chat_history.txt"));
+    // The line history is kept on disk, so PII is masked before it is recorded.
+    let history_redactor = Redactor::from_config(&RedactionConfig {
+        enabled: true,
+        default_policy: RedactionPolicy::Always,
+        custom_patterns: config.redaction.custom_patterns.clone(),
+        providers: HashMap::new(),
+    })?;
+    if let Some(path) = &history_file {
+        // A missing history file is normal on first use.
+        drop(editor.load_history(path));
+    }
+
+    loop {
+        let line = match editor.readline("you> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                return Err(AppError::ConfigError(format!(
+                    "Failed to read input: {err}"
+                )));
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let entry = history_redactor.as_ref().map_or_else(
+            || line.to_string(),
+            |redactor| redactor.redact(line, &mut RedactionMap::default()),
+        );
+        drop(editor.add_history_entry(entry));
+
+        if line.starts_with('/') {
+            let command = match parse_chat_command(line) {
+                Ok(command) => command,
+                Err(err) => {
+                    eprintln!("{err}");
+                    continue;
+                }
+            };
+            if run_chat_command(command, &mut chat, sessions).await? {
+                break;
+            }
+            chat.save_routing(sessions).await?;
+            continue;
+        }
+
+        chat_turn(&router, sessions, &mut chat, line).await?;
+    }
+
+    if let Some(path) = &history_file {
+        if let Some(parent) = path.parent() {
+            drop(fs::create_dir_all(parent));
+        }
+        drop(editor.save_history(path));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // Older history files may predate owner-only permissions.
+            drop(fs::set_permissions(path, fs::Permissions::from_mode(0o600)));
+        }
+    }
+    Ok(())
+}
+
+/// The session a chat is attached to, with its routing choices and turns.
+struct ChatState {
+    session: Session,
+    provider: Option<Provider>,
+    model: Option<String>,
+    workload: Option<Workload>,
+    history: Vec<ChatMessage>,
+}
+
+impl ChatState {
+    /// Attaches to `session`, restoring its routing choices and turns.
+    async fn open(sessions: &SessionStore, session: Session) -> Result<Self, AppError> {
+        let history = sessions
+            .messages(&session.id)
+            .await?
+            .iter()
+            .map(ChatMessage::from)
+            .collect();
+        Ok(Self {
+            provider: session.provider.as_deref().and_then(Provider::from_alias),
+            model: session.model.clone(),
+            workload: session
+                .workload
+                .as_deref()
+                .and_then(|key| parse_workload(key).ok()),
+            history,
+            session,
+        })
     }
 
-    let mut tags = options.tags.clone();
-    if let Some(provider_hint) = hints.provider.as_ref() {
-        tags.push(format!("provider:{}", provider_hint));
+    fn label(&self) -> &str {
+        self.session.name.as_deref().unwrap_or(&self.session.id)
     }
 
-    let mut metadata = options.metadata.clone();
-    drop(metadata.insert("cli".into(), "true".into()));
+    async fn save_routing(&self, sessions: &SessionStore) -> Result<(), AppError> {
+        sessions
+            .set_routing(
+                &self.session.id,
+                self.provider.map(|p| p.as_str()),
+                self.model.as_deref(),
+                self.workload.map(Workload::as_str),
+            )
+            .await
+    }
+}
 
+/// Sends one chat turn and stores it with the answer. A failed call is
+/// reported and leaves the session unchanged.
+async fn chat_turn(
+    router: &ProviderRouter,
+    sessions: &SessionStore,
+    chat: &mut ChatState,
+    line: &str,
+) -> Result<(), AppError> {
     let request = AIRequest {
-        model: options.model.clone().unwrap_or_default(),
-        prompt,
-        tags,
-        context: context_blocks,
-        metadata,
-        hints,
-    };
-
-    let router = ProviderRouter::from_config(
-        config,
-        credential_store,
-        usage_logger,
-        Some(catalog.clone()),
-    )
-    .await?;
-    let response = router.generate(&request).await?;
-
-    let output_string = match options.response_format.unwrap_or(ResponseFormat::Text) {
-        ResponseFormat::Json => serde_json::to_string_pretty(&json!({
-            "provider": response.provider.as_str(),
-            "content": response.content,
-        }))
-        .map_err(|err| AppError::ApiError(err.to_string()))?,
-        ResponseFormat::Markdown | ResponseFormat::Text => response.content.clone(),
+        model: chat.model.clone().unwrap_or_default(),
+        prompt: line.to_string(),
+        metadata: HashMap::from([("cli".to_string(), "true".to_string())]),
+        hints: RequestHints {
+            provider: chat.provider.map(|p| p.as_str().to_string()),
+            workload: chat.workload,
+            ..RequestHints::default()
+        },
+        history: chat.history.clone(),
+        ..AIRequest::default()
     };
 
-    // Output metadata header for verbose mode (before content)
-    if options.verbose && !matches!(options.response_format, Some(ResponseFormat::Json)) {
-        eprintln!("=== Metadata ===");
-        eprintln!("Provider: {}", response.provider.as_str());
-        eprintln!("\n=== Response ===");
-    }
+    let start = Instant::now();
+    match router.generate(&request).await {
+        Ok(response) => {
+            let answered_by = response.provider.as_str();
+            println!("{}", response.content);
+            eprintln!(
+                "[{answered_by}/{} · {} ms]",
+                response.model.as_deref().unwrap_or("default"),
+                start.elapsed().as_millis()
+            );
 
-    // Output main content
-    if let Some(path) = &options.output_file {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).map_err(|err| {
-                AppError::ConfigError(format!(
-                    "Failed to create output directory {}: {err}",
-                    parent.display()
-                ))
-            })?;
+            sessions
+                .append(&chat.session.id, ChatRole::User, line, None, None)
+                .await?;
+            sessions
+                .append(
+                    &chat.session.id,
+                    ChatRole::Assistant,
+                    &response.content,
+                    Some(answered_by),
+                    response.model.as_deref(),
+                )
+                .await?;
+            chat.history.push(ChatMessage {
+                role: ChatRole::User,
+                content: line.to_string(),
+            });
+            chat.history.push(ChatMessage {
+                role: ChatRole::Assistant,
+                content: response.content,
+            });
         }
-        fs::write(path, output_string).map_err(|err| {
-            AppError::ConfigError(format!(
-                "Failed to write output file {}: {err}",
-                path.display()
-            ))
-        })?;
-    } else {
-        println!("{}", output_string);
+        Err(err) => eprintln!("freegin-ai: {err}"),
     }
+    Ok(())
+}
 
-    // Output JSON metadata for --emit-metadata mode (after content)
-    if options.emit_metadata && !matches!(options.response_format, Some(ResponseFormat::Json)) {
-        let metadata_json = serde_json::to_string(&json!({
-            "provider": response.provider.as_str(),
-        }))
-        .map_err(|err| AppError::ApiError(err.to_string()))?;
-        println!("{}", metadata_json);
+/// Runs a chat slash command. Returns `true` when the chat should end.
+async fn run_chat_command(
+    command: ChatCommand,
+    chat: &mut ChatState,
+    sessions: &SessionStore,
+) -> Result<bool, AppError> {
+    match command {
+        ChatCommand::Provider(choice) => {
+            chat.provider = choice;
+            println!("Provider: {}", chat.provider.map_or("auto", |p| p.as_str()));
+        }
+        ChatCommand::Model(choice) => {
+            chat.model = choice;
+            println!("Model: {}", chat.model.as_deref().unwrap_or("auto"));
+        }
+        ChatCommand::Workload(choice) => {
+            chat.workload = choice;
+            println!(
+                "Workload: {}",
+                chat.workload.map_or("auto", Workload::as_str)
+            );
+        }
+        ChatCommand::Save(name) => match sessions.rename(&chat.session.id, &name).await {
+            Ok(()) => {
+                chat.session.name = Some(name.clone());
+                println!("Saved session as '{name}'.");
+            }
+            Err(AppError::Conflict(msg)) => eprintln!("{msg}."),
+            Err(err) => return Err(err),
+        },
+        ChatCommand::Load(None) => {
+            for saved in sessions.list(20).await? {
+                println!(
+                    "{:<32}  {:<20}  {}",
+                    saved.id,
+                    saved.name.as_deref().unwrap_or("-"),
+                    saved.updated_at
+                );
+            }
+        }
+        ChatCommand::Load(Some(key)) => match sessions.find(&key).await? {
+            Some(found) => {
+                *chat = ChatState::open(sessions, found).await?;
+                println!(
+                    "Loaded session {} ({} messages).",
+                    chat.label(),
+                    chat.history.len()
+                );
+            }
+            None => eprintln!("No session named '{key}'."),
+        },
+        ChatCommand::Reset => {
+            chat.session = sessions.create(None, None, None, None).await?;
+            chat.history.clear();
+            println!("Started a new session.");
+        }
+        ChatCommand::Help => println!("{CHAT_HELP}"),
+        ChatCommand::Quit => return Ok(true),
     }
-    // By default: no metadata output (clean response only)
-
-    Ok(())
+    Ok(false)
 }
 
 async fn handle_list_models(
@@ -1254,7 +3466,16 @@ async fn handle_list_models(
                 println!("  Active:");
                 for m in models {
                     let rationale = m.rationale.as_deref().unwrap_or("");
-                    println!("    {:3}  {} — {}", m.priority, m.model, rationale);
+                    println!(
+                        "    {:3}  {} — {}{}",
+                        m.priority,
+                        m.model,
+                        rationale,
+                        unavailable_note(&m)
+                    );
+                    if let Some(line) = capability_line(&m) {
+                        println!("         {line}");
+                    }
                 }
             }
 
@@ -1280,55 +3501,585 @@ async fn handle_list_models(
                 current_group = key;
             }
             let rationale = model.rationale.as_deref().unwrap_or("");
-            println!("  {:3}  {} — {}", model.priority, model.model, rationale);
+            println!(
+                "  {:3}  {} — {}{}",
+                model.priority,
+                model.model,
+                rationale,
+                unavailable_note(&model)
+            );
+            if let Some(line) = capability_line(&model) {
+                println!("       {line}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Summarises the capabilities recorded for a catalog entry.
+fn capability_line(model: &ModelEntry) -> Option<String> {
+    let summary = model.capabilities().summary();
+    (!summary.is_empty()).then_some(summary)
+}
+
+/// Marks catalog entries that model discovery no longer found.
+fn unavailable_note(model: &ModelEntry) -> String {
+    model
+        .unavailable_since()
+        .map(|since| format!(" [not listed by provider since {since}]"))
+        .unwrap_or_default()
+}
+
+async fn handle_adopt_model(
+    catalog: &CatalogStore,
+    options: AdoptModelOptions,
+) -> Result<(), AppError> {
+    catalog
+        .adopt_model(
+            options.provider,
+            options.workload,
+            options.model.clone(),
+            options.rationale.clone(),
+            options.metadata.clone(),
+            options.priority,
+        )
+        .await?;
+
+    println!(
+        "Adopted model '{}' for provider '{}' and workload '{:?}' with priority {}",
+        options.model,
+        options.provider.as_str(),
+        options.workload,
+        options.priority
+    );
+
+    // Show the new roster ordering
+    let models = catalog
+        .active_models(options.provider, Some(options.workload))
+        .await?;
+    if !models.is_empty() {
+        println!(
+            "\nActive models for {} / {:?}:",
+            options.provider.as_str(),
+            options.workload
+        );
+        for m in models {
+            let rationale = m.rationale.as_deref().unwrap_or("");
+            println!("  {:3}  {} — {}", m.priority, m.model, rationale);
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_retire_model(
+    catalog: &CatalogStore,
+    options: RetireModelOptions,
+) -> Result<(), AppError> {
+    let entries: Vec<ModelEntry> = catalog
+        .list_models(Some(options.provider), options.workload)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.model == options.model && entry.status == "active")
+        .collect();
+    if entries.is_empty() {
+        return Err(AppError::InvalidRequest(format!(
+            "No active catalog entry for {} / {}",
+            options.provider, options.model
+        )));
+    }
+    for entry in &entries {
+        let _ = catalog
+            .retire_model(entry.provider, entry.workload, &entry.model)
+            .await?;
+        println!(
+            "Retired '{}' for provider '{}' and workload '{}'",
+            entry.model,
+            entry.provider,
+            entry.workload.as_str()
+        );
+    }
+    Ok(())
+}
+
+async fn handle_deprecation_report(
+    catalog: &CatalogStore,
+    options: DeprecationReportOptions,
+) -> Result<(), AppError> {
+    let today = chrono::Utc::now().date_naive();
+    let entries =
+        deprecation::report(catalog, options.provider, today, options.within_days).await?;
+
+    if options.json {
+        let rendered = serde_json::to_string_pretty(&entries)
+            .map_err(|err| AppError::ApiError(format!("Failed to render report: {err}")))?;
+        println!("{rendered}");
+    } else if entries.is_empty() {
+        println!(
+            "No deprecated, unlisted or automatically retired catalog models \
+             (looking {} days ahead).",
+            options.within_days
+        );
+    } else {
+        println!(
+            "{:<12} {:<15} {:<40} {:<8} REASON",
+            "PROVIDER", "WORKLOAD", "MODEL", "STATUS"
+        );
+        for entry in &entries {
+            println!(
+                "{:<12} {:<15} {:<40} {:<8} {}",
+                entry.provider.as_str(),
+                entry.workload.as_str(),
+                entry.model,
+                entry.status,
+                entry.reason.describe()
+            );
+        }
+    }
+
+    if options.retire {
+        let mut retired = 0;
+        for entry in &entries {
+            if entry.status == "active"
+                && matches!(entry.reason, DeprecationReason::Deprecated { .. })
+                && catalog
+                    .retire_model(entry.provider, entry.workload, &entry.model)
+                    .await?
+            {
+                retired += 1;
+            }
+        }
+        if !options.json {
+            println!("\nRetired {retired} catalog entries past their deprecation date.");
+        }
+    }
+    Ok(())
+}
+
+async fn handle_trial_model(
+    catalog: &CatalogStore,
+    config: &config::AppConfig,
+    options: TrialModelOptions,
+) -> Result<(), AppError> {
+    if options.stop {
+        if catalog
+            .end_trial(options.provider, options.workload, &options.model)
+            .await?
+        {
+            println!(
+                "Ended trial of '{}' ({} / {}); the suggestion is pending again.",
+                options.model,
+                options.provider.as_str(),
+                options.workload.as_str()
+            );
+        } else {
+            println!(
+                "'{}' is not on trial for {} / {}.",
+                options.model,
+                options.provider.as_str(),
+                options.workload.as_str()
+            );
+        }
+        return Ok(());
+    }
+
+    let share = options.share.unwrap_or(config.trials.default_share);
+    catalog
+        .start_trial(options.provider, options.workload, &options.model, share)
+        .await?;
+    println!(
+        "'{}' ({} / {}) is on trial with {:.0}% shadow traffic.",
+        options.model,
+        options.provider.as_str(),
+        options.workload.as_str(),
+        share * 100.0
+    );
+    if !config.trials.enabled {
+        println!("Note: [trials] enabled = false, so no shadow requests are sent.");
+    }
+    println!("Compare it with the active models using `freegin-ai trial-report`.");
+    Ok(())
+}
+
+async fn handle_trial_report(
+    catalog: &CatalogStore,
+    shadow_log: &ShadowLog,
+    provider: Option<Provider>,
+) -> Result<(), AppError> {
+    let summaries = shadow_log.summaries(provider).await?;
+    let trials: Vec<_> = catalog
+        .list_suggestions(provider, None)
+        .await?
+        .into_iter()
+        .filter(|suggestion| suggestion.status == "trial")
+        .collect();
+
+    if summaries.is_empty() && trials.is_empty() {
+        println!("No trials. Start one with `freegin-ai trial-model <provider> <model>`.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<15} {:<40} {:>6} {:>8} {:>10} {:>10} {:>7} {:>7}  STATUS",
+        "PROVIDER",
+        "WORKLOAD",
+        "MODEL",
+        "SHADOW",
+        "SUCCESS",
+        "LATENCY",
+        "ACTIVE",
+        "LENGTH",
+        "OVERLAP"
+    );
+    for summary in &summaries {
+        let status = trials
+            .iter()
+            .find(|trial| {
+                trial.provider.as_str() == summary.provider
+                    && trial.workload.as_str() == summary.workload
+                    && trial.model == summary.model
+            })
+            .map_or_else(
+                || "ended".to_string(),
+                |trial| {
+                    format!(
+                        "trial {:.0}%",
+                        trial.trial_share.unwrap_or_default() * 100.0
+                    )
+                },
+            );
+        println!(
+            "{:<12} {:<15} {:<40} {:>6} {:>7.0}% {:>8.0}ms {:>8.0}ms {:>7} {:>7}  {}",
+            summary.provider,
+            summary.workload,
+            summary.model,
+            summary.requests,
+            summary.success_rate * 100.0,
+            summary.avg_latency_ms,
+            summary.avg_primary_latency_ms,
+            summary
+                .length_ratio
+                .map_or_else(|| "-".to_string(), |ratio| format!("{ratio:.2}x")),
+            summary
+                .avg_similarity
+                .map_or_else(|| "-".to_string(), |overlap| format!("{overlap:.2}")),
+            status
+        );
+    }
+    for trial in &trials {
+        let has_results = summaries.iter().any(|summary| {
+            trial.provider.as_str() == summary.provider
+                && trial.workload.as_str() == summary.workload
+                && trial.model == summary.model
+        });
+        if !has_results {
+            println!(
+                "{:<12} {:<15} {:<40} {:>6}  (no shadow traffic yet)",
+                trial.provider.as_str(),
+                trial.workload.as_str(),
+                trial.model,
+                0
+            );
+        }
+    }
+    println!(
+        "\nLATENCY is the trial model, ACTIVE the answer returned to callers. LENGTH and \
+         OVERLAP compare the trial answer with it."
+    );
+    Ok(())
+}
+
+async fn handle_catalog_export(
+    catalog: &CatalogStore,
+    options: CatalogExportOptions,
+) -> Result<(), AppError> {
+    let mut entries = catalog.list_models(options.provider, None).await?;
+    if options.active_only {
+        entries.retain(|entry| entry.status == "active");
+    }
+    let format = options.format.unwrap_or_else(|| {
+        options
+            .out
+            .as_deref()
+            .map_or(RosterFormat::Toml, RosterFormat::from_path)
+    });
+    let rendered = Roster::from_entries(&entries).render(format)?;
+
+    match &options.out {
+        Some(path) => {
+            fs::write(path, rendered).map_err(|err| {
+                AppError::ConfigError(format!("Failed to write {}: {err}", path.display()))
+            })?;
+            println!(
+                "Exported {} catalog entries to {}.",
+                entries.len(),
+                path.display()
+            );
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
+
+async fn handle_catalog_import(
+    catalog: &CatalogStore,
+    options: CatalogImportOptions,
+) -> Result<(), AppError> {
+    let roster = Roster::load(&options.file)?;
+    let plan = roster::plan_import(catalog, &roster, options.prune).await?;
+
+    for entry in &plan.add {
+        println!(
+            "+ {} / {} / {} (priority {}{})",
+            entry.provider,
+            entry.workload.as_str(),
+            entry.model,
+            entry.priority,
+            if entry.status == "active" {
+                ""
+            } else {
+                ", retired"
+            }
+        );
+    }
+    for (entry, changes) in &plan.update {
+        println!(
+            "~ {} / {} / {}: {}",
+            entry.provider,
+            entry.workload.as_str(),
+            entry.model,
+            changes.join(", ")
+        );
+    }
+    for model in &plan.retire {
+        println!(
+            "- {} / {} / {} (retire)",
+            model.provider,
+            model.workload.as_str(),
+            model.model
+        );
+    }
+
+    let summary = format!(
+        "{} to add, {} to update, {} to retire, {} unchanged",
+        plan.add.len(),
+        plan.update.len(),
+        plan.retire.len(),
+        plan.unchanged
+    );
+    if plan.is_empty() {
+        println!(
+            "Catalog already matches {} ({summary}).",
+            options.file.display()
+        );
+    } else if options.dry_run {
+        println!("Dry run: {summary}. Re-run without --dry-run to apply.");
+    } else {
+        roster::apply_import(catalog, &plan).await?;
+        println!("Applied {}: {summary}.", options.file.display());
+    }
+    Ok(())
+}
+
+async fn handle_alias_list(
+    config: &config::AppConfig,
+    catalog: &CatalogStore,
+    json: bool,
+) -> Result<(), AppError> {
+    let configured = aliases::from_config(&config.aliases)?;
+    let mut entries: Vec<ModelAlias> = configured
+        .into_iter()
+        .map(|(name, targets)| ModelAlias {
+            name,
+            targets,
+            source: AliasSource::Config,
+        })
+        .collect();
+    // Configured aliases shadow catalog aliases of the same name.
+    for alias in catalog.aliases().await? {
+        if !config.aliases.contains_key(&alias.name) {
+            entries.push(alias);
         }
     }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
 
+    if json {
+        let rendered = serde_json::to_string_pretty(&entries)
+            .map_err(|err| AppError::ApiError(format!("Failed to render aliases: {err}")))?;
+        println!("{rendered}");
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("No model aliases defined. Add one with `freegin-ai alias set <name> <provider/model>...`.");
+        return Ok(());
+    }
+    println!("{:<20} {:<8} TARGETS", "ALIAS", "SOURCE");
+    for alias in &entries {
+        let targets: Vec<String> = alias.targets.iter().map(ToString::to_string).collect();
+        println!(
+            "{:<20} {:<8} {}",
+            alias.name,
+            alias.source.as_str(),
+            targets.join(", ")
+        );
+    }
     Ok(())
 }
 
-async fn handle_adopt_model(
+async fn handle_alias_set(
+    config: &config::AppConfig,
     catalog: &CatalogStore,
-    options: AdoptModelOptions,
+    options: AliasSetOptions,
 ) -> Result<(), AppError> {
-    catalog
-        .adopt_model(
-            options.provider,
-            options.workload,
-            options.model.clone(),
-            None, // rationale can be added later
-            None, // metadata can be added later
-            options.priority,
-        )
-        .await?;
+    catalog.set_alias(&options.name, &options.targets).await?;
+    let targets: Vec<String> = options.targets.iter().map(ToString::to_string).collect();
+    println!("Alias '{}' -> {}", options.name, targets.join(", "));
+    if config.aliases.contains_key(&options.name) {
+        println!(
+            "Note: config.toml also defines '{}'; the configured alias takes precedence.",
+            options.name
+        );
+    }
+    Ok(())
+}
 
-    println!(
-        "Adopted model '{}' for provider '{}' and workload '{:?}' with priority {}",
-        options.model,
-        options.provider.as_str(),
-        options.workload,
-        options.priority
-    );
+async fn handle_alias_remove(
+    config: &config::AppConfig,
+    catalog: &CatalogStore,
+    name: &str,
+) -> Result<(), AppError> {
+    if catalog.remove_alias(name).await? {
+        println!("Removed alias '{name}'.");
+    } else if config.aliases.contains_key(name) {
+        return Err(AppError::InvalidRequest(format!(
+            "Alias '{name}' is defined in config.toml; remove it there"
+        )));
+    } else {
+        return Err(AppError::InvalidRequest(format!("No alias named '{name}'")));
+    }
+    Ok(())
+}
 
-    // Show the new roster ordering
-    let models = catalog
-        .active_models(options.provider, Some(options.workload))
-        .await?;
-    if !models.is_empty() {
+async fn handle_discover_models(
+    options: DiscoverOptions,
+    config: &config::AppConfig,
+    credential_store: &CredentialStore,
+    catalog: &CatalogStore,
+) -> Result<(), AppError> {
+    let router =
+        ProviderRouter::from_config(config, credential_store, None, Some(catalog.clone())).await?;
+    let providers = options
+        .provider
+        .map_or_else(|| router.providers().to_vec(), |provider| vec![provider]);
+    let reports = discovery::discover(&router, catalog, &providers).await?;
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&reports)
+            .map_err(|err| AppError::ApiError(err.to_string()))?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    for report in &reports {
+        if let Some(error) = &report.error {
+            println!("{}: discovery failed: {error}", report.provider);
+            continue;
+        }
         println!(
-            "\nActive models for {} / {:?}:",
-            options.provider.as_str(),
-            options.workload
+            "{}: {} models available, {} catalog entries updated",
+            report.provider, report.available, report.updated
         );
-        for m in models {
-            let rationale = m.rationale.as_deref().unwrap_or("");
-            println!("  {:3}  {} — {}", m.priority, m.model, rationale);
+        for model in &report.new_models {
+            println!("  new      {model}");
+        }
+        for entry in &report.missing {
+            println!("  missing  {entry} (active in the catalog, no longer listed)");
         }
     }
-
+    if reports.iter().any(|report| !report.missing.is_empty()) {
+        println!("\nMissing models stay active until retired; see `freegin-ai list-models`.");
+    }
     Ok(())
 }
 
+/// Builds the request asking the router for model suggestions.
+fn refresh_request(context: &RefreshContext) -> Result<AIRequest, AppError> {
+    let context_json = serde_json::to_string_pretty(context)
+        .map_err(|e| AppError::ApiError(format!("Failed to serialize context: {}", e)))?;
+
+    let prompt = format!(
+        r#"You are a model selection assistant. Analyze the following context and suggest 3-5 candidate models for the given provider and workload.
+
+Context:
+{}
+
+Requirements:
+- Respond with ONLY valid JSON matching this schema:
+{{
+  "suggestions": [
+    {{
+      "model": "provider/model-name",
+      "workload": "Chat|Code|Summarization|Extraction|Creative|Classification",
+      "rationale": "Brief explanation (max 40 words)",
+      "production_ready": true|false,
+      "notes": "Optional additional notes",
+      "metadata": {{"est_cost_per_1k_tokens": 0.15}}
+    }}
+  ]
+}}
+
+- Consider current models and usage statistics
+- Prioritize models with good cost/performance balance
+- Include newer models that might outperform current roster
+- Ensure model names are valid for the provider; when available_models is given, only suggest ids from it
+
+Output only the JSON, no other text."#,
+        context_json
+    );
+
+    Ok(AIRequest {
+        model: String::new(), // Let router pick
+        prompt,
+        tags: vec!["model-refresh".to_string()],
+        context: vec![],
+        metadata: HashMap::new(),
+        hints: RequestHints {
+            complexity: Some(RequestComplexity::Medium),
+            quality: Some(RequestQuality::Premium),
+            speed: Some(RequestSpeed::Normal),
+            guardrail: Some(RequestGuardrail::Strict),
+            response_format: Some(ResponseFormat::Json),
+            provider: None,
+            workload: None,
+        },
+        response_schema: None,
+        images: Vec::new(),
+        history: Vec::new(),
+    })
+}
+
+/// Prints the suggestions a dry run would insert.
+fn print_dry_run(suggestions: &SuggestionSet) {
+    println!("\n=== DRY RUN MODE ===");
+    println!(
+        "Would insert {} suggestions:\n",
+        suggestions.suggestions.len()
+    );
+    for (i, s) in suggestions.suggestions.iter().enumerate() {
+        println!("{}. {} ({})", i + 1, s.model, s.workload);
+        println!("   Rationale: {}", s.rationale.as_deref().unwrap_or("N/A"));
+        println!(
+            "   Production ready: {}",
+            s.production_ready.unwrap_or(false)
+        );
+        if let Some(notes) = &s.notes {
+            println!("   Notes: {}", notes);
+        }
+        println!();
+    }
+}
+
 async fn handle_refresh_models(
     options: RefreshOptions,
     config: &config::AppConfig,
@@ -1345,8 +4096,15 @@ async fn handle_refresh_models(
         workload
     );
 
-    // 1. Build context with current roster
+    // 1. Build context with current roster and, when discovered, the models
+    //    the provider actually serves
     let current_models = catalog.active_models(provider, Some(workload)).await?;
+    let available_models: Vec<String> = catalog
+        .available_models(provider)
+        .await?
+        .into_iter()
+        .map(|model| model.id)
+        .collect();
 
     // 2. Get usage statistics
     let stats = catalog
@@ -1380,59 +4138,11 @@ async fn handle_refresh_models(
             success_rate: stats.success_rate,
             avg_latency_ms: stats.avg_latency_ms,
         },
+        available_models: available_models.clone(),
     };
 
-    let context_json = serde_json::to_string_pretty(&context)
-        .map_err(|e| AppError::ApiError(format!("Failed to serialize context: {}", e)))?;
-
-    let prompt = format!(
-        r#"You are a model selection assistant. Analyze the following context and suggest 3-5 candidate models for the given provider and workload.
-
-Context:
-{}
-
-Requirements:
-- Respond with ONLY valid JSON matching this schema:
-{{
-  "suggestions": [
-    {{
-      "model": "provider/model-name",
-      "workload": "Chat|Code|Summarization|Extraction|Creative|Classification",
-      "rationale": "Brief explanation (max 40 words)",
-      "production_ready": true|false,
-      "notes": "Optional additional notes",
-      "metadata": {{"est_cost_per_1k_tokens": 0.15}}
-    }}
-  ]
-}}
-
-- Consider current models and usage statistics
-- Prioritize models with good cost/performance balance
-- Include newer models that might outperform current roster
-- Ensure model names are valid for the provider
-
-Output only the JSON, no other text."#,
-        context_json
-    );
-
     // 4. Call router to get suggestions (using the most reliable model available)
-    let request = AIRequest {
-        model: String::new(), // Let router pick
-        prompt,
-        tags: vec!["model-refresh".to_string()],
-        context: vec![],
-        metadata: HashMap::new(),
-        hints: RequestHints {
-            complexity: Some(RequestComplexity::Medium),
-            quality: Some(RequestQuality::Premium),
-            speed: Some(RequestSpeed::Normal),
-            guardrail: Some(RequestGuardrail::Strict),
-            response_format: Some(ResponseFormat::Json),
-            provider: None,
-            workload: None,
-        },
-    };
-
+    let request = refresh_request(&context)?;
     println!("Querying LLM for model suggestions...");
     let router = ProviderRouter::from_config(
         config,
@@ -1452,38 +4162,24 @@ Output only the JSON, no other text."#,
     })?;
 
     if options.dry_run {
-        println!("\n=== DRY RUN MODE ===");
-        println!(
-            "Would insert {} suggestions:\n",
-            suggestions.suggestions.len()
-        );
-        for (i, s) in suggestions.suggestions.iter().enumerate() {
-            println!("{}. {} ({})", i + 1, s.model, s.workload);
-            println!("   Rationale: {}", s.rationale.as_deref().unwrap_or("N/A"));
-            println!(
-                "   Production ready: {}",
-                s.production_ready.unwrap_or(false)
-            );
-            if let Some(notes) = &s.notes {
-                println!("   Notes: {}", notes);
-            }
-            println!();
-        }
+        print_dry_run(&suggestions);
         return Ok(());
     }
 
     // 6. Insert suggestions into database
     let mut inserted = 0;
     for suggestion in suggestions.suggestions {
-        let workload_enum = match suggestion.workload.to_lowercase().as_str() {
-            "chat" => Workload::Chat,
-            "code" => Workload::Code,
-            "summarization" => Workload::Summarization,
-            "extraction" => Workload::Extraction,
-            "creative" => Workload::Creative,
-            "classification" => Workload::Classification,
-            _ => continue, // Skip invalid workloads
+        let Some(workload_enum) = Workload::from_key(&suggestion.workload.to_lowercase()) else {
+            continue; // Skip invalid workloads
         };
+        if !available_models.is_empty() && !available_models.contains(&suggestion.model) {
+            println!(
+                "Skipping '{}': not listed by {} (see `freegin-ai discover-models`).",
+                suggestion.model,
+                provider.as_str()
+            );
+            continue;
+        }
 
         let metadata_str = suggestion
             .metadata
@@ -1516,6 +4212,8 @@ struct RefreshContext {
     workload: String,
     current_models: Vec<ModelInfo>,
     usage_stats: StatsInfo,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    available_models: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -1550,8 +4248,10 @@ struct Suggestion {
 async fn handle_status(
     catalog: &CatalogStore,
     db_pool: Arc<DbPool>,
+    budget: &config::BudgetConfig,
     options: StatusOptions,
 ) -> Result<(), AppError> {
+    let budget = Budget::from_config(Arc::clone(&db_pool), budget)?;
     let health_tracker = HealthTracker::new(db_pool);
 
     let providers = if let Some(p) = options.provider {
@@ -1572,50 +4272,7 @@ async fn handle_status(
     for provider in providers {
         // Get provider health status
         let health = health_tracker.get_health(provider).await?;
-
-        // Format health status
-        let health_icon = match health.status {
-            freegin_ai::health::HealthStatus::Available => "✓",
-            freegin_ai::health::HealthStatus::Degraded => "⚠",
-            freegin_ai::health::HealthStatus::Unavailable => "✗",
-        };
-        let health_text = match health.status {
-            freegin_ai::health::HealthStatus::Available => "AVAILABLE",
-            freegin_ai::health::HealthStatus::Degraded => "DEGRADED",
-            freegin_ai::health::HealthStatus::Unavailable => "UNAVAILABLE",
-        };
-
-        println!(
-            "\n═══ {} {} {} ═══",
-            provider.as_str().to_uppercase(),
-            health_icon,
-            health_text
-        );
-
-        // Show health details if there are issues
-        if health.status != freegin_ai::health::HealthStatus::Available {
-            if let Some(error) = &health.last_error {
-                println!("    Last error: {}", error);
-            }
-            if let Some(retry_after) = health.retry_after {
-                use chrono::Utc;
-                let now = Utc::now();
-                if retry_after > now {
-                    let duration = retry_after - now;
-                    let minutes = duration.num_minutes();
-                    let seconds = duration.num_seconds() % 60;
-                    println!(
-                        "    Retry after: {}m {}s (at {})",
-                        minutes,
-                        seconds,
-                        retry_after.format("%H:%M:%S")
-                    );
-                }
-            }
-            if health.consecutive_failures > 0 {
-                println!("    Consecutive failures: {}", health.consecutive_failures);
-            }
-        }
+        print_provider_health(provider, &health);
 
         // Get all workloads
         let workloads = [
@@ -1628,93 +4285,187 @@ async fn handle_status(
         ];
 
         for workload in workloads {
-            // Get active models
-            let active = catalog.active_models(provider, Some(workload)).await?;
+            print_workload_status(catalog, provider, workload).await?;
+        }
+    }
 
-            // Get suggestions
-            let suggestions = catalog
-                .list_suggestions(Some(provider), Some(workload))
-                .await?;
+    if let Some(budget) = budget {
+        print_budget(&budget, options.provider).await?;
+    }
 
-            // Get usage stats
-            let stats = catalog
-                .usage_stats(provider, Some(workload))
-                .await
-                .unwrap_or(freegin_ai::catalog::UsageStats {
-                    total_calls: 0,
-                    successful_calls: 0,
-                    success_rate: 0.0,
-                    avg_latency_ms: 0.0,
-                    max_latency_ms: 0,
-                });
+    println!();
+    Ok(())
+}
 
-            // Only show workload if there's data
-            if !active.is_empty() || !suggestions.is_empty() || stats.total_calls > 0 {
-                println!("\n┌─ {:?}", workload);
-
-                // Show currently favored (active roster)
-                if !active.is_empty() {
-                    println!("│ ▶ ACTIVE (currently favored):");
-                    for model in &active {
-                        let rationale = model
-                            .rationale
-                            .as_deref()
-                            .unwrap_or("")
-                            .chars()
-                            .take(50)
-                            .collect::<String>();
-                        println!(
-                            "│   [{:3}] {} {}",
-                            model.priority,
-                            model.model,
-                            if !rationale.is_empty() {
-                                format!("— {}", rationale)
-                            } else {
-                                String::new()
-                            }
-                        );
-                    }
-                }
+/// Prints the `status` header of a provider and, when it is not available,
+/// the last error, retry time and failure count.
+fn print_provider_health(provider: Provider, health: &ProviderHealth) {
+    // Format health status
+    let health_icon = match health.status {
+        freegin_ai::health::HealthStatus::Available => "✓",
+        freegin_ai::health::HealthStatus::Degraded => "⚠",
+        freegin_ai::health::HealthStatus::Unavailable => "✗",
+    };
+    let health_text = match health.status {
+        freegin_ai::health::HealthStatus::Available => "AVAILABLE",
+        freegin_ai::health::HealthStatus::Degraded => "DEGRADED",
+        freegin_ai::health::HealthStatus::Unavailable => "UNAVAILABLE",
+    };
 
-                // Show known models (suggestions)
-                if !suggestions.is_empty() {
-                    println!("│ ◆ KNOWN (suggestions):");
-                    for sugg in &suggestions {
-                        let rationale = sugg
-                            .rationale
-                            .as_deref()
-                            .unwrap_or("")
-                            .chars()
-                            .take(50)
-                            .collect::<String>();
-                        println!(
-                            "│   [{}] {} {}",
-                            sugg.status,
-                            sugg.model,
-                            if !rationale.is_empty() {
-                                format!("— {}", rationale)
-                            } else {
-                                String::new()
-                            }
-                        );
-                    }
-                }
+    println!(
+        "\n═══ {} {} {} ═══",
+        provider.as_str().to_uppercase(),
+        health_icon,
+        health_text
+    );
 
-                // Show usage stats
-                if stats.total_calls > 0 {
-                    println!("│ ⚡ USAGE:");
-                    println!(
-                        "│   Calls: {} | Success: {:.1}% | Avg latency: {:.0}ms",
-                        stats.total_calls, stats.success_rate, stats.avg_latency_ms
-                    );
-                }
+    // Show health details if there are issues
+    if health.status != freegin_ai::health::HealthStatus::Available {
+        if let Some(error) = &health.last_error {
+            println!("    Last error: {}", error);
+        }
+        if let Some(retry_after) = health.retry_after {
+            use chrono::Utc;
+            let now = Utc::now();
+            if retry_after > now {
+                let duration = retry_after - now;
+                let minutes = duration.num_minutes();
+                let seconds = duration.num_seconds() % 60;
+                println!(
+                    "    Retry after: {}m {}s (at {})",
+                    minutes,
+                    seconds,
+                    retry_after.format("%H:%M:%S")
+                );
+            }
+        }
+        if health.consecutive_failures > 0 {
+            println!("    Consecutive failures: {}", health.consecutive_failures);
+        }
+    }
+}
+
+/// Prints the `status` section of one workload: active models, suggestions
+/// and usage. Workloads without any of them are skipped.
+async fn print_workload_status(
+    catalog: &CatalogStore,
+    provider: Provider,
+    workload: Workload,
+) -> Result<(), AppError> {
+    // Get active models
+    let active = catalog.active_models(provider, Some(workload)).await?;
+
+    // Get suggestions
+    let suggestions = catalog
+        .list_suggestions(Some(provider), Some(workload))
+        .await?;
+
+    // Get usage stats
+    let stats = catalog
+        .usage_stats(provider, Some(workload))
+        .await
+        .unwrap_or(freegin_ai::catalog::UsageStats {
+            total_calls: 0,
+            successful_calls: 0,
+            success_rate: 0.0,
+            avg_latency_ms: 0.0,
+            max_latency_ms: 0,
+        });
+
+    // Only show workload if there's data
+    if !active.is_empty() || !suggestions.is_empty() || stats.total_calls > 0 {
+        println!("\n┌─ {:?}", workload);
+
+        // Show currently favored (active roster)
+        if !active.is_empty() {
+            println!("│ ▶ ACTIVE (currently favored):");
+            for model in &active {
+                let rationale = model
+                    .rationale
+                    .as_deref()
+                    .unwrap_or("")
+                    .chars()
+                    .take(50)
+                    .collect::<String>();
+                println!(
+                    "│   [{:3}] {} {}",
+                    model.priority,
+                    model.model,
+                    if !rationale.is_empty() {
+                        format!("— {}", rationale)
+                    } else {
+                        String::new()
+                    }
+                );
+            }
+        }
 
-                println!("└─");
+        // Show known models (suggestions)
+        if !suggestions.is_empty() {
+            println!("│ ◆ KNOWN (suggestions):");
+            for sugg in &suggestions {
+                let rationale = sugg
+                    .rationale
+                    .as_deref()
+                    .unwrap_or("")
+                    .chars()
+                    .take(50)
+                    .collect::<String>();
+                println!(
+                    "│   [{}] {} {}",
+                    sugg.status,
+                    sugg.model,
+                    if !rationale.is_empty() {
+                        format!("— {}", rationale)
+                    } else {
+                        String::new()
+                    }
+                );
             }
         }
+
+        // Show usage stats
+        if stats.total_calls > 0 {
+            println!("│ ⚡ USAGE:");
+            println!(
+                "│   Calls: {} | Success: {:.1}% | Avg latency: {:.0}ms",
+                stats.total_calls, stats.success_rate, stats.avg_latency_ms
+            );
+        }
+
+        println!("└─");
+    }
+    Ok(())
+}
+
+async fn print_budget(budget: &Budget, provider: Option<Provider>) -> Result<(), AppError> {
+    let usage: Vec<BudgetUsage> = budget
+        .usage(chrono::Utc::now())
+        .await?
+        .into_iter()
+        .filter(|usage| {
+            provider.is_none() || usage.provider.is_none() || usage.provider == provider
+        })
+        .collect();
+    if usage.is_empty() {
+        return Ok(());
     }
 
-    println!();
+    println!("\n═══ BUDGET ═══");
+    for usage in &usage {
+        let (icon, note) = if usage.is_exhausted() {
+            ("✗", " — cap reached, paid models are skipped")
+        } else if usage.share() >= budget.warn_at() {
+            ("⚠", " — approaching cap")
+        } else {
+            ("✓", "")
+        };
+        println!(
+            "  {icon} {} ({:.0}%){note}",
+            usage.describe(),
+            usage.share() * 100.0
+        );
+    }
     Ok(())
 }
 
@@ -1738,4 +4489,27 @@ mod tests {
         assert_eq!(opts.hints.complexity, Some(RequestComplexity::High));
         assert_eq!(opts.provider_override.as_deref(), Some("huggingface"));
     }
+
+    #[test]
+    fn parse_chat_command_handles_overrides() {
+        assert_eq!(
+            parse_chat_command("/provider groq"),
+            Ok(ChatCommand::Provider(Some(Provider::Groq)))
+        );
+        assert_eq!(
+            parse_chat_command("/model auto"),
+            Ok(ChatCommand::Model(None))
+        );
+        assert_eq!(
+            parse_chat_command("/workload code"),
+            Ok(ChatCommand::Workload(Some(Workload::Code)))
+        );
+        assert_eq!(
+            parse_chat_command("/save  weekly notes "),
+            Ok(ChatCommand::Save("weekly notes".into()))
+        );
+        assert_eq!(parse_chat_command("/load"), Ok(ChatCommand::Load(None)));
+        assert!(parse_chat_command("/save").is_err());
+        assert!(parse_chat_command("/bogus").is_err());
+    }
 }
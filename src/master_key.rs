@@ -0,0 +1,573 @@
+//! Sources for the key that encrypts stored provider credentials.
+//!
+//! By default the master key is a random `secret.key` file next to the
+//! config, so a copy of the config directory and the database is enough to
+//! read every token. Two sources keep the key itself off disk:
+//!
+//! * `passphrase` derives the key with Argon2id from a passphrase read from
+//!   `FREEGIN_PASSPHRASE` or prompted for on start. Only the salt and a check
+//!   value are written (`secret.kdf`), so a wrong passphrase is reported up
+//!   front instead of failing on every credential.
+//! * `keyring` keeps a random key in the desktop Secret Service. It needs a
+//!   Linux build with the `keyring` feature.
+//!
+//! `freegin-ai credentials rekey` moves the stored credentials to a fresh
+//! key, optionally held by a different source.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::RngCore;
+use tokio::fs;
+use tracing::warn;
+
+use crate::{config::MasterKeySource, error::AppError};
+
+/// Environment variable holding the passphrase of the `passphrase` source.
+pub const PASSPHRASE_ENV: &str = "FREEGIN_PASSPHRASE";
+/// Environment variable holding the new passphrase during a rekey.
+pub const NEW_PASSPHRASE_ENV: &str = "FREEGIN_NEW_PASSPHRASE";
+/// Size of the master key in bytes.
+pub const KEY_SIZE: usize = 32;
+
+const KEY_FILENAME: &str = "secret.key";
+const KDF_FILENAME: &str = "secret.kdf";
+/// Appended to a file name while a new key waits for the rekey to commit.
+const STAGED_SUFFIX: &str = ".new";
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+/// Sealed under a passphrase key so the right passphrase can be recognised.
+const CHECK_PLAINTEXT: &[u8] = b"freegin-ai master key";
+
+/// Directory holding `secret.key` and `secret.kdf`.
+///
+/// # Errors
+///
+/// Fails when the platform has no config directory.
+pub fn key_dir() -> Result<PathBuf, AppError> {
+    dirs::config_dir()
+        .map(|dir| dir.join("freegin-ai"))
+        .ok_or_else(|| AppError::ConfigError("Unable to determine config directory".into()))
+}
+
+/// Loads the master key from `source`, creating one on first use.
+///
+/// # Errors
+///
+/// Fails when the key cannot be read, unlocked or created, or when creating it
+/// would orphan credentials locked by another source.
+pub async fn load(source: MasterKeySource, dir: &Path) -> Result<[u8; KEY_SIZE], AppError> {
+    match source {
+        MasterKeySource::File => {
+            let path = dir.join(KEY_FILENAME);
+            if !exists(&path).await && exists(&dir.join(KDF_FILENAME)).await {
+                return Err(AppError::ConfigError(
+                    "Stored credentials are locked with a passphrase; set master_key = \"passphrase\" under [credentials]".into(),
+                ));
+            }
+            load_or_create_key(&path).await
+        }
+        MasterKeySource::Passphrase => {
+            if let Ok(record) = fs::read(dir.join(KDF_FILENAME)).await {
+                let passphrase = read_passphrase(PASSPHRASE_ENV, "Credential passphrase", false)?;
+                return unlock(&record, &passphrase);
+            }
+            refuse_orphaning(source, dir).await?;
+            let passphrase = read_passphrase(PASSPHRASE_ENV, "New credential passphrase", true)?;
+            let key = NewMasterKey::from_passphrase(dir, &passphrase)?;
+            key.save().await?;
+            Ok(key.key)
+        }
+        MasterKeySource::Keyring => {
+            // Key files next to the config mean the credentials have not
+            // been moved to the keyring yet; a keyring key would not open them.
+            refuse_orphaning(source, dir).await?;
+            if let Some(key) = secret_service::read(secret_service::USER).await? {
+                return Ok(key);
+            }
+            let key = NewMasterKey::random(source, dir);
+            key.save().await?;
+            Ok(key.key)
+        }
+    }
+}
+
+/// Derives a master key from a passphrase with Argon2id.
+///
+/// # Errors
+///
+/// Fails when Argon2 rejects the salt.
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_SIZE], AppError> {
+    let mut key = [0u8; KEY_SIZE];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| AppError::ConfigError(format!("Failed to derive key: {err}")))?;
+    Ok(key)
+}
+
+/// A freshly generated master key that has not been stored yet.
+pub struct NewMasterKey {
+    source: MasterKeySource,
+    dir: PathBuf,
+    key: [u8; KEY_SIZE],
+    kdf_record: Vec<u8>,
+}
+
+impl fmt::Debug for NewMasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NewMasterKey")
+            .field("source", &self.source)
+            .field("dir", &self.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NewMasterKey {
+    /// Generates a key for `source`, asking for the new passphrase (or
+    /// reading `FREEGIN_NEW_PASSPHRASE`) when the source needs one.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the new passphrase cannot be read or the key cannot be
+    /// derived.
+    pub fn generate(source: MasterKeySource, dir: &Path) -> Result<Self, AppError> {
+        match source {
+            MasterKeySource::Passphrase => {
+                let passphrase =
+                    read_passphrase(NEW_PASSPHRASE_ENV, "New credential passphrase", true)?;
+                Self::from_passphrase(dir, &passphrase)
+            }
+            MasterKeySource::File | MasterKeySource::Keyring => Ok(Self::random(source, dir)),
+        }
+    }
+
+    /// A random key for the `file` or `keyring` source.
+    #[must_use]
+    pub fn random(source: MasterKeySource, dir: &Path) -> Self {
+        let mut key = [0u8; KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        Self {
+            source,
+            dir: dir.to_path_buf(),
+            key,
+            kdf_record: Vec::new(),
+        }
+    }
+
+    /// A key derived from `passphrase` under a fresh salt.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the key cannot be derived or the check value cannot be
+    /// sealed.
+    pub fn from_passphrase(dir: &Path, passphrase: &str) -> Result<Self, AppError> {
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt)?;
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let check = XChaCha20Poly1305::new(&key.into())
+            .encrypt(XNonce::from_slice(&nonce), CHECK_PLAINTEXT)
+            .map_err(|err| AppError::ConfigError(format!("Failed to seal key check: {err}")))?;
+
+        let mut kdf_record = Vec::with_capacity(SALT_SIZE + NONCE_SIZE + check.len());
+        kdf_record.extend_from_slice(&salt);
+        kdf_record.extend_from_slice(&nonce);
+        kdf_record.extend_from_slice(&check);
+        Ok(Self {
+            source: MasterKeySource::Passphrase,
+            dir: dir.to_path_buf(),
+            key,
+            kdf_record,
+        })
+    }
+
+    /// The key bytes.
+    #[must_use]
+    pub const fn key(&self) -> &[u8; KEY_SIZE] {
+        &self.key
+    }
+
+    /// Where the key will be kept.
+    #[must_use]
+    pub const fn source(&self) -> MasterKeySource {
+        self.source
+    }
+
+    /// Stores the key in its source and deletes the `secret.key` or
+    /// `secret.kdf` left behind by the other sources.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the key cannot be written or moved into place.
+    pub async fn save(&self) -> Result<(), AppError> {
+        self.stage().await?;
+        self.install().await
+    }
+
+    /// Writes the key next to the current one (`secret.key.new`,
+    /// `secret.kdf.new` or a `master-key.new` keyring entry) without
+    /// touching the key in use.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the staged key cannot be written.
+    pub async fn stage(&self) -> Result<(), AppError> {
+        fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|err| AppError::ConfigError(format!("Failed to create config dir: {err}")))?;
+        match self.source {
+            MasterKeySource::File => write_private(&self.staged_path(), &self.key).await,
+            MasterKeySource::Passphrase => {
+                write_private(&self.staged_path(), &self.kdf_record).await
+            }
+            MasterKeySource::Keyring => {
+                secret_service::write(secret_service::STAGED_USER, &self.key).await
+            }
+        }
+    }
+
+    /// Moves a staged key into place and deletes the keys of the other
+    /// sources.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the staged key cannot be moved into place or a stale key
+    /// cannot be deleted.
+    pub async fn install(&self) -> Result<(), AppError> {
+        let key_path = self.dir.join(KEY_FILENAME);
+        let kdf_path = self.dir.join(KDF_FILENAME);
+        match self.source {
+            MasterKeySource::File => {
+                rename(&self.staged_path(), &key_path).await?;
+                remove_if_exists(&kdf_path).await
+            }
+            MasterKeySource::Passphrase => {
+                rename(&self.staged_path(), &kdf_path).await?;
+                remove_if_exists(&key_path).await
+            }
+            MasterKeySource::Keyring => {
+                secret_service::write(secret_service::USER, &self.key).await?;
+                secret_service::delete(secret_service::STAGED_USER).await?;
+                remove_if_exists(&key_path).await?;
+                remove_if_exists(&kdf_path).await
+            }
+        }
+    }
+
+    /// Removes a staged key that will not be installed. Failures are only
+    /// logged since the key in use is unaffected.
+    pub async fn discard(&self) {
+        let removed = match self.source {
+            MasterKeySource::File | MasterKeySource::Passphrase => {
+                remove_if_exists(&self.staged_path()).await
+            }
+            MasterKeySource::Keyring => secret_service::delete(secret_service::STAGED_USER).await,
+        };
+        if let Err(err) = removed {
+            warn!(error = %err, "Failed to remove the staged master key");
+        }
+    }
+
+    /// Where [`stage`](Self::stage) puts the key, for error messages.
+    #[must_use]
+    pub fn staged_location(&self) -> String {
+        match self.source {
+            MasterKeySource::File | MasterKeySource::Passphrase => {
+                self.staged_path().display().to_string()
+            }
+            MasterKeySource::Keyring => {
+                format!("keyring entry '{}'", secret_service::STAGED_USER)
+            }
+        }
+    }
+
+    fn staged_path(&self) -> PathBuf {
+        let name = match self.source {
+            MasterKeySource::Passphrase => KDF_FILENAME,
+            MasterKeySource::File | MasterKeySource::Keyring => KEY_FILENAME,
+        };
+        self.dir.join(format!("{name}{STAGED_SUFFIX}"))
+    }
+}
+
+/// Checks `passphrase` against a `secret.kdf` record and derives the key.
+fn unlock(record: &[u8], passphrase: &str) -> Result<[u8; KEY_SIZE], AppError> {
+    if record.len() <= SALT_SIZE + NONCE_SIZE {
+        return Err(AppError::ConfigError(format!(
+            "{KDF_FILENAME} is truncated; restore it from a backup"
+        )));
+    }
+    let (salt, rest) = record.split_at(SALT_SIZE);
+    let (nonce, check) = rest.split_at(NONCE_SIZE);
+    let key = derive_key(passphrase, salt)?;
+    let opened = XChaCha20Poly1305::new(&key.into())
+        .decrypt(XNonce::from_slice(nonce), check)
+        .map_err(|_| AppError::ConfigError("Wrong credential passphrase".into()))?;
+    if opened != CHECK_PLAINTEXT {
+        return Err(AppError::ConfigError("Wrong credential passphrase".into()));
+    }
+    Ok(key)
+}
+
+/// Stops a new source from silently replacing a `secret.key` or
+/// `secret.kdf` that still opens the stored credentials.
+async fn refuse_orphaning(source: MasterKeySource, dir: &Path) -> Result<(), AppError> {
+    for (file, current) in [
+        (KEY_FILENAME, MasterKeySource::File),
+        (KDF_FILENAME, MasterKeySource::Passphrase),
+    ] {
+        let path = dir.join(file);
+        if current != source && exists(&path).await {
+            return Err(AppError::ConfigError(format!(
+                "Stored credentials are encrypted with the key in {}; set master_key back to \"{}\" and run 'freegin-ai credentials rekey --to {}'",
+                path.display(),
+                current.as_str(),
+                source.as_str()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Reads a passphrase from the `env` variable, or prompts for it under
+/// `label` without echo (twice when `confirm` is set).
+///
+/// # Errors
+///
+/// Fails when no terminal is attached and `env` is unset, or when the
+/// passphrase is empty or not repeated correctly.
+pub fn read_passphrase(env: &str, label: &str, confirm: bool) -> Result<String, AppError> {
+    if let Ok(passphrase) = std::env::var(env) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+
+    let prompt = format!("{label} (input hidden): ");
+    let passphrase = rpassword::prompt_password(prompt).map_err(|err| {
+        AppError::ConfigError(format!(
+            "Failed to read passphrase (set {env} when no terminal is attached): {err}"
+        ))
+    })?;
+    if passphrase.is_empty() {
+        return Err(AppError::ConfigError(
+            "The passphrase must not be empty".into(),
+        ));
+    }
+    if confirm {
+        let again = rpassword::prompt_password("Repeat passphrase: ")
+            .map_err(|err| AppError::ConfigError(format!("Failed to read passphrase: {err}")))?;
+        if again != passphrase {
+            return Err(AppError::ConfigError("Passphrases do not match".into()));
+        }
+    }
+    Ok(passphrase)
+}
+
+async fn load_or_create_key(path: &Path) -> Result<[u8; KEY_SIZE], AppError> {
+    if let Ok(bytes) = fs::read(path).await {
+        if let Ok(key) = <[u8; KEY_SIZE]>::try_from(bytes.as_slice()) {
+            return Ok(key);
+        }
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|err| AppError::ConfigError(format!("Failed to create config dir: {err}")))?;
+    }
+
+    let mut key = [0u8; KEY_SIZE];
+    OsRng.fill_bytes(&mut key);
+    write_private(path, &key).await?;
+    Ok(key)
+}
+
+async fn write_private(path: &Path, bytes: &[u8]) -> Result<(), AppError> {
+    fs::write(path, bytes).await.map_err(|err| {
+        AppError::ConfigError(format!("Failed to write {}: {err}", path.display()))
+    })?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let perms = std::fs::Permissions::from_mode(0o600);
+        std::fs::set_permissions(path, perms)
+            .map_err(|err| AppError::ConfigError(format!("Failed to set key perms: {err}")))?;
+    }
+    Ok(())
+}
+
+async fn rename(from: &Path, to: &Path) -> Result<(), AppError> {
+    fs::rename(from, to).await.map_err(|err| {
+        AppError::ConfigError(format!(
+            "Failed to move {} to {}: {err}",
+            from.display(),
+            to.display()
+        ))
+    })
+}
+
+async fn remove_if_exists(path: &Path) -> Result<(), AppError> {
+    match fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(AppError::ConfigError(format!(
+            "Failed to remove {}: {err}",
+            path.display()
+        ))),
+    }
+}
+
+async fn exists(path: &Path) -> bool {
+    fs::try_exists(path).await.unwrap_or(false)
+}
+
+#[cfg(all(feature = "keyring", target_os = "linux"))]
+mod secret_service {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+    use super::KEY_SIZE;
+    use crate::error::AppError;
+
+    const SERVICE: &str = "freegin-ai";
+    /// Entry holding the key in use.
+    pub(super) const USER: &str = "master-key";
+    /// Entry holding a new key until a rekey commits.
+    pub(super) const STAGED_USER: &str = "master-key.new";
+
+    fn keyring_error(err: &keyring::Error) -> AppError {
+        AppError::ConfigError(format!("Secret Service error: {err}"))
+    }
+
+    /// Reads the key, or `None` when the keyring holds no such entry.
+    pub(super) async fn read(user: &'static str) -> Result<Option<[u8; KEY_SIZE]>, AppError> {
+        // Keyring calls block on D-Bus, which deadlocks on a runtime thread.
+        let encoded = tokio::task::spawn_blocking(move || {
+            let entry = keyring::Entry::new(SERVICE, user)?;
+            match entry.get_password() {
+                Ok(encoded) => Ok(Some(encoded)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+        .await
+        .map_err(|err| AppError::ConfigError(format!("Secret Service task failed: {err}")))?
+        .map_err(|err| keyring_error(&err))?;
+
+        encoded
+            .map(|encoded| {
+                BASE64
+                    .decode(encoded.trim())
+                    .ok()
+                    .and_then(|bytes| <[u8; KEY_SIZE]>::try_from(bytes.as_slice()).ok())
+                    .ok_or_else(|| {
+                        AppError::ConfigError("The keyring entry does not hold a valid key".into())
+                    })
+            })
+            .transpose()
+    }
+
+    pub(super) async fn write(user: &'static str, key: &[u8; KEY_SIZE]) -> Result<(), AppError> {
+        let encoded = BASE64.encode(key);
+        tokio::task::spawn_blocking(move || {
+            keyring::Entry::new(SERVICE, user)?.set_password(&encoded)
+        })
+        .await
+        .map_err(|err| AppError::ConfigError(format!("Secret Service task failed: {err}")))?
+        .map_err(|err| keyring_error(&err))
+    }
+
+    /// Deletes an entry; a missing entry is not an error.
+    pub(super) async fn delete(user: &'static str) -> Result<(), AppError> {
+        tokio::task::spawn_blocking(move || {
+            match keyring::Entry::new(SERVICE, user)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(err) => Err(err),
+            }
+        })
+        .await
+        .map_err(|err| AppError::ConfigError(format!("Secret Service task failed: {err}")))?
+        .map_err(|err| keyring_error(&err))
+    }
+}
+
+#[cfg(not(all(feature = "keyring", target_os = "linux")))]
+mod secret_service {
+    use super::KEY_SIZE;
+    use crate::error::AppError;
+
+    pub(super) const USER: &str = "master-key";
+    pub(super) const STAGED_USER: &str = "master-key.new";
+
+    fn unsupported() -> AppError {
+        AppError::ConfigError(
+            "This build has no keyring support; rebuild on Linux with '--features keyring'".into(),
+        )
+    }
+
+    #[allow(clippy::unused_async)]
+    pub(super) async fn read(_user: &'static str) -> Result<Option<[u8; KEY_SIZE]>, AppError> {
+        Err(unsupported())
+    }
+
+    #[allow(clippy::unused_async)]
+    pub(super) async fn write(_user: &'static str, _key: &[u8; KEY_SIZE]) -> Result<(), AppError> {
+        Err(unsupported())
+    }
+
+    #[allow(clippy::unused_async)]
+    pub(super) async fn delete(_user: &'static str) -> Result<(), AppError> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passphrase_record_unlocks_only_with_the_same_passphrase() {
+        let dir = std::env::temp_dir();
+        let key = NewMasterKey::from_passphrase(&dir, "correct horse").expect("derive");
+        assert_eq!(key.source(), MasterKeySource::Passphrase);
+
+        let unlocked = unlock(&key.kdf_record, "correct horse").expect("unlock");
+        assert_eq!(&unlocked, key.key());
+        let err = unlock(&key.kdf_record, "battery staple").expect_err("wrong passphrase");
+        assert!(err.to_string().contains("Wrong credential passphrase"));
+        assert!(unlock(&key.kdf_record[..SALT_SIZE], "correct horse").is_err());
+
+        let salt = &key.kdf_record[..SALT_SIZE];
+        assert_eq!(derive_key("correct horse", salt).expect("derive"), unlocked);
+    }
+
+    #[tokio::test]
+    async fn keyring_source_refuses_to_replace_a_passphrase_key() {
+        let dir = std::env::temp_dir().join(format!("freegin-orphan-{}", std::process::id()));
+        NewMasterKey::from_passphrase(&dir, "correct horse")
+            .expect("derive")
+            .save()
+            .await
+            .expect("save");
+
+        let err = load(MasterKeySource::Keyring, &dir)
+            .await
+            .expect_err("keyring over secret.kdf");
+        assert!(err
+            .to_string()
+            .contains("set master_key back to \"passphrase\""));
+        assert!(dir.join(KDF_FILENAME).exists());
+        assert!(!dir.join(format!("{KDF_FILENAME}{STAGED_SUFFIX}")).exists());
+
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
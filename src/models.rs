@@ -1,14 +1,17 @@
 //! Core data structures shared across web handlers and provider connectors.
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
-use crate::providers::Provider;
+use crate::{error::AppError, providers::Provider};
 
 /// Represents an incoming request to the `/generate` endpoint or CLI.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct AIRequest {
     /// The model to use for the generation (e.g., "gpt-4o", "gemini-1.5-pro").
+    /// Empty lets the router pick one from the catalog.
+    #[serde(default)]
     pub model: String,
     /// The user's prompt.
     pub prompt: String,
@@ -24,6 +27,211 @@ pub struct AIRequest {
     /// Routing hints that guide provider selection.
     #[serde(default)]
     pub hints: RequestHints,
+    /// Optional JSON Schema the response must satisfy when the response
+    /// format is `json`.
+    #[serde(default)]
+    pub response_schema: Option<serde_json::Value>,
+    /// Images attached to the prompt for vision-capable models.
+    #[serde(default)]
+    pub images: Vec<ImageInput>,
+    /// Earlier conversation turns, oldest first. `prompt` is the new user turn.
+    #[serde(default)]
+    pub history: Vec<ChatMessage>,
+}
+
+impl AIRequest {
+    /// Returns whether the caller requires a JSON response.
+    #[must_use]
+    pub const fn wants_json(&self) -> bool {
+        matches!(self.hints.response_format, Some(ResponseFormat::Json))
+            || self.response_schema.is_some()
+    }
+}
+
+/// A single turn of a conversation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    /// Who produced the message.
+    pub role: ChatRole,
+    /// Message text.
+    pub content: String,
+}
+
+/// Author of a conversation turn.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    /// Instructions that frame the conversation.
+    System,
+    /// A message from the user.
+    User,
+    /// A reply from the model.
+    Assistant,
+}
+
+impl ChatRole {
+    /// Returns the role name used by OpenAI-compatible APIs.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+        }
+    }
+
+    /// Parses a stored role name.
+    #[must_use]
+    pub fn from_key(value: &str) -> Option<Self> {
+        match value {
+            "system" => Some(Self::System),
+            "user" => Some(Self::User),
+            "assistant" => Some(Self::Assistant),
+            _ => None,
+        }
+    }
+}
+
+/// An image attached to a request.
+///
+/// Serialised as `{"url": "..."}`, `{"base64": "...", "mime_type": "image/png"}`
+/// or, for local CLI use only, `{"path": "..."}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ImageInput {
+    /// Where the image data comes from.
+    #[serde(flatten)]
+    pub source: ImageSource,
+    /// MIME type of the image; inferred from the data or file name when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Location or inline content of an image.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageSource {
+    /// A file on the local filesystem.
+    Path(String),
+    /// A publicly reachable HTTP(S) URL.
+    Url(String),
+    /// Base64-encoded image bytes (without a `data:` prefix).
+    Base64(String),
+}
+
+impl ImageInput {
+    /// Builds an image from a CLI argument, reading local files eagerly.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a local image cannot be read or its format is not recognised.
+    pub fn from_cli_arg(value: &str) -> Result<Self, AppError> {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Ok(Self {
+                source: ImageSource::Url(value.to_string()),
+                mime_type: None,
+            });
+        }
+        Self::from_path(Path::new(value))
+    }
+
+    /// Reads an image file and embeds it as base64.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or its format is not recognised.
+    pub fn from_path(path: &Path) -> Result<Self, AppError> {
+        let bytes = std::fs::read(path).map_err(|err| {
+            AppError::ConfigError(format!("Failed to read image {}: {err}", path.display()))
+        })?;
+        let mime_type = sniff_image_mime(&bytes)
+            .map(str::to_string)
+            .or_else(|| mime_from_extension(path).map(str::to_string))
+            .ok_or_else(|| {
+                AppError::InvalidRequest(format!("Unsupported image format for {}", path.display()))
+            })?;
+        Ok(Self {
+            source: ImageSource::Base64(BASE64.encode(bytes)),
+            mime_type: Some(mime_type),
+        })
+    }
+
+    /// Returns the image with any local path loaded into inline base64.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a local image cannot be read or its format is not recognised.
+    pub fn resolved(&self) -> Result<Self, AppError> {
+        match &self.source {
+            ImageSource::Path(path) => {
+                let mut loaded = Self::from_path(Path::new(path))?;
+                if self.mime_type.is_some() {
+                    loaded.mime_type.clone_from(&self.mime_type);
+                }
+                Ok(loaded)
+            }
+            ImageSource::Url(_) | ImageSource::Base64(_) => Ok(self.clone()),
+        }
+    }
+
+    /// Returns the MIME type, sniffing inline data when it was not supplied.
+    #[must_use]
+    pub fn effective_mime_type(&self) -> String {
+        if let Some(mime) = &self.mime_type {
+            return mime.clone();
+        }
+        let sniffed = match &self.source {
+            ImageSource::Base64(data) => BASE64
+                .decode(data.get(..64.min(data.len() / 4 * 4)).unwrap_or_default())
+                .ok()
+                .and_then(|bytes| sniff_image_mime(&bytes)),
+            ImageSource::Path(path) | ImageSource::Url(path) => {
+                mime_from_extension(Path::new(path))
+            }
+        };
+        sniffed.unwrap_or("image/png").to_string()
+    }
+
+    /// Returns a URL suitable for OpenAI-style `image_url` parts: the remote
+    /// URL itself or a `data:` URL for inline and local images.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a local image cannot be read or its format is not recognised.
+    pub fn to_url(&self) -> Result<String, AppError> {
+        let image = self.resolved()?;
+        match &image.source {
+            ImageSource::Url(url) => Ok(url.clone()),
+            ImageSource::Base64(data) | ImageSource::Path(data) => Ok(format!(
+                "data:{};base64,{data}",
+                image.effective_mime_type()
+            )),
+        }
+    }
+}
+
+fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
 }
 
 /// Represents the response sent back to the client.
@@ -33,6 +241,9 @@ pub struct AIResponse {
     pub content: String,
     /// The provider that ultimately handled the request.
     pub provider: Provider,
+    /// The model that produced the content, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
 }
 
 /// Hint parameters that influence provider/model selection.
@@ -136,6 +347,33 @@ pub enum Workload {
 }
 
 impl Workload {
+    /// Returns the lowercase identifier used in storage and configuration.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Summarization => "summarization",
+            Self::Code => "code",
+            Self::Extraction => "extraction",
+            Self::Creative => "creative",
+            Self::Classification => "classification",
+        }
+    }
+
+    /// Parses the identifier produced by [`Workload::as_str`].
+    #[must_use]
+    pub fn from_key(value: &str) -> Option<Self> {
+        match value {
+            "chat" => Some(Self::Chat),
+            "summarization" => Some(Self::Summarization),
+            "code" => Some(Self::Code),
+            "extraction" => Some(Self::Extraction),
+            "creative" => Some(Self::Creative),
+            "classification" => Some(Self::Classification),
+            _ => None,
+        }
+    }
+
     /// Returns all valid workload variant names.
     pub fn variants() -> &'static [&'static str] {
         &[
@@ -148,3 +386,29 @@ impl Workload {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_image_sources() {
+        let image: ImageInput =
+            serde_json::from_str(r#"{"base64":"aGVsbG8=","mime_type":"image/jpeg"}"#).unwrap();
+        assert_eq!(image.source, ImageSource::Base64("aGVsbG8=".into()));
+        assert_eq!(image.to_url().unwrap(), "data:image/jpeg;base64,aGVsbG8=");
+
+        let image: ImageInput = serde_json::from_str(r#"{"url":"https://x.test/a.png"}"#).unwrap();
+        assert_eq!(image.to_url().unwrap(), "https://x.test/a.png");
+    }
+
+    #[test]
+    fn sniffs_mime_type_from_inline_data() {
+        let png = BASE64.encode(b"\x89PNG\r\n\x1a\n0000");
+        let image = ImageInput {
+            source: ImageSource::Base64(png),
+            mime_type: None,
+        };
+        assert_eq!(image.effective_mime_type(), "image/png");
+    }
+}
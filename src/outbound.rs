@@ -0,0 +1,163 @@
+//! Guards for HTTP requests to URLs supplied by API callers.
+//!
+//! Image URLs and job webhooks come from whoever calls the API, so requests to
+//! them must not reach loopback, private networks or cloud metadata endpoints.
+//! [`PublicTarget::resolve`] resolves the host once and checks every address;
+//! [`PublicTarget::client_builder`] pins the connection to the checked address
+//! and disables redirects, so neither a second lookup nor a redirect can point
+//! the request elsewhere.
+
+use std::net::{IpAddr, SocketAddr};
+
+use reqwest::{redirect, Client, ClientBuilder, Url};
+
+use crate::error::AppError;
+
+/// An http(s) URL whose host resolved only to public addresses.
+#[derive(Debug, Clone)]
+pub struct PublicTarget {
+    url: Url,
+    host: String,
+    address: SocketAddr,
+}
+
+impl PublicTarget {
+    /// Parses `url` and resolves its host, requiring every address to be public.
+    ///
+    /// `label` names the URL in error messages, e.g. `"Image"` or `"Webhook"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an invalid-request error when the URL is not http(s), has no
+    /// host, does not resolve, or resolves to a non-public address.
+    pub async fn resolve(label: &str, url: &str) -> Result<Self, AppError> {
+        let invalid = |reason: String| AppError::InvalidRequest(format!("{label} {url}: {reason}"));
+        let parsed = Url::parse(url).map_err(|err| invalid(err.to_string()))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(invalid("only http and https URLs are allowed".into()));
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| invalid("URL has no host".into()))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = parsed
+            .port_or_known_default()
+            .ok_or_else(|| invalid("URL has no port".into()))?;
+
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|err| invalid(format!("failed to resolve {host}: {err}")))?
+            .collect();
+        let Some(&address) = addresses.first() else {
+            return Err(invalid(format!("{host} did not resolve")));
+        };
+        if let Some(blocked) = addresses.iter().find(|addr| !is_public_address(addr.ip())) {
+            return Err(invalid(format!(
+                "{host} resolves to the non-public address {}",
+                blocked.ip()
+            )));
+        }
+        Ok(Self {
+            url: parsed,
+            host,
+            address,
+        })
+    }
+
+    /// Returns the checked URL.
+    #[must_use]
+    pub const fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns a client builder pinned to the checked address with redirects
+    /// disabled.
+    pub fn client_builder(&self) -> ClientBuilder {
+        Client::builder()
+            .user_agent(format!("freegin-ai/{}", env!("CARGO_PKG_VERSION")))
+            .redirect(redirect::Policy::none())
+            .resolve(&self.host, self.address)
+    }
+}
+
+/// Whether an address is reachable on the public internet, as opposed to
+/// loopback, private, link-local (cloud metadata), shared or unspecified.
+#[must_use]
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or_else(
+            || {
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_multicast()
+                    || v6.is_unique_local()
+                    || v6.is_unicast_link_local())
+            },
+            |v4| is_public_address(IpAddr::V4(v4)),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_allowed() {
+        for blocked in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(
+                !is_public_address(blocked.parse().expect("address")),
+                "{blocked}"
+            );
+        }
+        for public in ["8.8.8.8", "142.250.74.46", "2001:4860:4860::8888"] {
+            assert!(
+                is_public_address(public.parse().expect("address")),
+                "{public}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn private_and_non_http_urls_are_refused() {
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.5:8080/hook",
+            "http://[::1]:8080/hook",
+            "http://localhost/hook",
+            "file:///etc/passwd",
+            "ftp://example.com/hook",
+        ] {
+            let err = PublicTarget::resolve("Webhook", url).await.expect_err(url);
+            assert!(matches!(err, AppError::InvalidRequest(_)), "{url}: {err}");
+        }
+    }
+}
@@ -15,7 +15,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, AIProvider, AvailableModel,
+        Provider,
+    },
 };
 
 /// A client for interacting with the Cerebras AI API.
@@ -58,10 +61,10 @@ impl AIProvider for CerebrasClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![CerebrasMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, request.prompt.clone(), |role, content| {
+                CerebrasMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -99,14 +102,29 @@ impl AIProvider for CerebrasClient {
         Ok(AIResponse {
             content,
             provider: Provider::Cerebras,
+            model: Some(body.model),
         })
     }
+
+    /// Lists the models Cerebras serves.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(
+            &self.http_client,
+            &api_url,
+            &self.api_key,
+            Provider::Cerebras,
+        )
+        .await
+    }
 }
 
 #[derive(Serialize)]
 struct CerebrasRequestBody {
     model: String,
     messages: Vec<CerebrasMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
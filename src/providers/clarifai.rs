@@ -15,7 +15,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{openai_messages, openai_response_format, AIProvider, Provider},
 };
 
 /// A client for interacting with the Clarifai AI API.
@@ -58,10 +58,10 @@ impl AIProvider for ClarifaiClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![ClarifaiMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, request.prompt.clone(), |role, content| {
+                ClarifaiMessage { role, content }
+            }),
+            response_format: openai_response_format(request, false),
         };
 
         let response = self
@@ -99,6 +99,7 @@ impl AIProvider for ClarifaiClient {
         Ok(AIResponse {
             content,
             provider: Provider::Clarifai,
+            model: Some(body.model),
         })
     }
 }
@@ -107,6 +108,8 @@ impl AIProvider for ClarifaiClient {
 struct ClarifaiRequestBody {
     model: String,
     messages: Vec<ClarifaiMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
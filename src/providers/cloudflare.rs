@@ -15,7 +15,9 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        number, openai_messages, openai_response_format, AIProvider, AvailableModel, Provider,
+    },
 };
 
 /// A client for interacting with the Cloudflare Workers AI API.
@@ -58,10 +60,10 @@ impl AIProvider for CloudflareClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![CloudflareMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, request.prompt.clone(), |role, content| {
+                CloudflareMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -99,14 +101,67 @@ impl AIProvider for CloudflareClient {
         Ok(AIResponse {
             content,
             provider: Provider::Cloudflare,
+            model: Some(body.model),
         })
     }
+
+    /// Lists text-generation models through Cloudflare's model search API.
+    ///
+    /// The search endpoint lives next to the OpenAI-compatible `/v1` routes:
+    /// `.../accounts/{ACCOUNT_ID}/ai/models/search`.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        const PER_PAGE: usize = 100;
+        let search_url = format!(
+            "{}/models/search",
+            self.base_url.trim_end_matches('/').trim_end_matches("/v1")
+        );
+
+        let mut models = Vec::new();
+        for page in 1.. {
+            let response = self
+                .http_client
+                .get(&search_url)
+                .bearer_auth(&self.api_key)
+                .query(&[
+                    ("task", "Text Generation".to_string()),
+                    ("per_page", PER_PAGE.to_string()),
+                    ("page", page.to_string()),
+                ])
+                .send()
+                .await
+                .map_err(|e| AppError::NetworkError(e.to_string()))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(AppError::ApiError(format!(
+                    "Cloudflare Workers AI model search failed with status {status}: {error_text}"
+                )));
+            }
+
+            let body = response
+                .json::<CloudflareModelSearch>()
+                .await
+                .map_err(|e| AppError::ApiError(e.to_string()))?;
+            let count = body.result.len();
+            models.extend(body.result.into_iter().map(CloudflareModel::into_available));
+            if count < PER_PAGE {
+                break;
+            }
+        }
+        Ok(models)
+    }
 }
 
 #[derive(Serialize)]
 struct CloudflareRequestBody {
     model: String,
     messages: Vec<CloudflareMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -129,3 +184,59 @@ struct CloudflareChoice {
 struct CloudflareMessageContent {
     content: Option<String>,
 }
+
+#[derive(Deserialize)]
+struct CloudflareModelSearch {
+    #[serde(default)]
+    result: Vec<CloudflareModel>,
+}
+
+#[derive(Deserialize)]
+struct CloudflareModel {
+    name: String,
+    #[serde(default)]
+    properties: Vec<CloudflareModelProperty>,
+}
+
+#[derive(Deserialize)]
+struct CloudflareModelProperty {
+    property_id: String,
+    value: serde_json::Value,
+}
+
+impl CloudflareModel {
+    /// Reads the context window and per-million-token prices from the
+    /// model's properties.
+    fn into_available(self) -> AvailableModel {
+        let property = |id: &str| {
+            self.properties
+                .iter()
+                .find(|property| property.property_id == id)
+                .map(|property| &property.value)
+        };
+        let price = |direction: &str| {
+            property("price")
+                .and_then(serde_json::Value::as_array)
+                .and_then(|prices| {
+                    prices.iter().find(|price| {
+                        price
+                            .get("unit")
+                            .and_then(serde_json::Value::as_str)
+                            .is_some_and(|unit| unit.contains(direction))
+                    })
+                })
+                .and_then(|price| price.get("price"))
+                .and_then(number)
+        };
+        AvailableModel {
+            context_window: property("context_window").and_then(|value| {
+                value
+                    .as_u64()
+                    .or_else(|| value.as_str().and_then(|raw| raw.trim().parse().ok()))
+            }),
+            input_price_per_mtok: price("input"),
+            output_price_per_mtok: price("output"),
+            id: self.name,
+        }
+    }
+}
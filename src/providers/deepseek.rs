@@ -11,7 +11,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, AIProvider, AvailableModel,
+        Provider,
+    },
 };
 
 /// A client for interacting with the DeepSeek API.
@@ -50,10 +53,10 @@ impl AIProvider for DeepSeekClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![DeepSeekMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, request.prompt.clone(), |role, content| {
+                DeepSeekMessage { role, content }
+            }),
+            response_format: openai_response_format(request, false),
         };
 
         let response = self
@@ -91,14 +94,29 @@ impl AIProvider for DeepSeekClient {
         Ok(AIResponse {
             content,
             provider: Provider::DeepSeek,
+            model: Some(body.model),
         })
     }
+
+    /// Lists the models `DeepSeek` serves.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(
+            &self.http_client,
+            &api_url,
+            &self.api_key,
+            Provider::DeepSeek,
+        )
+        .await
+    }
 }
 
 #[derive(Serialize)]
 struct DeepSeekRequestBody {
     model: String,
     messages: Vec<DeepSeekMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -120,4 +138,4 @@ struct DeepSeekChoice {
 #[derive(Deserialize)]
 struct DeepSeekMessageContent {
     content: Option<String>,
-}
\ No newline at end of file
+}
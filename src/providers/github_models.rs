@@ -15,7 +15,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, openai_user_content,
+        AIProvider, AvailableModel, Provider,
+    },
 };
 
 /// A client for interacting with the GitHub Models API.
@@ -34,9 +37,7 @@ impl GitHubModelsClient {
     /// * `base_url` - Base URL, e.g., https://models.inference.ai.azure.com
     pub fn new(github_token: String, base_url: String) -> Result<Self, AppError> {
         if github_token.trim().is_empty() {
-            return Err(AppError::ConfigError(
-                "GitHub token cannot be empty".into(),
-            ));
+            return Err(AppError::ConfigError("GitHub token cannot be empty".into()));
         }
         Ok(Self {
             github_token,
@@ -48,6 +49,10 @@ impl GitHubModelsClient {
 
 #[async_trait]
 impl AIProvider for GitHubModelsClient {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     /// Sends a generation request to the GitHub Models API.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!("{}/chat/completions", self.base_url);
@@ -58,10 +63,10 @@ impl AIProvider for GitHubModelsClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![GitHubModelsMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, openai_user_content(request)?, |role, content| {
+                GitHubModelsMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -99,20 +104,35 @@ impl AIProvider for GitHubModelsClient {
         Ok(AIResponse {
             content,
             provider: Provider::GitHubModels,
+            model: Some(body.model),
         })
     }
+
+    /// Lists the models GitHub Models serves.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(
+            &self.http_client,
+            &api_url,
+            &self.github_token,
+            Provider::GitHubModels,
+        )
+        .await
+    }
 }
 
 #[derive(Serialize)]
 struct GitHubModelsRequestBody {
     model: String,
     messages: Vec<GitHubModelsMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
 struct GitHubModelsMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
 }
 
 #[derive(Deserialize)]
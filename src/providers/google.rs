@@ -1,15 +1,20 @@
 //! Google Gemini provider connector implementing the `AIProvider` trait.
 
 use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     error::AppError,
-    models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    models::{AIRequest, AIResponse, ChatRole, ImageInput, ImageSource},
+    outbound::PublicTarget,
+    providers::{AIProvider, AvailableModel, Provider},
 };
 
+/// Largest image downloaded for inlining; Gemini caps a whole request at 20 MB.
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
 /// A client for interacting with the Google Gemini API.
 #[derive(Debug, Clone)]
 pub struct GoogleClient {
@@ -32,10 +37,87 @@ impl GoogleClient {
             http_client,
         })
     }
+
+    /// Converts an image into an `inlineData` part.
+    ///
+    /// Gemini only accepts inline bytes for arbitrary URLs, so remote images
+    /// are downloaded first (see [`fetch_image`]).
+    async fn inline_image(&self, image: &ImageInput) -> Result<GooglePart, AppError> {
+        let image = image.resolved()?;
+        let (data, mime_type) = match &image.source {
+            ImageSource::Url(url) => {
+                let (bytes, header_mime) = fetch_image(url).await?;
+                let mime_type = image
+                    .mime_type
+                    .clone()
+                    .or(header_mime)
+                    .unwrap_or_else(|| image.effective_mime_type());
+                (BASE64.encode(bytes), mime_type)
+            }
+            ImageSource::Base64(data) | ImageSource::Path(data) => {
+                (data.clone(), image.effective_mime_type())
+            }
+        };
+        Ok(GooglePart {
+            text: None,
+            inline_data: Some(GoogleInlineData { mime_type, data }),
+        })
+    }
+}
+
+/// Downloads an image URL taken from a request.
+///
+/// The URL comes from the caller, so it goes through [`PublicTarget`]: only
+/// public http(s) hosts are fetched, over a connection pinned to the checked
+/// address without following redirects. The body is capped at
+/// [`MAX_IMAGE_BYTES`]. Returns the bytes and the `image/*` content type,
+/// if any.
+async fn fetch_image(url: &str) -> Result<(Vec<u8>, Option<String>), AppError> {
+    let fetch_error = |reason: String| AppError::InvalidRequest(format!("Image {url}: {reason}"));
+    let target = PublicTarget::resolve("Image", url).await?;
+    let client = target
+        .client_builder()
+        .build()
+        .map_err(|err| AppError::ConfigError(format!("Failed to build HTTP client: {err}")))?;
+    let mut response = client
+        .get(target.url().clone())
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| AppError::NetworkError(format!("Failed to fetch image {url}: {e}")))?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_IMAGE_BYTES as u64)
+    {
+        return Err(fetch_error(format!("larger than {MAX_IMAGE_BYTES} bytes")));
+    }
+    let header_mime = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| value.starts_with("image/"))
+        .map(str::to_string);
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::NetworkError(format!("Failed to fetch image {url}: {e}")))?
+    {
+        if bytes.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err(fetch_error(format!("larger than {MAX_IMAGE_BYTES} bytes")));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok((bytes, header_mime))
 }
 
 #[async_trait]
 impl AIProvider for GoogleClient {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     /// Sends a generation request to the Google Gemini API.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!(
@@ -43,12 +125,42 @@ impl AIProvider for GoogleClient {
             self.base_url, request.model, self.api_key
         );
 
+        // Gemini uses "model" for assistant turns and a separate system instruction.
+        let mut system_parts = Vec::new();
+        let mut contents = Vec::new();
+        for turn in &request.history {
+            let role = match turn.role {
+                ChatRole::System => {
+                    system_parts.push(GooglePart::text(turn.content.clone()));
+                    continue;
+                }
+                ChatRole::User => "user",
+                ChatRole::Assistant => "model",
+            };
+            contents.push(GoogleContent {
+                role: Some(role.to_string()),
+                parts: vec![GooglePart::text(turn.content.clone())],
+            });
+        }
+
+        let mut parts = vec![GooglePart::text(request.prompt.clone())];
+        for image in &request.images {
+            parts.push(self.inline_image(image).await?);
+        }
+        contents.push(GoogleContent {
+            role: Some("user".to_string()),
+            parts,
+        });
+
         let body = GoogleRequestBody {
-            contents: vec![GoogleContent {
-                parts: vec![GooglePart {
-                    text: request.prompt.clone(),
-                }],
-            }],
+            contents,
+            system_instruction: (!system_parts.is_empty()).then_some(GoogleContent {
+                role: None,
+                parts: system_parts,
+            }),
+            generation_config: request.wants_json().then(|| GoogleGenerationConfig {
+                response_mime_type: "application/json".to_string(),
+            }),
         };
 
         let http_response = self
@@ -85,23 +197,121 @@ impl AIProvider for GoogleClient {
         Ok(AIResponse {
             content,
             provider: Provider::Google,
+            model: Some(request.model.clone()),
         })
     }
+
+    /// Lists the Gemini models that support `generateContent` (`models.list`).
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("key", self.api_key.clone()),
+                ("pageSize", "1000".to_string()),
+            ];
+            if let Some(token) = page_token.take() {
+                query.push(("pageToken", token));
+            }
+            let http_response = self
+                .http_client
+                .get(&api_url)
+                .query(&query)
+                .send()
+                .await
+                .map_err(|e| AppError::NetworkError(e.to_string()))?;
+
+            if !http_response.status().is_success() {
+                let status = http_response.status();
+                let error_text = http_response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "<failed to read error body>".into());
+                return Err(AppError::ApiError(format!(
+                    "Google Gemini model listing failed with status {status}: {error_text}"
+                )));
+            }
+
+            let page = http_response
+                .json::<GoogleModelList>()
+                .await
+                .map_err(|e| AppError::ApiError(e.to_string()))?;
+            models.extend(
+                page.models
+                    .into_iter()
+                    .filter(|model| {
+                        model
+                            .supported_generation_methods
+                            .iter()
+                            .any(|method| method == "generateContent")
+                    })
+                    .map(|model| AvailableModel {
+                        id: model
+                            .name
+                            .strip_prefix("models/")
+                            .unwrap_or(&model.name)
+                            .to_string(),
+                        context_window: model.input_token_limit,
+                        input_price_per_mtok: None,
+                        output_price_per_mtok: None,
+                    }),
+            );
+            match page.next_page_token.filter(|token| !token.is_empty()) {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(models)
+    }
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GoogleRequestBody {
     contents: Vec<GoogleContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GoogleContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GoogleGenerationConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleGenerationConfig {
+    response_mime_type: String,
 }
 
 #[derive(Serialize)]
 struct GoogleContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
     parts: Vec<GooglePart>,
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GooglePart {
-    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<GoogleInlineData>,
+}
+
+impl GooglePart {
+    const fn text(text: String) -> Self {
+        Self {
+            text: Some(text),
+            inline_data: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleInlineData {
+    mime_type: String,
+    data: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -124,3 +334,40 @@ struct GoogleContentResponse {
 struct GooglePartResponse {
     text: String,
 }
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleModelList {
+    #[serde(default)]
+    models: Vec<GoogleModel>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleModel {
+    name: String,
+    input_token_limit: Option<u64>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn private_and_non_http_image_urls_are_refused() {
+        for url in [
+            "http://127.0.0.1/cat.png",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]:8080/cat.png",
+            "http://localhost/cat.png",
+            "file:///etc/passwd",
+            "ftp://example.com/cat.png",
+        ] {
+            let err = fetch_image(url).await.expect_err(url);
+            assert!(matches!(err, AppError::InvalidRequest(_)), "{url}: {err}");
+        }
+    }
+}
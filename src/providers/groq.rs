@@ -11,7 +11,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, AIProvider, AvailableModel,
+        Provider,
+    },
 };
 
 /// A client for interacting with the Groq API.
@@ -48,10 +51,10 @@ impl AIProvider for GroqClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![GroqMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, request.prompt.clone(), |role, content| {
+                GroqMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -89,14 +92,23 @@ impl AIProvider for GroqClient {
         Ok(AIResponse {
             content,
             provider: Provider::Groq,
+            model: Some(body.model),
         })
     }
+
+    /// Lists the models Groq serves, with their context windows.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(&self.http_client, &api_url, &self.api_key, Provider::Groq).await
+    }
 }
 
 #[derive(Serialize)]
 struct GroqRequestBody {
     model: String,
     messages: Vec<GroqMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -118,4 +130,4 @@ struct GroqChoice {
 #[derive(Deserialize)]
 struct GroqMessageContent {
     content: Option<String>,
-}
\ No newline at end of file
+}
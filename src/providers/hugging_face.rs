@@ -1,12 +1,14 @@
 //! Hugging Face provider connector implementing the `AIProvider` trait.
 
+use std::fmt::Write as _;
+
 use async_trait::async_trait;
 use reqwest::{header, Client};
 use serde::Serialize;
 
 use crate::{
     error::AppError,
-    models::{AIRequest, AIResponse},
+    models::{AIRequest, AIResponse, ChatRole},
     providers::{AIProvider, Provider},
 };
 
@@ -50,7 +52,7 @@ impl AIProvider for HuggingFaceClient {
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!("{}/models/{}", self.base_url, request.model);
         let payload = HuggingFaceRequest {
-            inputs: request.prompt.clone(),
+            inputs: transcript(request),
             parameters: Some(HuggingFaceParameters {
                 return_full_text: Some(false),
             }),
@@ -85,6 +87,7 @@ impl AIProvider for HuggingFaceClient {
         Ok(AIResponse {
             content,
             provider: Provider::HuggingFace,
+            model: Some(request.model.clone()),
         })
     }
 }
@@ -102,6 +105,25 @@ struct HuggingFaceParameters {
     return_full_text: Option<bool>,
 }
 
+/// Flattens the conversation into a plain-text transcript for text-generation
+/// models. Single-turn requests are sent as the bare prompt.
+fn transcript(request: &AIRequest) -> String {
+    if request.history.is_empty() {
+        return request.prompt.clone();
+    }
+    let mut text = String::new();
+    for turn in &request.history {
+        let speaker = match turn.role {
+            ChatRole::System => "System",
+            ChatRole::User => "User",
+            ChatRole::Assistant => "Assistant",
+        };
+        let _ = writeln!(text, "{speaker}: {}", turn.content);
+    }
+    let _ = write!(text, "User: {}\nAssistant:", request.prompt);
+    text
+}
+
 fn extract_generated_text(value: serde_json::Value) -> Option<String> {
     match value {
         serde_json::Value::Array(items) => {
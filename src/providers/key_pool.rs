@@ -0,0 +1,139 @@
+//! Spreads calls to one provider over several stored API keys.
+//!
+//! Each key gets its own client. A call goes to the best usable key under
+//! the configured [`KeySelection`]; when the provider rejects that key with
+//! a rate-limit, quota or authentication error, the key is put on hold and
+//! the call moves on to the next key. Errors that affect the provider as a
+//! whole are returned straight away so the router can fall back.
+
+use std::{fmt, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::{debug, warn};
+
+use crate::{
+    config::KeySelection,
+    credentials::CredentialStore,
+    error::AppError,
+    health,
+    models::{AIRequest, AIResponse},
+};
+
+use super::{AIProvider, AvailableModel, Provider};
+
+/// A provider client backed by several named keys.
+pub struct KeyPool {
+    provider: Provider,
+    store: CredentialStore,
+    selection: KeySelection,
+    clients: Vec<(String, Arc<dyn AIProvider + Send + Sync>)>,
+}
+
+impl fmt::Debug for KeyPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<&str> = self.clients.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("KeyPool")
+            .field("provider", &self.provider)
+            .field("selection", &self.selection)
+            .field("keys", &keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyPool {
+    /// Creates a pool over one client per named key.
+    #[must_use]
+    pub fn new(
+        provider: Provider,
+        store: CredentialStore,
+        selection: KeySelection,
+        clients: Vec<(String, Arc<dyn AIProvider + Send + Sync>)>,
+    ) -> Self {
+        Self {
+            provider,
+            store,
+            selection,
+            clients,
+        }
+    }
+
+    fn client(&self, name: &str) -> Option<&Arc<dyn AIProvider + Send + Sync>> {
+        self.clients
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, client)| client)
+    }
+
+    /// Names of the keys to try, best first. Falls back to every key when
+    /// the store cannot be read.
+    async fn order(&self) -> Vec<String> {
+        match self.store.key_order(self.provider, self.selection).await {
+            Ok(order) => order,
+            Err(err) => {
+                warn!(provider = %self.provider, error = %err, "Failed to read key usage; trying keys in name order");
+                self.clients.iter().map(|(name, _)| name.clone()).collect()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for KeyPool {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        let mut last_error = None;
+        for name in self.order().await {
+            let Some(client) = self.client(&name) else {
+                continue;
+            };
+            if let Err(err) = self.store.record_key_use(self.provider, &name).await {
+                warn!(provider = %self.provider, key = %name, error = %err, "Failed to record key usage");
+            }
+            match client.generate(request).await {
+                Ok(response) => {
+                    if let Err(err) = self.store.record_key_success(self.provider, &name).await {
+                        warn!(provider = %self.provider, key = %name, error = %err, "Failed to record key success");
+                    }
+                    return Ok(response);
+                }
+                Err(err) => {
+                    let message = err.to_string();
+                    let Some(retry_after) = health::key_retry_after(&message, Utc::now()) else {
+                        return Err(err);
+                    };
+                    if let Err(store_err) = self
+                        .store
+                        .record_key_failure(self.provider, &name, &message, retry_after)
+                        .await
+                    {
+                        warn!(provider = %self.provider, key = %name, error = %store_err, "Failed to record key failure");
+                    }
+                    debug!(provider = %self.provider, key = %name, error = %message, "Key rejected; trying the next key");
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            AppError::ApiError(format!(
+                "Every {} API key is disabled, at its daily limit or held back after a rate limit",
+                self.provider
+            ))
+        }))
+    }
+
+    fn supports_images(&self) -> bool {
+        self.clients
+            .first()
+            .is_some_and(|(_, client)| client.supports_images())
+    }
+
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let order = self.order().await;
+        let client = order
+            .first()
+            .and_then(|name| self.client(name))
+            .or_else(|| self.clients.first().map(|(_, client)| client))
+            .ok_or_else(|| AppError::ConfigError(format!("No {} API key stored", self.provider)))?;
+        client.list_models().await
+    }
+}
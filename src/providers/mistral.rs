@@ -15,7 +15,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, openai_user_content,
+        AIProvider, AvailableModel, Provider,
+    },
 };
 
 /// A client for interacting with the Mistral AI API.
@@ -48,6 +51,10 @@ impl MistralClient {
 
 #[async_trait]
 impl AIProvider for MistralClient {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     /// Sends a generation request to the Mistral AI API.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!("{}/chat/completions", self.base_url);
@@ -58,10 +65,10 @@ impl AIProvider for MistralClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![MistralMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, openai_user_content(request)?, |role, content| {
+                MistralMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -99,20 +106,35 @@ impl AIProvider for MistralClient {
         Ok(AIResponse {
             content,
             provider: Provider::Mistral,
+            model: Some(body.model),
         })
     }
+
+    /// Lists the models Mistral serves, with their context lengths.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(
+            &self.http_client,
+            &api_url,
+            &self.api_key,
+            Provider::Mistral,
+        )
+        .await
+    }
 }
 
 #[derive(Serialize)]
 struct MistralRequestBody {
     model: String,
     messages: Vec<MistralMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
 struct MistralMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
 }
 
 #[derive(Deserialize)]
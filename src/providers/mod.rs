@@ -3,6 +3,7 @@
 use std::fmt;
 
 use async_trait::async_trait;
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -48,6 +49,39 @@ pub enum Provider {
 pub trait AIProvider: Send + Sync {
     /// Sends a request to the provider's API to generate content.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError>;
+
+    /// Returns whether the connector can send image parts to the provider.
+    fn supports_images(&self) -> bool {
+        false
+    }
+
+    /// Lists the models the provider currently serves, from its own API.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        Err(AppError::ConfigError(
+            "Provider does not publish a model list".into(),
+        ))
+    }
+}
+
+/// A model advertised by a provider's model-listing API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvailableModel {
+    /// Model identifier as accepted in requests.
+    pub id: String,
+    /// Advertised context length in tokens.
+    pub context_window: Option<u64>,
+    /// Advertised prompt price in USD per million tokens.
+    pub input_price_per_mtok: Option<f64>,
+    /// Advertised completion price in USD per million tokens.
+    pub output_price_per_mtok: Option<f64>,
+}
+
+impl AvailableModel {
+    /// Returns whether both advertised prices are zero.
+    #[must_use]
+    pub fn is_free(&self) -> bool {
+        self.input_price_per_mtok == Some(0.0) && self.output_price_per_mtok == Some(0.0)
+    }
 }
 
 impl Provider {
@@ -91,6 +125,170 @@ impl Provider {
             _ => None,
         }
     }
+
+    /// Returns whether the provider bills every request rather than
+    /// offering a free tier, so models without recorded prices cost money.
+    #[must_use]
+    pub const fn is_pay_as_you_go(&self) -> bool {
+        matches!(
+            self,
+            Self::DeepSeek | Self::Together | Self::OpenAI | Self::Anthropic
+        )
+    }
+}
+
+/// Builds the OpenAI-compatible `response_format` field for a request.
+///
+/// Returns `None` unless the request asks for JSON. Providers that only
+/// implement the basic JSON object mode pass `supports_schema = false`; the
+/// schema is then enforced by the router's post-validation instead.
+pub(crate) fn openai_response_format(
+    request: &AIRequest,
+    supports_schema: bool,
+) -> Option<serde_json::Value> {
+    if !request.wants_json() {
+        return None;
+    }
+    match request.response_schema.as_ref() {
+        Some(schema) if supports_schema => Some(serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "response",
+                "schema": schema,
+            },
+        })),
+        _ => Some(serde_json::json!({ "type": "json_object" })),
+    }
+}
+
+/// Builds the OpenAI-compatible `messages` array: earlier turns from
+/// `request.history` followed by `current` as the final user turn.
+pub(crate) fn openai_messages<M, C: From<String>>(
+    request: &AIRequest,
+    current: C,
+    message: impl Fn(String, C) -> M,
+) -> Vec<M> {
+    let mut messages: Vec<M> = request
+        .history
+        .iter()
+        .map(|turn| {
+            message(
+                turn.role.as_str().to_string(),
+                C::from(turn.content.clone()),
+            )
+        })
+        .collect();
+    messages.push(message("user".to_string(), current));
+    messages
+}
+
+/// Builds the OpenAI-compatible `content` of the user message.
+///
+/// Plain prompts stay a string; requests with images become an array of
+/// `text` and `image_url` parts, with local and inline images sent as
+/// `data:` URLs.
+pub(crate) fn openai_user_content(request: &AIRequest) -> Result<serde_json::Value, AppError> {
+    if request.images.is_empty() {
+        return Ok(serde_json::Value::String(request.prompt.clone()));
+    }
+    let mut parts = vec![serde_json::json!({ "type": "text", "text": request.prompt })];
+    for image in &request.images {
+        parts.push(serde_json::json!({
+            "type": "image_url",
+            "image_url": { "url": image.to_url()? },
+        }));
+    }
+    Ok(serde_json::Value::Array(parts))
+}
+
+/// Fetches an OpenAI-compatible `GET /models` listing.
+pub(crate) async fn fetch_openai_models(
+    http_client: &Client,
+    url: &str,
+    token: &str,
+    provider: Provider,
+) -> Result<Vec<AvailableModel>, AppError> {
+    let response = http_client
+        .get(url)
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| AppError::NetworkError(e.to_string()))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(AppError::ApiError(format!(
+            "{provider} model listing failed with status {status}: {error_text}"
+        )));
+    }
+
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| AppError::ApiError(e.to_string()))?;
+    Ok(parse_openai_models(&body))
+}
+
+/// Parses an OpenAI-style model listing.
+///
+/// Accepts `{"data": [...]}` or a bare array. Context lengths and prices use
+/// whichever field the provider advertises: `context_window` (Groq),
+/// `context_length` (`OpenRouter`, Together), `max_context_length` (Mistral);
+/// `OpenRouter` prices are USD per token, Together prices USD per million.
+pub(crate) fn parse_openai_models(body: &serde_json::Value) -> Vec<AvailableModel> {
+    let entries = body
+        .get("data")
+        .unwrap_or(body)
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let id = entry
+                .get("id")
+                .or_else(|| entry.get("name"))
+                .and_then(serde_json::Value::as_str)?;
+            let context_window = ["context_window", "context_length", "max_context_length"]
+                .iter()
+                .find_map(|key| entry.get(*key).and_then(serde_json::Value::as_u64))
+                .or_else(|| {
+                    entry
+                        .pointer("/top_provider/context_length")
+                        .and_then(serde_json::Value::as_u64)
+                });
+            let pricing = entry.get("pricing");
+            let per_token = |key: &str| {
+                pricing
+                    .and_then(|pricing| pricing.get(key))
+                    .and_then(number)
+                    .map(|price| price * 1_000_000.0)
+            };
+            let per_million = |key: &str| {
+                pricing
+                    .and_then(|pricing| pricing.get(key))
+                    .and_then(number)
+            };
+            Some(AvailableModel {
+                id: id.to_string(),
+                context_window,
+                input_price_per_mtok: per_token("prompt").or_else(|| per_million("input")),
+                output_price_per_mtok: per_token("completion").or_else(|| per_million("output")),
+            })
+        })
+        .collect()
+}
+
+/// Reads a JSON number, or a number encoded as a string.
+pub(crate) fn number(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|raw| raw.trim().parse().ok()))
 }
 
 impl fmt::Display for Provider {
@@ -107,6 +305,7 @@ pub mod github_models;
 pub mod google;
 pub mod groq;
 pub mod hugging_face;
+pub mod key_pool;
 pub mod mistral;
 pub mod openai;
 pub mod openrouter;
@@ -114,3 +313,46 @@ pub mod router;
 pub mod together;
 
 pub use router::ProviderRouter;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_openai_style_model_listings() {
+        let groq = serde_json::json!({
+            "object": "list",
+            "data": [{ "id": "llama-3.3-70b-versatile", "context_window": 131_072 }],
+        });
+        assert_eq!(
+            parse_openai_models(&groq),
+            vec![AvailableModel {
+                id: "llama-3.3-70b-versatile".into(),
+                context_window: Some(131_072),
+                input_price_per_mtok: None,
+                output_price_per_mtok: None,
+            }]
+        );
+
+        let openrouter = serde_json::json!({ "data": [{
+            "id": "meta-llama/llama-3.3-70b-instruct:free",
+            "context_length": 65536,
+            "pricing": { "prompt": "0", "completion": "0" },
+        }, {
+            "id": "openai/gpt-4o",
+            "pricing": { "prompt": "0.0000025", "completion": "0.00001" },
+        }]});
+        let models = parse_openai_models(&openrouter);
+        assert!(models[0].is_free());
+        assert_eq!(models[0].context_window, Some(65_536));
+        assert!((models[1].input_price_per_mtok.unwrap() - 2.5).abs() < 1e-9);
+
+        let together = serde_json::json!([{
+            "id": "mistralai/Mixtral-8x7B-Instruct-v0.1",
+            "context_length": 32768,
+            "pricing": { "input": 0.6, "output": 0.6 },
+        }]);
+        let models = parse_openai_models(&together);
+        assert_eq!(models[0].output_price_per_mtok, Some(0.6));
+    }
+}
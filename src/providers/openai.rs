@@ -7,7 +7,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, openai_user_content,
+        AIProvider, AvailableModel, Provider,
+    },
 };
 
 /// A client for interacting with the OpenAI API.
@@ -31,16 +34,20 @@ impl OpenAIClient {
 
 #[async_trait]
 impl AIProvider for OpenAIClient {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     /// Sends a generation request to the OpenAI API.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!("{}/chat/completions", self.base_url);
 
         let body = OpenAIRequestBody {
             model: request.model.clone(),
-            messages: vec![OpenAIMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, openai_user_content(request)?, |role, content| {
+                OpenAIMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -64,20 +71,29 @@ impl AIProvider for OpenAIClient {
         Ok(AIResponse {
             content,
             provider: Provider::OpenAI,
+            model: Some(request.model.clone()),
         })
     }
+
+    /// Lists the models `OpenAI` serves.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(&self.http_client, &api_url, &self.api_key, Provider::OpenAI).await
+    }
 }
 
 #[derive(Serialize)]
 struct OpenAIRequestBody {
     model: String,
     messages: Vec<OpenAIMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
 struct OpenAIMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
 }
 
 #[derive(Deserialize)]
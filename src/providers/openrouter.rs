@@ -15,7 +15,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, openai_user_content,
+        AIProvider, AvailableModel, Provider,
+    },
 };
 
 /// A client for interacting with the OpenRouter API.
@@ -48,6 +51,10 @@ impl OpenRouterClient {
 
 #[async_trait]
 impl AIProvider for OpenRouterClient {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     /// Sends a generation request to the OpenRouter API.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!("{}/chat/completions", self.base_url);
@@ -58,10 +65,10 @@ impl AIProvider for OpenRouterClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![OpenRouterMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, openai_user_content(request)?, |role, content| {
+                OpenRouterMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -99,20 +106,39 @@ impl AIProvider for OpenRouterClient {
         Ok(AIResponse {
             content,
             provider: Provider::OpenRouter,
+            model: Some(body.model),
         })
     }
+
+    /// Lists `OpenRouter`'s free models (`:free` variants or zero pricing).
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        let models = fetch_openai_models(
+            &self.http_client,
+            &api_url,
+            &self.api_key,
+            Provider::OpenRouter,
+        )
+        .await?;
+        Ok(models
+            .into_iter()
+            .filter(|model| model.id.ends_with(":free") || model.is_free())
+            .collect())
+    }
 }
 
 #[derive(Serialize)]
 struct OpenRouterRequestBody {
     model: String,
     messages: Vec<OpenRouterMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
 struct OpenRouterMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
 }
 
 #[derive(Deserialize)]
@@ -1,29 +1,44 @@
 //! Provider routing utilities and fallback logic.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     fmt,
     sync::Arc,
     time::Instant,
 };
 
+use chrono::Utc;
 use tracing::{debug, warn};
 
 use crate::{
-    catalog::{CatalogStore, ModelEntry},
-    config::AppConfig,
+    aliases::{self, AliasTarget},
+    audit::AuditLog,
+    budget::{self, Budget, CallCost},
+    capabilities::{ModelCapabilities, ModelTier, Requirements, Unmet},
+    catalog::{CatalogStore, ModelEntry, SuggestionEntry},
+    classifier::{self, WorkloadClassifier},
+    config::{AppConfig, KeySelection},
+    context,
     credentials::CredentialStore,
+    deprecation,
     error::AppError,
+    guardrail::{self, ModerationPipeline, ModerationStage},
     health::HealthTracker,
-    models::{AIRequest, AIResponse, RequestComplexity, RequestQuality, RequestSpeed},
+    models::{
+        AIRequest, AIResponse, RequestComplexity, RequestGuardrail, RequestHints, RequestQuality,
+        RequestSpeed, Workload,
+    },
+    redaction::{RedactionMap, Redactor},
+    shadow::{self, ShadowLog, ShadowRecord},
+    structured,
     usage::UsageLogger,
 };
 
 use super::{
     cerebras::CerebrasClient, clarifai::ClarifaiClient, cloudflare::CloudflareClient,
     deepseek::DeepSeekClient, github_models::GitHubModelsClient, google::GoogleClient,
-    groq::GroqClient, hugging_face::HuggingFaceClient, mistral::MistralClient,
-    openrouter::OpenRouterClient, together::TogetherClient, AIProvider, Provider,
+    groq::GroqClient, hugging_face::HuggingFaceClient, key_pool::KeyPool, mistral::MistralClient,
+    openrouter::OpenRouterClient, together::TogetherClient, AIProvider, AvailableModel, Provider,
 };
 
 /// Coordinates AI providers and encapsulates routing logic.
@@ -33,6 +48,15 @@ pub struct ProviderRouter {
     usage_logger: Option<UsageLogger>,
     catalog: Option<CatalogStore>,
     health_tracker: Option<HealthTracker>,
+    guardrails: ModerationPipeline,
+    redactor: Option<Redactor>,
+    audit: Option<AuditLog>,
+    shadow: Option<ShadowLog>,
+    shadow_share: f64,
+    aliases: HashMap<String, Vec<AliasTarget>>,
+    auto_retire_after: u32,
+    budget: Option<Budget>,
+    classifier: Option<WorkloadClassifier>,
 }
 
 impl fmt::Debug for ProviderRouter {
@@ -48,6 +72,15 @@ impl fmt::Debug for ProviderRouter {
             .field("usage_logger", &self.usage_logger.is_some())
             .field("catalog", &self.catalog.is_some())
             .field("health_tracker", &self.health_tracker.is_some())
+            .field("guardrails", &self.guardrails)
+            .field("redactor", &self.redactor.is_some())
+            .field("audit", &self.audit.is_some())
+            .field("shadow", &self.shadow.is_some())
+            .field("shadow_share", &self.shadow_share)
+            .field("aliases", &self.aliases.keys().collect::<Vec<_>>())
+            .field("auto_retire_after", &self.auto_retire_after)
+            .field("budget", &self.budget.is_some())
+            .field("classifier", &self.classifier)
             .finish()
     }
 }
@@ -62,6 +95,7 @@ impl ProviderRouter {
     ) -> Result<Self, AppError> {
         let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
         let mut fallback_order: Vec<Provider> = Vec::new();
+        let selection = config.credentials.selection;
 
         // Hugging Face
         let hf_cfg = config.providers.hugging_face.as_ref();
@@ -73,20 +107,23 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let hf_token = match hf_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::HuggingFace).await?,
-        };
+        let base_url = store
+            .resolve_base_url(
+                Provider::HuggingFace,
+                hf_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let hf_client = Self::connect(
+            store,
+            Provider::HuggingFace,
+            hf_token_cfg,
+            selection,
+            |token| HuggingFaceClient::new(token, base_url.clone()),
+        )
+        .await?;
 
-        if let Some(token) = hf_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::HuggingFace,
-                    hf_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = HuggingFaceClient::new(token, base_url)?;
-            drop(providers.insert(Provider::HuggingFace, Arc::new(client)));
+        if let Some(client) = hf_client {
+            drop(providers.insert(Provider::HuggingFace, client));
             fallback_order.push(Provider::HuggingFace);
         } else {
             debug!(
@@ -120,16 +157,19 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let groq_token = match groq_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::Groq).await?,
-        };
-        if let Some(token) = groq_token {
-            let base_url = store
-                .resolve_base_url(Provider::Groq, groq_cfg.map(|cfg| cfg.api_base_url.as_str()))
-                .to_string();
-            let client = GroqClient::new(token, base_url)?;
-            drop(providers.insert(Provider::Groq, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::Groq,
+                groq_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let groq_client =
+            Self::connect(store, Provider::Groq, groq_token_cfg, selection, |token| {
+                GroqClient::new(token, base_url.clone())
+            })
+            .await?;
+        if let Some(client) = groq_client {
+            drop(providers.insert(Provider::Groq, client));
             fallback_order.push(Provider::Groq);
         } else {
             debug!(provider = "groq", "Provider not configured (missing credentials)");
@@ -145,19 +185,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let deepseek_token = match deepseek_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::DeepSeek).await?,
-        };
-        if let Some(token) = deepseek_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::DeepSeek,
-                    deepseek_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = DeepSeekClient::new(token, base_url)?;
-            drop(providers.insert(Provider::DeepSeek, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::DeepSeek,
+                deepseek_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let deepseek_client = Self::connect(
+            store,
+            Provider::DeepSeek,
+            deepseek_token_cfg,
+            selection,
+            |token| DeepSeekClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = deepseek_client {
+            drop(providers.insert(Provider::DeepSeek, client));
             fallback_order.push(Provider::DeepSeek);
         } else {
             debug!(provider = "deepseek", "Provider not configured (missing credentials)");
@@ -173,19 +216,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let together_token = match together_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::Together).await?,
-        };
-        if let Some(token) = together_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::Together,
-                    together_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = TogetherClient::new(token, base_url)?;
-            drop(providers.insert(Provider::Together, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::Together,
+                together_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let together_client = Self::connect(
+            store,
+            Provider::Together,
+            together_token_cfg,
+            selection,
+            |token| TogetherClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = together_client {
+            drop(providers.insert(Provider::Together, client));
             fallback_order.push(Provider::Together);
         } else {
             debug!(provider = "together", "Provider not configured (missing credentials)");
@@ -201,19 +247,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let cloudflare_token = match cloudflare_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::Cloudflare).await?,
-        };
-        if let Some(token) = cloudflare_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::Cloudflare,
-                    cloudflare_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = CloudflareClient::new(token, base_url)?;
-            drop(providers.insert(Provider::Cloudflare, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::Cloudflare,
+                cloudflare_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let cloudflare_client = Self::connect(
+            store,
+            Provider::Cloudflare,
+            cloudflare_token_cfg,
+            selection,
+            |token| CloudflareClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = cloudflare_client {
+            drop(providers.insert(Provider::Cloudflare, client));
             fallback_order.push(Provider::Cloudflare);
         } else {
             debug!(provider = "cloudflare", "Provider not configured (missing credentials)");
@@ -229,19 +278,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let cerebras_token = match cerebras_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::Cerebras).await?,
-        };
-        if let Some(token) = cerebras_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::Cerebras,
-                    cerebras_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = CerebrasClient::new(token, base_url)?;
-            drop(providers.insert(Provider::Cerebras, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::Cerebras,
+                cerebras_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let cerebras_client = Self::connect(
+            store,
+            Provider::Cerebras,
+            cerebras_token_cfg,
+            selection,
+            |token| CerebrasClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = cerebras_client {
+            drop(providers.insert(Provider::Cerebras, client));
             fallback_order.push(Provider::Cerebras);
         } else {
             debug!(provider = "cerebras", "Provider not configured (missing credentials)");
@@ -257,19 +309,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let mistral_token = match mistral_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::Mistral).await?,
-        };
-        if let Some(token) = mistral_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::Mistral,
-                    mistral_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = MistralClient::new(token, base_url)?;
-            drop(providers.insert(Provider::Mistral, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::Mistral,
+                mistral_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let mistral_client = Self::connect(
+            store,
+            Provider::Mistral,
+            mistral_token_cfg,
+            selection,
+            |token| MistralClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = mistral_client {
+            drop(providers.insert(Provider::Mistral, client));
             fallback_order.push(Provider::Mistral);
         } else {
             debug!(provider = "mistral", "Provider not configured (missing credentials)");
@@ -285,19 +340,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let clarifai_token = match clarifai_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::Clarifai).await?,
-        };
-        if let Some(token) = clarifai_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::Clarifai,
-                    clarifai_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = ClarifaiClient::new(token, base_url)?;
-            drop(providers.insert(Provider::Clarifai, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::Clarifai,
+                clarifai_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let clarifai_client = Self::connect(
+            store,
+            Provider::Clarifai,
+            clarifai_token_cfg,
+            selection,
+            |token| ClarifaiClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = clarifai_client {
+            drop(providers.insert(Provider::Clarifai, client));
             fallback_order.push(Provider::Clarifai);
         } else {
             debug!(provider = "clarifai", "Provider not configured (missing credentials)");
@@ -313,19 +371,22 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let github_models_token = match github_models_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::GitHubModels).await?,
-        };
-        if let Some(token) = github_models_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::GitHubModels,
-                    github_models_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = GitHubModelsClient::new(token, base_url)?;
-            drop(providers.insert(Provider::GitHubModels, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::GitHubModels,
+                github_models_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let github_models_client = Self::connect(
+            store,
+            Provider::GitHubModels,
+            github_models_token_cfg,
+            selection,
+            |token| GitHubModelsClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = github_models_client {
+            drop(providers.insert(Provider::GitHubModels, client));
             fallback_order.push(Provider::GitHubModels);
         } else {
             debug!(provider = "github_models", "Provider not configured (missing credentials)");
@@ -341,25 +402,95 @@ impl ProviderRouter {
                 Some(trimmed.to_string())
             }
         });
-        let openrouter_token = match openrouter_token_cfg {
-            Some(token) => Some(token),
-            None => store.get_token(Provider::OpenRouter).await?,
-        };
-        if let Some(token) = openrouter_token {
-            let base_url = store
-                .resolve_base_url(
-                    Provider::OpenRouter,
-                    openrouter_cfg.map(|cfg| cfg.api_base_url.as_str()),
-                )
-                .to_string();
-            let client = OpenRouterClient::new(token, base_url)?;
-            drop(providers.insert(Provider::OpenRouter, Arc::new(client)));
+        let base_url = store
+            .resolve_base_url(
+                Provider::OpenRouter,
+                openrouter_cfg.map(|cfg| cfg.api_base_url.as_str()),
+            )
+            .to_string();
+        let openrouter_client = Self::connect(
+            store,
+            Provider::OpenRouter,
+            openrouter_token_cfg,
+            selection,
+            |token| OpenRouterClient::new(token, base_url.clone()),
+        )
+        .await?;
+        if let Some(client) = openrouter_client {
+            drop(providers.insert(Provider::OpenRouter, client));
             fallback_order.push(Provider::OpenRouter);
         } else {
             debug!(provider = "openrouter", "Provider not configured (missing credentials)");
         }
 
-        Self::from_map_internal(providers, fallback_order, usage_logger, catalog)
+        let guardrails = ModerationPipeline::from_config(&config.guardrails)?;
+        let audit = match (&usage_logger, config.audit.enabled) {
+            (Some(logger), true) => Some(AuditLog::new(
+                logger.pool(),
+                config.audit,
+                &config.redaction.custom_patterns,
+            )?),
+            _ => None,
+        };
+        let mut router = Self::from_map_internal(providers, fallback_order, usage_logger, catalog)?
+            .with_guardrails(guardrails)
+            .with_aliases(aliases::from_config(&config.aliases)?)
+            .with_auto_retire(config.catalog.auto_retire_after);
+        if let Some(classifier) = WorkloadClassifier::from_config(&config.classifier)? {
+            router = router.with_classifier(classifier);
+        }
+        if let Some(redactor) = Redactor::from_config(&config.redaction)? {
+            router = router.with_redactor(redactor);
+        }
+        if let Some(audit) = audit {
+            router = router.with_audit(audit);
+        }
+        if let Some(pool) = router.usage_logger.as_ref().map(UsageLogger::pool) {
+            if let Some(budget) = Budget::from_config(pool, &config.budget)? {
+                router = router.with_budget(budget);
+            }
+        }
+        if config.trials.enabled {
+            if let Some(pool) = router.usage_logger.as_ref().map(UsageLogger::pool) {
+                router = router.with_shadow(ShadowLog::new(pool), config.trials.default_share);
+            }
+        }
+        Ok(router)
+    }
+
+    /// Builds the client of `provider`: from the configured key when there
+    /// is one, otherwise from the stored keys, pooled when there are several.
+    async fn connect<C>(
+        store: &CredentialStore,
+        provider: Provider,
+        configured: Option<String>,
+        selection: KeySelection,
+        new_client: impl Fn(String) -> Result<C, AppError>,
+    ) -> Result<Option<Arc<dyn AIProvider + Send + Sync>>, AppError>
+    where
+        C: AIProvider + Send + Sync + 'static,
+    {
+        if let Some(token) = configured {
+            return Ok(Some(Arc::new(new_client(token)?)));
+        }
+        let mut keys = store.active_keys(provider).await?;
+        if keys.len() <= 1 {
+            return match keys.pop() {
+                Some((_, token)) => Ok(Some(Arc::new(new_client(token)?))),
+                None => Ok(None),
+            };
+        }
+        let mut clients: Vec<(String, Arc<dyn AIProvider + Send + Sync>)> = Vec::new();
+        for (name, token) in keys {
+            clients.push((name, Arc::new(new_client(token)?)));
+        }
+        debug!(provider = %provider, keys = clients.len(), "Pooling stored API keys");
+        Ok(Some(Arc::new(KeyPool::new(
+            provider,
+            store.clone(),
+            selection,
+            clients,
+        ))))
     }
 
     /// Convenience constructor for scenarios that build providers manually
@@ -394,95 +525,873 @@ impl ProviderRouter {
             usage_logger,
             catalog,
             health_tracker,
+            guardrails: ModerationPipeline::default(),
+            redactor: None,
+            audit: None,
+            shadow: None,
+            shadow_share: 0.0,
+            aliases: HashMap::new(),
+            auto_retire_after: 0,
+            budget: None,
+            classifier: None,
         })
     }
 
+    /// Attaches the model catalog used for model selection and context windows.
+    #[must_use]
+    pub fn with_catalog(mut self, catalog: CatalogStore) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Enables PII redaction for providers whose policy requires it.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Replaces the moderation pipeline used for `strict` guardrail requests.
+    #[must_use]
+    pub fn with_guardrails(mut self, guardrails: ModerationPipeline) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    /// Records every routed request and its outcome in `audit`.
+    #[must_use]
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Sends shadow copies of requests to suggested models on trial and logs
+    /// the results in `shadow`. `default_share` applies to trials without a
+    /// share of their own. Requires a catalog.
+    #[must_use]
+    pub fn with_shadow(mut self, shadow: ShadowLog, default_share: f64) -> Self {
+        self.shadow = Some(shadow);
+        self.shadow_share = default_share;
+        self
+    }
+
+    /// Resolves request models named in `aliases` to their targets. These
+    /// take precedence over aliases stored in the catalog.
+    #[must_use]
+    pub fn with_aliases(mut self, aliases: HashMap<String, Vec<AliasTarget>>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Retires a catalog model after `threshold` consecutive "model not
+    /// found" failures; 0 disables it. Requires a catalog and usage logging.
+    #[must_use]
+    pub const fn with_auto_retire(mut self, threshold: u32) -> Self {
+        self.auto_retire_after = threshold;
+        self
+    }
+
+    /// Skips paid models once a spend cap in `budget` has been reached.
+    #[must_use]
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Infers the workload of requests without a workload hint.
+    #[must_use]
+    pub fn with_classifier(mut self, classifier: WorkloadClassifier) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// Returns the targets of `name` when it is a configured or catalog alias.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the catalog cannot be read.
+    pub async fn resolve_alias(&self, name: &str) -> Result<Option<Vec<AliasTarget>>, AppError> {
+        if name.is_empty() {
+            return Ok(None);
+        }
+        if let Some(targets) = self.aliases.get(name) {
+            return Ok(Some(targets.clone()));
+        }
+        match &self.catalog {
+            Some(catalog) => catalog.alias(name).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Returns whether a client for `provider` is configured.
+    #[must_use]
+    pub fn has_provider(&self, provider: Provider) -> bool {
+        self.providers.contains_key(&provider)
+    }
+
+    /// Returns the configured providers in fallback order.
+    #[must_use]
+    pub fn providers(&self) -> &[Provider] {
+        &self.fallback_order
+    }
+
+    /// Lists the models `provider` currently serves, from its own API.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `provider` is not configured or its listing request fails.
+    pub async fn list_models(&self, provider: Provider) -> Result<Vec<AvailableModel>, AppError> {
+        let client = self.providers.get(&provider).ok_or_else(|| {
+            AppError::ConfigError(format!("Provider '{provider}' is not configured"))
+        })?;
+        client.list_models().await
+    }
+
     /// Attempts to fulfil the request by delegating to an appropriate provider.
     pub async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
-        for provider in self.select_candidates(request) {
-            // Check provider health before attempting to use it
-            if let Some(health_tracker) = &self.health_tracker {
-                match health_tracker.is_available(provider).await {
-                    Ok(false) => {
-                        debug!(provider = %provider, "Skipping unavailable provider");
-                        continue;
-                    }
-                    Err(err) => {
-                        warn!(provider = %provider, error = %err, "Failed to check provider health");
-                        // Continue anyway - don't let health check errors block routing
-                    }
-                    Ok(true) => {}
-                }
+        self.generate_pinned(request, None).await
+    }
+
+    /// Sends the request to `provider` only, without falling back to others.
+    ///
+    /// Health checks, redaction, guardrails and format validation still apply.
+    ///
+    /// # Errors
+    ///
+    /// Fails when `provider` is not configured or cannot serve the request.
+    pub async fn generate_on(
+        &self,
+        provider: Provider,
+        request: &AIRequest,
+    ) -> Result<AIResponse, AppError> {
+        if !self.providers.contains_key(&provider) {
+            return Err(AppError::ConfigError(format!(
+                "Provider '{provider}' is not configured"
+            )));
+        }
+        self.generate_pinned(request, Some(provider)).await
+    }
+
+    async fn generate_pinned(
+        &self,
+        request: &AIRequest,
+        only: Option<Provider>,
+    ) -> Result<AIResponse, AppError> {
+        let start = Instant::now();
+        let classified = self.infer_workload(request).await.map(|workload| {
+            let mut classified = request.clone();
+            classified.hints.workload = Some(workload);
+            classified
+        });
+        let request = classified.as_ref().unwrap_or(request);
+        let outcome = self.route(request, only).await;
+        let latency_ms = elapsed_ms(start);
+        if let Some(audit) = &self.audit {
+            if let Err(err) = audit.record(request, &outcome, latency_ms).await {
+                warn!(error = %err, "Failed to write audit log entry");
             }
+        }
+        // Pinned calls (comparisons, evaluations) are not representative traffic.
+        if let (Ok(response), None) = (&outcome, only) {
+            self.start_shadows(request, response, latency_ms).await;
+        }
+        outcome
+    }
 
-            if let Some(client) = self.providers.get(&provider) {
-                let mut routed_request = request.clone();
-                if routed_request.model.is_empty() {
-                    if let Some(model) = self.pick_model(provider, &routed_request).await? {
-                        routed_request.model = model;
-                    }
-                }
+    /// Sends a copy of `request` to each model on trial for its workload,
+    /// sampled by the trial's share. Shadow calls run in the background and
+    /// only their outcome is logged; the caller never sees them.
+    async fn start_shadows(
+        &self,
+        request: &AIRequest,
+        primary: &AIResponse,
+        primary_latency_ms: i64,
+    ) {
+        let (Some(shadow_log), Some(catalog)) = (&self.shadow, &self.catalog) else {
+            return;
+        };
+        // Strict requests are only sent to moderated, vetted routes.
+        if matches!(request.hints.guardrail, Some(RequestGuardrail::Strict)) {
+            return;
+        }
+        let workload = request.hints.workload.unwrap_or(Workload::Chat);
+        let trials = match catalog.trials(workload).await {
+            Ok(trials) => trials,
+            Err(err) => {
+                warn!(error = %err, "Failed to load model trials");
+                return;
+            }
+        };
 
+        for trial in trials {
+            if trial.provider == primary.provider
+                && primary.model.as_deref() == Some(trial.model.as_str())
+            {
+                continue;
+            }
+            if rand::random::<f64>() >= trial.trial_share.unwrap_or(self.shadow_share) {
+                continue;
+            }
+            let Some(call) = self.prepare_shadow(request, &trial).await else {
+                continue;
+            };
+
+            let shadow_log = shadow_log.clone();
+            let usage_logger = self.usage_logger.clone();
+            let primary_provider = primary.provider;
+            let primary_model = primary.model.clone();
+            let primary_content = primary.content.clone();
+            drop(tokio::spawn(async move {
                 let start = Instant::now();
-                match client.generate(&routed_request).await {
-                    Ok(response) => {
-                        // Record successful call
-                        if let Some(health_tracker) = &self.health_tracker {
-                            if let Err(err) = health_tracker.record_success(provider).await {
-                                warn!(provider = %provider, error = %err, "Failed to record provider success");
-                            }
+                let outcome = call.client.generate(&call.request).await;
+                let latency_ms = elapsed_ms(start);
+                let outcome = outcome
+                    .map_err(|err| err.to_string())
+                    .and_then(|mut response| {
+                        if let Some(map) = &call.redactions {
+                            response.content = map.restore(&response.content);
                         }
+                        Self::enforce_format(&call.request, &mut response)
+                            .map(|()| response.content)
+                            .map_err(|reason| format!("Invalid structured output: {reason}"))
+                    });
 
-                        if let Some(logger) = &self.usage_logger {
-                            if let Err(err) = logger
-                                .log(
-                                    provider,
-                                    Some(routed_request.model.as_str()),
-                                    true,
-                                    start.elapsed().as_millis() as i64,
-                                    None,
-                                )
-                                .await
-                            {
-                                warn!(provider = %provider, error = %err, "Failed to log provider usage");
-                            }
-                        }
-                        return Ok(response);
+                if let Some(logger) = &usage_logger {
+                    log_shadow_usage(logger, &trial, workload, &call, &outcome, latency_ms).await;
+                }
+
+                let record = ShadowRecord {
+                    provider: trial.provider,
+                    workload,
+                    model: trial.model,
+                    primary_provider,
+                    primary_model,
+                    success: outcome.is_ok(),
+                    latency_ms,
+                    primary_latency_ms,
+                    output_chars: outcome.as_ref().map_or(0, |content| char_count(content)),
+                    primary_chars: char_count(&primary_content),
+                    similarity: outcome
+                        .as_ref()
+                        .ok()
+                        .map(|content| shadow::similarity(content, &primary_content)),
+                    error: outcome.err(),
+                };
+                if let Err(err) = shadow_log.record(&record).await {
+                    warn!(error = %err, "Failed to record shadow result");
+                }
+            }));
+        }
+    }
+
+    /// Builds the copy of `request` sent to `trial`, or `None` when its
+    /// provider is not configured or unavailable, or the trial model cannot
+    /// serve the request. Shadow calls pass the same context window,
+    /// capability and spend cap checks as routed calls.
+    async fn prepare_shadow(
+        &self,
+        request: &AIRequest,
+        trial: &SuggestionEntry,
+    ) -> Option<ShadowCall> {
+        let client = self.providers.get(&trial.provider).cloned()?;
+        if !request.images.is_empty() && !client.supports_images() {
+            return None;
+        }
+        if !self.is_available(trial.provider).await {
+            return None;
+        }
+        let prepared = Self::prepare_request(request).ok()?;
+        let needs = Requirements::of(&prepared);
+        let (mut shadow_request, capabilities) = match self
+            .fit_candidate(
+                trial.provider,
+                Some(trial.model.clone()),
+                &prepared,
+                &needs,
+                &mut Skipped::default(),
+            )
+            .await
+        {
+            Ok(fitted) => fitted?,
+            Err(err) => {
+                warn!(provider = %trial.provider, model = %trial.model, error = %err, "Failed to prepare shadow request");
+                return None;
+            }
+        };
+        let redactions = self.redact_for(trial.provider, &mut shadow_request);
+        Some(ShadowCall {
+            client,
+            request: shadow_request,
+            redactions,
+            capabilities,
+        })
+    }
+
+    async fn route(
+        &self,
+        request: &AIRequest,
+        only: Option<Provider>,
+    ) -> Result<AIResponse, AppError> {
+        let strict = matches!(request.hints.guardrail, Some(RequestGuardrail::Strict));
+        let mut moderated = request.clone();
+        if strict {
+            self.moderate_request(&mut moderated).await?;
+        }
+        let mut prepared = Self::prepare_request(&moderated)?;
+        let alias = self.resolve_alias(&prepared.model).await?;
+        // Only models the router picked itself may be swapped for a larger tier.
+        let may_escalate = alias.is_none() && prepared.model.is_empty();
+        if alias.is_some() {
+            // The alias name is not a model id; each target supplies its own.
+            prepared.model.clear();
+        }
+        let request = &prepared;
+        let needs = Requirements::of(request);
+        let mut skipped = Skipped::default();
+        let mut candidates = self.candidates(request, alias, only, strict).await?;
+
+        while let Some((provider, target_model)) = candidates.pop_front() {
+            if !self.is_available(provider).await {
+                continue;
+            }
+            let Some(client) = self.providers.get(&provider) else {
+                continue;
+            };
+            if !request.images.is_empty() && !client.supports_images() {
+                debug!(provider = %provider, "Skipping provider without image support");
+                continue;
+            }
+            let Some((mut routed_request, capabilities)) = self
+                .fit_candidate(provider, target_model, request, &needs, &mut skipped)
+                .await?
+            else {
+                continue;
+            };
+
+            let redactions = self.redact_for(provider, &mut routed_request);
+
+            let start = Instant::now();
+            match client.generate(&routed_request).await {
+                Ok(mut response) => {
+                    if let Some(map) = &redactions {
+                        response.content = map.restore(&response.content);
                     }
-                    Err(err) => {
-                        let error_message = err.to_string();
 
-                        // Record failed call with error classification
-                        if let Some(health_tracker) = &self.health_tracker {
-                            if let Err(health_err) = health_tracker
-                                .record_failure(provider, &error_message)
+                    if let Err(reason) = Self::enforce_format(&routed_request, &mut response) {
+                        // The provider is healthy; only this output is unusable, so skip
+                        // the health tracker and move on to the next candidate.
+                        self.log_failure(
+                            provider,
+                            &routed_request,
+                            start,
+                            format!("Invalid structured output: {reason}"),
+                        )
+                        .await;
+                        skipped.invalid = Some(format!("{provider}: {reason}"));
+                        if may_escalate {
+                            if let Some(model) = self
+                                .larger_tier(provider, &routed_request, capabilities.tier)
                                 .await
                             {
-                                warn!(provider = %provider, error = %health_err, "Failed to record provider failure");
+                                warn!(provider = %provider, reason = %reason, model = %model, "Provider output failed validation; escalating to a larger model");
+                                candidates.push_front((provider, Some(model)));
+                                continue;
                             }
                         }
+                        warn!(provider = %provider, reason = %reason, "Provider output failed validation; trying next candidate");
+                        continue;
+                    }
 
-                        if let Some(logger) = &self.usage_logger {
-                            if let Err(log_err) = logger
-                                .log(
-                                    provider,
-                                    Some(routed_request.model.as_str()),
-                                    false,
-                                    start.elapsed().as_millis() as i64,
-                                    Some(error_message.clone()),
-                                )
-                                .await
-                            {
-                                warn!(provider = %provider, error = %log_err, "Failed to log provider usage");
-                            }
+                    if strict {
+                        response.content = self
+                            .moderate_text(ModerationStage::Output, &response.content)
+                            .await?;
+                    }
+
+                    if response.model.is_none() && !routed_request.model.is_empty() {
+                        response.model = Some(routed_request.model.clone());
+                    }
+
+                    self.record_success(provider, &routed_request, &capabilities, &response, start)
+                        .await;
+                    return Ok(response);
+                }
+                Err(err) => {
+                    let error_message = err.to_string();
+
+                    // Record failed call with error classification
+                    if let Some(health_tracker) = &self.health_tracker {
+                        if let Err(health_err) = health_tracker
+                            .record_failure(provider, &error_message)
+                            .await
+                        {
+                            warn!(provider = %provider, error = %health_err, "Failed to record provider failure");
                         }
-                        warn!(provider = %provider, error = %err, "Provider call failed; trying next candidate");
                     }
+
+                    self.log_failure(provider, &routed_request, start, error_message.clone())
+                        .await;
+                    if deprecation::is_model_gone(&error_message) {
+                        self.retire_if_gone(provider, &routed_request.model).await;
+                    }
+                    warn!(provider = %provider, error = %err, "Provider call failed; trying next candidate");
                 }
             }
         }
 
-        Err(AppError::NoProviderAvailable)
+        Err(skipped.into_error(needs.context_tokens))
+    }
+
+    /// Lists the providers to try for `request`, each with the model it must
+    /// use when that is already fixed. An alias fixes both the providers and
+    /// their order.
+    async fn candidates(
+        &self,
+        request: &AIRequest,
+        alias: Option<Vec<AliasTarget>>,
+        only: Option<Provider>,
+        strict: bool,
+    ) -> Result<VecDeque<(Provider, Option<String>)>, AppError> {
+        if let Some(targets) = alias {
+            return Ok(targets
+                .into_iter()
+                .filter(|target| only.is_none_or(|provider| provider == target.provider))
+                .map(|target| (target.provider, target.model))
+                .collect());
+        }
+        let mut providers = only.map_or_else(
+            || self.select_candidates(request),
+            |provider| vec![provider],
+        );
+        if strict {
+            providers = self.prefer_safety_tuned(request, providers).await;
+        }
+        if only.is_none()
+            && request.hints.provider.is_none()
+            && matches!(request.hints.quality, Some(RequestQuality::Standard))
+        {
+            Ok(self.order_by_cost(request, providers).await?.into())
+        } else {
+            Ok(providers
+                .into_iter()
+                .map(|provider| (provider, None))
+                .collect())
+        }
+    }
+
+    /// Returns whether `provider` may be tried. Health check errors never
+    /// block routing.
+    async fn is_available(&self, provider: Provider) -> bool {
+        let Some(health_tracker) = &self.health_tracker else {
+            return true;
+        };
+        match health_tracker.is_available(provider).await {
+            Ok(available) => {
+                if !available {
+                    debug!(provider = %provider, "Skipping unavailable provider");
+                }
+                available
+            }
+            Err(err) => {
+                warn!(provider = %provider, error = %err, "Failed to check provider health");
+                true
+            }
+        }
+    }
+
+    /// Settles the model `provider` would serve `request` with and checks
+    /// that it can: the context window, required capabilities and spend caps.
+    ///
+    /// Returns `None`, noting the reason in `skipped`, when the model must be
+    /// passed over. Otherwise the request is returned with its history cut
+    /// to fit the window.
+    async fn fit_candidate(
+        &self,
+        provider: Provider,
+        target_model: Option<String>,
+        request: &AIRequest,
+        needs: &Requirements,
+        skipped: &mut Skipped,
+    ) -> Result<Option<(AIRequest, ModelCapabilities)>, AppError> {
+        let mut routed_request = request.clone();
+        if let Some(model) = target_model {
+            routed_request.model = model;
+        }
+        if routed_request.model.is_empty() {
+            if let Some(model) = self.pick_model(provider, &routed_request).await? {
+                routed_request.model = model;
+            }
+        }
+
+        let capabilities = self
+            .capabilities(provider, &routed_request.model)
+            .await
+            .unwrap_or_default();
+        let window = capabilities
+            .context_window
+            .and_then(|tokens| usize::try_from(tokens).ok());
+        if let Some(reason) = capabilities.unmet(needs, Utc::now().date_naive()) {
+            debug!(provider = %provider, model = %routed_request.model, reason = %reason, "Skipping model that cannot serve the request");
+            if matches!(reason, Unmet::ContextWindow { .. }) {
+                skipped.too_large = true;
+            } else {
+                skipped.unsupported =
+                    Some(format!("{provider} / {}: {reason}", routed_request.model));
+            }
+            return Ok(None);
+        }
+        if let Some(reason) = self.over_budget(provider, &capabilities).await {
+            debug!(provider = %provider, model = %routed_request.model, reason = %reason, "Skipping paid model over its spend cap");
+            skipped.over_budget = Some(reason);
+            return Ok(None);
+        }
+        if !routed_request.history.is_empty() {
+            let window = window.unwrap_or(context::DEFAULT_CONTEXT_WINDOW);
+            let dropped = context::truncate_history(&mut routed_request, window);
+            if dropped > 0 {
+                debug!(provider = %provider, dropped, window, "Dropped oldest history turns to fit the context window");
+            }
+        }
+        Ok(Some((routed_request, capabilities)))
+    }
+
+    /// Records a successful call with the health tracker and usage log.
+    async fn record_success(
+        &self,
+        provider: Provider,
+        request: &AIRequest,
+        capabilities: &ModelCapabilities,
+        response: &AIResponse,
+        start: Instant,
+    ) {
+        if let Some(health_tracker) = &self.health_tracker {
+            if let Err(err) = health_tracker.record_success(provider).await {
+                warn!(provider = %provider, error = %err, "Failed to record provider success");
+            }
+        }
+
+        if let Some(logger) = &self.usage_logger {
+            let cost = CallCost::estimate(
+                capabilities,
+                context::request_tokens(request),
+                context::estimate_tokens(&response.content),
+            );
+            if let Err(err) = logger
+                .log_success(
+                    provider,
+                    Some(request.model.as_str()),
+                    request.hints.workload,
+                    elapsed_ms(start),
+                    &cost,
+                )
+                .await
+            {
+                warn!(provider = %provider, error = %err, "Failed to log provider usage");
+            }
+        }
+    }
+
+    /// Records a failed call in the usage log.
+    async fn log_failure(
+        &self,
+        provider: Provider,
+        request: &AIRequest,
+        start: Instant,
+        error: String,
+    ) {
+        let Some(logger) = &self.usage_logger else {
+            return;
+        };
+        if let Err(err) = logger
+            .log(
+                provider,
+                Some(request.model.as_str()),
+                request.hints.workload,
+                false,
+                elapsed_ms(start),
+                Some(error),
+            )
+            .await
+        {
+            warn!(provider = %provider, error = %err, "Failed to log provider usage");
+        }
+    }
+
+    /// Infers the workload of a request without a workload hint from its
+    /// prompt, asking the classification model when the cues are unclear.
+    /// Strict requests are classified by heuristics only, since inference
+    /// runs before input moderation.
+    /// Returns `None` when the request has a workload or no classifier is set.
+    async fn infer_workload(&self, request: &AIRequest) -> Option<Workload> {
+        let classifier = self.classifier.as_ref()?;
+        if request.hints.workload.is_some() {
+            return None;
+        }
+        let guess = classifier::classify(request);
+        let Some((provider, model)) = classifier.model() else {
+            return Some(guess.workload);
+        };
+        if guess.confidence >= classifier.min_confidence()
+            || matches!(request.hints.guardrail, Some(RequestGuardrail::Strict))
+        {
+            return Some(guess.workload);
+        }
+        if !self.providers.contains_key(&provider) {
+            warn!(provider = %provider, "Classifier provider is not configured; using heuristics");
+            return Some(guess.workload);
+        }
+
+        // Routed like any pinned call, so health, spend caps, redaction and
+        // usage logging apply to the classification request too.
+        let classify_request = AIRequest {
+            model: model.to_string(),
+            prompt: classifier::classification_prompt(&request.prompt),
+            hints: RequestHints {
+                workload: Some(Workload::Classification),
+                ..RequestHints::default()
+            },
+            ..AIRequest::default()
+        };
+        match self.route(&classify_request, Some(provider)).await {
+            Ok(answer) => {
+                let workload = classifier::parse_answer(&answer.content);
+                debug!(provider = %provider, model, answer = %answer.content, "Classified request workload");
+                Some(workload.unwrap_or(guess.workload))
+            }
+            Err(err) => {
+                warn!(provider = %provider, model, error = %err, "Workload classification failed; using heuristics");
+                Some(guess.workload)
+            }
+        }
+    }
+
+    /// Orders `providers` by the estimated cost of `request` on the model
+    /// each would use, cheapest first. Free models cost nothing; paid models
+    /// without recorded prices go last. Ties keep their routing order.
+    async fn order_by_cost(
+        &self,
+        request: &AIRequest,
+        providers: Vec<Provider>,
+    ) -> Result<Vec<(Provider, Option<String>)>, AppError> {
+        let mut priced = Vec::with_capacity(providers.len());
+        for provider in providers {
+            let model = self.pick_model(provider, request).await?;
+            let capabilities = match &model {
+                Some(model) => self.capabilities(provider, model).await.unwrap_or_default(),
+                None => ModelCapabilities::default(),
+            };
+            let cost =
+                budget::estimate_request(provider, &capabilities, request).unwrap_or(i64::MAX);
+            priced.push((cost, provider, model));
+        }
+        priced.sort_by_key(|(cost, _, _)| *cost);
+        Ok(priced
+            .into_iter()
+            .map(|(_, provider, model)| (provider, model))
+            .collect())
+    }
+
+    /// Explains why a paid model of `provider` must be skipped because a
+    /// spend cap has been reached, or returns `None`.
+    async fn over_budget(
+        &self,
+        provider: Provider,
+        capabilities: &ModelCapabilities,
+    ) -> Option<String> {
+        let budget = self.budget.as_ref()?;
+        if !budget::is_paid(provider, capabilities) {
+            return None;
+        }
+        match budget.exhausted(provider, Utc::now()).await {
+            Ok(usage) => usage.map(|usage| usage.describe()),
+            Err(err) => {
+                warn!(provider = %provider, error = %err, "Failed to check spend caps");
+                None
+            }
+        }
+    }
+
+    /// Retires `model` once its recent calls keep failing because the
+    /// provider no longer serves it.
+    async fn retire_if_gone(&self, provider: Provider, model: &str) {
+        let Some(catalog) = &self.catalog else {
+            return;
+        };
+        if model.is_empty() || self.auto_retire_after == 0 {
+            return;
+        }
+        match catalog
+            .retire_if_gone(provider, model, self.auto_retire_after)
+            .await
+        {
+            Ok(retired) => {
+                for entry in retired {
+                    warn!(provider = %provider, model, workload = entry.workload.as_str(), "Retired catalog model the provider no longer serves");
+                }
+            }
+            Err(err) => {
+                warn!(provider = %provider, model, error = %err, "Failed to check model for retirement");
+            }
+        }
+    }
+
+    /// Runs the prompt and context of a strict request through the
+    /// moderation pipeline, redacting in place or failing with a violation.
+    async fn moderate_request(&self, request: &mut AIRequest) -> Result<(), AppError> {
+        request.prompt = self
+            .guardrails
+            .apply_rules(ModerationStage::Input, &request.prompt)?;
+        for context in &mut request.context {
+            *context = self
+                .guardrails
+                .apply_rules(ModerationStage::Input, context)?;
+        }
+        for turn in &mut request.history {
+            turn.content = self
+                .guardrails
+                .apply_rules(ModerationStage::Input, &turn.content)?;
+        }
+
+        if self.guardrails.moderator().is_some() {
+            let mut combined = request.context.join("\n\n");
+            if !combined.is_empty() {
+                combined.push_str("\n\n");
+            }
+            combined.push_str(&request.prompt);
+            self.consult_moderator(ModerationStage::Input, &combined)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Applies local rules and the moderation model to a single text.
+    async fn moderate_text(&self, stage: ModerationStage, text: &str) -> Result<String, AppError> {
+        let text = self.guardrails.apply_rules(stage, text)?;
+        self.consult_moderator(stage, &text).await?;
+        Ok(text)
+    }
+
+    async fn consult_moderator(&self, stage: ModerationStage, text: &str) -> Result<(), AppError> {
+        let Some((provider, model)) = self.guardrails.moderator() else {
+            return Ok(());
+        };
+        let client = self.providers.get(&provider).ok_or_else(|| {
+            AppError::ConfigError(format!(
+                "Guardrail moderation provider '{provider}' is not configured"
+            ))
+        })?;
+
+        let mut moderation_request = AIRequest {
+            model: model.to_string(),
+            prompt: guardrail::moderation_prompt(stage, text),
+            ..AIRequest::default()
+        };
+        // Only the verdict is read back, so placeholders need no restoring.
+        drop(self.redact_for(provider, &mut moderation_request));
+        // Strict mode fails closed: an unreachable moderator blocks the request.
+        let verdict = client.generate(&moderation_request).await.map_err(|err| {
+            AppError::GuardrailViolation(format!(
+                "{} could not be moderated by {provider}/{model}: {err}",
+                stage.as_str()
+            ))
+        })?;
+
+        guardrail::unsafe_verdict(&verdict.content).map_or(Ok(()), |categories| {
+            Err(AppError::GuardrailViolation(format!(
+                "{} flagged as unsafe by {provider}/{model} ({categories})",
+                stage.as_str()
+            )))
+        })
+    }
+
+    /// Moves providers whose active catalog models are flagged `safety_tuned`
+    /// ahead of the rest, keeping an explicitly requested provider first.
+    async fn prefer_safety_tuned(
+        &self,
+        request: &AIRequest,
+        candidates: Vec<Provider>,
+    ) -> Vec<Provider> {
+        let Some(catalog) = &self.catalog else {
+            return candidates;
+        };
+        let pinned = self
+            .provider_from_hints(request)
+            .or_else(|| self.provider_from_tags(request));
+
+        let mut head = Vec::new();
+        let mut tuned = Vec::new();
+        let mut rest = Vec::new();
+        for provider in candidates {
+            if Some(provider) == pinned {
+                head.push(provider);
+                continue;
+            }
+            let is_tuned = match catalog
+                .active_models(provider, request.hints.workload)
+                .await
+            {
+                Ok(models) => models
+                    .iter()
+                    .any(|entry| entry.metadata_flag("safety_tuned")),
+                Err(err) => {
+                    warn!(provider = %provider, error = %err, "Failed to read catalog for guardrail ordering");
+                    false
+                }
+            };
+            if is_tuned {
+                tuned.push(provider);
+            } else {
+                rest.push(provider);
+            }
+        }
+
+        head.extend(tuned);
+        head.extend(rest);
+        head
+    }
+
+    /// Redacts the prompt and context in place when the provider's policy
+    /// requires it, returning the placeholder map for re-hydration.
+    fn redact_for(&self, provider: Provider, request: &mut AIRequest) -> Option<RedactionMap> {
+        let redactor = self.redactor.as_ref().filter(|r| r.applies_to(provider))?;
+        let mut map = RedactionMap::default();
+        request.prompt = redactor.redact(&request.prompt, &mut map);
+        for context in &mut request.context {
+            *context = redactor.redact(context, &mut map);
+        }
+        for turn in &mut request.history {
+            turn.content = redactor.redact(&turn.content, &mut map);
+        }
+        if !map.is_empty() {
+            debug!(provider = %provider, values = map.len(), "Redacted sensitive values before provider call");
+        }
+        Some(map)
+    }
+
+    /// Applies request-level adjustments that every candidate should see,
+    /// such as the JSON-only instruction for structured output.
+    fn prepare_request(request: &AIRequest) -> Result<AIRequest, AppError> {
+        let mut prepared = request.clone();
+        if prepared.wants_json() {
+            if let Some(schema) = prepared.response_schema.as_ref() {
+                structured::check_schema(schema).map_err(AppError::InvalidRequest)?;
+            }
+            let instruction = structured::json_instruction(prepared.response_schema.as_ref());
+            prepared.prompt = format!("{}\n\n{instruction}", prepared.prompt);
+        }
+        Ok(prepared)
+    }
+
+    /// Validates provider output against the requested format, normalising
+    /// JSON responses to a compact document on success.
+    fn enforce_format(request: &AIRequest, response: &mut AIResponse) -> Result<(), String> {
+        if !request.wants_json() {
+            return Ok(());
+        }
+        let value =
+            structured::parse_and_validate(&response.content, request.response_schema.as_ref())?;
+        response.content = value.to_string();
+        Ok(())
     }
 
     fn select_candidates(&self, request: &AIRequest) -> Vec<Provider> {
@@ -595,6 +1504,59 @@ impl ProviderRouter {
         picks
     }
 
+    /// Looks up the capabilities of `model` in the catalog. Returns `None`
+    /// when the model is not an active catalog entry.
+    async fn capabilities(&self, provider: Provider, model: &str) -> Option<ModelCapabilities> {
+        let catalog = self.catalog.as_ref()?;
+        match catalog.active_models(provider, None).await {
+            Ok(models) => models
+                .iter()
+                .find(|entry| entry.model == model)
+                .map(ModelEntry::capabilities),
+            Err(err) => {
+                warn!(provider = %provider, error = %err, "Failed to read catalog for model capabilities");
+                None
+            }
+        }
+    }
+
+    /// Finds the next larger tier than `tier` among the provider's suitable
+    /// models for the request's workload, for retrying output that failed
+    /// validation. Untagged models never take part in escalation.
+    async fn larger_tier(
+        &self,
+        provider: Provider,
+        request: &AIRequest,
+        tier: Option<ModelTier>,
+    ) -> Option<String> {
+        let tier = tier?;
+        let catalog = self.catalog.as_ref()?;
+        let models = match catalog
+            .active_models(provider, request.hints.workload)
+            .await
+        {
+            Ok(models) => models,
+            Err(err) => {
+                warn!(provider = %provider, error = %err, "Failed to read catalog for a larger model");
+                return None;
+            }
+        };
+        let needs = Requirements::of(request);
+        let today = Utc::now().date_naive();
+        models
+            .into_iter()
+            .filter_map(|entry| {
+                let capabilities = entry.capabilities();
+                let larger = capabilities.tier.filter(|candidate| *candidate > tier)?;
+                capabilities
+                    .unmet(&needs, today)
+                    .is_none()
+                    .then_some((larger, entry.model))
+            })
+            .min_by_key(|(larger, _)| *larger)
+            .map(|(_, model)| model)
+    }
+
     async fn pick_model(
         &self,
         provider: Provider,
@@ -608,11 +1570,55 @@ impl ProviderRouter {
         // Otherwise, look up the default model for this provider/workload in the catalog
         let workload = request.hints.workload;
         if let Some(catalog) = &self.catalog {
-            let models = catalog
+            // Models known to be unable to serve the request (too small,
+            // deprecated, lacking a needed capability) are only used when
+            // nothing else is left; the caller then skips the provider.
+            let needs = Requirements::of(request);
+            let today = Utc::now().date_naive();
+            let (mut models, unsuitable): (Vec<ModelEntry>, Vec<ModelEntry>) = catalog
                 .active_models(provider, workload)
                 .await?
                 .into_iter()
-                .collect::<Vec<ModelEntry>>();
+                .partition(|entry| entry.capabilities().unmet(&needs, today).is_none());
+            if models.is_empty() {
+                return Ok(unsuitable.first().map(|entry| entry.model.clone()));
+            }
+            // A complexity hint prefers the closest size tier; untagged models
+            // count as medium and priority still breaks ties.
+            if let Some(complexity) = request.hints.complexity {
+                let wanted = ModelTier::for_complexity(complexity);
+                models.sort_by_key(|entry| {
+                    entry
+                        .capabilities()
+                        .tier
+                        .unwrap_or(ModelTier::Medium)
+                        .distance(wanted)
+                });
+            }
+            if matches!(request.hints.guardrail, Some(RequestGuardrail::Strict)) {
+                if let Some(entry) = models
+                    .iter()
+                    .find(|entry| entry.metadata_flag("safety_tuned"))
+                {
+                    return Ok(Some(entry.model.clone()));
+                }
+            }
+            if needs.vision {
+                if let Some(entry) = models
+                    .iter()
+                    .find(|entry| entry.capabilities().vision == Some(true))
+                {
+                    return Ok(Some(entry.model.clone()));
+                }
+            }
+            if needs.json {
+                if let Some(entry) = models
+                    .iter()
+                    .find(|entry| entry.capabilities().json_mode == Some(true))
+                {
+                    return Ok(Some(entry.model.clone()));
+                }
+            }
             if let Some(entry) = models.first() {
                 return Ok(Some(entry.model.clone()));
             }
@@ -621,3 +1627,98 @@ impl ProviderRouter {
         Ok(None)
     }
 }
+
+/// Logs a shadow call like a routed one, with its estimated cost on success
+/// so shadow traffic counts against spend caps.
+async fn log_shadow_usage(
+    logger: &UsageLogger,
+    trial: &SuggestionEntry,
+    workload: Workload,
+    call: &ShadowCall,
+    outcome: &Result<String, String>,
+    latency_ms: i64,
+) {
+    let model = Some(trial.model.as_str());
+    let result = match outcome {
+        Ok(content) => {
+            let cost = CallCost::estimate(
+                &call.capabilities,
+                context::request_tokens(&call.request),
+                context::estimate_tokens(content),
+            );
+            logger
+                .log_success(trial.provider, model, Some(workload), latency_ms, &cost)
+                .await
+        }
+        Err(error) => {
+            logger
+                .log(
+                    trial.provider,
+                    model,
+                    Some(workload),
+                    false,
+                    latency_ms,
+                    Some(error.clone()),
+                )
+                .await
+        }
+    };
+    if let Err(err) = result {
+        warn!(provider = %trial.provider, error = %err, "Failed to log shadow usage");
+    }
+}
+
+/// A shadow request ready to send to a trial model.
+struct ShadowCall {
+    client: Arc<dyn AIProvider + Send + Sync>,
+    request: AIRequest,
+    redactions: Option<RedactionMap>,
+    capabilities: ModelCapabilities,
+}
+
+/// Why candidates were passed over while routing a request.
+#[derive(Debug, Default)]
+struct Skipped {
+    /// The last provider whose output failed format validation.
+    invalid: Option<String>,
+    /// Whether a model was skipped for too small a context window.
+    too_large: bool,
+    /// The last model lacking a capability the request needs.
+    unsupported: Option<String>,
+    /// The last spend cap that ruled out a paid model.
+    over_budget: Option<String>,
+}
+
+impl Skipped {
+    /// The error reported once every candidate has been tried.
+    fn into_error(self, required: usize) -> AppError {
+        match (self.invalid, self.unsupported) {
+            (Some(reason), _) => AppError::InvalidResponse(format!(
+                "no provider returned output matching the requested format (last failure from {reason})"
+            )),
+            (None, _) if self.too_large => AppError::InvalidRequest(format!(
+                "request needs about {required} tokens, more than the context window of any available model"
+            )),
+            (None, Some(reason)) => AppError::InvalidRequest(format!(
+                "no available model supports this request (last candidate {reason})"
+            )),
+            (None, None) => self
+                .over_budget
+                .map_or(AppError::NoProviderAvailable, |reason| {
+                    AppError::BudgetExceeded(format!(
+                        "{reason}; no free provider could serve the request"
+                    ))
+                }),
+        }
+    }
+}
+
+/// Milliseconds since `start`, as stored in the usage log.
+fn elapsed_ms(start: Instant) -> i64 {
+    i64::try_from(start.elapsed().as_millis()).unwrap_or(i64::MAX)
+}
+
+/// Length of `text` in characters, as stored with shadow results.
+fn char_count(text: &str) -> i64 {
+    i64::try_from(text.chars().count()).unwrap_or(i64::MAX)
+}
@@ -11,7 +11,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     error::AppError,
     models::{AIRequest, AIResponse},
-    providers::{AIProvider, Provider},
+    providers::{
+        fetch_openai_models, openai_messages, openai_response_format, openai_user_content,
+        AIProvider, AvailableModel, Provider,
+    },
 };
 
 /// A client for interacting with the Together AI API.
@@ -40,6 +43,10 @@ impl TogetherClient {
 
 #[async_trait]
 impl AIProvider for TogetherClient {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     /// Sends a generation request to the Together AI API.
     async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
         let api_url = format!("{}/chat/completions", self.base_url);
@@ -50,10 +57,10 @@ impl AIProvider for TogetherClient {
             } else {
                 request.model.clone()
             },
-            messages: vec![TogetherMessage {
-                role: "user".to_string(),
-                content: request.prompt.clone(),
-            }],
+            messages: openai_messages(request, openai_user_content(request)?, |role, content| {
+                TogetherMessage { role, content }
+            }),
+            response_format: openai_response_format(request, true),
         };
 
         let response = self
@@ -91,20 +98,35 @@ impl AIProvider for TogetherClient {
         Ok(AIResponse {
             content,
             provider: Provider::Together,
+            model: Some(body.model),
         })
     }
+
+    /// Lists the models Together serves, with context lengths and prices.
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        let api_url = format!("{}/models", self.base_url);
+        fetch_openai_models(
+            &self.http_client,
+            &api_url,
+            &self.api_key,
+            Provider::Together,
+        )
+        .await
+    }
 }
 
 #[derive(Serialize)]
 struct TogetherRequestBody {
     model: String,
     messages: Vec<TogetherMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
 struct TogetherMessage {
     role: String,
-    content: String,
+    content: serde_json::Value,
 }
 
 #[derive(Deserialize)]
@@ -120,4 +142,4 @@ struct TogetherChoice {
 #[derive(Deserialize)]
 struct TogetherMessageContent {
     content: Option<String>,
-}
\ No newline at end of file
+}
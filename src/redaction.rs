@@ -0,0 +1,207 @@
+//! PII redaction and re-hydration around third-party provider calls.
+//!
+//! Before a request leaves for a provider whose policy requires it, emails,
+//! phone numbers, API-key-like tokens, IP addresses and any custom patterns
+//! in the prompt and context are replaced with stable placeholders such as
+//! `[EMAIL_1]`. The mapping never leaves the process; placeholders echoed
+//! back by the model are restored to the original values in the response.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::{
+    config::{RedactionConfig, RedactionPolicy},
+    error::AppError,
+    providers::Provider,
+};
+
+/// Detects sensitive values and decides which providers require redaction.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    rules: Vec<(String, Regex)>,
+    default_policy: RedactionPolicy,
+    provider_policies: HashMap<Provider, RedactionPolicy>,
+}
+
+/// Placeholder assignments for a single provider call.
+#[derive(Debug, Default, Clone)]
+pub struct RedactionMap {
+    by_value: HashMap<String, String>,
+    by_placeholder: Vec<(String, String)>,
+    counters: HashMap<String, usize>,
+}
+
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    ("EMAIL", r"(?i)\b[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,}\b"),
+    // Provider-style secrets: sk-..., ghp_..., hf_..., AKIA..., xox[bp]-...
+    (
+        "API_KEY",
+        r"\b(?:sk-[A-Za-z0-9_-]{16,}|gh[pousr]_[A-Za-z0-9]{20,}|hf_[A-Za-z0-9]{20,}|AKIA[0-9A-Z]{16}|xox[abpr]-[A-Za-z0-9-]{10,})\b",
+    ),
+    // Long opaque tokens mixing letters and digits.
+    (
+        "SECRET",
+        r"\b(?:[A-Za-z]+[0-9]+|[0-9]+[A-Za-z]+)[A-Za-z0-9_-]{28,}\b",
+    ),
+    (
+        "IP",
+        r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b",
+    ),
+    (
+        "PHONE",
+        r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,4}\)[\s.-]?|\b\d{2,4}[\s.-])\d{3,4}[\s.-]\d{3,4}\b",
+    ),
+];
+
+impl Redactor {
+    /// Builds the redactor from the `[redaction]` configuration section.
+    ///
+    /// Returns `None` when redaction is disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error when a custom pattern is not a valid
+    /// regex.
+    pub fn from_config(config: &RedactionConfig) -> Result<Option<Self>, AppError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let mut rules = Vec::new();
+        for (label, pattern) in BUILTIN_RULES {
+            let regex = Regex::new(pattern).map_err(|err| {
+                AppError::ConfigError(format!("Invalid built-in redaction rule {label}: {err}"))
+            })?;
+            rules.push(((*label).to_string(), regex));
+        }
+        for (idx, pattern) in config.custom_patterns.iter().enumerate() {
+            let regex = Regex::new(pattern).map_err(|err| {
+                AppError::ConfigError(format!("Invalid redaction pattern '{pattern}': {err}"))
+            })?;
+            rules.push((format!("CUSTOM{}", idx + 1), regex));
+        }
+
+        let mut provider_policies = HashMap::new();
+        for (name, policy) in &config.providers {
+            let provider = Provider::from_alias(name).ok_or_else(|| {
+                AppError::ConfigError(format!("Unknown provider '{name}' in redaction policy"))
+            })?;
+            let _ = provider_policies.insert(provider, *policy);
+        }
+
+        Ok(Some(Self {
+            rules,
+            default_policy: config.default_policy,
+            provider_policies,
+        }))
+    }
+
+    /// Returns whether requests to `provider` must be redacted.
+    #[must_use]
+    pub fn applies_to(&self, provider: Provider) -> bool {
+        let policy = self
+            .provider_policies
+            .get(&provider)
+            .copied()
+            .unwrap_or(self.default_policy);
+        policy == RedactionPolicy::Always
+    }
+
+    /// Replaces sensitive values in `text`, recording placeholders in `map`.
+    pub fn redact(&self, text: &str, map: &mut RedactionMap) -> String {
+        let mut current = text.to_string();
+        for (label, regex) in &self.rules {
+            current = regex
+                .replace_all(&current, |caps: &regex::Captures<'_>| {
+                    map.placeholder_for(label, &caps[0])
+                })
+                .into_owned();
+        }
+        current
+    }
+}
+
+impl RedactionMap {
+    /// Returns whether any value was redacted.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.by_placeholder.is_empty()
+    }
+
+    /// Returns the number of distinct values that were redacted.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.by_placeholder.len()
+    }
+
+    fn placeholder_for(&mut self, label: &str, value: &str) -> String {
+        if let Some(existing) = self.by_value.get(value) {
+            return existing.clone();
+        }
+        let counter = self.counters.entry(label.to_string()).or_insert(0);
+        *counter += 1;
+        let placeholder = format!("[{label}_{counter}]");
+        drop(self.by_value.insert(value.to_string(), placeholder.clone()));
+        self.by_placeholder
+            .push((placeholder.clone(), value.to_string()));
+        placeholder
+    }
+
+    /// Restores original values for every placeholder found in `text`.
+    #[must_use]
+    pub fn restore(&self, text: &str) -> String {
+        let mut restored = text.to_string();
+        for (placeholder, value) in &self.by_placeholder {
+            restored = restored.replace(placeholder, value);
+        }
+        restored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(custom: &[&str]) -> Redactor {
+        let config = RedactionConfig {
+            enabled: true,
+            default_policy: RedactionPolicy::Always,
+            custom_patterns: custom.iter().map(ToString::to_string).collect(),
+            providers: HashMap::from([("groq".to_string(), RedactionPolicy::Never)]),
+        };
+        Redactor::from_config(&config).unwrap().unwrap()
+    }
+
+    #[test]
+    fn redacts_and_restores_common_pii() {
+        let redactor = redactor(&[]);
+        let mut map = RedactionMap::default();
+        let text = "Mail jane.doe@example.com or call +1 415-555-0100 from 10.0.0.12 \
+                    using sk-abcdefghijklmnopqrstuv. jane.doe@example.com again.";
+        let redacted = redactor.redact(text, &mut map);
+
+        assert!(!redacted.contains("jane.doe@example.com"));
+        assert!(!redacted.contains("10.0.0.12"));
+        assert!(!redacted.contains("sk-abcdefghijklmnopqrstuv"));
+        assert!(!redacted.contains("555-0100"));
+        assert_eq!(redacted.matches("[EMAIL_1]").count(), 2);
+        assert_eq!(map.restore(&redacted), text);
+    }
+
+    #[test]
+    fn applies_custom_patterns() {
+        let redactor = redactor(&[r"ACME-\d{4}"]);
+        let mut map = RedactionMap::default();
+        let redacted = redactor.redact("Ticket ACME-1234 is open", &mut map);
+        assert_eq!(redacted, "Ticket [CUSTOM1_1] is open");
+        assert_eq!(map.restore("Closed [CUSTOM1_1]."), "Closed ACME-1234.");
+    }
+
+    #[test]
+    fn honours_per_provider_policy() {
+        let redactor = redactor(&[]);
+        assert!(redactor.applies_to(Provider::OpenRouter));
+        assert!(!redactor.applies_to(Provider::Groq));
+    }
+}
@@ -0,0 +1,416 @@
+//! Declarative model rosters for `catalog export` and `catalog import`.
+//!
+//! A roster lists catalog entries as `[[models]]` tables in TOML, or as a
+//! `models:` sequence in YAML. Importing makes the database match the file:
+//! listed entries are added or updated, and with `prune` the active entries
+//! the file does not mention are retired. The default roster seeded into new
+//! databases is the repository's `catalog.toml`, compiled in.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    capabilities::ModelCapabilities,
+    catalog::{CatalogStore, ModelEntry},
+    error::AppError,
+    models::Workload,
+    providers::Provider,
+};
+
+/// The default roster, seeded into new databases.
+pub const DEFAULT_ROSTER: &str = include_str!("../catalog.toml");
+
+/// File format of a roster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RosterFormat {
+    /// TOML with `[[models]]` tables.
+    Toml,
+    /// YAML with a `models:` sequence.
+    Yaml,
+}
+
+impl RosterFormat {
+    /// Picks the format from a file extension; TOML unless `.yaml`/`.yml`.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") => {
+                Self::Yaml
+            }
+            _ => Self::Toml,
+        }
+    }
+
+    /// Parses `toml` or `yaml`.
+    #[must_use]
+    pub fn from_key(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A set of catalog entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roster {
+    /// Catalog entries, one per provider, workload and model.
+    #[serde(default)]
+    pub models: Vec<RosterEntry>,
+}
+
+/// One catalog entry in a roster file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RosterEntry {
+    /// Provider serving the model.
+    #[serde(with = "provider_key")]
+    pub provider: Provider,
+    /// Workload the entry applies to.
+    pub workload: Workload,
+    /// Model identifier.
+    pub model: String,
+    /// Priority (lower = preferred).
+    #[serde(default = "default_priority")]
+    pub priority: i64,
+    /// `active` or `retired`.
+    #[serde(default = "default_status", skip_serializing_if = "is_active")]
+    pub status: String,
+    /// Human-readable reason for the entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+    /// Catalog metadata such as `context_window` or `vision`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+const fn default_priority() -> i64 {
+    100
+}
+
+fn default_status() -> String {
+    "active".to_string()
+}
+
+fn is_active(status: &str) -> bool {
+    status == "active"
+}
+
+impl RosterEntry {
+    const fn key(&self) -> (Provider, Workload, &str) {
+        (self.provider, self.workload, self.model.as_str())
+    }
+
+    /// Serialized metadata as stored in the catalog.
+    #[must_use]
+    pub fn metadata_json(&self) -> Option<String> {
+        self.metadata
+            .as_ref()
+            .filter(|metadata| !metadata.is_empty())
+            .map(|metadata| serde_json::Value::Object(metadata.clone()).to_string())
+    }
+}
+
+impl Roster {
+    /// Returns the compiled-in default roster.
+    ///
+    /// # Errors
+    ///
+    /// Fails only if the compiled-in roster is invalid.
+    pub fn defaults() -> Result<Self, AppError> {
+        Self::parse(DEFAULT_ROSTER, RosterFormat::Toml)
+    }
+
+    /// Reads a roster file; the format follows the file extension.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or is not a valid roster.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            AppError::ConfigError(format!("Failed to read {}: {err}", path.display()))
+        })?;
+        Self::parse(&text, RosterFormat::from_path(path)).map_err(|err| match err {
+            AppError::InvalidRequest(message) => {
+                AppError::InvalidRequest(format!("{}: {message}", path.display()))
+            }
+            other => other,
+        })
+    }
+
+    /// Parses and validates a roster.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the text cannot be parsed or an entry is invalid.
+    pub fn parse(text: &str, format: RosterFormat) -> Result<Self, AppError> {
+        let roster: Self = match format {
+            RosterFormat::Toml => {
+                toml::from_str(text).map_err(|err| AppError::InvalidRequest(err.to_string()))?
+            }
+            RosterFormat::Yaml => serde_norway::from_str(text)
+                .map_err(|err| AppError::InvalidRequest(err.to_string()))?,
+        };
+
+        let mut seen = HashSet::new();
+        for entry in &roster.models {
+            if entry.model.trim().is_empty() {
+                return Err(AppError::InvalidRequest(format!(
+                    "Entry for {} / {} has an empty model",
+                    entry.provider,
+                    entry.workload.as_str()
+                )));
+            }
+            if !matches!(entry.status.as_str(), "active" | "retired") {
+                return Err(AppError::InvalidRequest(format!(
+                    "Model '{}' has status '{}'; expected active or retired",
+                    entry.model, entry.status
+                )));
+            }
+            if let Some(metadata) = entry.metadata_json() {
+                if let Err(AppError::InvalidRequest(message)) = ModelCapabilities::parse(&metadata)
+                {
+                    return Err(AppError::InvalidRequest(format!(
+                        "Model '{}': {message}",
+                        entry.model
+                    )));
+                }
+            }
+            if !seen.insert(entry.key()) {
+                return Err(AppError::InvalidRequest(format!(
+                    "Model '{}' is listed twice for {} / {}",
+                    entry.model,
+                    entry.provider,
+                    entry.workload.as_str()
+                )));
+            }
+        }
+        Ok(roster)
+    }
+
+    /// Builds a roster from catalog entries.
+    #[must_use]
+    pub fn from_entries(entries: &[ModelEntry]) -> Self {
+        Self {
+            models: entries
+                .iter()
+                .map(|entry| RosterEntry {
+                    provider: entry.provider,
+                    workload: entry.workload,
+                    model: entry.model.clone(),
+                    priority: entry.priority,
+                    status: entry.status.clone(),
+                    rationale: entry.rationale.clone(),
+                    // TOML has no null; drop null values so every roster
+                    // can be written in either format.
+                    metadata: entry
+                        .metadata_object()
+                        .map(|mut metadata| {
+                            metadata.retain(|_, value| !value.is_null());
+                            metadata
+                        })
+                        .filter(|metadata| !metadata.is_empty()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Renders the roster in `format`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the roster cannot be serialized.
+    pub fn render(&self, format: RosterFormat) -> Result<String, AppError> {
+        match format {
+            RosterFormat::Toml => toml::to_string_pretty(self)
+                .map_err(|err| AppError::ApiError(format!("Failed to render TOML: {err}"))),
+            RosterFormat::Yaml => serde_norway::to_string(self)
+                .map_err(|err| AppError::ApiError(format!("Failed to render YAML: {err}"))),
+        }
+    }
+}
+
+/// Changes needed to make the catalog match a roster.
+#[derive(Debug, Clone, Default)]
+pub struct ImportPlan {
+    /// Entries not yet in the catalog.
+    pub add: Vec<RosterEntry>,
+    /// Entries that differ from the catalog, with a description of each change.
+    pub update: Vec<(RosterEntry, Vec<String>)>,
+    /// Active catalog entries missing from the roster (only with `prune`).
+    pub retire: Vec<ModelEntry>,
+    /// Entries that already match.
+    pub unchanged: usize,
+}
+
+impl ImportPlan {
+    /// Returns whether the catalog already matches the roster.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.update.is_empty() && self.retire.is_empty()
+    }
+}
+
+/// Compares a roster with the catalog.
+///
+/// The roster is authoritative for the entries it lists, including their
+/// metadata. With `prune`, active catalog entries it does not list are
+/// retired; otherwise they are left alone.
+///
+/// # Errors
+///
+/// Fails when the catalog cannot be read.
+pub async fn plan_import(
+    catalog: &CatalogStore,
+    roster: &Roster,
+    prune: bool,
+) -> Result<ImportPlan, AppError> {
+    let current = catalog.list_models(None, None).await?;
+    let mut plan = ImportPlan::default();
+
+    for entry in &roster.models {
+        let existing = current.iter().find(|model| {
+            model.provider == entry.provider
+                && model.workload == entry.workload
+                && model.model == entry.model
+        });
+        let Some(existing) = existing else {
+            plan.add.push(entry.clone());
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        if existing.status != entry.status {
+            changes.push(format!("status {} -> {}", existing.status, entry.status));
+        }
+        if existing.priority != entry.priority {
+            changes.push(format!(
+                "priority {} -> {}",
+                existing.priority, entry.priority
+            ));
+        }
+        if existing.rationale != entry.rationale {
+            changes.push("rationale".to_string());
+        }
+        let existing_metadata = existing.metadata_object().unwrap_or_default();
+        if existing_metadata != entry.metadata.clone().unwrap_or_default() {
+            changes.push("metadata".to_string());
+        }
+
+        if changes.is_empty() {
+            plan.unchanged += 1;
+        } else {
+            plan.update.push((entry.clone(), changes));
+        }
+    }
+
+    if prune {
+        let listed: HashSet<_> = roster.models.iter().map(RosterEntry::key).collect();
+        plan.retire = current
+            .into_iter()
+            .filter(|model| model.status == "active")
+            .filter(|model| {
+                !listed.contains(&(model.provider, model.workload, model.model.as_str()))
+            })
+            .collect();
+    }
+    Ok(plan)
+}
+
+/// Applies a plan produced by [`plan_import`].
+///
+/// # Errors
+///
+/// Fails when a catalog update fails.
+pub async fn apply_import(catalog: &CatalogStore, plan: &ImportPlan) -> Result<(), AppError> {
+    let entries = plan
+        .add
+        .iter()
+        .chain(plan.update.iter().map(|(entry, _)| entry));
+    for entry in entries {
+        catalog.upsert_model(entry).await?;
+    }
+    for model in &plan.retire {
+        let _ = catalog
+            .retire_model(model.provider, model.workload, &model.model)
+            .await?;
+    }
+    Ok(())
+}
+
+mod provider_key {
+    use std::fmt::Display;
+
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    use crate::providers::Provider;
+
+    pub(super) fn serialize<S: Serializer, P: Display>(
+        provider: &P,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(provider)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Provider, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Provider::from_alias(&name)
+            .ok_or_else(|| D::Error::custom(format!("unknown provider '{name}'")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_roster_parses() {
+        let roster = Roster::defaults().unwrap();
+        assert!(!roster.models.is_empty());
+        let groq = roster
+            .models
+            .iter()
+            .find(|entry| entry.provider == Provider::Groq && entry.workload == Workload::Chat)
+            .unwrap();
+        assert_eq!(groq.model, "llama-3.3-70b-versatile");
+        assert_eq!(
+            groq.metadata_json().as_deref(),
+            Some(r#"{"context_window":131072,"tier":"large"}"#)
+        );
+    }
+
+    #[test]
+    fn roster_round_trips_through_toml_and_yaml() {
+        let roster = Roster::parse(
+            "[[models]]\nprovider = \"github\"\nworkload = \"code\"\nmodel = \"gpt-4o\"\n\
+             metadata = { vision = true }\n",
+            RosterFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(roster.models[0].provider, Provider::GitHubModels);
+        assert_eq!(roster.models[0].priority, 100);
+        assert_eq!(roster.models[0].status, "active");
+
+        for format in [RosterFormat::Toml, RosterFormat::Yaml] {
+            let text = roster.render(format).unwrap();
+            assert_eq!(Roster::parse(&text, format).unwrap(), roster);
+        }
+    }
+
+    #[test]
+    fn rejects_duplicates_and_unknown_values() {
+        let duplicate = "models:\n  - { provider: groq, workload: chat, model: a }\n  \
+                         - { provider: groq, workload: chat, model: a }\n";
+        assert!(Roster::parse(duplicate, RosterFormat::Yaml).is_err());
+        let status = "models:\n  - { provider: groq, workload: chat, model: a, status: paused }\n";
+        assert!(Roster::parse(status, RosterFormat::Yaml).is_err());
+        let provider = "models:\n  - { provider: nope, workload: chat, model: a }\n";
+        assert!(Roster::parse(provider, RosterFormat::Yaml).is_err());
+        let metadata =
+            "models:\n  - { provider: groq, workload: chat, model: a, metadata: { vision: 1 } }\n";
+        assert!(Roster::parse(metadata, RosterFormat::Yaml).is_err());
+    }
+}
@@ -1,36 +1,150 @@
 //! Defines the API routes and handlers for the web server.
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use axum::{extract::State, routing::post, Json, Router};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     error::AppError,
-    models::{AIRequest, AIResponse},
-    providers::ProviderRouter,
+    jobs::{Job, JobQueue},
+    models::{AIRequest, AIResponse, ImageSource, RequestHints, Workload},
+    providers::{Provider, ProviderRouter},
+    sessions::{Session, SessionMessage, SessionStore},
+    templates::{PromptTemplate, TemplateLibrary},
 };
 
 /// Shared application state passed into route handlers.
 #[derive(Clone, Debug)]
 pub struct AppState {
     provider_router: Arc<ProviderRouter>,
+    jobs: Option<JobQueue>,
+    sessions: Option<SessionStore>,
+    templates: Option<TemplateLibrary>,
 }
 
 impl AppState {
     /// Creates a new `AppState` instance.
     pub fn new(provider_router: Arc<ProviderRouter>) -> Self {
-        Self { provider_router }
+        Self {
+            provider_router,
+            jobs: None,
+            sessions: None,
+            templates: None,
+        }
+    }
+
+    /// Enables the asynchronous job endpoints backed by `jobs`.
+    #[must_use]
+    pub fn with_jobs(mut self, jobs: JobQueue) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Enables the conversation session endpoints backed by `sessions`.
+    #[must_use]
+    pub fn with_sessions(mut self, sessions: SessionStore) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Enables the prompt template endpoints backed by `templates`.
+    #[must_use]
+    pub fn with_templates(mut self, templates: TemplateLibrary) -> Self {
+        self.templates = Some(templates);
+        self
     }
 
     fn provider_router(&self) -> &ProviderRouter {
         self.provider_router.as_ref()
     }
+
+    fn jobs(&self) -> Result<&JobQueue, AppError> {
+        self.jobs
+            .as_ref()
+            .ok_or_else(|| AppError::ConfigError("The job queue is not enabled".into()))
+    }
+
+    fn sessions(&self) -> Result<&SessionStore, AppError> {
+        self.sessions
+            .as_ref()
+            .ok_or_else(|| AppError::ConfigError("Sessions are not enabled".into()))
+    }
+
+    fn templates(&self) -> Result<&TemplateLibrary, AppError> {
+        self.templates
+            .as_ref()
+            .ok_or_else(|| AppError::ConfigError("Prompt templates are not enabled".into()))
+    }
+}
+
+/// Body of `POST /api/v1/jobs`: a generation request plus an optional callback.
+#[derive(Debug, Deserialize)]
+struct JobSubmission {
+    #[serde(flatten)]
+    request: AIRequest,
+    #[serde(default)]
+    webhook_url: Option<String>,
+}
+
+/// Body of `POST /api/v1/sessions`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NewSession {
+    name: Option<String>,
+    provider: Option<String>,
+    model: Option<String>,
+    workload: Option<Workload>,
+}
+
+/// Response of `GET /api/v1/sessions/{id}`.
+#[derive(Debug, Serialize)]
+struct SessionTranscript {
+    #[serde(flatten)]
+    session: Session,
+    messages: Vec<SessionMessage>,
+}
+
+/// Response of `POST /api/v1/sessions/{id}/messages`.
+#[derive(Debug, Serialize)]
+struct SessionReply {
+    session_id: String,
+    #[serde(flatten)]
+    response: AIResponse,
+}
+
+/// Body of `POST /api/v1/templates/{name}/generate`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TemplateInvocation {
+    variables: HashMap<String, String>,
+    hints: RequestHints,
+    model: Option<String>,
+    metadata: HashMap<String, String>,
 }
 
 /// Creates the main API router for the application.
 pub fn api_router(state: AppState) -> Router {
     Router::new()
         .route("/api/v1/generate", post(generate_handler))
+        .route("/api/v1/jobs", post(submit_job_handler))
+        .route("/api/v1/jobs/:id", get(job_status_handler))
+        .route("/api/v1/templates", get(list_templates_handler))
+        .route(
+            "/api/v1/templates/:name/generate",
+            post(template_generate_handler),
+        )
+        .route("/api/v1/sessions", post(create_session_handler))
+        .route("/api/v1/sessions/:id", get(session_handler))
+        .route(
+            "/api/v1/sessions/:id/messages",
+            post(session_message_handler),
+        )
         .with_state(state)
 }
 
@@ -41,7 +155,153 @@ async fn generate_handler(
 ) -> Result<Json<AIResponse>, AppError> {
     tracing::info!(model = %payload.model, tags = ?payload.tags, "Received generation request");
 
+    reject_local_images(&payload)?;
+
     let response = state.provider_router().generate(&payload).await?;
 
     Ok(Json(response))
 }
+
+/// Handler for `POST /api/v1/jobs`: enqueues the request and returns at once.
+async fn submit_job_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<JobSubmission>,
+) -> Result<(StatusCode, Json<Job>), AppError> {
+    tracing::info!(model = %payload.request.model, tags = ?payload.request.tags, "Received job submission");
+
+    reject_local_images(&payload.request)?;
+
+    let job = state
+        .jobs()?
+        .submit(&payload.request, payload.webhook_url.as_deref())
+        .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// Handler for `GET /api/v1/jobs/{id}`.
+async fn job_status_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    let job = state
+        .jobs()?
+        .store()
+        .get(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("job '{id}'")))?;
+
+    Ok(Json(job))
+}
+
+/// Handler for `GET /api/v1/templates`.
+async fn list_templates_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<PromptTemplate>>, AppError> {
+    Ok(Json(state.templates()?.list()?))
+}
+
+/// Handler for `POST /api/v1/templates/{name}/generate`: renders the
+/// template with the supplied variables and runs it like `/generate`.
+async fn template_generate_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<TemplateInvocation>,
+) -> Result<Json<AIResponse>, AppError> {
+    tracing::info!(template = %name, "Received template generation request");
+
+    let template = state.templates()?.load(&name)?;
+    let mut request = template.to_request(&payload.variables, &payload.hints)?;
+    if let Some(model) = payload.model {
+        request.model = model;
+    }
+    request.metadata = payload.metadata;
+
+    let response = state.provider_router().generate(&request).await?;
+
+    Ok(Json(response))
+}
+
+/// Handler for `POST /api/v1/sessions`.
+async fn create_session_handler(
+    State(state): State<AppState>,
+    payload: Option<Json<NewSession>>,
+) -> Result<(StatusCode, Json<Session>), AppError> {
+    let Json(payload) = payload.unwrap_or_default();
+    if let Some(provider) = payload.provider.as_deref() {
+        if Provider::from_alias(provider).is_none() {
+            return Err(AppError::InvalidRequest(format!(
+                "Unknown provider '{provider}'"
+            )));
+        }
+    }
+
+    let sessions = state.sessions()?;
+    let session = sessions
+        .create(
+            payload.name.as_deref(),
+            payload.provider.as_deref(),
+            payload.model.as_deref(),
+            payload.workload.map(Workload::as_str),
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+/// Handler for `GET /api/v1/sessions/{id}`: the session and its turns.
+async fn session_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SessionTranscript>, AppError> {
+    let sessions = state.sessions()?;
+    let session = find_session(sessions, &id).await?;
+    let messages = sessions.messages(&session.id).await?;
+
+    Ok(Json(SessionTranscript { session, messages }))
+}
+
+/// Handler for `POST /api/v1/sessions/{id}/messages`: the body is a
+/// generation request whose `prompt` is the new user turn.
+async fn session_message_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<AIRequest>,
+) -> Result<Json<SessionReply>, AppError> {
+    reject_local_images(&payload)?;
+
+    let sessions = state.sessions()?;
+    let session = find_session(sessions, &id).await?;
+    let prompt = payload.prompt.clone();
+    let request = sessions.prepare_turn(&session, payload).await?;
+    tracing::info!(session = %session.id, turns = request.history.len(), "Received session message");
+
+    let response = state.provider_router().generate(&request).await?;
+    sessions.record_turn(&session, &prompt, &response).await?;
+
+    Ok(Json(SessionReply {
+        session_id: session.id,
+        response,
+    }))
+}
+
+async fn find_session(sessions: &SessionStore, id: &str) -> Result<Session, AppError> {
+    sessions
+        .find(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("session '{id}'")))
+}
+
+/// Never let HTTP clients read files from the server's filesystem.
+fn reject_local_images(request: &AIRequest) -> Result<(), AppError> {
+    if request
+        .images
+        .iter()
+        .any(|image| matches!(image.source, ImageSource::Path(_)))
+    {
+        return Err(AppError::InvalidRequest(
+            "images must be provided as a URL or base64 data".into(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,404 @@
+//! Persistent conversation sessions.
+//!
+//! A session is an ordered list of chat turns plus the routing choices that
+//! were active for it (provider, model, workload). Sessions back both the
+//! `freegin-ai chat` REPL and the HTTP session endpoints, so a conversation
+//! can be resumed after the process exits.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{sqlite::SqliteRow, Row};
+
+use crate::{
+    database::{DbError, DbPool},
+    error::AppError,
+    models::{AIRequest, AIResponse, ChatMessage, ChatRole, Workload},
+};
+
+/// Metadata of a stored conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    /// Opaque session identifier.
+    pub id: String,
+    /// Optional human-friendly name (unique when set).
+    pub name: Option<String>,
+    /// Provider pinned for the session, if any.
+    pub provider: Option<String>,
+    /// Model pinned for the session, if any.
+    pub model: Option<String>,
+    /// Workload hint for the session, if any.
+    pub workload: Option<String>,
+    /// RFC 3339 creation timestamp.
+    pub created_at: String,
+    /// RFC 3339 timestamp of the last change.
+    pub updated_at: String,
+}
+
+/// A stored conversation turn.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionMessage {
+    /// Who produced the message.
+    pub role: ChatRole,
+    /// Message text.
+    pub content: String,
+    /// Provider that produced an assistant turn.
+    pub provider: Option<String>,
+    /// Model that produced an assistant turn.
+    pub model: Option<String>,
+    /// RFC 3339 creation timestamp.
+    pub created_at: String,
+}
+
+impl From<&SessionMessage> for ChatMessage {
+    fn from(message: &SessionMessage) -> Self {
+        Self {
+            role: message.role,
+            content: message.content.clone(),
+        }
+    }
+}
+
+impl Session {
+    /// Returns the stored workload hint, if it is a known workload.
+    pub fn workload_hint(&self) -> Option<Workload> {
+        self.workload.as_deref().and_then(Workload::from_key)
+    }
+}
+
+/// Persistence for conversation sessions.
+#[derive(Clone, Debug)]
+pub struct SessionStore {
+    pool: Arc<DbPool>,
+}
+
+const SESSION_COLUMNS: &str = "id, name, provider, model, workload, created_at, updated_at";
+
+impl SessionStore {
+    /// Creates a new session store backed by the `SQLite` pool.
+    #[must_use]
+    pub const fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Creates an empty session with the given name and routing choices.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`AppError::Conflict`] when another session has `name`, or
+    /// when the query fails.
+    pub async fn create(
+        &self,
+        name: Option<&str>,
+        provider: Option<&str>,
+        model: Option<&str>,
+        workload: Option<&str>,
+    ) -> Result<Session, AppError> {
+        let id = format!("{:032x}", rand::random::<u128>());
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r"INSERT INTO chat_sessions
+               (id, name, provider, model, workload, created_at, updated_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(name)
+        .bind(provider)
+        .bind(model)
+        .bind(workload)
+        .bind(&now)
+        .bind(&now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| name_error(err, name.unwrap_or_default()))?;
+        let _ = result.rows_affected();
+
+        Ok(Session {
+            id,
+            name: name.map(str::to_string),
+            provider: provider.map(str::to_string),
+            model: model.map(str::to_string),
+            workload: workload.map(str::to_string),
+            created_at: now.clone(),
+            updated_at: now,
+        })
+    }
+
+    /// Looks up a session by identifier or name.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn find(&self, key: &str) -> Result<Option<Session>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {SESSION_COLUMNS} FROM chat_sessions WHERE id = ? OR name = ? LIMIT 1"
+        ))
+        .bind(key)
+        .bind(key)
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(row.as_ref().map(session_from_row))
+    }
+
+    /// Lists sessions, most recently updated first.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn list(&self, limit: i64) -> Result<Vec<Session>, AppError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {SESSION_COLUMNS} FROM chat_sessions ORDER BY updated_at DESC LIMIT ?"
+        ))
+        .bind(limit)
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(rows.iter().map(session_from_row).collect())
+    }
+
+    /// Names a session.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`AppError::Conflict`] when another session has `name`, or
+    /// when the query fails.
+    pub async fn rename(&self, id: &str, name: &str) -> Result<(), AppError> {
+        let result = sqlx::query("UPDATE chat_sessions SET name = ?, updated_at = ? WHERE id = ?")
+            .bind(name)
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| name_error(err, name))?;
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Stores the routing choices of a session.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the update fails.
+    pub async fn set_routing(
+        &self,
+        id: &str,
+        provider: Option<&str>,
+        model: Option<&str>,
+        workload: Option<&str>,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"UPDATE chat_sessions
+               SET provider = ?, model = ?, workload = ?, updated_at = ?
+               WHERE id = ?",
+        )
+        .bind(provider)
+        .bind(model)
+        .bind(workload)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Appends a turn to a session.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the insert fails.
+    pub async fn append(
+        &self,
+        id: &str,
+        role: ChatRole,
+        content: &str,
+        provider: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<(), AppError> {
+        let now = Utc::now().to_rfc3339();
+        let result = sqlx::query(
+            r"INSERT INTO chat_messages (session_id, role, content, provider, model, created_at)
+               VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(role.as_str())
+        .bind(content)
+        .bind(provider)
+        .bind(model)
+        .bind(&now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+
+        let result = sqlx::query("UPDATE chat_sessions SET updated_at = ? WHERE id = ?")
+            .bind(&now)
+            .bind(id)
+            .execute(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Builds the request for the next turn of `session`: the stored history
+    /// plus the session's routing, unless the caller overrides it.
+    ///
+    /// The session provider goes in as a hint, so the router keeps using it
+    /// while it answers but still falls back when it fails.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the session history cannot be loaded.
+    pub async fn prepare_turn(
+        &self,
+        session: &Session,
+        mut request: AIRequest,
+    ) -> Result<AIRequest, AppError> {
+        request.history = self
+            .messages(&session.id)
+            .await?
+            .iter()
+            .map(ChatMessage::from)
+            .collect();
+        if request.hints.provider.is_none() {
+            request.hints.provider.clone_from(&session.provider);
+        }
+        if request.model.is_empty() {
+            if let Some(model) = &session.model {
+                request.model.clone_from(model);
+            }
+        }
+        if request.hints.workload.is_none() {
+            request.hints.workload = session.workload_hint();
+        }
+        Ok(request)
+    }
+
+    /// Stores a completed turn and makes the answering provider sticky.
+    ///
+    /// Both turns and the routing update are written in one transaction, so
+    /// a concurrent turn on the same session cannot land between them. When
+    /// the answering provider differs from the session's, the model that
+    /// answered replaces the session model, which belonged to the old one.
+    ///
+    /// # Errors
+    ///
+    /// Fails when either turn or the routing cannot be stored.
+    pub async fn record_turn(
+        &self,
+        session: &Session,
+        prompt: &str,
+        response: &AIResponse,
+    ) -> Result<(), AppError> {
+        let now = Utc::now().to_rfc3339();
+        let provider = response.provider.as_str();
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        for (role, content, provider, model) in [
+            (ChatRole::User, prompt, None, None),
+            (
+                ChatRole::Assistant,
+                response.content.as_str(),
+                Some(provider),
+                response.model.as_deref(),
+            ),
+        ] {
+            let result = sqlx::query(
+                r"INSERT INTO chat_messages (session_id, role, content, provider, model, created_at)
+                   VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&session.id)
+            .bind(role.as_str())
+            .bind(content)
+            .bind(provider)
+            .bind(model)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+            let _ = result.rows_affected();
+        }
+        let result = sqlx::query(
+            r"UPDATE chat_sessions
+               SET model = CASE WHEN provider IS ? THEN model ELSE ? END,
+                   provider = ?, updated_at = ?
+               WHERE id = ?",
+        )
+        .bind(provider)
+        .bind(response.model.as_deref())
+        .bind(provider)
+        .bind(&now)
+        .bind(&session.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        tx.commit()
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))
+    }
+
+    /// Returns the turns of a session, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn messages(&self, id: &str) -> Result<Vec<SessionMessage>, AppError> {
+        let rows = sqlx::query(
+            r"SELECT role, content, provider, model, created_at
+               FROM chat_messages WHERE session_id = ? ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        rows.iter()
+            .map(|row| {
+                let role: String = row.get("role");
+                Ok(SessionMessage {
+                    role: ChatRole::from_key(&role).ok_or_else(|| {
+                        AppError::ApiError(format!("Unknown chat role '{role}' in session {id}"))
+                    })?,
+                    content: row.get("content"),
+                    provider: row.get("provider"),
+                    model: row.get("model"),
+                    created_at: row.get("created_at"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Maps a violation of the unique session name to a conflict.
+fn name_error(err: sqlx::Error, name: &str) -> AppError {
+    if err
+        .as_database_error()
+        .is_some_and(sqlx::error::DatabaseError::is_unique_violation)
+    {
+        AppError::Conflict(format!("A session named '{name}' already exists"))
+    } else {
+        AppError::DatabaseError(DbError::QueryFailed(err))
+    }
+}
+
+fn session_from_row(row: &SqliteRow) -> Session {
+    Session {
+        id: row.get("id"),
+        name: row.get("name"),
+        provider: row.get("provider"),
+        model: row.get("model"),
+        workload: row.get("workload"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
@@ -0,0 +1,201 @@
+//! Shadow traffic for suggested models on trial.
+//!
+//! While a suggestion has the `trial` status, the router copies a share of
+//! the requests for its workload to the trial model after the active model
+//! has answered. The shadow answer is never returned to the caller; only
+//! its outcome is logged next to the answer that was returned, so
+//! `freegin-ai trial-report` can compare the two before a model is adopted.
+//! Prompts and outputs are not stored; use the audit log for full text.
+
+use std::{collections::HashSet, sync::Arc};
+
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::{
+    database::{DbError, DbPool},
+    error::AppError,
+    models::Workload,
+    providers::Provider,
+};
+
+/// Outcome of one shadow request and the answer it shadowed.
+#[derive(Debug, Clone)]
+pub struct ShadowRecord {
+    /// Provider of the trial model.
+    pub provider: Provider,
+    /// Workload of the request.
+    pub workload: Workload,
+    /// Trial model.
+    pub model: String,
+    /// Provider that answered the caller.
+    pub primary_provider: Provider,
+    /// Model that answered the caller.
+    pub primary_model: Option<String>,
+    /// Whether the trial model answered.
+    pub success: bool,
+    /// Trial model latency in milliseconds.
+    pub latency_ms: i64,
+    /// Latency of the answer returned to the caller.
+    pub primary_latency_ms: i64,
+    /// Length of the trial answer in characters.
+    pub output_chars: i64,
+    /// Length of the returned answer in characters.
+    pub primary_chars: i64,
+    /// Word overlap between the two answers (0-1), when the trial answered.
+    pub similarity: Option<f64>,
+    /// Error message when the trial model failed.
+    pub error: Option<String>,
+}
+
+/// Aggregated shadow results of a trial model.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrialSummary {
+    /// Provider of the trial model.
+    pub provider: String,
+    /// Workload of the trial.
+    pub workload: String,
+    /// Trial model.
+    pub model: String,
+    /// Shadow requests sent.
+    pub requests: i64,
+    /// Share of shadow requests that succeeded.
+    pub success_rate: f64,
+    /// Mean latency of the trial model.
+    pub avg_latency_ms: f64,
+    /// Mean latency of the answers returned to callers.
+    pub avg_primary_latency_ms: f64,
+    /// Mean trial answer length relative to the returned answer.
+    pub length_ratio: Option<f64>,
+    /// Mean word overlap with the returned answers.
+    pub avg_similarity: Option<f64>,
+}
+
+/// Persistence for shadow results.
+#[derive(Clone, Debug)]
+pub struct ShadowLog {
+    pool: Arc<DbPool>,
+}
+
+impl ShadowLog {
+    /// Creates a new shadow log backed by the `SQLite` pool.
+    #[must_use]
+    pub const fn new(pool: Arc<DbPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Stores one shadow result.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the insert fails.
+    pub async fn record(&self, record: &ShadowRecord) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"INSERT INTO shadow_results
+               (provider, workload, model, primary_provider, primary_model, success, latency_ms,
+                primary_latency_ms, output_chars, primary_chars, similarity, error, created_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(record.provider.as_str())
+        .bind(record.workload.as_str())
+        .bind(&record.model)
+        .bind(record.primary_provider.as_str())
+        .bind(&record.primary_model)
+        .bind(i32::from(record.success))
+        .bind(record.latency_ms)
+        .bind(record.primary_latency_ms)
+        .bind(record.output_chars)
+        .bind(record.primary_chars)
+        .bind(record.similarity)
+        .bind(&record.error)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+        let _ = result.rows_affected();
+        Ok(())
+    }
+
+    /// Summarises shadow results per trial model, optionally for one provider.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the query fails.
+    pub async fn summaries(
+        &self,
+        provider: Option<Provider>,
+    ) -> Result<Vec<TrialSummary>, AppError> {
+        let mut sql = String::from(
+            r"SELECT provider, workload, model,
+                      COUNT(*) AS requests,
+                      AVG(success) AS success_rate,
+                      AVG(latency_ms) AS avg_latency_ms,
+                      AVG(primary_latency_ms) AS avg_primary_latency_ms,
+                      AVG(CASE WHEN success = 1 AND primary_chars > 0
+                               THEN CAST(output_chars AS REAL) / primary_chars END) AS length_ratio,
+                      AVG(similarity) AS avg_similarity
+               FROM shadow_results",
+        );
+        if provider.is_some() {
+            sql.push_str(" WHERE provider = ?");
+        }
+        sql.push_str(" GROUP BY provider, workload, model ORDER BY provider, workload, model");
+
+        let mut query = sqlx::query(&sql);
+        if let Some(provider) = provider {
+            query = query.bind(provider.as_str());
+        }
+        let rows = query
+            .fetch_all(&*self.pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TrialSummary {
+                provider: row.get("provider"),
+                workload: row.get("workload"),
+                model: row.get("model"),
+                requests: row.get("requests"),
+                success_rate: row.get("success_rate"),
+                avg_latency_ms: row.get("avg_latency_ms"),
+                avg_primary_latency_ms: row.get("avg_primary_latency_ms"),
+                length_ratio: row.get("length_ratio"),
+                avg_similarity: row.get("avg_similarity"),
+            })
+            .collect())
+    }
+}
+
+/// Jaccard overlap of the lowercase words of two answers.
+#[must_use]
+// Word counts of an answer stay far below 2^53, past which `f64` loses precision.
+#[allow(clippy::cast_precision_loss)]
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(&b).count();
+    let total = a.union(&b).count();
+    shared as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_is_word_overlap() {
+        assert!((similarity("The cat sat.", "the CAT sat") - 1.0).abs() < f64::EPSILON);
+        assert!((similarity("a b", "b c") - 1.0 / 3.0).abs() < 1e-9);
+        assert!(similarity("yes", "no").abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,361 @@
+//! Structured (JSON) output helpers used when a request asks for JSON.
+//!
+//! Providers are asked to use their native JSON mode where one exists, but
+//! the returned content is still untrusted: models wrap JSON in Markdown
+//! fences, add commentary, or ignore the schema. This module extracts the
+//! JSON document from a response and validates it against the subset of
+//! JSON Schema that request authors typically rely on.
+
+use serde_json::{Map, Value};
+
+/// Builds the instruction appended to prompts that require JSON output.
+#[must_use]
+pub fn json_instruction(schema: Option<&Value>) -> String {
+    let base = "Respond with a single valid JSON document only, with no surrounding text.";
+    schema.map_or_else(
+        || base.to_string(),
+        |schema| format!("{base} The JSON must conform to this JSON Schema:\n{schema}"),
+    )
+}
+
+/// Checks that a caller-supplied schema is usable before any provider is called.
+///
+/// # Errors
+///
+/// Returns the reason when the schema is neither an object nor a boolean.
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    match schema {
+        Value::Object(_) | Value::Bool(_) => Ok(()),
+        _ => Err("JSON schema must be an object or a boolean".to_string()),
+    }
+}
+
+/// Extracts a JSON document from model output and validates it against the schema.
+///
+/// # Errors
+///
+/// Returns the reason when no JSON can be extracted or the value does not match
+/// the schema.
+pub fn parse_and_validate(content: &str, schema: Option<&Value>) -> Result<Value, String> {
+    let value = extract_json(content)?;
+    if let Some(schema) = schema {
+        validate(&value, schema)?;
+    }
+    Ok(value)
+}
+
+/// Parses JSON from raw model output, tolerating Markdown code fences and
+/// leading or trailing prose around a single object or array.
+///
+/// # Errors
+///
+/// Returns the parser's message when no JSON document can be found.
+pub fn extract_json(content: &str) -> Result<Value, String> {
+    let trimmed = strip_code_fence(content.trim());
+    if let Ok(value) = serde_json::from_str::<Value>(trimmed) {
+        return Ok(value);
+    }
+
+    // Fall back to the outermost object/array embedded in surrounding text.
+    for (open, close) in [('{', '}'), ('[', ']')] {
+        if let (Some(start), Some(end)) = (trimmed.find(open), trimmed.rfind(close)) {
+            if start < end {
+                if let Ok(value) = serde_json::from_str::<Value>(&trimmed[start..=end]) {
+                    return Ok(value);
+                }
+            }
+        }
+    }
+
+    serde_json::from_str::<Value>(trimmed)
+        .map_err(|err| format!("response is not valid JSON: {err}"))
+}
+
+fn strip_code_fence(content: &str) -> &str {
+    let Some(rest) = content.strip_prefix("```") else {
+        return content;
+    };
+    // Skip an optional language tag on the opening fence line.
+    let body = rest.split_once('\n').map_or(rest, |(_, body)| body);
+    body.trim_end()
+        .strip_suffix("```")
+        .map_or(body, str::trim_end)
+}
+
+/// Validates a JSON value against a JSON Schema.
+///
+/// Supports `type`, `enum`, `const`, `properties`, `required`,
+/// `additionalProperties`, `items`, `minItems`/`maxItems`,
+/// `minLength`/`maxLength`, `minimum`/`maximum`, `allOf`, `anyOf` and `oneOf`.
+/// Unknown keywords are ignored, matching the permissive behaviour of most
+/// validators for annotations.
+///
+/// # Errors
+///
+/// Returns the path and reason of the first mismatch.
+pub fn validate(value: &Value, schema: &Value) -> Result<(), String> {
+    validate_at(value, schema, "$")
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(false) => return Err(format!("{path}: no value is allowed here")),
+        Value::Object(map) => map,
+        _ => return Ok(()),
+    };
+
+    if let Some(expected) = schema.get("type") {
+        check_type(value, expected, path)?;
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            return Err(format!(
+                "{path}: value {value} is not one of {}",
+                Value::Array(options.clone())
+            ));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!(
+                "{path}: expected constant {expected}, found {value}"
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(object, schema, path)?,
+        Value::Array(items) => validate_array(items, schema, path)?,
+        Value::String(text) => validate_string(text, schema, path)?,
+        Value::Number(number) => {
+            if let Some(number) = number.as_f64() {
+                validate_number(number, schema, path)?;
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for sub in schemas {
+            validate_at(value, sub, path)?;
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+        if !schemas
+            .iter()
+            .any(|sub| validate_at(value, sub, path).is_ok())
+        {
+            return Err(format!("{path}: value does not match any allowed schema"));
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+        let matches = schemas
+            .iter()
+            .filter(|sub| validate_at(value, sub, path).is_ok())
+            .count();
+        if matches != 1 {
+            return Err(format!(
+                "{path}: value must match exactly one schema, matched {matches}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_type(value: &Value, expected: &Value, path: &str) -> Result<(), String> {
+    let allowed: Vec<&str> = match expected {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => return Ok(()),
+    };
+
+    if allowed.iter().any(|name| type_matches(value, name)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{path}: expected {}, found {}",
+            allowed.join(" or "),
+            type_name(value)
+        ))
+    }
+}
+
+fn type_matches(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().is_some_and(|number| number.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+        Value::Number(_) => "number",
+    }
+}
+
+fn validate_object(
+    object: &Map<String, Value>,
+    schema: &Map<String, Value>,
+    path: &str,
+) -> Result<(), String> {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                return Err(format!("{path}: missing required property '{key}'"));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, child) in object {
+        let child_path = format!("{path}.{key}");
+        if let Some(child_schema) = properties.and_then(|props| props.get(key)) {
+            validate_at(child, child_schema, &child_path)?;
+        } else if let Some(additional) = schema.get("additionalProperties") {
+            if matches!(additional, Value::Bool(false)) {
+                return Err(format!("{path}: unexpected property '{key}'"));
+            }
+            validate_at(child, additional, &child_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_array(items: &[Value], schema: &Map<String, Value>, path: &str) -> Result<(), String> {
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if (items.len() as u64) < min {
+            return Err(format!(
+                "{path}: expected at least {min} items, found {}",
+                items.len()
+            ));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if (items.len() as u64) > max {
+            return Err(format!(
+                "{path}: expected at most {max} items, found {}",
+                items.len()
+            ));
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        for (idx, item) in items.iter().enumerate() {
+            validate_at(item, item_schema, &format!("{path}[{idx}]"))?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_string(text: &str, schema: &Map<String, Value>, path: &str) -> Result<(), String> {
+    let length = text.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            return Err(format!("{path}: string shorter than {min} characters"));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            return Err(format!("{path}: string longer than {max} characters"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_number(number: f64, schema: &Map<String, Value>, path: &str) -> Result<(), String> {
+    if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+        if number < min {
+            return Err(format!("{path}: {number} is less than minimum {min}"));
+        }
+    }
+    if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+        if number > max {
+            return Err(format!("{path}: {number} is greater than maximum {max}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_fenced_json() {
+        let content = "```json\n{\"answer\": 42}\n```";
+        assert_eq!(extract_json(content), Ok(json!({"answer": 42})));
+    }
+
+    #[test]
+    fn extracts_json_surrounded_by_prose() {
+        let content = "Sure! Here it is: {\"ok\": true} Let me know if you need more.";
+        assert_eq!(extract_json(content), Ok(json!({"ok": true})));
+    }
+
+    #[test]
+    fn rejects_non_json() {
+        assert!(extract_json("definitely not json").is_err());
+    }
+
+    #[test]
+    fn validates_required_and_types() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "tags"],
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "score": {"type": "integer", "minimum": 0, "maximum": 10}
+            },
+            "additionalProperties": false
+        });
+
+        assert!(validate(&json!({"name": "a", "tags": ["x"], "score": 3}), &schema).is_ok());
+        assert!(validate(&json!({"name": "a"}), &schema)
+            .unwrap_err()
+            .contains("missing required property 'tags'"));
+        assert!(validate(&json!({"name": "a", "tags": [1]}), &schema)
+            .unwrap_err()
+            .contains("$.tags[0]"));
+        assert!(
+            validate(&json!({"name": "a", "tags": [], "extra": 1}), &schema)
+                .unwrap_err()
+                .contains("unexpected property 'extra'")
+        );
+        assert!(validate(&json!({"name": "a", "tags": [], "score": 11}), &schema).is_err());
+    }
+
+    #[test]
+    fn validates_enum_and_any_of() {
+        let schema = json!({
+            "anyOf": [
+                {"type": "string", "enum": ["yes", "no"]},
+                {"type": "null"}
+            ]
+        });
+        assert!(validate(&json!("yes"), &schema).is_ok());
+        assert!(validate(&Value::Null, &schema).is_ok());
+        assert!(validate(&json!("maybe"), &schema).is_err());
+    }
+}
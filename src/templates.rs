@@ -0,0 +1,352 @@
+//! Named prompt templates with variable substitution.
+//!
+//! Templates are TOML files in the template directory, one per template,
+//! named `<name>.toml`:
+//!
+//! ```toml
+//! description = "Summarize a pull request"
+//! prompt = """
+//! Summarize this diff for a {{audience}} reader:
+//!
+//! {{diff}}
+//! """
+//!
+//! [defaults]
+//! audience = "technical"
+//!
+//! [hints]
+//! workload = "summarization"
+//! speed = "fast"
+//! ```
+//!
+//! `{{name}}` placeholders are replaced in a single pass, so values are
+//! inserted verbatim even if they contain braces themselves.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    config::TemplatesConfig,
+    error::AppError,
+    models::{AIRequest, RequestHints},
+};
+
+/// A reusable prompt with placeholders and default routing hints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    /// Template name (the file stem).
+    #[serde(default)]
+    pub name: String,
+    /// One-line description shown in listings.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Prompt text containing `{{variable}}` placeholders.
+    pub prompt: String,
+    /// Model to use unless the caller overrides it.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Default routing hints (workload, quality, provider, ...).
+    #[serde(default)]
+    pub hints: RequestHints,
+    /// Default values for variables the caller may omit.
+    #[serde(default)]
+    pub defaults: HashMap<String, String>,
+}
+
+impl PromptTemplate {
+    /// Returns the placeholder names used in the prompt, sorted.
+    pub fn variables(&self) -> Vec<String> {
+        PLACEHOLDER
+            .captures_iter(&self.prompt)
+            .map(|caps| caps[1].to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Substitutes `vars` (falling back to the template defaults) into the prompt.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a placeholder has no value or a variable is not used by
+    /// the template, which usually means a typo.
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<String, AppError> {
+        let used = self.variables();
+        let unknown: Vec<&str> = vars
+            .keys()
+            .filter(|key| !used.contains(key))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(AppError::InvalidRequest(format!(
+                "Template '{}' has no variable(s) {}",
+                self.name,
+                unknown.join(", ")
+            )));
+        }
+        let missing: Vec<&str> = used
+            .iter()
+            .filter(|key| !vars.contains_key(*key) && !self.defaults.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::InvalidRequest(format!(
+                "Template '{}' requires variable(s) {}",
+                self.name,
+                missing.join(", ")
+            )));
+        }
+
+        Ok(PLACEHOLDER
+            .replace_all(&self.prompt, |caps: &regex::Captures<'_>| {
+                vars.get(&caps[1])
+                    .or_else(|| self.defaults.get(&caps[1]))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .into_owned())
+    }
+
+    /// Builds a request from the template. Hints set by the caller take
+    /// precedence over the template's defaults, field by field.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the template cannot be rendered with `vars`.
+    pub fn to_request(
+        &self,
+        vars: &HashMap<String, String>,
+        hints: &RequestHints,
+    ) -> Result<AIRequest, AppError> {
+        Ok(AIRequest {
+            model: self.model.clone().unwrap_or_default(),
+            prompt: self.render(vars)?,
+            hints: merge_hints(hints, &self.hints),
+            tags: vec![format!("template:{}", self.name)],
+            ..AIRequest::default()
+        })
+    }
+}
+
+/// Returns `primary` with unset fields taken from `fallback`.
+fn merge_hints(primary: &RequestHints, fallback: &RequestHints) -> RequestHints {
+    RequestHints {
+        complexity: primary.complexity.or(fallback.complexity),
+        quality: primary.quality.or(fallback.quality),
+        speed: primary.speed.or(fallback.speed),
+        guardrail: primary.guardrail.or(fallback.guardrail),
+        response_format: primary.response_format.or(fallback.response_format),
+        provider: primary
+            .provider
+            .clone()
+            .or_else(|| fallback.provider.clone()),
+        workload: primary.workload.or(fallback.workload),
+    }
+}
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z0-9_.-]+)\s*\}\}").expect("placeholder pattern is valid")
+});
+
+/// Directory of prompt templates.
+#[derive(Debug, Clone)]
+pub struct TemplateLibrary {
+    dir: PathBuf,
+}
+
+impl TemplateLibrary {
+    /// Creates a library reading templates from `dir`.
+    #[must_use]
+    pub const fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Uses the configured directory, or `~/.config/freegin-ai/templates`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when no directory is configured and the config directory cannot be
+    /// determined.
+    pub fn from_config(config: &TemplatesConfig) -> Result<Self, AppError> {
+        let dir = match &config.dir {
+            Some(dir) => dir.clone(),
+            None => dirs::config_dir()
+                .map(|dir| dir.join("freegin-ai").join("templates"))
+                .ok_or_else(|| {
+                    AppError::ConfigError("Unable to determine config directory".into())
+                })?,
+        };
+        Ok(Self::new(dir))
+    }
+
+    /// Returns the template directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Loads a template by name.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the name is invalid or the template is missing, unreadable or
+    /// malformed.
+    pub fn load(&self, name: &str) -> Result<PromptTemplate, AppError> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(AppError::InvalidRequest(format!(
+                "Invalid template name '{name}'"
+            )));
+        }
+        let path = self.dir.join(format!("{name}.toml"));
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(AppError::NotFound(format!("template '{name}'")));
+            }
+            Err(err) => {
+                return Err(AppError::ConfigError(format!(
+                    "Failed to read template {}: {err}",
+                    path.display()
+                )));
+            }
+        };
+        parse_template(name, &raw, &path)
+    }
+
+    /// Loads every template in the directory, sorted by name.
+    ///
+    /// Files that cannot be read or parsed, or whose name is not a valid
+    /// template name, are skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the directory exists but cannot be read.
+    pub fn list(&self) -> Result<Vec<PromptTemplate>, AppError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(AppError::ConfigError(format!(
+                    "Failed to read template directory {}: {err}",
+                    self.dir.display()
+                )));
+            }
+        };
+
+        let mut templates = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                warn!(path = %path.display(), "Skipping template with a non UTF-8 name");
+                continue;
+            };
+            match self.load(name) {
+                Ok(template) => templates.push(template),
+                Err(err) => warn!(path = %path.display(), error = %err, "Skipping template"),
+            }
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+}
+
+fn parse_template(name: &str, raw: &str, path: &Path) -> Result<PromptTemplate, AppError> {
+    let mut template: PromptTemplate = toml::from_str(raw).map_err(|err| {
+        AppError::ConfigError(format!("Invalid template {}: {err}", path.display()))
+    })?;
+    template.name = name.to_string();
+    Ok(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Workload;
+
+    fn template() -> PromptTemplate {
+        parse_template(
+            "summarize-pr",
+            r#"
+prompt = "Summarize for {{ audience }}:\n{{diff}}"
+
+[defaults]
+audience = "engineers"
+
+[hints]
+workload = "summarization"
+"#,
+            Path::new("summarize-pr.toml"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_variables_and_defaults() {
+        let template = template();
+        assert_eq!(template.variables(), vec!["audience", "diff"]);
+
+        let vars = HashMap::from([("diff".to_string(), "+ {{audience}}".to_string())]);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Summarize for engineers:\n+ {{audience}}"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_unknown_variables() {
+        let template = template();
+        assert!(template.render(&HashMap::new()).is_err());
+
+        let vars = HashMap::from([
+            ("diff".to_string(), "x".to_string()),
+            ("dif".to_string(), "x".to_string()),
+        ]);
+        assert!(template.render(&vars).is_err());
+    }
+
+    #[test]
+    fn caller_hints_override_template_defaults() {
+        let template = template();
+        let vars = HashMap::from([("diff".to_string(), "x".to_string())]);
+        let hints = RequestHints {
+            provider: Some("groq".into()),
+            ..RequestHints::default()
+        };
+        let request = template.to_request(&vars, &hints).unwrap();
+        assert_eq!(request.hints.workload, Some(Workload::Summarization));
+        assert_eq!(request.hints.provider.as_deref(), Some("groq"));
+        assert_eq!(request.tags, vec!["template:summarize-pr"]);
+    }
+
+    #[test]
+    fn listing_skips_unusable_templates() {
+        let dir = std::env::temp_dir().join(format!("freegin-templates-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("greet.toml"), "prompt = \"Hello {{name}}\"").unwrap();
+        fs::write(dir.join("broken.toml"), "prompt = ").unwrap();
+        fs::write(dir.join("v1.2.toml"), "prompt = \"Dotted\"").unwrap();
+        fs::write(dir.join("notes.txt"), "not a template").unwrap();
+
+        let templates = TemplateLibrary::new(dir.clone()).list().unwrap();
+        let names: Vec<_> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["greet"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -5,8 +5,10 @@ use std::sync::Arc;
 use chrono::Utc;
 
 use crate::{
+    budget::CallCost,
     database::{DbError, DbPool},
     error::AppError,
+    models::Workload,
     providers::Provider,
 };
 
@@ -27,11 +29,13 @@ impl UsageLogger {
         Arc::clone(&self.pool)
     }
 
-    /// Persists a usage record.
+    /// Persists a usage record. `workload` is the request's workload, given
+    /// or inferred.
     pub async fn log(
         &self,
         provider: Provider,
         model: Option<&str>,
+        workload: Option<Workload>,
         success: bool,
         latency_ms: i64,
         error_message: Option<String>,
@@ -40,11 +44,12 @@ impl UsageLogger {
         let success_flag = i32::from(success);
 
         let result = sqlx::query(
-            r#"INSERT INTO provider_usage (provider, model, success, latency_ms, error_message, created_at)
-               VALUES (?, ?, ?, ?, ?, ?)"#,
+            r"INSERT INTO provider_usage (provider, model, workload, success, latency_ms, error_message, created_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(provider.as_str())
         .bind(model)
+        .bind(workload.map(Workload::as_str))
         .bind(success_flag)
         .bind(latency_ms)
         .bind(error_message)
@@ -57,4 +62,46 @@ impl UsageLogger {
 
         Ok(())
     }
+
+    /// Persists a successful call together with its estimated tokens and
+    /// cost, which spend caps are checked against.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the insert fails.
+    pub async fn log_success(
+        &self,
+        provider: Provider,
+        model: Option<&str>,
+        workload: Option<Workload>,
+        latency_ms: i64,
+        cost: &CallCost,
+    ) -> Result<(), AppError> {
+        let now = Utc::now().to_rfc3339();
+
+        let result = sqlx::query(
+            r"INSERT INTO provider_usage (provider, model, workload, success, latency_ms,
+                   prompt_tokens, completion_tokens, total_tokens, input_cost_micros,
+                   output_cost_micros, total_cost_micros, created_at)
+               VALUES (?, ?, ?, 1, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(provider.as_str())
+        .bind(model)
+        .bind(workload.map(Workload::as_str))
+        .bind(latency_ms)
+        .bind(cost.prompt_tokens)
+        .bind(cost.completion_tokens)
+        .bind(cost.total_tokens())
+        .bind(cost.input_cost_micros)
+        .bind(cost.output_cost_micros)
+        .bind(cost.total_micros())
+        .bind(now)
+        .execute(&*self.pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(DbError::QueryFailed(err)))?;
+
+        let _ = result.rows_affected();
+
+        Ok(())
+    }
 }
@@ -1,7 +1,7 @@
 #![allow(missing_docs)]
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use axum::{
@@ -11,10 +11,40 @@ use axum::{
 use tower::util::ServiceExt;
 
 use freegin_ai::{
+    aliases::{self, AliasTarget},
+    audit::{AuditLog, AuditQuery},
+    batch::{run_batch, BatchOptions, BatchResult},
+    budget::{Budget, CallCost},
+    catalog::CatalogStore,
+    classifier::WorkloadClassifier,
+    compare::{self, CompareTarget},
+    config::{
+        AuditConfig, BudgetConfig, ClassifierConfig, ContextConfig, ContextStrategy,
+        GuardrailConfig, JobsConfig, KeySelection, MasterKeySource, RedactionConfig, SpendLimit,
+    },
+    context,
+    credential_bundle::CredentialBundle,
+    credentials::{CredentialStore, KeyStatus},
+    database,
+    deprecation::{self, DeprecationReason},
+    discovery,
     error::AppError,
-    models::{AIRequest, AIResponse, RequestHints},
-    providers::{AIProvider, Provider, ProviderRouter},
+    eval::{self, EvalStore, EvalSuite},
+    guardrail::ModerationPipeline,
+    jobs::{JobQueue, JobStatus, JobStore},
+    master_key::{self, NewMasterKey},
+    models::{
+        AIRequest, AIResponse, ChatMessage, ChatRole, ImageInput, ImageSource, RequestComplexity,
+        RequestGuardrail, RequestHints, RequestQuality, ResponseFormat, Workload,
+    },
+    providers::{key_pool::KeyPool, AIProvider, AvailableModel, Provider, ProviderRouter},
+    redaction::Redactor,
+    roster::{self, Roster, RosterFormat},
     routes::{api_router, AppState},
+    sessions::SessionStore,
+    shadow::ShadowLog,
+    templates::TemplateLibrary,
+    usage::UsageLogger,
 };
 
 struct EchoProvider {
@@ -27,6 +57,7 @@ impl AIProvider for EchoProvider {
         Ok(AIResponse {
             content: format!("echo: {}", request.prompt),
             provider: self.provider,
+            model: None,
         })
     }
 }
@@ -93,6 +124,9 @@ async fn router_prefers_provider_hint() -> anyhow::Result<()> {
             provider: Some("huggingface".into()),
             ..RequestHints::default()
         },
+        response_schema: None,
+        images: Vec::new(),
+        history: Vec::new(),
     };
 
     let response = router.generate(&request).await?;
@@ -100,3 +134,2276 @@ async fn router_prefers_provider_hint() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+struct StaticProvider {
+    provider: Provider,
+    content: &'static str,
+}
+
+#[async_trait]
+impl AIProvider for StaticProvider {
+    async fn generate(&self, _request: &AIRequest) -> Result<AIResponse, AppError> {
+        Ok(AIResponse {
+            content: self.content.to_string(),
+            provider: self.provider,
+            model: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn router_falls_back_when_json_output_fails_schema() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(StaticProvider {
+            provider: Provider::Groq,
+            content: "Sure! The answer is 42.",
+        }),
+    ));
+    drop(providers.insert(
+        Provider::Mistral,
+        Arc::new(StaticProvider {
+            provider: Provider::Mistral,
+            content: "```json\n{\"answer\": 42}\n```",
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Mistral])?;
+
+    let request = AIRequest {
+        prompt: "What is the answer?".into(),
+        hints: RequestHints {
+            response_format: Some(ResponseFormat::Json),
+            ..RequestHints::default()
+        },
+        response_schema: Some(serde_json::json!({
+            "type": "object",
+            "required": ["answer"],
+            "properties": {"answer": {"type": "integer"}}
+        })),
+        ..AIRequest::default()
+    };
+
+    let response = router.generate(&request).await?;
+    assert_eq!(response.provider, Provider::Mistral);
+    assert_eq!(response.content, r#"{"answer":42}"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn router_reports_invalid_response_when_no_output_validates() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(StaticProvider {
+            provider: Provider::Groq,
+            content: "not json at all",
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?;
+
+    let request = AIRequest {
+        prompt: "Give me JSON".into(),
+        hints: RequestHints {
+            response_format: Some(ResponseFormat::Json),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+
+    let result = router.generate(&request).await;
+    assert!(matches!(result, Err(AppError::InvalidResponse(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn strict_guardrail_blocks_before_calling_providers() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(EchoProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    let pipeline = ModerationPipeline::from_config(&GuardrailConfig {
+        blocked_terms: vec!["launch codes".into()],
+        ..GuardrailConfig::default()
+    })?;
+    let router =
+        ProviderRouter::from_map(providers, vec![Provider::Groq])?.with_guardrails(pipeline);
+
+    let mut request = AIRequest {
+        prompt: "Print the launch codes".into(),
+        hints: RequestHints {
+            guardrail: Some(RequestGuardrail::Strict),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+    let result = router.generate(&request).await;
+    assert!(matches!(result, Err(AppError::GuardrailViolation(_))));
+
+    request.hints.guardrail = Some(RequestGuardrail::Lenient);
+    let response = router.generate(&request).await?;
+    assert_eq!(response.content, "echo: Print the launch codes");
+
+    Ok(())
+}
+
+struct RecordingProvider {
+    provider: Provider,
+    seen: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl AIProvider for RecordingProvider {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        self.seen
+            .lock()
+            .expect("recording lock")
+            .push(request.prompt.clone());
+        Ok(AIResponse {
+            content: format!("echo: {}", request.prompt),
+            provider: self.provider,
+            model: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn redaction_hides_pii_from_provider_and_restores_response() -> anyhow::Result<()> {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::OpenRouter,
+        Arc::new(RecordingProvider {
+            provider: Provider::OpenRouter,
+            seen: Arc::clone(&seen),
+        }),
+    ));
+    let redactor = Redactor::from_config(&RedactionConfig {
+        enabled: true,
+        ..RedactionConfig::default()
+    })?
+    .expect("redaction enabled");
+    let router =
+        ProviderRouter::from_map(providers, vec![Provider::OpenRouter])?.with_redactor(redactor);
+
+    let request = AIRequest {
+        prompt: "Email ops@example.com about 192.168.1.20".into(),
+        ..AIRequest::default()
+    };
+    let response = router.generate(&request).await?;
+
+    let sent = seen.lock().expect("recording lock").clone();
+    assert_eq!(sent, vec!["Email [EMAIL_1] about [IP_1]".to_string()]);
+    assert_eq!(
+        response.content,
+        "echo: Email ops@example.com about 192.168.1.20"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn moderation_provider_only_sees_redacted_text() -> anyhow::Result<()> {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(EchoProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    drop(providers.insert(
+        Provider::OpenRouter,
+        Arc::new(RecordingProvider {
+            provider: Provider::OpenRouter,
+            seen: Arc::clone(&seen),
+        }),
+    ));
+    let redactor = Redactor::from_config(&RedactionConfig {
+        enabled: true,
+        ..RedactionConfig::default()
+    })?
+    .expect("redaction enabled");
+    let pipeline = ModerationPipeline::from_config(&GuardrailConfig {
+        moderation_provider: Some("openrouter".into()),
+        moderation_model: Some("llama-guard".into()),
+        ..GuardrailConfig::default()
+    })?;
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?
+        .with_redactor(redactor)
+        .with_guardrails(pipeline);
+
+    let request = AIRequest {
+        prompt: "Email ops@example.com".into(),
+        hints: RequestHints {
+            guardrail: Some(RequestGuardrail::Strict),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+    drop(router.generate(&request).await?);
+
+    let sent = seen.lock().expect("recording lock").clone();
+    assert!(!sent.is_empty());
+    assert!(sent
+        .iter()
+        .all(|prompt| !prompt.contains("ops@example.com")));
+    assert!(sent.iter().any(|prompt| prompt.contains("[EMAIL_1]")));
+
+    Ok(())
+}
+
+struct VisionProvider {
+    provider: Provider,
+}
+
+#[async_trait]
+impl AIProvider for VisionProvider {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        Ok(AIResponse {
+            content: format!("saw {} image(s)", request.images.len()),
+            provider: self.provider,
+            model: None,
+        })
+    }
+
+    fn supports_images(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn image_requests_skip_providers_without_vision() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(EchoProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    drop(providers.insert(
+        Provider::Google,
+        Arc::new(VisionProvider {
+            provider: Provider::Google,
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Google])?;
+
+    let request = AIRequest {
+        prompt: "Describe this".into(),
+        images: vec![ImageInput {
+            source: ImageSource::Url("https://example.com/cat.png".into()),
+            mime_type: None,
+        }],
+        ..AIRequest::default()
+    };
+
+    let response = router.generate(&request).await?;
+    assert_eq!(response.provider, Provider::Google);
+    assert_eq!(response.content, "saw 1 image(s)");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn http_rejects_local_image_paths() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Google,
+        Arc::new(VisionProvider {
+            provider: Provider::Google,
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Google])?;
+    let app = api_router(AppState::new(Arc::new(router)));
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/generate")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"model":"","prompt":"Describe","images":[{"path":"/etc/passwd"}]}"#,
+        ))?;
+
+    let response = app.oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn batch_writes_results_and_resumes_failed_lines() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("prompts.jsonl");
+    let output = dir.join("results.jsonl");
+    std::fs::write(
+        &input,
+        concat!(
+            r#"{"model":"","prompt":"one","metadata":{"id":"a"}}"#,
+            "\n\n",
+            r#"{"model":"","prompt":"two"}"#,
+            "\n",
+            r#"{"model":"","prompt":"three"}"#,
+            "\n",
+        ),
+    )?;
+    // A previous run completed line 1, failed line 3 and never reached line 4.
+    std::fs::write(
+        &output,
+        concat!(
+            r#"{"line":1,"id":"a","provider":"groq","model":"m","latency_ms":5,"content":"done","error":null}"#,
+            "\n",
+            r#"{"line":3,"provider":null,"model":null,"latency_ms":5,"content":null,"error":"boom"}"#,
+            "\n",
+        ),
+    )?;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(RecordingProvider {
+            provider: Provider::Groq,
+            seen: Arc::clone(&seen),
+        }),
+    ));
+    let router = Arc::new(ProviderRouter::from_map(providers, vec![Provider::Groq])?);
+
+    let options = BatchOptions {
+        input,
+        output: output.clone(),
+        concurrency: 2,
+        resume: true,
+    };
+    let summary = run_batch(router, &options).await?;
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(summary.succeeded, 2);
+
+    let mut seen = seen.lock().expect("recording lock").clone();
+    seen.sort();
+    assert_eq!(seen, vec!["three".to_string(), "two".to_string()]);
+
+    let mut results = std::fs::read_to_string(&output)?
+        .lines()
+        .map(serde_json::from_str::<BatchResult>)
+        .collect::<Result<Vec<_>, _>>()?;
+    results.sort_by_key(|result| result.line);
+    assert_eq!(
+        results.iter().map(|result| result.line).collect::<Vec<_>>(),
+        vec![1, 3, 4]
+    );
+    assert!(results.iter().all(BatchResult::is_success));
+    assert_eq!(results[1].provider.as_deref(), Some("groq"));
+    assert!(!dir.join("results.jsonl.tmp").exists());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Panics on the prompt "panic" and echoes everything else.
+struct PanickingProvider {
+    provider: Provider,
+}
+
+#[async_trait]
+impl AIProvider for PanickingProvider {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        assert_ne!(request.prompt, "panic", "provider panicked");
+        Ok(AIResponse {
+            content: format!("echo: {}", request.prompt),
+            provider: self.provider,
+            model: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn batch_records_a_panicking_request_and_keeps_going() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-batch-panic-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join("prompts.jsonl");
+    let output = dir.join("results.jsonl");
+    std::fs::write(
+        &input,
+        concat!(
+            r#"{"model":"","prompt":"panic","metadata":{"id":"bad"}}"#,
+            "\n",
+            r#"{"model":"","prompt":"two"}"#,
+            "\n",
+            r#"{"model":"","prompt":"three"}"#,
+            "\n",
+        ),
+    )?;
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(PanickingProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    let router = Arc::new(ProviderRouter::from_map(providers, vec![Provider::Groq])?);
+
+    let options = BatchOptions {
+        input,
+        output: output.clone(),
+        concurrency: 1,
+        resume: false,
+    };
+    let summary = run_batch(router, &options).await?;
+    assert_eq!(summary.processed, 3);
+    assert_eq!(summary.succeeded, 2);
+    assert_eq!(summary.failed, 1);
+
+    let failed = std::fs::read_to_string(&output)?
+        .lines()
+        .map(serde_json::from_str::<BatchResult>)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .find(|result| !result.is_success())
+        .expect("failed line");
+    assert_eq!(failed.line, 1);
+    assert_eq!(failed.id.as_deref(), Some("bad"));
+    assert!(failed.error.is_some_and(|error| error.contains("panic")));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn jobs_are_queued_processed_and_survive_restart() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-jobs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("jobs.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    // A job claimed by a worker that died with the previous process is requeued.
+    let store = JobStore::new(Arc::clone(&pool));
+    let stale = store
+        .enqueue(
+            &AIRequest {
+                prompt: "stale".into(),
+                ..AIRequest::default()
+            },
+            None,
+        )
+        .await?;
+    assert!(store.claim_next().await?.is_some());
+    assert_eq!(store.requeue_interrupted().await?, 1);
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(EchoProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    let router = Arc::new(ProviderRouter::from_map(providers, vec![Provider::Groq])?);
+    let queue = JobQueue::new(store.clone());
+    let config = JobsConfig {
+        workers: 1,
+        poll_interval_ms: 10,
+        ..JobsConfig::default()
+    };
+    let workers = queue.spawn_workers(&router, &config);
+    let app = api_router(AppState::new(router).with_jobs(queue));
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/jobs")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"model":"","prompt":"later"}"#))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let bytes = body::to_bytes(response.into_body(), usize::MAX).await?;
+    let submitted: serde_json::Value = serde_json::from_slice(&bytes)?;
+    let id = submitted["id"].as_str().expect("job id").to_string();
+    assert_eq!(submitted["status"], "queued");
+
+    let mut finished = None;
+    for _ in 0..200 {
+        let request = Request::builder()
+            .uri(format!("/api/v1/jobs/{id}"))
+            .body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = body::to_bytes(response.into_body(), usize::MAX).await?;
+        let job: serde_json::Value = serde_json::from_slice(&bytes)?;
+        if job["status"] == "succeeded" {
+            finished = Some(job);
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let finished = finished.expect("job did not finish");
+    assert_eq!(finished["response"]["content"], "echo: later");
+
+    let stale = store.get(&stale.id).await?.expect("stale job");
+    assert_eq!(stale.status, JobStatus::Succeeded);
+    assert_eq!(stale.attempts, 2);
+
+    let missing = Request::builder()
+        .uri("/api/v1/jobs/does-not-exist")
+        .body(Body::empty())?;
+    assert_eq!(
+        app.clone().oneshot(missing).await?.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // Webhooks may not point at the host's own network.
+    let internal = Request::builder()
+        .method("POST")
+        .uri("/api/v1/jobs")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            r#"{"model":"","prompt":"x","webhook_url":"http://169.254.169.254/latest"}"#,
+        ))?;
+    assert_eq!(
+        app.oneshot(internal).await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    for worker in workers {
+        worker.abort();
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_persist_turns_and_names() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-sessions-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("sessions.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let sessions = SessionStore::new(Arc::clone(&pool));
+    let first = sessions.create(None, None, None, None).await?;
+    sessions
+        .append(&first.id, ChatRole::User, "Hi", None, None)
+        .await?;
+    sessions
+        .append(
+            &first.id,
+            ChatRole::Assistant,
+            "Hello!",
+            Some("groq"),
+            Some("llama"),
+        )
+        .await?;
+    sessions
+        .set_routing(&first.id, Some("groq"), None, Some("code"))
+        .await?;
+    sessions.rename(&first.id, "notes").await?;
+
+    let loaded = sessions.find("notes").await?.expect("named session");
+    assert_eq!(loaded.id, first.id);
+    assert_eq!(loaded.provider.as_deref(), Some("groq"));
+    assert_eq!(loaded.workload.as_deref(), Some("code"));
+
+    let history: Vec<ChatMessage> = sessions
+        .messages(&first.id)
+        .await?
+        .iter()
+        .map(ChatMessage::from)
+        .collect();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].role, ChatRole::Assistant);
+    assert_eq!(history[1].content, "Hello!");
+
+    // A name already in use is never taken from its session.
+    let second = sessions.create(None, None, None, None).await?;
+    let taken = sessions.rename(&second.id, "notes").await;
+    assert!(matches!(taken, Err(AppError::Conflict(_))));
+    assert!(matches!(
+        sessions.create(Some("notes"), None, None, None).await,
+        Err(AppError::Conflict(_))
+    ));
+    assert_eq!(sessions.find("notes").await?.expect("named").id, first.id);
+    sessions.rename(&first.id, "notes").await?;
+
+    // Routing given at creation is stored with the session, and a turn
+    // answered by another provider replaces the model pinned for the old one.
+    let pinned = sessions
+        .create(Some("pinned"), Some("groq"), Some("llama"), Some("chat"))
+        .await?;
+    let stored = sessions.find("pinned").await?.expect("pinned session");
+    assert_eq!(stored.model.as_deref(), Some("llama"));
+    assert_eq!(stored.workload.as_deref(), Some("chat"));
+    let answer = |model: Option<&str>| AIResponse {
+        content: "ok".into(),
+        provider: Provider::Mistral,
+        model: model.map(str::to_string),
+    };
+    sessions
+        .record_turn(&pinned, "hi", &answer(Some("mistral-small")))
+        .await?;
+    let moved = sessions.find("pinned").await?.expect("pinned session");
+    assert_eq!(moved.provider.as_deref(), Some("mistral"));
+    assert_eq!(moved.model.as_deref(), Some("mistral-small"));
+    assert_eq!(moved.workload.as_deref(), Some("chat"));
+    sessions.record_turn(&moved, "again", &answer(None)).await?;
+    let kept = sessions.find("pinned").await?.expect("pinned session");
+    assert_eq!(kept.model.as_deref(), Some("mistral-small"));
+    assert_eq!(sessions.messages(&pinned.id).await?.len(), 4);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+struct ConversationProvider {
+    provider: Provider,
+    fail_first: bool,
+    calls: Arc<Mutex<Vec<usize>>>,
+}
+
+#[async_trait]
+impl AIProvider for ConversationProvider {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        let call = {
+            let mut calls = self.calls.lock().expect("conversation lock");
+            calls.push(request.history.len());
+            calls.len()
+        };
+        if self.fail_first && call == 1 {
+            return Err(AppError::ApiError("temporarily unavailable".into()));
+        }
+        Ok(AIResponse {
+            content: format!("{} says: {}", self.provider, request.prompt),
+            provider: self.provider,
+            model: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn http_sessions_keep_history_and_sticky_provider() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-http-sessions-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("sessions.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let groq_calls = Arc::new(Mutex::new(Vec::new()));
+    let mistral_calls = Arc::new(Mutex::new(Vec::new()));
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(ConversationProvider {
+            provider: Provider::Groq,
+            fail_first: true,
+            calls: Arc::clone(&groq_calls),
+        }),
+    ));
+    drop(providers.insert(
+        Provider::Mistral,
+        Arc::new(ConversationProvider {
+            provider: Provider::Mistral,
+            fail_first: false,
+            calls: Arc::clone(&mistral_calls),
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Mistral])?;
+    let app = api_router(
+        AppState::new(Arc::new(router)).with_sessions(SessionStore::new(Arc::clone(&pool))),
+    );
+
+    let create = Request::builder()
+        .method("POST")
+        .uri("/api/v1/sessions")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"name":"support"}"#))?;
+    let response = app.clone().oneshot(create).await?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let bytes = body::to_bytes(response.into_body(), usize::MAX).await?;
+    let session: serde_json::Value = serde_json::from_slice(&bytes)?;
+    let id = session["id"].as_str().expect("session id").to_string();
+
+    let duplicate = Request::builder()
+        .method("POST")
+        .uri("/api/v1/sessions")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"name":"support"}"#))?;
+    assert_eq!(
+        app.clone().oneshot(duplicate).await?.status(),
+        StatusCode::CONFLICT
+    );
+
+    for prompt in ["first", "second"] {
+        let turn = Request::builder()
+            .method("POST")
+            .uri(format!("/api/v1/sessions/{id}/messages"))
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "prompt": prompt }).to_string(),
+            ))?;
+        let response = app.clone().oneshot(turn).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = body::to_bytes(response.into_body(), usize::MAX).await?;
+        let reply: serde_json::Value = serde_json::from_slice(&bytes)?;
+        assert_eq!(reply["session_id"], id.as_str());
+        assert_eq!(reply["provider"], serde_json::to_value(Provider::Mistral)?);
+    }
+
+    // Groq failed once; afterwards the session stuck with Mistral, which saw
+    // the first exchange as history on the second turn.
+    assert_eq!(*groq_calls.lock().expect("groq calls"), vec![0]);
+    assert_eq!(*mistral_calls.lock().expect("mistral calls"), vec![0, 2]);
+
+    let transcript = Request::builder()
+        .uri("/api/v1/sessions/support")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(transcript).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = body::to_bytes(response.into_body(), usize::MAX).await?;
+    let transcript: serde_json::Value = serde_json::from_slice(&bytes)?;
+    assert_eq!(transcript["provider"], "mistral");
+    assert_eq!(transcript["messages"].as_array().map(Vec::len), Some(4));
+    assert_eq!(transcript["messages"][3]["content"], "mistral says: second");
+
+    let missing = Request::builder()
+        .method("POST")
+        .uri("/api/v1/sessions/nope/messages")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"prompt":"hi"}"#))?;
+    assert_eq!(app.oneshot(missing).await?.status(), StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn routing_skips_models_with_too_small_context_window() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-context-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("context.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    catalog.seed_defaults().await?;
+    let seeded = catalog.active_models(Provider::Google, None).await?;
+    assert!(seeded
+        .iter()
+        .all(|entry| entry.context_window() == Some(1_048_576) && entry.metadata_flag("vision")));
+    for entry in catalog.list_models(None, None).await? {
+        assert!(
+            catalog
+                .retire_model(entry.provider, entry.workload, &entry.model)
+                .await?
+        );
+    }
+
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "small-model".into(),
+            None,
+            Some(r#"{"context_window":4096}"#.into()),
+            10,
+        )
+        .await?;
+    catalog
+        .adopt_model(
+            Provider::Mistral,
+            Workload::Chat,
+            "large-model".into(),
+            None,
+            Some(r#"{"context_window":32000}"#.into()),
+            10,
+        )
+        .await?;
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    for provider in [Provider::Groq, Provider::Mistral] {
+        drop(providers.insert(provider, Arc::new(EchoProvider { provider })));
+    }
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Mistral])?
+        .with_catalog(catalog);
+
+    let request = |chars: usize| AIRequest {
+        prompt: "x".repeat(chars),
+        hints: RequestHints {
+            workload: Some(Workload::Chat),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+
+    let short = router.generate(&request(400)).await?;
+    assert_eq!(short.provider, Provider::Groq);
+
+    // ~6700 tokens: too large for the 4K Groq model.
+    let long = router.generate(&request(20_000)).await?;
+    assert_eq!(long.provider, Provider::Mistral);
+    assert_eq!(long.model.as_deref(), Some("large-model"));
+
+    let huge = router.generate(&request(200_000)).await;
+    assert!(matches!(huge, Err(AppError::InvalidRequest(_))));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn oversized_context_is_summarized_to_budget() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(StaticProvider {
+            provider: Provider::Groq,
+            content: "  condensed notes  ",
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?;
+    let config = ContextConfig {
+        strategy: ContextStrategy::Summarize,
+        max_tokens: 500,
+        chunk_tokens: 1000,
+    };
+
+    let blocks = vec!["a".repeat(7_500), "small file".to_string()];
+    let fitted = context::fit_context(&router, blocks, &config).await?;
+    // 2 500 tokens split into 1 000-token chunks: three summaries for the first file.
+    assert_eq!(
+        fitted[0],
+        "condensed notes\n\ncondensed notes\n\ncondensed notes"
+    );
+    assert_eq!(fitted[1], "condensed notes");
+
+    let untouched = vec!["fits".to_string()];
+    assert_eq!(
+        context::fit_context(&router, untouched.clone(), &config).await?,
+        untouched
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn http_generates_from_prompt_template() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-templates-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("summarize-pr.toml"),
+        r#"
+description = "Summarize a pull request"
+prompt = "Summarize for {{audience}}: {{diff}}"
+
+[defaults]
+audience = "reviewers"
+
+[hints]
+workload = "summarization"
+"#,
+    )?;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(RecordingProvider {
+            provider: Provider::Groq,
+            seen: Arc::clone(&seen),
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?;
+    let app = api_router(
+        AppState::new(Arc::new(router)).with_templates(TemplateLibrary::new(dir.clone())),
+    );
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/templates/summarize-pr/generate")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"variables": {"diff": "+fn main() {}"}}"#))?;
+    let response = app.clone().oneshot(request).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        *seen.lock().expect("recording lock"),
+        vec!["Summarize for reviewers: +fn main() {}".to_string()]
+    );
+
+    let listing = Request::builder()
+        .uri("/api/v1/templates")
+        .body(Body::empty())?;
+    let response = app.clone().oneshot(listing).await?;
+    let bytes = body::to_bytes(response.into_body(), usize::MAX).await?;
+    let templates: serde_json::Value = serde_json::from_slice(&bytes)?;
+    assert_eq!(templates[0]["name"], "summarize-pr");
+
+    let missing_var = Request::builder()
+        .method("POST")
+        .uri("/api/v1/templates/summarize-pr/generate")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"variables": {}}"#))?;
+    assert_eq!(
+        app.clone().oneshot(missing_var).await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let unknown = Request::builder()
+        .method("POST")
+        .uri("/api/v1/templates/nope/generate")
+        .header("content-type", "application/json")
+        .body(Body::from("{}"))?;
+    assert_eq!(app.oneshot(unknown).await?.status(), StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn audit_log_records_redacted_requests_with_retention() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-audit-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("audit.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let config = AuditConfig {
+        enabled: true,
+        max_entries: 2,
+        ..AuditConfig::default()
+    };
+    let audit = AuditLog::new(Arc::clone(&pool), config, &[])?;
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(EchoProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    let router =
+        ProviderRouter::from_map(providers, vec![Provider::Groq])?.with_audit(audit.clone());
+
+    for prompt in ["first", "second", "mail jane@example.com"] {
+        drop(
+            router
+                .generate(&AIRequest {
+                    prompt: prompt.into(),
+                    ..AIRequest::default()
+                })
+                .await?,
+        );
+    }
+    let failed = router
+        .generate(&AIRequest {
+            prompt: "route nowhere".into(),
+            hints: RequestHints {
+                provider: Some("mistral".into()),
+                ..RequestHints::default()
+            },
+            images: vec![ImageInput {
+                source: ImageSource::Url("https://example.com/cat.png".into()),
+                mime_type: None,
+            }],
+            ..AIRequest::default()
+        })
+        .await;
+    assert!(failed.is_err());
+
+    // Retention runs on the first write and then every `PRUNE_INTERVAL` writes.
+    let unpruned = audit
+        .search(&AuditQuery {
+            limit: 10,
+            ..AuditQuery::default()
+        })
+        .await?;
+    assert_eq!(unpruned.len(), 4);
+    assert_eq!(audit.prune().await?, 2);
+
+    let all = audit
+        .search(&AuditQuery {
+            limit: 10,
+            ..AuditQuery::default()
+        })
+        .await?;
+    assert_eq!(all.len(), 2, "retention keeps the newest entries only");
+    assert!(!all[0].success);
+    assert_eq!(all[1].request.prompt, "mail [EMAIL_1]");
+    assert_eq!(all[1].response.as_deref(), Some("echo: mail [EMAIL_1]"));
+    assert_eq!(all[1].provider.as_deref(), Some("groq"));
+
+    let hits = audit
+        .search(&AuditQuery {
+            text: Some("EMAIL_1".into()),
+            limit: 10,
+            ..AuditQuery::default()
+        })
+        .await?;
+    assert_eq!(hits.len(), 1);
+    let fetched = audit.get(&hits[0].id).await?.expect("entry");
+    assert_eq!(fetched.request.prompt, "mail [EMAIL_1]");
+
+    let failures = audit
+        .search(&AuditQuery {
+            failures_only: true,
+            limit: 10,
+            ..AuditQuery::default()
+        })
+        .await?;
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].error.is_some());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn compare_runs_each_target_without_fallback() -> anyhow::Result<()> {
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(StaticProvider {
+            provider: Provider::Groq,
+            content: "groq answer",
+        }),
+    ));
+    drop(providers.insert(
+        Provider::Mistral,
+        Arc::new(StaticProvider {
+            provider: Provider::Mistral,
+            content: "mistral answer",
+        }),
+    ));
+    let router = Arc::new(ProviderRouter::from_map(
+        providers,
+        vec![Provider::Groq, Provider::Mistral],
+    )?);
+
+    let targets = vec![
+        CompareTarget::parse("mistral:mistral-small-latest")?,
+        CompareTarget::parse("groq")?,
+        CompareTarget::parse("cerebras")?,
+    ];
+    let request = AIRequest {
+        prompt: "Which is best?".into(),
+        ..AIRequest::default()
+    };
+    let results = compare::run_comparison(router, &request, &targets).await?;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].target, "mistral:mistral-small-latest");
+    assert_eq!(results[0].content.as_deref(), Some("mistral answer"));
+    assert_eq!(results[1].content.as_deref(), Some("groq answer"));
+    assert!(results[1].completion_tokens > 0);
+    // Cerebras is not configured: the target fails instead of falling back.
+    assert!(!results[2].is_success());
+    assert_eq!(results[2].provider, "cerebras");
+    Ok(())
+}
+
+struct ModelNameProvider {
+    provider: Provider,
+}
+
+#[async_trait]
+impl AIProvider for ModelNameProvider {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        Ok(AIResponse {
+            content: request.model.clone(),
+            provider: self.provider,
+            model: Some(request.model.clone()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn eval_scores_reorder_catalog_models() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-eval-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("eval.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "weak-model".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "good-model".into(),
+            None,
+            None,
+            15,
+        )
+        .await?;
+    // Not configured in the router, so it is not evaluated.
+    catalog
+        .adopt_model(
+            Provider::Mistral,
+            Workload::Chat,
+            "other".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(ModelNameProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?;
+
+    let suite_path = dir.join("suite.yaml");
+    std::fs::write(
+        &suite_path,
+        "cases:\n  - id: quality\n    prompt: \"Who are you?\"\n    check: { type: regex, pattern: \"^good\" }\n  \
+         - id: judged\n    prompt: \"Write a haiku.\"\n    check: { type: judge, criteria: \"A haiku.\" }\n",
+    )?;
+    let suite = EvalSuite::load(&suite_path)?;
+    assert_eq!(suite.name, "suite");
+
+    let before = catalog
+        .active_models(Provider::Groq, Some(Workload::Chat))
+        .await?;
+    assert_eq!(before[0].model, "weak-model");
+
+    let store = EvalStore::new(Arc::clone(&pool));
+    let report = eval::run_suite(&router, &catalog, &store, &suite, None).await?;
+    assert_eq!(report.results.len(), 4);
+    assert_eq!(report.scores.len(), 2);
+    // The judge's reply is not a verdict: those cases are errors, not zeros.
+    assert!(report
+        .results
+        .iter()
+        .filter(|result| result.case_id == "judged")
+        .all(|result| result
+            .error
+            .as_deref()
+            .is_some_and(|err| err.starts_with("judge failed"))));
+    let good = report
+        .scores
+        .iter()
+        .find(|score| score.model == "good-model")
+        .expect("score");
+    assert!((good.score - 1.0).abs() < f64::EPSILON);
+    assert_eq!(good.cases, 1);
+
+    let after = catalog
+        .active_models(Provider::Groq, Some(Workload::Chat))
+        .await?;
+    assert_eq!(after[0].model, "good-model");
+    assert_eq!(store.scores(Some(Provider::Groq)).await?.len(), 2);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn trial_models_receive_shadow_traffic() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-trial-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("trial.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "active-model".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+    catalog
+        .start_trial(Provider::Mistral, Workload::Chat, "trial-model", 1.0)
+        .await?;
+    assert!(catalog
+        .start_trial(Provider::Mistral, Workload::Chat, "trial-model", 1.5)
+        .await
+        .is_err());
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(StaticProvider {
+            provider: Provider::Groq,
+            content: "the answer is four",
+        }),
+    ));
+    drop(providers.insert(
+        Provider::Mistral,
+        Arc::new(StaticProvider {
+            provider: Provider::Mistral,
+            content: "the answer is 4",
+        }),
+    ));
+    let shadow_log = ShadowLog::new(Arc::clone(&pool));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Mistral])?
+        .with_catalog(catalog.clone())
+        .with_shadow(shadow_log.clone(), 0.0);
+
+    let response = router
+        .generate(&AIRequest {
+            prompt: "What is 2 + 2?".into(),
+            ..AIRequest::default()
+        })
+        .await?;
+    assert_eq!(response.provider, Provider::Groq);
+    assert_eq!(response.content, "the answer is four");
+
+    let mut summaries = Vec::new();
+    for _ in 0..40 {
+        summaries = shadow_log.summaries(Some(Provider::Mistral)).await?;
+        if !summaries.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0].model, "trial-model");
+    assert_eq!(summaries[0].requests, 1);
+    assert!((summaries[0].success_rate - 1.0).abs() < f64::EPSILON);
+    assert!(summaries[0]
+        .avg_similarity
+        .is_some_and(|overlap| overlap > 0.5));
+
+    catalog
+        .adopt_model(
+            Provider::Mistral,
+            Workload::Chat,
+            "trial-model".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+    assert!(catalog.trials(Workload::Chat).await?.is_empty());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+struct ListingProvider {
+    models: Mutex<Vec<AvailableModel>>,
+}
+
+#[async_trait]
+impl AIProvider for ListingProvider {
+    async fn generate(&self, _request: &AIRequest) -> Result<AIResponse, AppError> {
+        Err(AppError::ApiError("listing only".into()))
+    }
+
+    async fn list_models(&self) -> Result<Vec<AvailableModel>, AppError> {
+        Ok(self.models.lock().expect("models").clone())
+    }
+}
+
+fn available(id: &str, context_window: Option<u64>) -> AvailableModel {
+    AvailableModel {
+        id: id.into(),
+        context_window,
+        input_price_per_mtok: Some(0.0),
+        output_price_per_mtok: Some(0.0),
+    }
+}
+
+#[tokio::test]
+async fn discovery_records_listed_models_and_flags_missing_ones() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-discovery-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("discovery.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "kept".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Code,
+            "gone".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+
+    let listing = Arc::new(ListingProvider {
+        models: Mutex::new(vec![available("kept", Some(8192))]),
+    });
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(Provider::Groq, listing.clone()));
+    drop(providers.insert(
+        Provider::Mistral,
+        Arc::new(StaticProvider {
+            provider: Provider::Mistral,
+            content: "no listing",
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Mistral])?;
+
+    let reports = discovery::discover(&router, &catalog, router.providers()).await?;
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].available, 1);
+    assert_eq!(reports[0].updated, 1);
+    assert!(reports[0].new_models.is_empty());
+    assert_eq!(reports[0].missing, vec!["code/gone".to_string()]);
+    assert!(reports[1].error.is_some());
+
+    let models = catalog.list_models(Some(Provider::Groq), None).await?;
+    let kept = models
+        .iter()
+        .find(|entry| entry.model == "kept")
+        .expect("kept");
+    assert_eq!(kept.context_window(), Some(8192));
+    assert!(kept.unavailable_since().is_none());
+    let gone = models
+        .iter()
+        .find(|entry| entry.model == "gone")
+        .expect("gone");
+    assert!(gone.unavailable_since().is_some());
+    assert_eq!(gone.status, "active");
+
+    *listing.models.lock().expect("models") = vec![
+        available("kept", Some(8192)),
+        available("gone", None),
+        available("newer", None),
+    ];
+    let reports = discovery::discover(&router, &catalog, &[Provider::Groq]).await?;
+    assert_eq!(
+        reports[0].new_models,
+        vec!["gone".to_string(), "newer".to_string()]
+    );
+    assert!(reports[0].missing.is_empty());
+    let models = catalog.list_models(Some(Provider::Groq), None).await?;
+    assert!(models
+        .iter()
+        .all(|entry| entry.unavailable_since().is_none()));
+    assert_eq!(catalog.available_models(Provider::Groq).await?.len(), 3);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn catalog_import_adds_updates_and_prunes_entries() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-roster-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("roster.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    catalog.seed_defaults().await?;
+    let seeded = catalog.list_models(None, None).await?;
+    assert_eq!(seeded.len(), Roster::defaults()?.models.len());
+    let gemini = seeded
+        .iter()
+        .find(|entry| entry.provider == Provider::Google)
+        .expect("gemini");
+    assert!(gemini.metadata_flag("vision"));
+
+    // An export imports back without changes.
+    let exported = Roster::from_entries(&seeded).render(RosterFormat::Yaml)?;
+    let plan = roster::plan_import(
+        &catalog,
+        &Roster::parse(&exported, RosterFormat::Yaml)?,
+        true,
+    )
+    .await?;
+    assert!(plan.is_empty());
+    assert_eq!(plan.unchanged, seeded.len());
+
+    let path = dir.join("catalog.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[models]]
+provider = "groq"
+workload = "chat"
+model = "llama-3.3-70b-versatile"
+priority = 5
+
+[[models]]
+provider = "groq"
+workload = "code"
+model = "qwen-coder"
+metadata = { context_window = 32768 }
+"#,
+    )?;
+    let file = Roster::load(&path)?;
+
+    let plan = roster::plan_import(&catalog, &file, false).await?;
+    assert_eq!(plan.add.len(), 1);
+    assert_eq!(plan.update.len(), 1);
+    assert!(plan.update[0].1.contains(&"priority 10 -> 5".to_string()));
+    assert!(plan.retire.is_empty());
+
+    let plan = roster::plan_import(&catalog, &file, true).await?;
+    assert_eq!(plan.retire.len(), seeded.len() - 1);
+    roster::apply_import(&catalog, &plan).await?;
+
+    let active: Vec<_> = catalog
+        .list_models(None, None)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.status == "active")
+        .collect();
+    assert_eq!(active.len(), 2);
+    let coder = active
+        .iter()
+        .find(|entry| entry.model == "qwen-coder")
+        .expect("added");
+    assert_eq!(coder.priority, 100);
+    assert_eq!(coder.context_window(), Some(32_768));
+    assert!(roster::plan_import(&catalog, &file, true).await?.is_empty());
+
+    // Seeding does not bring pruned defaults back.
+    catalog.seed_defaults().await?;
+    assert!(roster::plan_import(&catalog, &file, true).await?.is_empty());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_aliases_route_to_their_targets_in_order() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-alias-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("alias.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    catalog
+        .adopt_model(
+            Provider::Mistral,
+            Workload::Code,
+            "catalog-coder".into(),
+            None,
+            None,
+            10,
+        )
+        .await?;
+    catalog
+        .set_alias(
+            "best-code",
+            &[
+                AliasTarget::parse("groq/unavailable-coder")?,
+                AliasTarget::parse("mistral")?,
+            ],
+        )
+        .await?;
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(ListingProvider {
+            models: Mutex::new(Vec::new()),
+        }),
+    ));
+    for provider in [Provider::Mistral, Provider::Cerebras] {
+        drop(providers.insert(provider, Arc::new(ModelNameProvider { provider })));
+    }
+    let mut configured = HashMap::new();
+    drop(configured.insert(
+        "fast-chat".to_string(),
+        vec![
+            "cerebras/llama3.1-8b".to_string(),
+            "mistral/mistral-small".to_string(),
+        ],
+    ));
+    let router = ProviderRouter::from_map(
+        providers,
+        vec![Provider::Groq, Provider::Mistral, Provider::Cerebras],
+    )?
+    .with_catalog(catalog.clone())
+    .with_aliases(aliases::from_config(&configured)?);
+
+    let request = |model: &str| AIRequest {
+        prompt: "hi".into(),
+        model: model.into(),
+        hints: RequestHints {
+            workload: Some(Workload::Code),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+
+    // Configured alias: the first target answers with its own model id.
+    let fast = router.generate(&request("fast-chat")).await?;
+    assert_eq!(fast.provider, Provider::Cerebras);
+    assert_eq!(fast.model.as_deref(), Some("llama3.1-8b"));
+
+    // Catalog alias: Groq fails, then Mistral's catalog model is picked.
+    let code = router.generate(&request("best-code")).await?;
+    assert_eq!(code.provider, Provider::Mistral);
+    assert_eq!(code.model.as_deref(), Some("catalog-coder"));
+
+    // Pinning a provider keeps only the alias targets on it.
+    let pinned = router
+        .generate_on(Provider::Mistral, &request("fast-chat"))
+        .await?;
+    assert_eq!(pinned.model.as_deref(), Some("mistral-small"));
+
+    // Names that are not aliases are still passed through as model ids.
+    let raw = router.generate(&request("some-model")).await?;
+    assert_eq!(raw.model.as_deref(), Some("some-model"));
+
+    assert_eq!(catalog.aliases().await?.len(), 1);
+    assert!(catalog.remove_alias("best-code").await?);
+    assert!(router.resolve_alias("best-code").await?.is_none());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn routing_filters_models_by_capabilities() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-caps-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("caps.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    let invalid = catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "bad".into(),
+            None,
+            Some(r#"{"vision":"yes"}"#.into()),
+            1,
+        )
+        .await;
+    assert!(matches!(invalid, Err(AppError::InvalidRequest(_))));
+
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "retired-soon".into(),
+            None,
+            Some(r#"{"deprecation_date":"2000-01-01"}"#.into()),
+            1,
+        )
+        .await?;
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "plain-text".into(),
+            None,
+            Some(r#"{"json_mode":false,"context_window":32768}"#.into()),
+            5,
+        )
+        .await?;
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "structured".into(),
+            None,
+            Some(r#"{"json_mode":true,"context_window":32768}"#.into()),
+            10,
+        )
+        .await?;
+    catalog
+        .adopt_model(
+            Provider::Mistral,
+            Workload::Chat,
+            "no-system".into(),
+            None,
+            Some(r#"{"system_prompt":false}"#.into()),
+            10,
+        )
+        .await?;
+    let listed = catalog.active_models(Provider::Groq, None).await?;
+    assert_eq!(listed[2].capabilities().summary(), "32K ctx, json");
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    for provider in [Provider::Groq, Provider::Mistral] {
+        drop(providers.insert(provider, Arc::new(ModelNameProvider { provider })));
+    }
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq, Provider::Mistral])?
+        .with_catalog(catalog);
+    let request = || AIRequest {
+        prompt: r#"Reply with {"ok": true}"#.into(),
+        hints: RequestHints {
+            workload: Some(Workload::Chat),
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+
+    // The deprecated model is skipped even though it has the best priority.
+    let text = router.generate(&request()).await?;
+    assert_eq!(text.model.as_deref(), Some("plain-text"));
+
+    // A model without JSON mode is not used for JSON requests.
+    let mut json = request();
+    json.hints.response_format = Some(ResponseFormat::Json);
+    json.model = "plain-text".into();
+    let rejected = router.generate_on(Provider::Groq, &json).await;
+    assert!(
+        matches!(rejected, Err(AppError::InvalidRequest(message)) if message.contains("JSON mode"))
+    );
+
+    // A system turn rules out the Mistral model that ignores system prompts.
+    let mut system = request();
+    system.history = vec![ChatMessage {
+        role: ChatRole::System,
+        content: "Be brief.".into(),
+    }];
+    let rejected = router.generate_on(Provider::Mistral, &system).await;
+    assert!(matches!(rejected, Err(AppError::InvalidRequest(_))));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn vanished_models_are_retired_and_reported() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-deprecation-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("deprecation.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    for workload in [Workload::Chat, Workload::Code] {
+        catalog
+            .adopt_model(Provider::Groq, workload, "mixtral".into(), None, None, 5)
+            .await?;
+    }
+    catalog
+        .adopt_model(
+            Provider::Groq,
+            Workload::Chat,
+            "sunset".into(),
+            None,
+            Some(r#"{"deprecation_date":"2000-01-01"}"#.into()),
+            1,
+        )
+        .await?;
+
+    let usage = UsageLogger::new(Arc::clone(&pool));
+    let gone = || Some("The model `mixtral` has been decommissioned".to_string());
+    usage
+        .log(Provider::Groq, Some("mixtral"), None, false, 10, gone())
+        .await?;
+    usage
+        .log(Provider::Groq, Some("mixtral"), None, false, 10, gone())
+        .await?;
+    // Two failures are not enough with a threshold of three.
+    assert!(catalog
+        .retire_if_gone(Provider::Groq, "mixtral", 3)
+        .await?
+        .is_empty());
+
+    usage
+        .log(Provider::Groq, Some("mixtral"), None, false, 10, gone())
+        .await?;
+    let retired = catalog.retire_if_gone(Provider::Groq, "mixtral", 3).await?;
+    assert_eq!(retired.len(), 2);
+    let active = catalog.active_models(Provider::Groq, None).await?;
+    assert!(active.iter().all(|entry| entry.model != "mixtral"));
+
+    let today = chrono::Utc::now().date_naive();
+    let report = deprecation::report(&catalog, Some(Provider::Groq), today, 30).await?;
+    assert_eq!(report.len(), 3);
+    for entry in report.iter().filter(|entry| entry.model == "mixtral") {
+        assert!(entry.reason.describe().contains("decommissioned"));
+        assert!(matches!(
+            entry.reason,
+            DeprecationReason::AutoRetired { .. }
+        ));
+    }
+    assert!(report.iter().any(|entry| entry.model == "sunset"
+        && matches!(entry.reason, DeprecationReason::Deprecated { .. })));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn standard_quality_prefers_cheap_models_within_spend_caps() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-budget-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("budget.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    for (provider, model, metadata) in [
+        (
+            Provider::DeepSeek,
+            "deepseek-chat",
+            r#"{"input_price_per_mtok":0.27,"output_price_per_mtok":1.10}"#,
+        ),
+        (Provider::Together, "together-chat", "{}"),
+        (Provider::Groq, "groq-chat", r#"{"free_tier":true}"#),
+    ] {
+        catalog
+            .adopt_model(
+                provider,
+                Workload::Chat,
+                model.into(),
+                None,
+                Some(metadata.into()),
+                10,
+            )
+            .await?;
+    }
+
+    let order = vec![Provider::Together, Provider::DeepSeek, Provider::Groq];
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    for provider in &order {
+        drop(providers.insert(
+            *provider,
+            Arc::new(ModelNameProvider {
+                provider: *provider,
+            }),
+        ));
+    }
+    let mut config = BudgetConfig::default();
+    let _ = config.providers.insert(
+        "deepseek".into(),
+        SpendLimit {
+            daily_usd: Some(0.001),
+            monthly_usd: None,
+        },
+    );
+    let budget = Budget::from_config(Arc::clone(&pool), &config)?.expect("caps configured");
+    let router = ProviderRouter::from_map(providers, order)?
+        .with_catalog(catalog)
+        .with_budget(budget);
+
+    let request = |quality| AIRequest {
+        prompt: "Hello".into(),
+        hints: RequestHints {
+            workload: Some(Workload::Chat),
+            quality,
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+
+    // Without a quality hint the fallback order decides; `standard` puts the
+    // free model first.
+    let response = router.generate(&request(None)).await?;
+    assert_eq!(response.provider, Provider::Together);
+    let response = router
+        .generate(&request(Some(RequestQuality::Standard)))
+        .await?;
+    assert_eq!(response.provider, Provider::Groq);
+
+    // Once DeepSeek's daily cap is spent it is skipped, even when pinned.
+    UsageLogger::new(Arc::clone(&pool))
+        .log_success(
+            Provider::DeepSeek,
+            Some("deepseek-chat"),
+            Some(Workload::Chat),
+            10,
+            &CallCost {
+                input_cost_micros: 600,
+                output_cost_micros: 600,
+                ..CallCost::default()
+            },
+        )
+        .await?;
+    let pinned = router.generate_on(Provider::DeepSeek, &request(None)).await;
+    assert!(
+        matches!(pinned, Err(AppError::BudgetExceeded(message)) if message.contains("deepseek daily"))
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_without_workload_are_classified_for_model_selection() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-classify-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("classify.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    for (workload, model, priority) in [
+        (Workload::Chat, "chat-model", 1),
+        (Workload::Code, "code-model", 5),
+        (Workload::Creative, "story-model", 5),
+    ] {
+        catalog
+            .adopt_model(Provider::Groq, workload, model.into(), None, None, priority)
+            .await?;
+    }
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(ModelNameProvider {
+            provider: Provider::Groq,
+        }),
+    ));
+    drop(providers.insert(
+        Provider::Cerebras,
+        Arc::new(StaticProvider {
+            provider: Provider::Cerebras,
+            content: "Creative.",
+        }),
+    ));
+    let classifier = WorkloadClassifier::from_config(&ClassifierConfig {
+        provider: Some("cerebras".into()),
+        model: Some("llama3.1-8b".into()),
+        ..ClassifierConfig::default()
+    })?
+    .expect("classifier enabled");
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?
+        .with_catalog(catalog)
+        .with_classifier(classifier);
+    let request = |prompt: &str| AIRequest {
+        prompt: prompt.into(),
+        ..AIRequest::default()
+    };
+
+    // Clear cues are classified locally.
+    let code = router
+        .generate(&request("Fix the bug in this Rust function"))
+        .await?;
+    assert_eq!(code.model.as_deref(), Some("code-model"));
+
+    // Without cues the classification model decides.
+    let creative = router.generate(&request("Rain on a tin roof")).await?;
+    assert_eq!(creative.model.as_deref(), Some("story-model"));
+
+    // Strict requests are not sent to the classifier before moderation.
+    let mut strict = request("Rain on a tin roof");
+    strict.hints.guardrail = Some(RequestGuardrail::Strict);
+    let strict = router.generate(&strict).await?;
+    assert_eq!(strict.model.as_deref(), Some("chat-model"));
+
+    // An explicit workload is never overridden.
+    let mut chat = request("Fix the bug in this Rust function");
+    chat.hints.workload = Some(Workload::Chat);
+    let chat = router.generate(&chat).await?;
+    assert_eq!(chat.model.as_deref(), Some("chat-model"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+/// Answers with valid JSON only when called with `capable_model`.
+struct TieredProvider {
+    provider: Provider,
+    capable_model: &'static str,
+}
+
+#[async_trait]
+impl AIProvider for TieredProvider {
+    async fn generate(&self, request: &AIRequest) -> Result<AIResponse, AppError> {
+        let content = if request.model == self.capable_model {
+            r#"{"ok": true}"#.to_string()
+        } else {
+            "Sure! Here is the JSON you asked for.".to_string()
+        };
+        Ok(AIResponse {
+            content,
+            provider: self.provider,
+            model: Some(request.model.clone()),
+        })
+    }
+}
+
+#[tokio::test]
+async fn complexity_picks_model_tier_and_escalates_invalid_output() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-tiers-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("tiers.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let catalog = CatalogStore::new(Arc::clone(&pool));
+    for (model, metadata, priority) in [
+        ("llama-8b", r#"{"tier":"small"}"#, 5),
+        ("llama-70b", r#"{"tier":"large"}"#, 10),
+        ("untagged", "{}", 20),
+    ] {
+        catalog
+            .adopt_model(
+                Provider::Groq,
+                Workload::Chat,
+                model.into(),
+                None,
+                Some(metadata.into()),
+                priority,
+            )
+            .await?;
+    }
+
+    let mut providers: HashMap<Provider, Arc<dyn AIProvider + Send + Sync>> = HashMap::new();
+    drop(providers.insert(
+        Provider::Groq,
+        Arc::new(TieredProvider {
+            provider: Provider::Groq,
+            capable_model: "llama-70b",
+        }),
+    ));
+    let router = ProviderRouter::from_map(providers, vec![Provider::Groq])?.with_catalog(catalog);
+    let request = |complexity| AIRequest {
+        prompt: "Say hello".into(),
+        hints: RequestHints {
+            workload: Some(Workload::Chat),
+            complexity,
+            ..RequestHints::default()
+        },
+        ..AIRequest::default()
+    };
+
+    // Without a complexity hint the catalog priority decides.
+    for (complexity, expected) in [
+        (None, "llama-8b"),
+        (Some(RequestComplexity::Low), "llama-8b"),
+        (Some(RequestComplexity::Medium), "untagged"),
+        (Some(RequestComplexity::High), "llama-70b"),
+    ] {
+        let response = router.generate(&request(complexity)).await?;
+        assert_eq!(response.model.as_deref(), Some(expected), "{complexity:?}");
+    }
+
+    // The small model's reply is not JSON, so the request escalates to the
+    // large model on the same provider.
+    let mut json = request(Some(RequestComplexity::Low));
+    json.hints.response_format = Some(ResponseFormat::Json);
+    let response = router.generate(&json).await?;
+    assert_eq!(response.model.as_deref(), Some("llama-70b"));
+    assert_eq!(response.content, r#"{"ok":true}"#);
+
+    // A model named by the caller is never swapped for another.
+    json.model = "llama-8b".into();
+    let pinned = router.generate(&json).await;
+    assert!(matches!(pinned, Err(AppError::InvalidResponse(_))));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+struct RateLimitedProvider;
+
+#[async_trait]
+impl AIProvider for RateLimitedProvider {
+    async fn generate(&self, _request: &AIRequest) -> Result<AIResponse, AppError> {
+        Err(AppError::ApiError(
+            "Groq request failed with status 429 Too Many Requests: rate limit reached".into(),
+        ))
+    }
+}
+
+#[tokio::test]
+async fn named_keys_are_pooled_and_exhausted_keys_skipped() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-keys-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("keys.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+
+    // A database from before named keys keeps its key as `default`.
+    let _ = sqlx::query(
+        "CREATE TABLE provider_credentials (provider TEXT PRIMARY KEY, nonce BLOB NOT NULL, \
+         ciphertext BLOB NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL)",
+    )
+    .execute(&*pool)
+    .await?;
+    let inserted = sqlx::query(
+        "INSERT INTO provider_credentials VALUES ('mistral', x'00', x'00', 'then', 'then')",
+    )
+    .execute(&*pool)
+    .await?;
+    assert_eq!(inserted.rows_affected(), 1);
+    database::ensure_schema(&pool).await?;
+
+    let store = CredentialStore::with_key(Arc::clone(&pool), [7; 32]);
+    let migrated = store.list_keys(None).await?;
+    assert_eq!(migrated.len(), 1);
+    assert_eq!(
+        (migrated[0].provider, migrated[0].name.as_str()),
+        (Provider::Mistral, "default")
+    );
+
+    store.add_key(Provider::Groq, "a", "key-a", None).await?;
+    store.add_key(Provider::Groq, "b", "key-b", Some(2)).await?;
+    assert!(store
+        .add_key(Provider::Groq, "b", "again", None)
+        .await
+        .is_err());
+    assert_eq!(
+        store.active_keys(Provider::Groq).await?,
+        [("a".into(), "key-a".into()), ("b".into(), "key-b".into())]
+    );
+
+    let clients: Vec<(String, Arc<dyn AIProvider + Send + Sync>)> = vec![
+        ("a".into(), Arc::new(RateLimitedProvider)),
+        (
+            "b".into(),
+            Arc::new(StaticProvider {
+                provider: Provider::Groq,
+                content: "from b",
+            }),
+        ),
+    ];
+    let keys = KeyPool::new(
+        Provider::Groq,
+        store.clone(),
+        KeySelection::RoundRobin,
+        clients,
+    );
+    let request = AIRequest {
+        prompt: "Hello".into(),
+        ..AIRequest::default()
+    };
+
+    // Key `a` is rate limited, so `b` serves and `a` is held back.
+    assert_eq!(keys.generate(&request).await?.content, "from b");
+    let a = store.key(Provider::Groq, "a").await?.expect("key a");
+    assert!(a.retry_after.is_some());
+    assert!(a
+        .last_error
+        .as_deref()
+        .is_some_and(|error| error.contains("429")));
+    assert_eq!(
+        store
+            .key_order(Provider::Groq, KeySelection::RoundRobin)
+            .await?,
+        ["b"]
+    );
+
+    // `b` reaches its daily limit of two requests.
+    assert_eq!(keys.generate(&request).await?.content, "from b");
+    let b = store.key(Provider::Groq, "b").await?.expect("key b");
+    assert_eq!((b.requests_today, b.total_requests), (2, 2));
+    let exhausted = keys.generate(&request).await;
+    assert!(
+        matches!(exhausted, Err(AppError::ApiError(message)) if message.contains("rate limit"))
+    );
+
+    // Rotating `a` clears its hold; disabling it takes it out of selection.
+    assert!(store.rotate_key(Provider::Groq, "a", "key-a2").await?);
+    assert_eq!(
+        store
+            .key_order(Provider::Groq, KeySelection::LeastUsed)
+            .await?,
+        ["a"]
+    );
+    assert!(
+        store
+            .set_key_status(Provider::Groq, "a", KeyStatus::Disabled)
+            .await?
+    );
+    assert!(store
+        .key_order(Provider::Groq, KeySelection::LeastUsed)
+        .await?
+        .is_empty());
+    assert_eq!(
+        store.get_token(Provider::Groq).await?.as_deref(),
+        Some("key-b")
+    );
+
+    assert!(store.remove_key(Provider::Groq, "b").await?);
+    assert_eq!(store.list_keys(Some(Provider::Groq)).await?.len(), 1);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn rekey_moves_credentials_to_a_new_master_key() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-rekey-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let url = format!("sqlite://{}", dir.join("rekey.db").display());
+    let pool = Arc::new(database::init_db(&url).await?);
+    database::ensure_schema(&pool).await?;
+
+    let old_key = master_key::load(MasterKeySource::File, &dir).await?;
+    assert!(dir.join("secret.key").exists());
+    let store = CredentialStore::with_key(Arc::clone(&pool), old_key);
+    store.set_token(Provider::Groq, "groq-secret").await?;
+    store
+        .add_key(Provider::Groq, "spare", "groq-spare", None)
+        .await?;
+    store.set_token(Provider::Mistral, "mistral-secret").await?;
+
+    // A store with the wrong key cannot rekey and leaves everything alone.
+    let wrong = CredentialStore::with_key(Arc::clone(&pool), [9; 32]);
+    let unsaved = NewMasterKey::random(MasterKeySource::File, &dir);
+    assert!(wrong.rekey(&unsaved).await.is_err());
+    assert_eq!(
+        master_key::load(MasterKeySource::File, &dir).await?,
+        old_key
+    );
+    assert_eq!(
+        store.get_token(Provider::Groq).await?.as_deref(),
+        Some("groq-secret")
+    );
+
+    // A commit that fails (here on a deferred foreign key) keeps the old
+    // key file and drops the staged one.
+    for statement in [
+        "CREATE TABLE rekey_parent (id INTEGER PRIMARY KEY)",
+        "CREATE TABLE rekey_guard (parent INTEGER REFERENCES rekey_parent(id) \
+         DEFERRABLE INITIALLY DEFERRED)",
+        "CREATE TRIGGER rekey_blocker AFTER UPDATE ON provider_credentials \
+         BEGIN INSERT INTO rekey_guard VALUES (1); END",
+    ] {
+        let _ = sqlx::query(statement).execute(&*pool).await?;
+    }
+    let blocked = NewMasterKey::from_passphrase(&dir, "never installed")?;
+    assert!(store.rekey(&blocked).await.is_err());
+    assert!(!dir.join("secret.kdf").exists());
+    assert!(!dir.join("secret.kdf.new").exists());
+    assert_eq!(
+        master_key::load(MasterKeySource::File, &dir).await?,
+        old_key
+    );
+    assert_eq!(
+        store.get_token(Provider::Mistral).await?.as_deref(),
+        Some("mistral-secret")
+    );
+    let _ = sqlx::query("DROP TRIGGER rekey_blocker")
+        .execute(&*pool)
+        .await?;
+
+    // Moving to a passphrase replaces the key file with the salt record.
+    let new_key = NewMasterKey::from_passphrase(&dir, "correct horse")?;
+    let (rekeyed, count) = store.rekey(&new_key).await?;
+    assert_eq!(count, 3);
+    assert!(!dir.join("secret.key").exists());
+    assert!(dir.join("secret.kdf").exists());
+    assert!(store.get_token(Provider::Groq).await.is_err());
+    assert_eq!(
+        rekeyed.active_keys(Provider::Groq).await?,
+        [
+            ("default".into(), "groq-secret".into()),
+            ("spare".into(), "groq-spare".into())
+        ]
+    );
+    let reopened = CredentialStore::with_key(Arc::clone(&pool), *new_key.key());
+    assert_eq!(
+        reopened.get_token(Provider::Mistral).await?.as_deref(),
+        Some("mistral-secret")
+    );
+    let locked = master_key::load(MasterKeySource::File, &dir)
+        .await
+        .expect_err("file source refuses a passphrase setup");
+    assert!(locked.to_string().contains("passphrase"));
+
+    // And back to a key file.
+    let file_key = NewMasterKey::random(MasterKeySource::File, &dir);
+    let (_, count) = rekeyed.rekey(&file_key).await?;
+    assert_eq!(count, 3);
+    assert!(!dir.join("secret.kdf").exists());
+    assert_eq!(
+        &master_key::load(MasterKeySource::File, &dir).await?,
+        file_key.key()
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn exported_bundle_imports_into_another_store() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("freegin-bundle-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let mut stores = Vec::new();
+    for (db, key) in [("from.db", [1; 32]), ("to.db", [2; 32])] {
+        let url = format!("sqlite://{}", dir.join(db).display());
+        let pool = Arc::new(database::init_db(&url).await?);
+        database::ensure_schema(&pool).await?;
+        stores.push(CredentialStore::with_key(pool, key));
+    }
+    let (source, target) = (&stores[0], &stores[1]);
+
+    source.set_token(Provider::Groq, "groq-default").await?;
+    source
+        .add_key(Provider::Groq, "work", "groq-work", Some(100))
+        .await?;
+    let _ = source
+        .set_key_status(Provider::Groq, "work", KeyStatus::Disabled)
+        .await?;
+    source.set_token(Provider::Mistral, "mistral-key").await?;
+
+    let exported = source.export_keys().await?;
+    assert_eq!(exported.len(), 3);
+    let sealed = CredentialBundle::new(exported).seal("moving day")?;
+    let bundle = CredentialBundle::open(&sealed, "moving day")?;
+
+    // The target already has a `work` key that is cooling down.
+    target
+        .add_key(Provider::Groq, "work", "old-work", None)
+        .await?;
+    target
+        .record_key_failure(
+            Provider::Groq,
+            "work",
+            "rate limit",
+            chrono::Utc::now() + chrono::Duration::hours(1),
+        )
+        .await?;
+    for key in &bundle.keys {
+        target.import_key(key).await?;
+    }
+
+    assert_eq!(
+        target.get_token(Provider::Mistral).await?.as_deref(),
+        Some("mistral-key")
+    );
+    assert_eq!(
+        target.active_keys(Provider::Groq).await?,
+        [("default".into(), "groq-default".into())]
+    );
+    let work = target
+        .key(Provider::Groq, "work")
+        .await?
+        .expect("imported key");
+    assert_eq!(work.status, KeyStatus::Disabled);
+    assert_eq!(work.daily_limit, Some(100));
+    assert_eq!(work.last_error, None);
+    assert_eq!(work.retry_after, None);
+    let work_secret = target
+        .export_keys()
+        .await?
+        .into_iter()
+        .find(|key| key.name == "work")
+        .map(|key| key.token);
+    assert_eq!(work_secret.as_deref(), Some("groq-work"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}